@@ -0,0 +1,144 @@
+//! Measure the peak memory used to parse `callgrind_annotate` outputs of various sizes.
+//!
+//! Inputs are generated on the fly, so that only the memory of the parser is measured. Each
+//! symbol is repeated a number of times (as inlined functions are), so that the size of the input
+//! grows independently of the number of symbols. The peak memory must depend on the latter only.
+//!
+//! Run with `cargo bench --bench parse_memory`.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    io::{BufReader, Read},
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Instant,
+};
+
+use callgrind_differ::callgrind::parse;
+
+/// An allocator which keeps track of the peak number of bytes allocated.
+struct PeakAllocator;
+
+/// The number of bytes currently allocated.
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+/// The highest value [`CURRENT`] reached since the last call to [`reset_peak`].
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for PeakAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = unsafe { System.alloc(layout) };
+        if !ptr.is_null() {
+            let current = CURRENT.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+            PEAK.fetch_max(current, Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) };
+        CURRENT.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: PeakAllocator = PeakAllocator;
+
+/// Reset the peak to the number of bytes currently allocated.
+fn reset_peak() {
+    PEAK.store(CURRENT.load(Ordering::Relaxed), Ordering::Relaxed);
+}
+
+/// A `callgrind_annotate` output with `n_symbols` symbols, each of which appears `repeat` times.
+struct Input {
+    /// The number of distinct symbols.
+    n_symbols: usize,
+    /// The number of times each symbol appears.
+    repeat: usize,
+    /// The index of the next line of the table of symbols to generate.
+    next: usize,
+    /// Bytes generated but not yet read.
+    pending: Vec<u8>,
+    /// The number of bytes generated so far.
+    size: usize,
+}
+
+impl Input {
+    /// Create the input.
+    fn new(n_symbols: usize, repeat: usize) -> Self {
+        let header = "\
+--------------------------------------------------------------------------------
+Events shown:    Ir
+--------------------------------------------------------------------------------
+Ir
+--------------------------------------------------------------------------------
+1,000,000,000 (100.0%)  PROGRAM TOTALS
+
+--------------------------------------------------------------------------------
+Ir                    file:function
+--------------------------------------------------------------------------------
+";
+        Self {
+            n_symbols,
+            repeat,
+            next: 0,
+            pending: header.as_bytes().to_vec(),
+            size: header.len(),
+        }
+    }
+}
+
+impl Read for Input {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        while self.pending.is_empty() && self.next < self.n_symbols * self.repeat {
+            let symbol = self.next % self.n_symbols;
+            self.pending = format!(
+                "{:>11} ( 0.01%)  src/module_{}.rs:crate::module_{}::function_{symbol} [/bin/x]\n",
+                1000 + symbol,
+                symbol % 100,
+                symbol % 100
+            )
+            .into_bytes();
+            self.size += self.pending.len();
+            self.next += 1;
+        }
+        let n = buf.len().min(self.pending.len());
+        buf[..n].copy_from_slice(&self.pending[..n]);
+        self.pending.drain(..n);
+        Ok(n)
+    }
+}
+
+fn main() {
+    println!(
+        "{:>10} {:>7} {:>12} {:>12} {:>9}",
+        "symbols", "repeat", "input (MiB)", "peak (MiB)", "time (s)"
+    );
+    let mut peaks = vec![];
+    for (n_symbols, repeat) in [(10_000, 1), (10_000, 100), (100_000, 1), (100_000, 20)] {
+        let mut input = Input::new(n_symbols, repeat);
+        reset_peak();
+        let start = Instant::now();
+        let run = parse(BufReader::new(&mut input), &[], None).expect("Failed to parse");
+        let elapsed = start.elapsed();
+        let peak = PEAK.load(Ordering::Relaxed);
+        assert_eq!(run.symbols.len(), n_symbols);
+        drop(run);
+        println!(
+            "{n_symbols:>10} {repeat:>7} {:>12.1} {:>12.1} {:>9.3}",
+            input.size as f64 / f64::from(1 << 20),
+            peak as f64 / f64::from(1 << 20),
+            elapsed.as_secs_f64()
+        );
+        peaks.push((n_symbols, peak));
+    }
+
+    // The peak must not grow with the size of the input for a given number of symbols.
+    for pair in peaks.chunks(2) {
+        let [(n_symbols, small), (_, large)] = *pair else {
+            unreachable!()
+        };
+        assert!(
+            large < small * 3 / 2,
+            "Peak memory for {n_symbols} symbols grew with the size of the input ({small} -> {large} bytes)"
+        );
+    }
+}
@@ -0,0 +1,157 @@
+//! Benchmark parsing, merging, adding runs to records and sorting them on synthetic inputs.
+//!
+//! Run with `cargo bench --bench runs`. Inputs have 10k, 100k and 1M symbols.
+
+use std::{fmt::Write, hint::black_box};
+
+use callgrind_differ::{
+    args::SortBy,
+    callgrind::parse,
+    runs::{Records, Run},
+};
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+
+/// The numbers of symbols of the inputs.
+const SIZES: [usize; 3] = [10_000, 100_000, 1_000_000];
+
+/// Return the name of the `i`-th synthetic symbol.
+///
+/// Names are long and share prefixes, as demangled symbols do.
+fn symbol_name(i: usize) -> String {
+    format!(
+        "<crate::module_{}::Type_{} as core::ops::Fn<(&str,)>>::call::function_{i}",
+        i % 100,
+        i % 1000
+    )
+}
+
+/// Return the IR count of the `i`-th symbol in the run `seed`.
+fn ir(i: usize, seed: usize) -> u64 {
+    ((i * 7919 + seed * 104_729) % 1_000_000) as u64 + 1
+}
+
+/// Render a `callgrind_annotate` output with `n_symbols` symbols.
+fn annotate_output(n_symbols: usize) -> String {
+    let dashes = "-".repeat(80);
+    let total = (0..n_symbols).map(|i| ir(i, 0)).sum::<u64>();
+    let mut s = format!(
+        "{dashes}\nEvents shown:    Ir\n{dashes}\nIr\n{dashes}\n{total} (100.0%)  PROGRAM TOTALS\n\n\
+         {dashes}\nIr                    file:function\n{dashes}\n"
+    );
+    for i in 0..n_symbols {
+        let _ = writeln!(
+            s,
+            "{:>11} ( 0.01%)  src/module_{}.rs:{} [/bin/x]",
+            ir(i, 0),
+            i % 100,
+            symbol_name(i)
+        );
+    }
+    s
+}
+
+/// Build a run with `n_symbols` symbols. Runs with a different `seed` have different counts, and
+/// one symbol out of 10 is replaced by a new one.
+fn run(n_symbols: usize, seed: usize) -> Run {
+    let mut run = Run::new();
+    for i in 0..n_symbols {
+        let i = if seed != 0 && i % 10 == 0 {
+            i + n_symbols
+        } else {
+            i
+        };
+        run.add_ir(&symbol_name(i), ir(i, seed));
+    }
+    run.total_ir = run.symbols.iter().map(|symbol| symbol.ir).sum();
+    run
+}
+
+/// Build records of `n_runs` runs with `n_symbols` symbols each.
+fn records(n_symbols: usize, n_runs: usize) -> Records {
+    let mut records = Records::new();
+    for seed in 0..n_runs {
+        records.add_run(run(n_symbols, seed));
+    }
+    records
+}
+
+/// Benchmark [`parse`].
+fn bench_parse(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse");
+    group.sample_size(10);
+    for n_symbols in SIZES {
+        let input = annotate_output(n_symbols);
+        group.throughput(Throughput::Bytes(input.len() as u64));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(n_symbols),
+            &input,
+            |b, input| {
+                b.iter(|| parse(black_box(input.as_bytes()), &[], None).unwrap());
+            },
+        );
+    }
+    group.finish();
+}
+
+/// Benchmark [`Run::merge`] on 3 samples.
+fn bench_merge(c: &mut Criterion) {
+    let mut group = c.benchmark_group("merge");
+    group.sample_size(10);
+    for n_symbols in SIZES {
+        group.throughput(Throughput::Elements(n_symbols as u64));
+        group.bench_function(BenchmarkId::from_parameter(n_symbols), |b| {
+            b.iter_batched(
+                || (0..3).map(|seed| run(n_symbols, seed)).collect::<Vec<_>>(),
+                Run::merge,
+                BatchSize::PerIteration,
+            );
+        });
+    }
+    group.finish();
+}
+
+/// Benchmark [`Records::add_run`], adding a run to records which already have 2.
+fn bench_add_run(c: &mut Criterion) {
+    let mut group = c.benchmark_group("add_run");
+    group.sample_size(10);
+    for n_symbols in SIZES {
+        group.throughput(Throughput::Elements(n_symbols as u64));
+        group.bench_function(BenchmarkId::from_parameter(n_symbols), |b| {
+            b.iter_batched(
+                || (records(n_symbols, 2), run(n_symbols, 2)),
+                |(mut records, run)| {
+                    records.add_run(run);
+                    records
+                },
+                BatchSize::PerIteration,
+            );
+        });
+    }
+    group.finish();
+}
+
+/// Benchmark [`Records::sort`] by symbol and by the IR of the last column, on 3 runs.
+fn bench_sort(c: &mut Criterion) {
+    let mut group = c.benchmark_group("sort");
+    group.sample_size(10);
+    for by in ["symbol", "-last-ir"] {
+        let sort_by = by.parse::<SortBy>().unwrap();
+        for n_symbols in SIZES {
+            group.throughput(Throughput::Elements(n_symbols as u64));
+            group.bench_function(BenchmarkId::new(by, n_symbols), |b| {
+                b.iter_batched(
+                    || records(n_symbols, 3),
+                    |mut records| {
+                        records.sort(sort_by).unwrap();
+                        records
+                    },
+                    BatchSize::PerIteration,
+                );
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_parse, bench_merge, bench_add_run, bench_sort);
+criterion_main!(benches);
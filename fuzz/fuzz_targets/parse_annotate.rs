@@ -0,0 +1,13 @@
+//! Fuzz the `callgrind_annotate` parser. Any input must be either accepted or rejected with an
+//! error, but never panic.
+//!
+//! Run with `cargo +nightly fuzz run parse_annotate`.
+
+#![no_main]
+
+use callgrind_differ::callgrind::parse;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = parse(data, &[], Some(10_000));
+});
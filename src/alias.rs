@@ -0,0 +1,97 @@
+use std::{collections::HashMap, path::Path};
+
+use anyhow::{bail, Context, Result};
+
+/// The separator of the former and new name of a symbol in an alias file.
+pub const SEPARATOR: &str = " => ";
+
+/// A rename of a symbol, declared in an alias file (see `history alias`) so that the runs
+/// measured before and after the rename show as a single series.
+///
+/// Each alias is an `<old name> => <new name>` line of the file, and lines starting with `#` are
+/// comments:
+/// ```no_compile
+/// # Split out of the parser in v1.4.
+/// parser::Parser<T>::scan => scanner::Scanner<T>::scan
+/// ```
+/// Aliases apply in order, so that a symbol renamed twice is renamed by two aliases.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Alias {
+    /// The former name of the symbol.
+    pub from: String,
+    /// The new name of the symbol.
+    pub to: String,
+}
+
+impl Alias {
+    /// Read the aliases from an alias file.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be read or an alias is not well-formed.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Vec<Self>> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        Self::parse(&contents).with_context(|| format!("Invalid alias in {}", path.display()))
+    }
+
+    /// Parse the aliases from the contents of an alias file.
+    ///
+    /// # Errors
+    /// Returns an error if a line is neither empty, a comment nor an alias, an alias renames a
+    /// symbol to itself, or a symbol is renamed twice.
+    pub fn parse(contents: &str) -> Result<Vec<Self>> {
+        let mut aliases: Vec<Self> = vec![];
+        for (i, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((from, to)) = line
+                .split_once(SEPARATOR)
+                .map(|(from, to)| (from.trim(), to.trim()))
+                .filter(|(from, to)| !from.is_empty() && !to.is_empty())
+            else {
+                bail!("Line {}: expected `<old name> => <new name>`", i + 1);
+            };
+            if from == to {
+                bail!("Line {}: {from} is renamed to itself", i + 1);
+            }
+            if aliases.iter().any(|alias| alias.from == from) {
+                bail!("Line {}: {from} is renamed twice", i + 1);
+            }
+            aliases.push(Self {
+                from: from.to_string(),
+                to: to.to_string(),
+            });
+        }
+        Ok(aliases)
+    }
+}
+
+/// Return the name of the symbol named `name` once `aliases` are applied, or `None` if they do
+/// not rename it.
+pub fn rename(aliases: &[Alias], name: &str) -> Option<String> {
+    let renamed = aliases.iter().fold(
+        name,
+        |name, alias| {
+            if alias.from == name {
+                &alias.to
+            } else {
+                name
+            }
+        },
+    );
+    (renamed != name).then(|| renamed.to_string())
+}
+
+/// Return the former names of each symbol renamed by `aliases`, by new name, in order.
+pub fn former_names(aliases: &[Alias]) -> HashMap<&str, Vec<&str>> {
+    let mut former = HashMap::<&str, Vec<&str>>::new();
+    for alias in aliases {
+        let mut names = former.remove(alias.from.as_str()).unwrap_or_default();
+        names.push(&alias.from);
+        former.entry(&alias.to).or_default().extend(names);
+    }
+    former
+}
@@ -1,9 +1,18 @@
-use std::{borrow::Cow, fmt::Display, path::Path, str::FromStr};
+use std::{borrow::Cow, ffi::OsString, fmt::Display, ops::Range, path::Path, str::FromStr};
 
-use anyhow::{bail, Result};
-use clap::Parser;
+use anyhow::{bail, Context, Result};
+use clap::{Parser, Subcommand};
 use itertools::Itertools;
 
+use crate::{
+    callgrind::{InputLimits, IR_EVENT},
+    format::InputFormats,
+    history::History,
+    metric::Metric,
+    noise::DEFAULT_NOISE_FLOOR_FILE,
+    render::OutputRenderers,
+};
+
 /// The field on which to sort the output by.
 #[derive(Debug, Clone, Copy)]
 pub enum SortByField {
@@ -15,6 +24,11 @@ pub enum SortByField {
     LastIR,
     /// Sort by the instruction count of the given column (0-indexed).
     ColumnIR(u32),
+    /// Sort by the trend of the instruction count across runs (see [`Show::Trend`]).
+    Trend,
+    /// Sort by the impact of the change of the last column: its absolute difference times the
+    /// share of the total IR of the symbol (see [`DiffCell::impact`](crate::diff::DiffCell::impact)).
+    Impact,
 }
 
 /// The order in which to sort (ascending / descending).
@@ -27,6 +41,8 @@ pub enum SortByOrder {
 }
 
 /// How to sort the output. The default is by ascending symbol.
+///
+/// Ties on instruction counts are broken by ascending symbol name, in either order.
 #[derive(Debug, Clone, Copy)]
 pub struct SortBy {
     /// The field on which to sort the output.
@@ -66,6 +82,8 @@ impl FromStr for SortBy {
             "symbol" => SortByField::Symbol,
             "last-ir" => SortByField::LastIR,
             "first-ir" => SortByField::FirstIR,
+            "trend" => SortByField::Trend,
+            "impact" => SortByField::Impact,
             // We only accept things like "column3" or "column0".
             mut s if s.starts_with("column") => {
                 s = &s["column".len()..];
@@ -78,7 +96,10 @@ impl FromStr for SortBy {
                     bail!("Invalid column number: {s}");
                 }
             }
-            _ => bail!("Invalid sort-by. Accepted values are: symbol, first-ir, last-ir, columnX"),
+            _ => bail!(
+                "Invalid sort-by. Accepted values are: symbol, first-ir, last-ir, columnX, trend, \
+                 impact"
+            ),
         };
 
         Ok(Self { field, order })
@@ -103,6 +124,11 @@ pub enum RelativeTo {
     Previous,
     /// Every column is compared to the n-th column (0-indexed).
     Column(u32),
+    /// Every column is compared to the mean of the (up to) n columns preceding it.
+    Rolling(u32),
+    /// Every column is compared to the most recent pinned run of the history (see
+    /// `history pin`).
+    Pinned,
 }
 
 impl FromStr for RelativeTo {
@@ -113,6 +139,7 @@ impl FromStr for RelativeTo {
             "first" => Ok(Self::First),
             "last" => Ok(Self::Last),
             "previous" => Ok(Self::Previous),
+            "pinned" => Ok(Self::Pinned),
             s if s.starts_with("column") => {
                 let number: &str = &s["column".len()..];
                 if let Ok(x) = number.parse::<u32>() {
@@ -121,7 +148,39 @@ impl FromStr for RelativeTo {
                     bail!("Invalid column number: {number}");
                 }
             }
-            _ => bail!("Invalid relative-to. Accepted values are: first, last, previous, columnX"),
+            s if s.starts_with("rolling:") => {
+                let number: &str = &s["rolling:".len()..];
+                match number.parse::<u32>() {
+                    Ok(x) if x > 0 => Ok(Self::Rolling(x)),
+                    _ => bail!("Invalid rolling window: {number}"),
+                }
+            }
+            _ => bail!(
+                "Invalid relative-to. Accepted values are: first, last, previous, columnX, rolling:N, pinned"
+            ),
+        }
+    }
+}
+
+impl RelativeTo {
+    /// Return the indices of the columns that the column at index `i` is compared to.
+    ///
+    /// The reference value is the mean of these columns. Unless the reference is rolling, there
+    /// is only one such column. If the range is `i..i + 1`, the column is its own reference. With
+    /// [`RelativeTo::Previous`] and [`RelativeTo::Rolling`], this is the case of the first column.
+    ///
+    /// The pinned run is known from the metadata of the runs only, which
+    /// [`ReferenceResolver`](crate::diff::ReferenceResolver) resolves it from: it is the first
+    /// column here.
+    pub fn reference_columns(self, i: usize, n_runs: usize) -> Range<usize> {
+        let single = |x: usize| x..x + 1;
+        match self {
+            Self::First | Self::Pinned => single(0),
+            Self::Last => single(n_runs - 1),
+            Self::Previous | Self::Rolling(_) if i == 0 => single(0),
+            Self::Previous => single(i - 1),
+            Self::Column(x) => single(x as usize),
+            Self::Rolling(n) => i.saturating_sub(n as usize)..i,
         }
     }
 }
@@ -144,6 +203,44 @@ pub enum Show {
     PercentageDiff,
     /// The difference in IR count with respect to [`RelativeTo`].
     IRCountDiff,
+    /// How the difference in IR count to the previous column changed from the difference of the
+    /// previous column to the one before it (with [`RelativeTo::Previous`]).
+    Acceleration,
+    /// Whether the difference in IR count is larger than `--significance-sigmas` times the
+    /// pooled standard deviation of the samples of the column and its reference.
+    Significance,
+    /// The minimum, maximum, mean and standard deviation of the IR count across all runs, shown
+    /// once after the columns of the runs.
+    Stats,
+    /// The trend of the IR count across all runs, i.e. the slope of its linear regression (in IR
+    /// per run), shown once after the columns of the runs.
+    Trend,
+    /// The change of the IR count from the first run to the last, regardless of [`RelativeTo`],
+    /// shown once after the columns of the runs.
+    Cumulative,
+    /// A rate computed from the counts of other events, shown in each column (the reference
+    /// included) along with its difference to the rate of the reference.
+    Metric(Metric),
+    /// The rank of the symbol by IR count within its run, shown in each column (the reference
+    /// included) along with the number of places it moved from its rank in the reference.
+    Rank,
+    /// How much the IR count varies across all runs, shown once before the name of the symbol.
+    Stability,
+}
+
+impl Show {
+    /// Return whether this is shown for each column of data, rather than once after them.
+    pub fn is_run_column(self) -> bool {
+        !matches!(
+            self,
+            Self::Stats
+                | Self::Trend
+                | Self::Cumulative
+                | Self::Metric(_)
+                | Self::Rank
+                | Self::Stability
+        )
+    }
 }
 
 impl FromStr for Show {
@@ -155,8 +252,20 @@ impl FromStr for Show {
             "ircount" => Ok(Self::IRCount),
             "percentagediff" => Ok(Self::PercentageDiff),
             "ircountdiff" => Ok(Self::IRCountDiff),
+            "significance" => Ok(Self::Significance),
+            "acceleration" => Ok(Self::Acceleration),
+            "stats" => Ok(Self::Stats),
+            "trend" => Ok(Self::Trend),
+            "cumulative" => Ok(Self::Cumulative),
+            "rank" => Ok(Self::Rank),
+            "stability" => Ok(Self::Stability),
+            "d1-miss-rate" => Ok(Self::Metric(Metric::D1MissRate)),
+            "ll-miss-rate" => Ok(Self::Metric(Metric::LLMissRate)),
+            "branch-miss-rate" => Ok(Self::Metric(Metric::BranchMissRate)),
             _ => bail!(
-                "Invalid show. Accepted values are: all, ircount, percentagediff, ircountdiff"
+                "Invalid show. Accepted values are: all, ircount, percentagediff, ircountdiff, \
+                 significance, acceleration, stats, trend, cumulative, rank, stability, d1-miss-rate, ll-miss-rate, \
+                 branch-miss-rate"
             ),
         }
     }
@@ -201,6 +310,402 @@ impl Display for Color {
     }
 }
 
+/// A percentage, e.g. a threshold above which a change is a regression.
+///
+/// It is written as a number with an optional trailing `%` (e.g. `5%`, `0.5`).
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
+pub struct Percentage(pub f64);
+
+impl FromStr for Percentage {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let number = s.strip_suffix('%').unwrap_or(s);
+        match number.parse::<f64>() {
+            Ok(x) if x.is_finite() && x >= 0.0 => Ok(Self(x)),
+            _ => bail!("Invalid percentage: {s}"),
+        }
+    }
+}
+
+impl Display for Percentage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}%", self.0)
+    }
+}
+
+/// The weights of the estimated cycles event (`CEst`), as computed by `--cycle-model`.
+///
+/// It is written as a comma-separated list of `<cost>=<weight>`, where the costs are `ir` (the
+/// instruction count), `l1` (the first-level cache misses, `I1mr + D1mr + D1mw`), `ll` (the
+/// last-level cache misses, `ILmr + DLmr + DLmw`) and `bm` (the branch mispredictions,
+/// `Bcm + Bim`), e.g. `ir=1,l1=10,ll=100,bm=15`. Costs which are not given keep their default
+/// weight, which is that of `KCachegrind`'s estimate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CycleModel {
+    /// The weight of an instruction.
+    pub ir: u64,
+    /// The weight of a first-level cache miss.
+    pub l1: u64,
+    /// The weight of a last-level cache miss.
+    pub ll: u64,
+    /// The weight of a branch misprediction.
+    pub bm: u64,
+}
+
+impl Default for CycleModel {
+    fn default() -> Self {
+        Self {
+            ir: 1,
+            l1: 10,
+            ll: 100,
+            bm: 10,
+        }
+    }
+}
+
+impl FromStr for CycleModel {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut model = Self::default();
+        for weight in s.split(',').filter(|weight| !weight.is_empty()) {
+            let Some((cost, value)) = weight.split_once('=') else {
+                bail!("Invalid cycle model weight {weight}: expected `<cost>=<weight>`");
+            };
+            let Ok(value) = value.parse::<u64>() else {
+                bail!("Invalid weight for {cost}: {value}");
+            };
+            match cost {
+                "ir" => model.ir = value,
+                "l1" => model.l1 = value,
+                "ll" => model.ll = value,
+                "bm" => model.bm = value,
+                _ => bail!("Invalid cost {cost}. Accepted values are: ir, l1, ll, bm"),
+            }
+        }
+        Ok(model)
+    }
+}
+
+impl Display for CycleModel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "ir={},l1={},ll={},bm={}",
+            self.ir, self.l1, self.ll, self.bm
+        )
+    }
+}
+
+/// Metadata about the workload of a run, given with `--run-meta`.
+///
+/// It is written as a comma-separated list of `<key>=<value>`. The only key is `iterations`, the
+/// number of iterations of the workload the run measured (e.g. `iterations=1000`).
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RunMeta {
+    /// The number of iterations of the workload, which is never 0.
+    pub iterations: Option<u64>,
+}
+
+impl FromStr for RunMeta {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut meta = Self::default();
+        for entry in s.split(',').filter(|entry| !entry.is_empty()) {
+            let Some((key, value)) = entry.split_once('=') else {
+                bail!("Invalid run metadata {entry}: expected `<key>=<value>`");
+            };
+            match key {
+                "iterations" => match value.parse::<u64>() {
+                    Ok(iterations) if iterations > 0 => meta.iterations = Some(iterations),
+                    _ => bail!("Invalid iteration count: {value}"),
+                },
+                _ => bail!("Invalid run metadata key {key}. Accepted values are: iterations"),
+            }
+        }
+        Ok(meta)
+    }
+}
+
+impl Display for RunMeta {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(iterations) = self.iterations {
+            write!(f, "iterations={iterations}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Where the name of a run appended with `add` without `--name` comes from (see `--name-from`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NameSource {
+    /// The name of the file of the run (e.g. `callgrind.out.1234`).
+    File,
+    /// The date the file of the run was last modified, in UTC (e.g. `2024-03-22 09:33`).
+    Mtime,
+    /// The command line of the profiled program, as recorded in the file. Runs with no recorded
+    /// command are named after their file.
+    Cmd,
+}
+
+impl FromStr for NameSource {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "file" => Ok(Self::File),
+            "mtime" => Ok(Self::Mtime),
+            "cmd" => Ok(Self::Cmd),
+            _ => bail!("Invalid --name-from. Accepted values are: file, mtime, cmd"),
+        }
+    }
+}
+
+impl Display for NameSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::File => write!(f, "file"),
+            Self::Mtime => write!(f, "mtime"),
+            Self::Cmd => write!(f, "cmd"),
+        }
+    }
+}
+
+/// What to do when a run is appended to the history from a file that already was (see
+/// `--on-duplicate`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OnDuplicate {
+    /// Fail without appending any run.
+    Refuse,
+    /// Append the other runs only.
+    Skip,
+    /// Append the run again.
+    Allow,
+}
+
+impl FromStr for OnDuplicate {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "refuse" => Ok(Self::Refuse),
+            "skip" => Ok(Self::Skip),
+            "allow" => Ok(Self::Allow),
+            _ => bail!("Invalid --on-duplicate. Accepted values are: refuse, skip, allow"),
+        }
+    }
+}
+
+impl Display for OnDuplicate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Refuse => write!(f, "refuse"),
+            Self::Skip => write!(f, "skip"),
+            Self::Allow => write!(f, "allow"),
+        }
+    }
+}
+
+/// The format runs of the history are exported to (see `history export`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// A CSV, with a row per symbol and a column per run (see [`crate::csv::write`]).
+    Csv,
+}
+
+impl FromStr for ExportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "csv" => Ok(Self::Csv),
+            _ => bail!("Invalid export format. Accepted values are: csv"),
+        }
+    }
+}
+
+impl Display for ExportFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Csv => write!(f, "csv"),
+        }
+    }
+}
+
+/// The snapshots of massif outputs loaded as runs (see `--massif-snapshots`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MassifSnapshots {
+    /// A run per file, from its peak snapshot.
+    Peak,
+    /// A run per detailed snapshot of each file.
+    All,
+}
+
+impl FromStr for MassifSnapshots {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "peak" => Ok(Self::Peak),
+            "all" => Ok(Self::All),
+            _ => bail!("Invalid --massif-snapshots. Accepted values are: peak, all"),
+        }
+    }
+}
+
+impl Display for MassifSnapshots {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Peak => write!(f, "peak"),
+            Self::All => write!(f, "all"),
+        }
+    }
+}
+
+/// The metadata by which counts are divided before diffing (see `--per`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Per {
+    /// Divide by the number of iterations of the workload of each run.
+    Iterations,
+}
+
+impl FromStr for Per {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "iterations" => Ok(Self::Iterations),
+            _ => bail!("Invalid --per. Accepted values are: iterations"),
+        }
+    }
+}
+
+impl Display for Per {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Iterations => write!(f, "iterations"),
+        }
+    }
+}
+
+/// A percentage threshold, optionally qualified with the event it applies to.
+///
+/// It is written as `<event>=<percentage>` (e.g. `LLd=2%`) or as a bare percentage, in which case
+/// it applies to the instruction count (`Ir`).
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct EventThreshold {
+    /// The event the threshold applies to, or `None` for the instruction count.
+    pub event: Option<String>,
+    /// The threshold itself.
+    pub threshold: Percentage,
+}
+
+impl EventThreshold {
+    /// Return whether the threshold applies to the instruction count.
+    pub fn is_ir(&self) -> bool {
+        self.event.as_deref().is_none_or(|event| event == IR_EVENT)
+    }
+}
+
+impl FromStr for EventThreshold {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('=') {
+            Some((event, threshold)) => {
+                if event.is_empty() || event.contains(char::is_whitespace) {
+                    bail!("Invalid event name: {event}");
+                }
+                Ok(Self {
+                    event: Some(event.to_string()),
+                    threshold: threshold.parse()?,
+                })
+            }
+            None => Ok(Self {
+                event: None,
+                threshold: s.parse()?,
+            }),
+        }
+    }
+}
+
+impl Display for EventThreshold {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(event) = &self.event {
+            write!(f, "{event}=")?;
+        }
+        write!(f, "{}", self.threshold)
+    }
+}
+
+/// An instruction count given on the command line.
+///
+/// It may be written as an integer, with optional `,` or `_` separators (e.g. `1,200,000,000`),
+/// or in scientific notation (e.g. `1.2e9`).
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IrCount(pub u64);
+
+impl FromStr for IrCount {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let digits = s.replace([',', '_'], "");
+        if let Ok(x) = digits.parse::<u64>() {
+            return Ok(Self(x));
+        }
+        match digits.parse::<f64>() {
+            Ok(x) if x.is_finite() && x >= 0.0 && x < u64::MAX as f64 => Ok(Self(x.round() as u64)),
+            _ => bail!("Invalid instruction count: {s}"),
+        }
+    }
+}
+
+impl Display for IrCount {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// A size in bytes given on the command line.
+///
+/// It may be written as an integer with an optional binary suffix: `K`, `M` or `G` (e.g. `512M`,
+/// `2G`). A trailing `B` or `iB` is accepted as well (e.g. `512MiB`).
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ByteSize(pub u64);
+
+impl FromStr for ByteSize {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let number = s
+            .strip_suffix("iB")
+            .or_else(|| s.strip_suffix('B'))
+            .unwrap_or(s);
+        let (number, multiplier) = match number.chars().last().map(|c| c.to_ascii_uppercase()) {
+            Some('K') => (&number[..number.len() - 1], 1 << 10),
+            Some('M') => (&number[..number.len() - 1], 1 << 20),
+            Some('G') => (&number[..number.len() - 1], 1 << 30),
+            _ => (number, 1),
+        };
+        match number
+            .parse::<u64>()
+            .ok()
+            .and_then(|x| x.checked_mul(multiplier))
+        {
+            Some(x) => Ok(Self(x)),
+            None => bail!("Invalid size: {s}"),
+        }
+    }
+}
+
+impl Display for ByteSize {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
 /// A string replacement to perform on a symbol name.
 #[derive(Default, Debug, Clone)]
 pub struct StringReplacement {
@@ -224,14 +729,35 @@ impl StringReplacement {
 impl FromStr for StringReplacement {
     type Err = anyhow::Error;
 
+    /// Parse a `from/to` replacement, in which `\/` stands for a `/` that is not the separator
+    /// (e.g. `\/srv\/app\//` strips `/srv/app/` from Python symbols).
+    ///
+    /// ```
+    /// # use std::borrow::Cow;
+    /// # use callgrind_differ::args::StringReplacement;
+    /// let replacement = r"<module> \/srv\/app\//".parse::<StringReplacement>().unwrap();
+    /// let name = replacement.perform(Cow::Borrowed("<module> /srv/app/main.py:1"));
+    /// assert_eq!(name, "main.py:1");
+    /// ```
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s.split_once('/') {
-            Some((from, to)) => Ok(Self {
-                from: from.to_string(),
-                to: to.to_string(),
-            }),
-            None => bail!("No '/' in string replacement"),
+        let mut parts = [String::new(), String::new()];
+        let mut part = 0;
+        let mut chars = s.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' if chars.peek() == Some(&'/') => {
+                    chars.next();
+                    parts[part].push('/');
+                }
+                '/' if part == 0 => part = 1,
+                c => parts[part].push(c),
+            }
+        }
+        if part == 0 {
+            bail!("No '/' in string replacement");
         }
+        let [from, to] = parts;
+        Ok(Self { from, to })
     }
 }
 
@@ -241,12 +767,123 @@ impl Display for StringReplacement {
     }
 }
 
+/// The options of callgrind passed through by `profile`, which are recorded as the simulation
+/// options of its run (see [`crate::profile::simulation_options`]).
+#[derive(clap::Args, Debug, Clone, PartialEq, Eq)]
+pub struct CallgrindOptions {
+    /// Simulate the caches, recording their misses as events (`--cache-sim=yes`).
+    #[arg(
+        long,
+        value_name = "yes|no",
+        default_value = "no",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "yes",
+        action = clap::ArgAction::Set,
+        value_parser = clap::builder::BoolishValueParser::new(),
+        hide_possible_values = true
+    )]
+    pub cache_sim: bool,
+    /// Simulate the branch predictors, recording their mispredictions as events
+    /// (`--branch-sim=yes`).
+    #[arg(
+        long,
+        value_name = "yes|no",
+        default_value = "no",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "yes",
+        action = clap::ArgAction::Set,
+        value_parser = clap::builder::BoolishValueParser::new(),
+        hide_possible_values = true
+    )]
+    pub branch_sim: bool,
+    /// Toggle the collection of costs on entering and leaving the functions matching the pattern
+    /// (e.g. `bench::*`). May be given multiple times.
+    #[arg(long)]
+    pub toggle_collect: Vec<String>,
+    /// Whether to collect costs from the start of the program. With `--collect-atstart=no`, only
+    /// the costs within the functions of `--toggle-collect` are collected.
+    #[arg(
+        long,
+        value_name = "yes|no",
+        default_value = "yes",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "yes",
+        action = clap::ArgAction::Set,
+        value_parser = clap::builder::BoolishValueParser::new(),
+        hide_possible_values = true
+    )]
+    pub collect_atstart: bool,
+}
+
+impl Default for CallgrindOptions {
+    /// The defaults of callgrind: no simulation, costs collected from the start.
+    fn default() -> Self {
+        Self {
+            cache_sim: false,
+            branch_sim: false,
+            toggle_collect: vec![],
+            collect_atstart: true,
+        }
+    }
+}
+
+/// A range of runs of the history, from the oldest to the most recent (e.g. `v1.0..v1.4` or
+/// `3..7`).
+///
+/// Each end is either the name of a run or its id in the history (see `history list`), and is
+/// included in the range. An end may be left out to start from the oldest run (`..v1.4`) or to
+/// end at the most recent one (`v1.0..`). A single run is written without `..`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RunRange {
+    /// The first run of the range, or `None` to start from the oldest run.
+    pub first: Option<String>,
+    /// The last run of the range, or `None` to end at the most recent run.
+    pub last: Option<String>,
+}
+
+impl FromStr for RunRange {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let end = |end: &str| (!end.is_empty()).then(|| end.to_string());
+        match s.split_once("..") {
+            Some((first, last)) => Ok(Self {
+                first: end(first),
+                last: end(last),
+            }),
+            None if s.is_empty() => bail!("Empty run range"),
+            None => Ok(Self {
+                first: end(s),
+                last: end(s),
+            }),
+        }
+    }
+}
+
+impl Display for RunRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (&self.first, &self.last) {
+            (Some(first), Some(last)) if first == last => write!(f, "{first}"),
+            (first, last) => write!(
+                f,
+                "{}..{}",
+                first.as_deref().unwrap_or_default(),
+                last.as_deref().unwrap_or_default()
+            ),
+        }
+    }
+}
+
 /// A tool to help keep track of performance changes over time.
 #[derive(Parser, Debug)]
-#[command()]
+#[command(args_override_self = true)]
+#[allow(clippy::struct_excessive_bools)]
 pub struct Args {
     /// Show all lines, even those without a change.
-    #[arg(short, long, default_value_t = false)]
+    #[arg(short, long, default_value_t = false, global = true)]
     pub all: bool,
     /// Whether the output should be colored or not.
     ///
@@ -254,8 +891,16 @@ pub struct Args {
     ///  * `always`: The output will always be colored (default)
     ///  * `default`: The output is colored only if the output is a tty
     ///  * `never`: The output is never colored
-    #[arg(short, long, default_value = "always")]
+    #[arg(short, long, default_value = "always", global = true)]
     pub color: Color,
+    /// How to render the differences on the standard output.
+    ///
+    /// Accepted values are:
+    ///  * `table`: A table of every run (default)
+    ///  * `markdown`: The report of `--export-pr-comment`
+    ///  * `json`: The JSON summary of the last run, as printed by `pr-compare`
+    #[arg(long, default_value = "table", global = true)]
+    pub output_format: String,
     /// By which field to sort by.
     ///
     /// Accepted values are:
@@ -263,10 +908,17 @@ pub struct Args {
     ///   * `first-ir`: Sort by the instruction count of the first column.
     ///   * `last-ir`: Sort by the instruction count of the last column.
     ///   * `columnX`: With `X` a number, sort by the X-th column (0-indexed).
+    ///   * `trend`: Sort by the trend of the instruction count across runs (see `--show=trend`).
+    ///   * `impact`: Sort by the absolute difference of the instruction count of the last column
+    ///     to its reference, times the share of the total IR of the symbol, so that a small change
+    ///     of a hot symbol outranks a large change of a trivial one.
     ///
     /// Additionally, a `-` can be prepended to sort in descending order (a `+` can be prepended
     /// for ascending order, but that is already the default.
     ///
+    /// Symbols with the same instruction count are sorted by ascending symbol name, so that the
+    /// output does not change between invocations.
+    ///
     /// ```no_compile
     /// symbol        // Sort by ascending symbol (default)
     /// +symbol       // Sort by ascending symbol
@@ -274,8 +926,9 @@ pub struct Args {
     /// -first-ir     // Sort by descending ir for the first column
     /// column0       // Sort by ascending ir for the first column
     /// -column3      // Sort by descending ir for the 4th column
+    /// -impact       // Sort by descending impact, the most impactful change first
     /// ```
-    #[arg(long, default_value = "symbol")]
+    #[arg(long, default_value = "symbol", global = true)]
     pub sort_by: SortBy,
     /// Path to an output file in which to write the IR as CSV.
     #[arg(long, default_value_t)]
@@ -290,12 +943,47 @@ pub struct Args {
     /// A replacement to perform in the symbol names.
     ///
     /// The replacement has the form `foo/bar` and will replace any occurence of `foo` within the
-    /// symbol name by `bar`. This option can be repeated any number of times.
-    #[arg(long, num_args=0..)]
+    /// symbol name by `bar`. A `/` within `foo` or `bar` is written `\/` (e.g. to strip the
+    /// module paths of Python symbols). This option can be repeated any number of times.
+    #[arg(long, num_args=0.., global = true)]
     pub string_replace: Vec<StringReplacement>,
-    /// Path to an output file in which to write a graph of the IR values. Currently unsupported.
+    /// Split the IR inlined into each function into a separate `<function> (inlined)` symbol, so
+    /// that a function's own code and the code inlined into it are diffed separately.
+    ///
+    /// Only raw `callgrind.out` inputs record where code was inlined from (with their `fi=` and
+    /// `fe=` lines); this has no effect on other inputs.
+    #[arg(long, global = true)]
+    pub split_inlined: bool,
+    /// The sample type of `pprof` inputs to count, e.g. `alloc_space` for the bytes allocated by
+    /// a Go heap profile.
+    ///
+    /// Defaults to the sample type `pprof` itself shows by default (e.g. `cpu` for CPU profiles).
+    /// The other sample types are counted as events.
+    #[cfg(feature = "pprof")]
+    #[arg(long, global = true)]
+    pub pprof_sample_type: Option<String>,
+    /// The snapshots of massif outputs to load as runs: the peak snapshot of each file (`peak`),
+    /// so that files are compared like the profiles of other tools, or each detailed snapshot of
+    /// each file (`all`), to see how heap usage evolves during a run.
+    #[arg(long, default_value = "peak", global = true)]
+    pub massif_snapshots: MassifSnapshots,
+    /// Path to an output file in which to write an SVG graph of the IR values.
+    ///
+    /// The graph plots the change of the total IR and of that of the 5 symbols with the highest IR
+    /// in the last column. When every run has a timestamp (e.g. runs of the history), runs are
+    /// placed by date and long periods without measurements are dashed.
     #[arg(long, default_value_t)]
     pub export_graph: String,
+    /// Path to an output file in which to write the changes of the last column compared to its
+    /// reference as a callgrind profile, to explore them in `kcachegrind` or `qcachegrind`.
+    ///
+    /// Counts cannot be negative in a callgrind profile: each event is split into its increase
+    /// (e.g. `IrInc`) and its decrease (e.g. `IrDec`), both positive. The profile is flat: it
+    /// holds the self cost of each symbol whose counts changed, all in an unknown file (`fl=???`),
+    /// but no call (`cfn=` and `calls=` lines), as runs do not keep the call graph of their
+    /// inputs. Inclusive costs and caller/callee views are thus empty.
+    #[arg(long, default_value_t)]
+    pub export_callgrind_diff: String,
     /// The column which is the reference for IR. Other columns have diffs relative to it.
     ///
     /// Accepted values are:
@@ -303,20 +991,407 @@ pub struct Args {
     ///   * `last`: Differences are shown relative to the last column.
     ///   * `previous`: Differences are shown relative to the column preceding it.
     ///   * `columnX`: With `X` a number, relative to the X-th column (0-indexed).
+    ///   * `rolling:N`: With `N` a number, relative to the mean of the N columns preceding it
+    ///     (or fewer, for the first columns).
+    ///   * `pinned`: Relative to the most recent run pinned in the history (see `history pin`).
     #[arg(long, default_value = "first")]
     pub relative_to: RelativeTo,
     /// A comma-separated list of what to show for each column of data.
     ///
     /// Accepted values are:
     ///   * `ircount`: The IR count.
-    ///   * `percentagediff`: The percentage/ratio of ir count with respect to [`relative_to`].
-    ///   * `ircountdiff`: The IR count difference with respect to [`relative_to`].
+    ///   * `percentagediff`: The percentage/ratio of ir count with respect to [`relative_to`](Self::relative_to).
+    ///   * `ircountdiff`: The IR count difference with respect to [`relative_to`](Self::relative_to).
+    ///   * `significance`: `*` if the IR count difference is larger than `--significance-sigmas`
+    ///     times the pooled standard deviation of the samples (see `--merge`), `~` if it is not.
+    ///   * `acceleration`: With `--relative-to=previous`, the IR count difference minus that of the
+    ///     previous column, which tells whether a regression is accelerating (`+`) or recovering
+    ///     (`-`).
     ///   * `all`: `ircountdiff` + `percentagediff` + `ircount`
+    ///   * `stats`: The minimum, maximum, mean and standard deviation of the IR count across all
+    ///     runs, shown after the columns of the runs. This adds to the other values.
+    ///   * `trend`: The slope of the linear regression of the IR count across all runs (in IR per
+    ///     run) and its direction, shown after the statistics. This adds to the other values.
+    ///   * `cumulative`: The change of the IR count from the first run to the last, regardless of
+    ///     [`relative_to`](Self::relative_to), shown last. This adds to the other values.
+    ///   * `stability`: A mark of how much the IR count varies across all runs, shown before the
+    ///     name of the symbol: `●` if its coefficient of variation is below 1%, `◐` if it is below
+    ///     10% and `○` otherwise. This tells which rows to trust before interpreting small
+    ///     differences. This adds to the other values.
+    ///   * `rank`: The rank of the symbol by IR count within its run (`#1` being the hottest), and
+    ///     the number of places it climbed (`+`) or fell (`-`) with respect to
+    ///     [`relative_to`](Self::relative_to). This adds to the other values, in each column.
+    ///   * `d1-miss-rate`: The rate of data reads and writes which missed the D1 cache
+    ///     (`(D1mr + D1mw) / (Dr + Dw)`), and its difference in percentage points with respect to
+    ///     [`relative_to`](Self::relative_to). This adds to the other values, in each column.
+    ///   * `ll-miss-rate`: Likewise, the rate of accesses which missed the LL cache
+    ///     (`(ILmr + DLmr + DLmw) / (Ir + Dr + Dw)`).
+    ///   * `branch-miss-rate`: Likewise, the rate of mispredicted branches
+    ///     (`(Bcm + Bim) / (Bc + Bi)`).
+    ///
+    /// Rates require the inputs to show the events they are computed from (e.g.
+    /// `callgrind_annotate --show=Ir,Dr,Dw,D1mr,D1mw`).
     ///
     /// Any value re-specified will be ignored. `all` has precedence. To show all columns in a
     /// different order than `all`, specify each column individually but not `all`.
-    #[arg(long, num_args=0.., value_delimiter=',')]
+    #[arg(long, num_args=0.., value_delimiter=',', global = true)]
     pub show: Vec<Show>,
+    /// Path to a history store directory.
+    ///
+    /// When set, every run loaded from a `callgrind_annotate` file is appended to the store,
+    /// unless `--read-only` is given. The store is created if it doesn't exist. Runs can also be
+    /// appended with `add`, and the history rendered with `show`.
+    #[arg(long, default_value_t, global = true)]
+    pub history: String,
+    /// Compare the inputs without appending them to the store of `--history`, which is only read
+    /// (e.g. for `--compare-ref`), so that the same inputs can be compared again.
+    ///
+    /// This is implied by `--preset`.
+    #[arg(long, default_value_t = false, global = true)]
+    pub read_only: bool,
+    /// The target whose history is used in the store of `--history` (e.g. `parser_bench`), so
+    /// that a single store holds a history per benchmark or binary.
+    ///
+    /// Without it, the runs are those of the store itself, and `show` lists the targets of the
+    /// store, if any.
+    #[arg(long, default_value_t, global = true)]
+    pub target: String,
+    /// What to do when a file is appended to the store of `--history` while a run from a file
+    /// with the same SHA-256 already is (e.g. when a CI job is retried): fail (`refuse`), leave
+    /// that run out (`skip`) or append it anyway (`allow`).
+    #[arg(long, default_value = "refuse", global = true)]
+    pub on_duplicate: OnDuplicate,
+    /// A preset of arguments declared in the `config` file of the store of `--history` (e.g.
+    /// `preset.release-check: --relative-to=pinned --fail-on-regression=2%`), so that a whole
+    /// team runs identical comparisons.
+    ///
+    /// The arguments of the preset come before those of the command line: options given on the
+    /// command line replace those of the preset, and add to its lists (e.g. `--show`). The inputs
+    /// are not appended to the store (see `--read-only`).
+    #[arg(long, default_value_t, global = true)]
+    pub preset: String,
+    /// Stamp the runs loaded from `callgrind_annotate` files with the commit hash, branch and tag
+    /// (if `HEAD` is tagged) of the git repository in the current directory.
+    #[arg(long, default_value_t = false, global = true)]
+    pub tag_from_git: bool,
+    /// Metadata about the workload of a run (e.g. `iterations=1000`). This option can be repeated,
+    /// once per run, in the order the runs are loaded.
+    ///
+    /// The metadata is stored along with the run in the history. See `--per`.
+    #[arg(long, value_name = "KEY=VALUE")]
+    pub run_meta: Vec<RunMeta>,
+    /// Divide all the counts of each run by the given metadata of the run before diffing, so that
+    /// runs of workloads of different sizes remain comparable.
+    ///
+    /// With `iterations`, counts are those of a single iteration of the workload, rounded to the
+    /// nearest integer. Every run must have an iteration count, given with `--run-meta` or stored
+    /// in the history.
+    #[arg(long)]
+    pub per: Option<Per>,
+    /// A git ref (branch name or commit hash prefix) whose most recent run in the history is
+    /// used as the reference column.
+    ///
+    /// The run is inserted as the first column and overrides `--relative-to`. Requires
+    /// `--history`.
+    #[arg(long, default_value_t)]
+    pub compare_ref: String,
+    /// Warn about symbols of the last column whose IR increased by more than the given
+    /// percentage with respect to their reference (e.g. `1%`).
+    #[arg(long, global = true)]
+    pub warn_on_regression: Option<Percentage>,
+    /// Fail if a symbol of the last column has its IR increased by more than the given
+    /// percentage with respect to its reference (e.g. `5%`).
+    ///
+    /// The percentage may be qualified with an event shown in the inputs to gate on that event
+    /// instead of the IR (e.g. `LLd=2%` for last-level data cache misses, `Bcm=1%` for
+    /// mispredicted conditional branches). This option can be repeated, once per event. If given
+    /// multiple times for the same event, the last one wins.
+    #[arg(long, global = true)]
+    pub fail_on_regression: Vec<EventThreshold>,
+    /// Fail if no symbol matching the given pattern was hit in the last column, or if one that was
+    /// hit in the reference was not (e.g. a SIMD kernel that must stay present).
+    ///
+    /// `*` and `?` wildcards are accepted. This option can be repeated any number of times.
+    #[arg(long, global = true)]
+    pub fail_on_missing_symbol: Vec<String>,
+    /// Path to a rules file mapping symbol patterns to the regression they are allowed.
+    ///
+    /// Each line is a pattern (`*` and `?` wildcards are accepted) followed by a percentage, e.g.
+    /// `parser::* 1%`. The first matching rule applies to a symbol and overrides
+    /// `--fail-on-regression`. Lines starting with `#` are comments.
+    #[arg(long, default_value_t, global = true)]
+    pub regression_rules: String,
+    /// Load the inputs from the manifest of a benchmark suite instead of the positional arguments.
+    ///
+    /// Each line is a benchmark, a version and a `callgrind_annotate` file, optionally followed by
+    /// the weight of the benchmark, e.g. `parse v1.3 bench/parse-v1.3.cg 2`. Files of the same
+    /// benchmark and version are merged. After the table, the ratio of the total IR of each
+    /// version to the first one is printed for each benchmark on the standard error, along with
+    /// their geometric mean (and weighted geometric mean, if weights are given).
+    #[arg(long, value_name = "MANIFEST", default_value_t)]
+    pub suite: String,
+    /// Warn if the symbols of a run account for less than its total IR by more than the given
+    /// percentage.
+    ///
+    /// This happens when `callgrind_annotate` omits symbols below its `--threshold`, in which case
+    /// the table is incomplete.
+    #[arg(long, default_value = "5%", value_name = "PERCENT", global = true)]
+    pub max_unaccounted: Percentage,
+    /// Fail if the total IR of the last column exceeds the given instruction count (e.g. `1.2e9`).
+    #[arg(long, value_name = "IR", global = true)]
+    pub total_budget: Option<IrCount>,
+    /// Print how far the total IR of each run is from `--total-budget`, as a chart, and the run at
+    /// which the budget will be exceeded if the trend of the total IR goes on, on the standard
+    /// error.
+    #[arg(long, default_value_t = false)]
+    pub burn_down: bool,
+    /// Refuse `callgrind_annotate` inputs larger than the given size (e.g. `512M`).
+    ///
+    /// Inputs are read as a stream, and reading stops as soon as the limit is exceeded, so that a
+    /// malformed or adversarial input does not hang the job.
+    #[arg(long, value_name = "SIZE", global = true)]
+    pub max_input_size: Option<ByteSize>,
+    /// Refuse `callgrind_annotate` inputs with more than the given number of symbols.
+    #[arg(long, value_name = "COUNT", global = true)]
+    pub max_symbols: Option<usize>,
+    /// Print a single machine-readable status line at the end, e.g.
+    /// `callgrind_differ: status=regression-detected code=3`.
+    ///
+    /// The exit codes are:
+    ///   * 0 (`ok`): No error.
+    ///   * 1 (`usage-error`): Invalid command line, configuration (e.g. `--regression-rules`) or
+    ///     history (e.g. a duplicate run, or an unknown `--compare-ref`), or any other error.
+    ///   * 2 (`parse-error`): An input could not be read or parsed.
+    ///   * 3 (`regression-detected`): A symbol regressed beyond its allowed threshold, or a
+    ///     required symbol is missing.
+    ///   * 4 (`budget-violated`): The total IR exceeded `--total-budget`.
+    #[arg(long, default_value_t = false, global = true)]
+    pub report_status: bool,
+    /// Merge every `N` consecutive `callgrind_annotate` files into a single column.
+    ///
+    /// This is meant for repeated runs of the same binary. The IR count of a symbol in the merged
+    /// column is its mean IR count across the files, and its standard deviation is used as a
+    /// noise band (see `--noise-band`).
+    #[arg(long, default_value_t = 1)]
+    pub merge: u32,
+    /// The width of the noise band, in standard deviations.
+    ///
+    /// Changes between merged columns which are within `SIGMAS` times their combined standard
+    /// deviation are considered noise: they are not highlighted and do not trigger
+    /// `--warn-on-regression`, `--fail-on-regression` or the regression rules.
+    #[arg(long, default_value_t = 2.0, value_name = "SIGMAS", global = true)]
+    pub noise_band: f64,
+    /// Read the noise of each symbol from a file written by `calibrate` (or from
+    /// `callgrind-noise-floor.txt` if `auto` is given).
+    ///
+    /// Changes which are no larger than the spread of their symbol across the calibration runs,
+    /// or than `--noise-band` times its standard deviation, are considered noise, as those within
+    /// the noise band are.
+    #[arg(long, value_name = "FILE", default_value_t)]
+    pub noise_floor: String,
+    /// The multiple of the pooled standard deviation of the samples of a column and of its
+    /// reference beyond which a change is significant (see `--show=significance`).
+    ///
+    /// Columns which were not merged from multiple samples have a standard deviation of 0, so that
+    /// any change between them is significant.
+    #[arg(long, default_value_t = 2.0, value_name = "SIGMAS", global = true)]
+    pub significance_sigmas: f64,
+    /// The deviation, in standard deviations of the other samples, beyond which a sample merged
+    /// with `--merge` is an outlier for a symbol.
+    ///
+    /// Outliers are reported on the standard error. Each sample is compared to the other samples of
+    /// the same column, so that at least 3 samples are needed.
+    #[arg(long, default_value_t = 3.0, value_name = "SIGMAS")]
+    pub outlier_sigmas: f64,
+    /// Leave the samples which are outliers for any symbol out of the merged columns (see
+    /// `--outlier-sigmas`).
+    ///
+    /// If every sample of a column is an outlier, they are all kept.
+    #[arg(long, default_value_t = false)]
+    pub drop_outliers: bool,
+    /// Path to an output file in which to write a Markdown report meant for a pull request
+    /// comment.
+    ///
+    /// The report compares the last column to its reference. It contains a hidden block with the
+    /// headline numbers (one `key: value` per line) for machine consumption.
+    #[arg(long, default_value_t, global = true)]
+    pub export_pr_comment: String,
+    /// Path to an output file in which to write a JSON report of the evaluation of the
+    /// thresholds and budgets.
+    ///
+    /// For each rule of `--regression-rules`, threshold of `--fail-on-regression`, pattern of
+    /// `--fail-on-missing-symbol` and `--total-budget`, the report lists the symbols it applied
+    /// to, their measured and allowed values, and whether the check passed.
+    #[arg(long, default_value_t, global = true)]
+    pub export_policy_report: String,
+    /// A token to embed in the pull request comment as a hidden marker
+    /// (`<!-- callgrind_differ:<token> -->`).
+    ///
+    /// This allows a bot to find the comment it previously posted and update it instead of
+    /// posting a new one.
+    #[arg(long, global = true)]
+    pub comment_marker: Option<String>,
+    /// A URL to which a JSON summary of the comparison of the last column to its reference is
+    /// posted (e.g. a Bencher or custom dashboard ingestion endpoint).
+    ///
+    /// The request is performed with `curl`. If the `CALLGRIND_DIFFER_TOKEN` environment variable
+    /// is set, it is sent as a bearer token.
+    #[cfg(feature = "push")]
+    #[arg(long, default_value_t, global = true)]
+    pub push_to: String,
+    /// The URL of a Prometheus pushgateway (e.g. `http://pushgateway:9091`) to which the total
+    /// IR and the IR of the 10 symbols with the highest IR in the last column are pushed as
+    /// gauges.
+    ///
+    /// The gauges are labeled with the name of the run and its commit, if known, and replace the
+    /// metrics of the `callgrind_differ` job, so that the pushgateway always holds those of the
+    /// latest run. Prometheus then records their history as it scrapes them. As with `--push-to`,
+    /// the request is performed with `curl` and sends the `CALLGRIND_DIFFER_TOKEN`, if set.
+    #[cfg(feature = "push")]
+    #[arg(long, default_value_t, global = true)]
+    pub push_prometheus: String,
+    /// Report regressions as GitHub Actions workflow commands (`::warning` and `::error`).
+    ///
+    /// Regressions beyond `--warn-on-regression` are reported as warnings, and those beyond
+    /// `--fail-on-regression` as errors. They then show up inline in the Actions UI.
+    #[arg(long, default_value_t = false, global = true)]
+    pub github_annotations: bool,
+    /// Path to an output file in which to write the regressions as a GitLab code quality
+    /// report, so that they show up in the widget of merge requests.
+    ///
+    /// Each regression is an issue: `minor` beyond `--warn-on-regression` and `major` beyond
+    /// `--fail-on-regression`. As symbols have no location in the sources, issues are located at
+    /// the first line of the last input (or of the history store). Their fingerprint only depends
+    /// on the symbol and the event, so that GitLab tells new regressions from those of the target
+    /// branch.
+    #[cfg(feature = "gitlab")]
+    #[arg(long, default_value_t, global = true)]
+    pub export_gitlab_codequality: String,
+    /// Print statistics about each stage on the standard error.
+    ///
+    /// These are the time taken to parse each input and the number of symbols it has, the number
+    /// of symbols of merged runs and of the records, the time taken to render the table and the
+    /// peak memory used.
+    #[arg(long, default_value_t = false, global = true)]
+    pub stats: bool,
+    /// List the runs at which the total IR or the IR of a symbol jumped up by more than the given
+    /// percentage (5% if only the flag is given), on the standard error.
+    ///
+    /// The IR counts of each series are split into segments of similar counts, so that jumps are
+    /// told apart from noise, and each jump is reported with the runs (and their commits) it
+    /// happened between. This is meant for a history of runs, in chronological order.
+    #[arg(
+        long,
+        value_name = "PERCENT",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "5%"
+    )]
+    pub find_regressions: Option<Percentage>,
+    /// Add an estimated cycles event (`CEst`) to the inputs, computed from the instruction count,
+    /// the cache misses and the branch mispredictions with the given weights.
+    ///
+    /// The weights are a comma-separated list of `ir`, `l1`, `ll` and `bm` weights, e.g.
+    /// `ir=1,l1=10,ll=100,bm=15`. Those which are not given (or all of them, if only the flag is
+    /// given) default to `ir=1,l1=10,ll=100,bm=10`. Events which the inputs do not show count as
+    /// 0. `CEst` is then checked like any other event (e.g. `--fail-on-regression=CEst=2%`),
+    /// which makes regressions of memory-bound code visible.
+    #[arg(
+        long,
+        value_name = "WEIGHTS",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = ""
+    )]
+    pub cycle_model: Option<CycleModel>,
+    /// Diff the counts of the given event (e.g. `D1mr`, or `CEst` with `--cycle-model`) instead
+    /// of the instruction count, e.g. to check whether cache misses regressed over a history.
+    ///
+    /// The table (including its `Total IR` row) and the thresholds of the instruction count then
+    /// apply to the counts of the event, and the instruction count becomes an `Ir` event in its
+    /// place.
+    #[arg(long, default_value_t, global = true)]
+    pub event: String,
+    /// Break down the change of the total IR of the last column to its reference by symbol, on the
+    /// standard error.
+    ///
+    /// Symbols are listed from the largest change to the smallest, with their share of the change
+    /// of the total IR and the cumulative share of the symbols listed so far, so that the few
+    /// symbols accounting for most of a regression stand out. Symbols which appeared or vanished
+    /// are listed with their whole count, and the part of the change the symbols do not account
+    /// for (e.g. below the threshold of `callgrind_annotate`) last, so that the shares sum to
+    /// 100%.
+    #[arg(long, default_value_t = false)]
+    pub explain_total: bool,
+    /// Print a histogram of the changes of the IR counts of the symbols in the last column to its
+    /// reference, on the standard error.
+    ///
+    /// Symbols are counted by the percentage of their change, in buckets of `< -10%`, `-10..-1%`,
+    /// `±1%`, `+1..+10%` and `> +10%`, which tells whether a change is broad or localized.
+    #[arg(long, default_value_t = false)]
+    pub histogram: bool,
+    /// List, for each pair of adjacent runs, the given number of symbols (5 if only the flag is
+    /// given) whose IR count changed the most, on the standard error.
+    ///
+    /// Changes are those from each run to the next, regardless of `--relative-to`, which makes
+    /// for a summary of the history, from the first run to the last.
+    #[arg(
+        long,
+        value_name = "K",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "5"
+    )]
+    pub movers: Option<usize>,
+    /// List, for each symbol, the first and last run in which it had a non-zero IR count, on the
+    /// standard error.
+    ///
+    /// Symbols which appeared or vanished within the given number of latest runs (5 if only the
+    /// flag is given) are marked as such, which catches functions that were accidentally no
+    /// longer inlined, or that were eliminated as dead code, across a long history.
+    #[arg(
+        long,
+        value_name = "N",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "5"
+    )]
+    pub lifetimes: Option<usize>,
+    /// Show one row per crate instead of one per symbol, with the IR counts of the symbols of
+    /// the crate summed.
+    ///
+    /// The crate of a symbol is the first segment of its path. Symbols without a path (e.g. C
+    /// functions) are grouped under `(no crate)`. Thresholds, exports and analyses then apply to
+    /// the crates, which gives a module-level overview of where a regression lives.
+    #[arg(long, default_value_t = false)]
+    pub group_summary: bool,
+    /// A comma-separated list of the indices of the runs of the control group (e.g. `0,1,2`),
+    /// compared to those of `--treatment`.
+    ///
+    /// The runs of each group are aggregated into a single `control` or `treatment` run, where
+    /// the IR count of each symbol is its mean across the runs of the group, and their standard
+    /// deviation is kept as with `--merge` (see `--show=significance`). The table then has a
+    /// single diff between the groups rather than one per run, summarized on the standard error.
+    #[arg(long, value_name = "RUNS", value_delimiter = ',')]
+    pub control: Vec<usize>,
+    /// A comma-separated list of the indices of the runs of the treatment group (e.g. `3,4,5`).
+    /// See `--control`.
+    #[arg(long, value_name = "RUNS", value_delimiter = ',')]
+    pub treatment: Vec<usize>,
+    /// Only show the smallest set of symbols which together account for the given percentage of
+    /// the total IR of the last column (e.g. `95%`), the hottest first, and merge the others into
+    /// a single `(remainder)` row.
+    ///
+    /// This tells where optimization efforts should go.
+    #[arg(long, value_name = "PERCENT")]
+    pub pareto: Option<Percentage>,
+    /// Rank the other symbols by how strongly their IR count correlates with that of the given
+    /// symbol across runs, on the standard error.
+    ///
+    /// This is the Pearson correlation coefficient, from -1 to 1. Symbols moving against the given
+    /// one (e.g. because of inlining shifts) rank as high as those moving with it.
+    #[arg(long, value_name = "SYMBOL", default_value_t)]
+    pub correlate: String,
     /// `callgrind_annotate` files or CSV file. Positional arguments.
     ///
     /// If the file name ends with `.csv` (case-insensitive), then the argument will be interpreted
@@ -330,32 +1405,457 @@ pub struct Args {
     /// (`callgrind_annotate`), then a CSV and then another run. The columns of the CSV file will
     /// be surrounded by the columns of the runs.
     pub inputs: Vec<String>,
+    /// A subcommand. If none is given, the inputs are compared.
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+/// The subcommands of the program.
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Compare the run of a pull request to that of its base.
+    ///
+    /// Exactly two runs are loaded and the head is compared to the base (as with
+    /// `--relative-to=first`). The CI thresholds are applied (defaulting to
+    /// `--warn-on-regression=1%` and `--fail-on-regression=5%` if no threshold is given), and both
+    /// the table and the JSON summary are printed.
+    PrCompare {
+        /// The base run: either a `callgrind_annotate` file or a git ref to look up in the
+        /// history.
+        #[arg(long)]
+        base: String,
+        /// The head run: either a `callgrind_annotate` file or a git ref to look up in the
+        /// history.
+        #[arg(long)]
+        head: String,
+    },
+    /// Measure the noise of the IR count of each symbol over repeated runs of the same binary.
+    ///
+    /// The standard deviation and the spread (the difference between the highest and the lowest
+    /// count) of each symbol across the runs are written to a file, which `--noise-floor` later
+    /// reads.
+    Calibrate {
+        /// The file to write the noise floor to.
+        #[arg(long, default_value = DEFAULT_NOISE_FLOOR_FILE)]
+        output: String,
+        /// The repeated runs, as for the inputs of a comparison.
+        #[arg(required = true)]
+        runs: Vec<String>,
+    },
+    /// Append a run to the history store given with `--history`.
+    ///
+    /// The run is stamped with the current time, its `--run-meta` and, with `--tag-from-git`,
+    /// the state of the git repository in the current directory.
+    Add {
+        /// The file holding the run, in any input format that holds a single run.
+        file: String,
+        /// The name of the run (e.g. `v1.4`). If not given, the name is derived as per
+        /// `--name-from`.
+        #[arg(long, default_value_t)]
+        name: String,
+        /// Where to derive the name of the run from if `--name` is not given: the name of the
+        /// file (`file`), the date it was last modified (`mtime`) or the command line of the
+        /// profiled program, as recorded by callgrind (`cmd`).
+        #[arg(long, default_value = "file")]
+        name_from: NameSource,
+    },
+    /// Run a program under `valgrind --tool=callgrind` and append its run to the history store
+    /// given with `--history`, as `add` does.
+    ///
+    /// The options of callgrind given to `profile` are passed through, and recorded as the
+    /// simulation options of the run: comparing runs collected with different options warns about
+    /// it. valgrind is looked up in the `PATH`, unless the `VALGRIND` environment variable names
+    /// it.
+    Profile {
+        /// The options passed through to callgrind.
+        #[command(flatten)]
+        callgrind: CallgrindOptions,
+        /// The name of the run (e.g. `v1.4`). If not given, the run is named after the command
+        /// line of the program.
+        #[arg(long, default_value_t)]
+        name: String,
+        /// The file to write the `callgrind.out` file of the run to. By default, it is written to a
+        /// temporary file, removed once the run is appended to the history.
+        #[arg(long, default_value_t)]
+        out_file: String,
+        /// The program to profile and its arguments, after `--`.
+        #[arg(required = true, last = true)]
+        program: Vec<String>,
+    },
+    /// Render the runs of the history store given with `--history`, from the oldest to the most
+    /// recent, as if they were the inputs of a comparison (see `history show` to render some of
+    /// them).
+    ///
+    /// Without `--target`, the targets of the store are listed instead, if it has any.
+    Show,
+    /// Manage the history store given with `--history`.
+    History {
+        /// What to do with the history.
+        #[command(subcommand)]
+        command: HistoryCommand,
+    },
+}
+
+/// The subcommands of `history`.
+#[derive(Subcommand, Debug)]
+pub enum HistoryCommand {
+    /// List the runs of the history, with their id, name, date, commit, total IR and note.
+    List,
+    /// Render the runs of the given range of the history, as if they were the inputs of a
+    /// comparison.
+    Show {
+        /// The runs to render (e.g. `v1.0..v1.4`, `3..7` or `v1.2..`). All runs by default.
+        #[arg(default_value_t)]
+        runs: RunRange,
+    },
+    /// Compare the most recent run of the history to that of another store (e.g. measured on
+    /// another machine or branch), as if they were the inputs of a comparison.
+    ///
+    /// The history is the reference. With `--target`, the runs of the target are compared.
+    Diff {
+        /// The path to the other store.
+        store: String,
+    },
+    /// Render the runs of the history as a static HTML site, publishable as is (e.g. to GitHub
+    /// Pages from CI): the trend of the total IR and of the heaviest symbols, the regressions and
+    /// changes of the latest run compared to its reference, and a page per symbol with the graph
+    /// of its IR.
+    ///
+    /// The table of the runs is printed as with `history show`, and regressions fail as they do.
+    #[cfg(feature = "dashboard")]
+    Dashboard {
+        /// The directory to write the site to. It is created if it doesn't exist.
+        out_dir: String,
+        /// The runs to render (e.g. `v1.0..v1.4`, `3..7` or `v1.2..`). All runs by default.
+        #[arg(long, default_value_t)]
+        runs: RunRange,
+    },
+    /// Append the runs of another store (e.g. collected on another machine) to the history,
+    /// tagging each with its origin, so that runs from several machines can be tracked in one
+    /// place.
+    ///
+    /// The runs are appended in order, after those of the history, and are not pinned. Runs that
+    /// were already merged keep their origin. With `--target`, the runs of the target are merged.
+    /// Rendering runs of different origins warns that they were measured on different machines.
+    Merge {
+        /// The path to the other store.
+        store: String,
+        /// The origin of the merged runs (e.g. `ci-runner-2`). The name of the directory of the
+        /// other store by default.
+        #[arg(long, default_value_t)]
+        origin: String,
+        /// Keep the merged runs in a lane of their own, the history of the target named after
+        /// their origin (or `<target>@<origin>` with `--target`), rather than among the runs of
+        /// the history, so that comparisons across machines are explicit.
+        #[arg(long, default_value_t = false)]
+        lane: bool,
+    },
+    /// Append the runs of a file to the history, in order (e.g. the columns of a CSV, with or
+    /// without a header naming them).
+    Import {
+        /// The file holding the runs, in any input format.
+        file: String,
+    },
+    /// Write the runs of the given range of the history to a file, e.g. for tools which consume
+    /// CSV exports.
+    ///
+    /// Runs with no name are named `#<id>`. The file can be imported back with `history import`.
+    Export {
+        /// The runs to export (e.g. `v1.0..v1.5`, `3..7` or `v1.2..`). All runs by default.
+        #[arg(long, default_value_t)]
+        runs: RunRange,
+        /// The format of the file.
+        #[arg(long, default_value = "csv")]
+        format: ExportFormat,
+        /// The file to write the runs to.
+        file: String,
+    },
+    /// Find the first run of the history whose IR count of a symbol (or total IR count) regressed
+    /// beyond a threshold relative to the first run, and print its git metadata along with that
+    /// of the run before it, e.g. to start a `git bisect` between them.
+    Bisect {
+        /// The symbol whose IR count is followed. The total IR count by default.
+        #[arg(long, default_value_t)]
+        symbol: String,
+        /// How much the IR count may increase relative to the first run before regressing (e.g.
+        /// `5%`).
+        #[arg(long)]
+        threshold: Percentage,
+        /// The runs to walk (e.g. `v1.0..v1.5`, `3..7` or `v1.2..`). All runs by default.
+        #[arg(long, default_value_t)]
+        runs: RunRange,
+    },
+    /// Remove runs from the history (e.g. a run that was mis-measured). Pinned runs cannot be
+    /// removed.
+    Remove {
+        /// The runs to remove (e.g. `v1.3`, `7` or `3..5`).
+        runs: RunRange,
+    },
+    /// Set a free-text note on a run of the history (e.g. what changed), shown by `history list`,
+    /// in the JSON summary and in the PR comment.
+    Annotate {
+        /// The run to annotate, by name or id.
+        run: String,
+        /// The note. An empty note removes the note of the run.
+        note: String,
+    },
+    /// Pin a run of the history as a baseline: it is never pruned, removed or renamed, and other
+    /// runs can be compared to it with `--relative-to=pinned`.
+    Pin {
+        /// The run to pin, by name or id.
+        run: String,
+    },
+    /// Unpin a run of the history (see `history pin`).
+    Unpin {
+        /// The run to unpin, by name or id.
+        run: String,
+    },
+    /// Rename a run of the history. Pinned runs cannot be renamed.
+    Rename {
+        /// The run to rename, by name or id.
+        run: String,
+        /// The new name of the run.
+        name: String,
+    },
+    /// Remove old runs from the history. Pinned runs are kept, regardless of their age.
+    Prune {
+        /// Keep the given number of most recent runs.
+        #[arg(long)]
+        keep_last: usize,
+        /// Also keep older runs that were measured on a git tag (see `--tag-from-git`).
+        #[arg(long, default_value_t = false)]
+        keep_tagged: bool,
+        /// List the runs which would be removed, without removing them.
+        #[arg(long, default_value_t = false)]
+        dry_run: bool,
+    },
+    /// Check the history for inconsistencies: unreadable or stale files and runs whose counts
+    /// contradict their header. Fails if any is left. Runs loaded twice from the same file (e.g.
+    /// with `--on-duplicate=allow`) are noted, but do not make it fail.
+    Fsck {
+        /// Repair the inconsistencies which can be: remove stale files and rename unreadable run
+        /// files with a `.corrupt` extension, so that the rest of the history can be read.
+        #[arg(long, default_value_t = false)]
+        repair: bool,
+    },
+    /// Record the symbol renames of an alias file in the history, one `<old name> => <new name>`
+    /// per line, and re-key its runs once, so that a renamed symbol shows as a single series.
+    /// Runs appended later are re-keyed as well.
+    Alias {
+        /// The alias file. Without one, list the renames recorded in the history.
+        file: Option<String>,
+    },
+    /// Move old runs of the history into a single file holding the IR count of each symbol in
+    /// all of them, so that rendering recent runs does not read a file per run ever recorded.
+    /// Compacted runs are still listed, shown, pinned, removed and pruned as other runs are.
+    Compact {
+        /// Keep the given number of most recent runs in files of their own.
+        #[arg(long, default_value_t = 10)]
+        keep_recent: usize,
+    },
 }
 
 impl Args {
+    /// Expand `--preset`, if any, by parsing `args` (the command line these arguments were parsed
+    /// from) again, with the arguments of the preset inserted after the name of the program. The
+    /// expanded arguments are `--read-only`.
+    ///
+    /// # Errors
+    /// Returns an error if `--preset` is given without `--history`, the preset is not declared or
+    /// its arguments are not well-formed.
+    pub fn with_preset<I, T>(self, args: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString>,
+    {
+        if self.preset.is_empty() {
+            return Ok(self);
+        }
+        if self.history.is_empty() {
+            bail!("`--preset` requires `--history`");
+        }
+        let history = History::open_target(&self.history, &self.target)?;
+        let Some(preset) = history
+            .presets()?
+            .into_iter()
+            .find(|preset| preset.name == self.preset)
+        else {
+            bail!(
+                "No preset {} in the configuration of {} (declare it as `preset.{}: <arguments>`)",
+                self.preset,
+                self.history,
+                self.preset
+            );
+        };
+        let mut args = args.into_iter().map(Into::into);
+        let program = args.next();
+        let mut expanded = Self::try_parse_from(
+            program
+                .into_iter()
+                .chain(preset.args.into_iter().map(OsString::from))
+                .chain(args),
+        )
+        .with_context(|| format!("Invalid arguments in preset {}", preset.name))?;
+        // Running a preset twice on the same inputs must not find them already in the history.
+        expanded.read_only = true;
+        Ok(expanded)
+    }
+
     /// Perform final check for values in the arguments.
     ///
     /// # Returns
     /// If all arguments are well-formed, returns an `Ok`. Otherwise, returns an `Err`.
+    ///
+    /// # Errors
+    /// Returns an error describing the first argument which is not well-formed.
     pub fn validated(mut self) -> Result<Self> {
-        self.check_csv_names_count()?;
-        self.check_input_length()?;
+        self.check_output_format()?;
+        if let Some(command) = &self.command {
+            self.check_command(command)?;
+        } else {
+            // Subcommands load their runs from their own arguments or from the history.
+            self.check_csv_names_count()?;
+            self.check_input_length()?;
+        }
+        self.check_merge()?;
+        self.check_suite()?;
+        self.check_history()?;
+        self.check_burn_down()?;
+        self.check_acceleration()?;
+        self.check_groups()?;
         self.sanitize_show();
         Ok(self)
     }
 
+    /// Return what to show for each column of data, i.e. `show` without what is shown once after
+    /// them (see [`Show::is_run_column`]).
+    pub fn run_columns(&self) -> impl Iterator<Item = Show> + '_ {
+        self.show
+            .iter()
+            .copied()
+            .filter(|show| show.is_run_column())
+    }
+
+    /// Return the path of the noise floor file of `--noise-floor`, if any.
+    pub fn noise_floor_path(&self) -> Option<&str> {
+        match self.noise_floor.as_str() {
+            "" => None,
+            "auto" => Some(DEFAULT_NOISE_FLOOR_FILE),
+            path => Some(path),
+        }
+    }
+
+    /// Return whether the statistics of the IR count across runs are to be shown.
+    pub fn show_stats(&self) -> bool {
+        self.show.contains(&Show::Stats)
+    }
+
+    /// Return whether the trend of the IR count across runs is to be shown.
+    pub fn show_trend(&self) -> bool {
+        self.show.contains(&Show::Trend)
+    }
+
+    /// Return the metrics to show in each column, in order.
+    pub fn metrics(&self) -> impl Iterator<Item = Metric> + '_ {
+        self.show.iter().filter_map(|show| match show {
+            Show::Metric(metric) => Some(*metric),
+            _ => None,
+        })
+    }
+
+    /// Return whether the stability of each symbol across runs is to be shown.
+    pub fn show_stability(&self) -> bool {
+        self.show.contains(&Show::Stability)
+    }
+
+    /// Return whether the rank of each symbol within its run is to be shown.
+    pub fn show_rank(&self) -> bool {
+        self.show.contains(&Show::Rank)
+    }
+
+    /// Return whether the change of the IR count from the first run to the last is to be shown.
+    pub fn show_cumulative(&self) -> bool {
+        self.show.contains(&Show::Cumulative)
+    }
+
+    /// Return the `--fail-on-regression` threshold for the instruction count, if any.
+    pub fn ir_fail_threshold(&self) -> Option<Percentage> {
+        self.fail_on_regression
+            .iter()
+            .rev()
+            .find(|threshold| threshold.is_ir())
+            .map(|threshold| threshold.threshold)
+    }
+
+    /// Return the `--fail-on-regression` thresholds for events other than the instruction count.
+    ///
+    /// Each event appears once, with the last threshold given for it.
+    pub fn event_fail_thresholds(&self) -> Vec<(&str, Percentage)> {
+        let mut thresholds: Vec<(&str, Percentage)> = vec![];
+        for threshold in &self.fail_on_regression {
+            let Some(event) = threshold.event.as_deref().filter(|_| !threshold.is_ir()) else {
+                continue;
+            };
+            if let Some(existing) = thresholds.iter_mut().find(|(name, _)| *name == event) {
+                existing.1 = threshold.threshold;
+            } else {
+                thresholds.push((event, threshold.threshold));
+            }
+        }
+        thresholds
+    }
+
+    /// Return the limits to enforce when parsing `callgrind_annotate` files.
+    pub fn input_limits(&self) -> InputLimits {
+        InputLimits {
+            max_size: self.max_input_size.map(|ByteSize(size)| size),
+            max_symbols: self.max_symbols,
+        }
+    }
+
+    /// Return the number of `callgrind_annotate` files in `inputs`.
+    fn callgrind_inputs_count(&self) -> usize {
+        let formats = InputFormats::default();
+        self.inputs
+            .iter()
+            .filter(|file| {
+                formats
+                    .find(Path::new(file))
+                    .is_some_and(|format| format.is_sample(self))
+            })
+            .count()
+    }
+
+    /// Check that the `callgrind_annotate` files can be evenly merged as per `merge`.
+    fn check_merge(&self) -> Result<()> {
+        if self.merge == 0 {
+            bail!("`--merge` must be at least 1");
+        }
+        let count = self.callgrind_inputs_count();
+        if !count.is_multiple_of(self.merge as usize) {
+            bail!(
+                "Cannot merge {count} callgrind files in groups of {}",
+                self.merge
+            );
+        }
+        if !(self.noise_band.is_finite() && self.noise_band >= 0.0) {
+            bail!("Invalid noise band: {}", self.noise_band);
+        }
+        if !(self.significance_sigmas.is_finite() && self.significance_sigmas >= 0.0) {
+            bail!("Invalid significance sigmas: {}", self.significance_sigmas);
+        }
+        if !(self.outlier_sigmas.is_finite() && self.outlier_sigmas >= 0.0) {
+            bail!("Invalid outlier sigmas: {}", self.outlier_sigmas);
+        }
+        Ok(())
+    }
+
     /// Check that the number of names in `csv_names` matches the number of runs in `inputs`.
+    ///
+    /// Files that are merged together (see `merge`) count as a single run.
     fn check_csv_names_count(&self) -> Result<()> {
         if !self.csv_names.is_empty() {
-            let runs_count = self
-                .inputs
-                .iter()
-                .filter(|file| {
-                    !Path::new(file)
-                        .extension()
-                        .map_or(false, |ext| ext.eq_ignore_ascii_case("csv"))
-                })
-                .count();
+            let runs_count = self.callgrind_inputs_count() / (self.merge as usize);
             if runs_count != self.csv_names.len() {
                 bail!("Mismatch between `csv-names` count {} and number of callgrind files {runs_count}", self.csv_names.len());
             }
@@ -367,7 +1867,26 @@ impl Args {
     ///
     /// If `All` is specified, replace with individual columns.
     /// Otherwise, remove duplicates but keep ordering of first occurence.
+    /// `Stability`, `Rank`, metrics, then `Stats`, `Trend` and `Cumulative` are moved last, in
+    /// that order, without counting as columns of the runs.
     fn sanitize_show(&mut self) {
+        let mut once = vec![];
+        for show in [Show::Stability, Show::Rank] {
+            if self.show.contains(&show) {
+                once.push(show);
+            }
+        }
+        for show in &self.show {
+            if matches!(show, Show::Metric(_)) && !once.contains(show) {
+                once.push(*show);
+            }
+        }
+        once.extend(
+            [Show::Stats, Show::Trend, Show::Cumulative]
+                .into_iter()
+                .filter(|show| self.show.contains(show)),
+        );
+        self.show.retain(|show| show.is_run_column());
         if self.show.is_empty() || self.show.iter().contains(&Show::All) {
             self.show = vec![Show::IRCountDiff, Show::PercentageDiff, Show::IRCount];
         } else {
@@ -379,11 +1898,126 @@ impl Args {
             }
             self.show = new_show;
         }
+        self.show.extend(once);
+    }
+
+    /// Make sure that the arguments of the subcommand are well-formed.
+    fn check_command(&self, command: &Command) -> Result<()> {
+        match command {
+            Command::History { .. } if self.history.is_empty() => {
+                bail!("`history` requires `--history`")
+            }
+            Command::Add { .. } if self.history.is_empty() => {
+                bail!("`add` requires `--history`")
+            }
+            Command::Profile { .. } if self.history.is_empty() => {
+                bail!("`profile` requires `--history`")
+            }
+            Command::Show if self.history.is_empty() => bail!("`show` requires `--history`"),
+            Command::Calibrate { runs, .. } if runs.len() < 2 => {
+                bail!("`calibrate` requires at least 2 inputs")
+            }
+            Command::History { .. }
+            | Command::Add { .. }
+            | Command::Profile { .. }
+            | Command::Show
+            | Command::PrCompare { .. }
+            | Command::Calibrate { .. } => Ok(()),
+        }
+    }
+
+    /// Make sure that options which need a history store are given one.
+    fn check_history(&self) -> Result<()> {
+        if !self.compare_ref.is_empty() && self.history.is_empty() {
+            bail!("`--compare-ref` requires `--history`");
+        }
+        if self.read_only && self.history.is_empty() {
+            bail!("`--read-only` requires `--history`");
+        }
+        self.check_target()
+    }
+
+    /// Make sure that `--target` is given a history store and names a directory within it.
+    fn check_target(&self) -> Result<()> {
+        if self.target.is_empty() {
+            return Ok(());
+        }
+        if self.history.is_empty() {
+            bail!("`--target` requires `--history`");
+        }
+        if self.target.starts_with('.')
+            || !self
+                .target
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+        {
+            bail!(
+                "Invalid target {:?}: targets are made of letters, digits, `_`, `-` and `.`, and \
+                 do not start with `.`",
+                self.target
+            );
+        }
+        Ok(())
+    }
+
+    /// Make sure that `--show=acceleration` compares each column to the previous one.
+    fn check_acceleration(&self) -> Result<()> {
+        if self.show.contains(&Show::Acceleration)
+            && !matches!(self.relative_to, RelativeTo::Previous)
+        {
+            bail!("`--show=acceleration` requires `--relative-to=previous`");
+        }
+        Ok(())
+    }
+
+    /// Make sure that `--control` and `--treatment` are given together, and share no run.
+    fn check_groups(&self) -> Result<()> {
+        if self.control.is_empty() != self.treatment.is_empty() {
+            bail!("`--control` and `--treatment` must be given together");
+        }
+        if let Some(run) = self.control.iter().find(|run| self.treatment.contains(run)) {
+            bail!("Run {run} cannot be in both `--control` and `--treatment`");
+        }
+        Ok(())
+    }
+
+    /// Return whether the runs are compared as a control and a treatment group.
+    pub fn compares_groups(&self) -> bool {
+        !self.control.is_empty()
+    }
+
+    /// Make sure that `--burn-down` is given a budget.
+    fn check_burn_down(&self) -> Result<()> {
+        if self.burn_down && self.total_budget.is_none() {
+            bail!("`--burn-down` requires `--total-budget`");
+        }
+        Ok(())
+    }
+
+    /// Check that `output_format` is the name of a renderer.
+    fn check_output_format(&self) -> Result<()> {
+        let renderers = OutputRenderers::default();
+        if renderers.find(&self.output_format).is_none() {
+            bail!(
+                "Invalid output-format. Accepted values are: {}",
+                renderers.names().join(", ")
+            );
+        }
+        Ok(())
+    }
+
+    /// Make sure that `--suite` is not given along with positional arguments, which it replaces.
+    fn check_suite(&self) -> Result<()> {
+        if !self.suite.is_empty() && !self.inputs.is_empty() {
+            bail!("`--suite` cannot be given along with input files");
+        }
+        Ok(())
     }
 
-    /// Make sure we are provided with 1 positional argument at least.
+    /// Make sure we are provided with 1 positional argument at least, unless inputs come from
+    /// `--suite`.
     fn check_input_length(&self) -> Result<()> {
-        if self.inputs.is_empty() {
+        if self.inputs.is_empty() && self.suite.is_empty() {
             bail!("No input file")
         }
         Ok(())
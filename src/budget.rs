@@ -0,0 +1,68 @@
+use crate::runs::{slope_of, Records};
+
+/// The distance of the total IR of each run to a budget, and when the budget will be exceeded if
+/// the trend goes on (see `--burn-down`).
+pub struct BurnDown {
+    /// The budget, in IR.
+    pub budget: u64,
+    /// The budget minus the total IR of each run, which is negative for runs over budget.
+    pub remaining: Vec<i128>,
+    /// The trend of the total IR, i.e. the slope of its linear regression (in IR per run).
+    pub slope: f64,
+    /// When the budget is exceeded.
+    pub projection: Projection,
+}
+
+/// When the budget of a [`BurnDown`] is exceeded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Projection {
+    /// The last run is over budget, as are the runs since the one at the given index.
+    ExceededSince(usize),
+    /// The trend reaches the budget at the run at the given index, after the last run.
+    ExceededAt(usize),
+    /// The trend is flat or decreasing, hence never reaches the budget.
+    Never,
+}
+
+impl BurnDown {
+    /// Compute the burn-down of the total IR of the runs of `records` against `budget`.
+    ///
+    /// The projection extends the linear regression of the total IR of all runs.
+    ///
+    /// # Panics
+    /// Panics if there is no run in `records`.
+    pub fn new(records: &Records, budget: u64) -> Self {
+        let totals = &records.runs_total_irs;
+        assert!(!totals.is_empty(), "The records have no run");
+        let remaining = totals
+            .iter()
+            .map(|total| i128::from(budget) - i128::from(*total))
+            .collect::<Vec<_>>();
+        let slope = slope_of(totals);
+        let last = totals.len() - 1;
+        let projection = if remaining[last] < 0 {
+            let since = remaining
+                .iter()
+                .rposition(|remaining| *remaining >= 0)
+                .map_or(0, |run| run + 1);
+            Projection::ExceededSince(since)
+        } else if slope > 0.0 {
+            // The regression line goes through the mean of the runs at their mean index.
+            let n = totals.len() as f64;
+            let mean = totals.iter().map(|total| *total as f64).sum::<f64>() / n;
+            let crossing = (n - 1.0) / 2.0 + (budget as f64 - mean) / slope;
+            // The first run strictly over budget, and at least the one after the last.
+            let run = (crossing.floor() + 1.0).max(last as f64 + 1.0);
+            // The cast saturates for trends too slow to ever reach the budget.
+            Projection::ExceededAt(run as usize)
+        } else {
+            Projection::Never
+        };
+        Self {
+            budget,
+            remaining,
+            slope,
+            projection,
+        }
+    }
+}
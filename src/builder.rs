@@ -0,0 +1,129 @@
+use crate::{
+    callgrind::IR_EVENT,
+    error::{Error, Result},
+    runs::{Records, Run},
+};
+
+/// A builder for [`Records`] from sources other than `callgrind_annotate` outputs.
+///
+/// Runs are added one after the other, either whole or from the IR count of each symbol. Counts
+/// of other events can then be added to the last run. Everything is validated, so that building
+/// never panics.
+///
+/// ```
+/// # use callgrind_differ::builder::RecordsBuilder;
+/// # fn main() -> callgrind_differ::error::Result<()> {
+/// let mut builder = RecordsBuilder::new();
+/// builder
+///     .add_named_run("base", [("parse", 500), ("main", 50)])
+///     .add_event_counters("LLd", [("parse", 12)])?
+///     .add_named_run("head", [("parse", 550), ("main", 40)]);
+/// let records = builder.finish()?;
+/// assert_eq!(records.n_runs(), 2);
+/// assert_eq!(records.events, ["LLd"]);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Default)]
+pub struct RecordsBuilder {
+    /// The runs added so far.
+    runs: Vec<Run>,
+}
+
+impl RecordsBuilder {
+    /// Create a builder with no run.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a run.
+    ///
+    /// # Errors
+    /// Returns an error if the run has the same symbol or event twice.
+    pub fn add_run(&mut self, run: Run) -> Result<&mut Self> {
+        run.validate()?;
+        self.runs.push(run);
+        Ok(self)
+    }
+
+    /// Add a run named `name` from the IR count of each of its symbols.
+    ///
+    /// A symbol may be given multiple times, in which case its counts are added up. The total IR
+    /// of the run is the sum of the IR counts of its symbols.
+    pub fn add_named_run<N, S, I>(&mut self, name: N, irs: I) -> &mut Self
+    where
+        N: Into<String>,
+        S: AsRef<str>,
+        I: IntoIterator<Item = (S, u64)>,
+    {
+        let mut run = Run::new_named(name.into());
+        for (symbol, ir) in irs {
+            run.add_ir(symbol.as_ref(), ir);
+            run.total_ir = run.total_ir.saturating_add(ir);
+        }
+        self.runs.push(run);
+        self
+    }
+
+    /// Add the counts of the event `event` for the symbols of the last run.
+    ///
+    /// Symbols that the last run does not have yet are added with an IR count of 0. Symbols for
+    /// which no count is given have a count of 0. A symbol may be given multiple times, in which
+    /// case its counts are added up. The total of the event is the sum of its counts.
+    ///
+    /// # Errors
+    /// Returns an error if no run was added yet, if `event` is not a valid event name (empty or
+    /// with whitespace), if it is [`IR_EVENT`] or if the last run already has counts for it.
+    pub fn add_event_counters<E, S, I>(&mut self, event: E, counts: I) -> Result<&mut Self>
+    where
+        E: Into<String>,
+        S: AsRef<str>,
+        I: IntoIterator<Item = (S, u64)>,
+    {
+        let event = event.into();
+        let Some(run) = self.runs.last_mut() else {
+            return Err(Error::InvalidRun {
+                run: String::new(),
+                reason: format!("No run to add counts of {event} to"),
+            });
+        };
+        let invalid = |reason| Error::InvalidRun {
+            run: run.name.clone(),
+            reason,
+        };
+        if event.is_empty() || event.contains(char::is_whitespace) {
+            return Err(invalid(format!("Invalid event name {event:?}")));
+        }
+        if event == IR_EVENT || run.event_index(&event).is_some() {
+            return Err(invalid(format!("Duplicate event {event}")));
+        }
+
+        run.events.push(event);
+        let n_events = run.events.len();
+        run.event_totals.resize(n_events - 1, 0);
+        for symbol in &mut run.symbols {
+            symbol.events.resize(n_events, 0);
+        }
+        let mut symbol_counts = vec![0; n_events];
+        let mut total = 0u64;
+        for (symbol, count) in counts {
+            symbol_counts[n_events - 1] = count;
+            run.add_counts(symbol.as_ref(), 0, &symbol_counts);
+            total = total.saturating_add(count);
+        }
+        run.event_totals.push(total);
+        Ok(self)
+    }
+
+    /// Build the records from the runs, in the order they were added.
+    ///
+    /// # Errors
+    /// Returns an error if a run cannot be added to the records (see [`Records::try_add_run`]).
+    pub fn finish(self) -> Result<Records> {
+        let mut records = Records::new();
+        for run in self.runs {
+            records.try_add_run(run)?;
+        }
+        Ok(records)
+    }
+}
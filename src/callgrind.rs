@@ -1,82 +1,411 @@
-use std::borrow::Cow;
+use std::{
+    borrow::Cow,
+    io::{BufRead, Read},
+    path::Path,
+};
 
 use itertools::Itertools;
 
-use crate::{args::StringReplacement, runs::Run};
+use crate::{
+    args::{Args, StringReplacement},
+    error::{Error, Result},
+    format::InputFormat,
+    runs::Run,
+};
 
-/// Parse the total IR line.
+/// The name of the instruction count event.
+pub const IR_EVENT: &str = "Ir";
+
+/// The events callgrind records with `--cache-sim=yes`.
+const CACHE_SIM_EVENTS: &[&str] = &["I1mr", "D1mr", "D1mw", "ILmr", "DLmr", "DLmw"];
+
+/// The events callgrind records with `--branch-sim=yes`.
+const BRANCH_SIM_EVENTS: &[&str] = &["Bc", "Bcm", "Bi", "Bim"];
+
+/// Return the simulation options callgrind was run with, as told by the events it recorded
+/// (e.g. `--cache-sim=yes --branch-sim=no`).
+///
+/// ```
+/// # use callgrind_differ::callgrind::simulation_options;
+/// assert_eq!(
+///     simulation_options(&["Ir", "Bc", "Bcm"]),
+///     "--cache-sim=no --branch-sim=yes"
+/// );
+/// ```
+pub fn simulation_options<S: AsRef<str>>(events: &[S]) -> String {
+    let simulated = |simulation_events: &[&str]| {
+        if events
+            .iter()
+            .any(|event| simulation_events.contains(&event.as_ref()))
+        {
+            "yes"
+        } else {
+            "no"
+        }
+    };
+    format!(
+        "--cache-sim={} --branch-sim={}",
+        simulated(CACHE_SIM_EVENTS),
+        simulated(BRANCH_SIM_EVENTS)
+    )
+}
+
+/// `callgrind_annotate` outputs, each of which holds a single sample.
+///
+/// This is the fallback format: any file which no other format detects is parsed as such.
+pub struct CallgrindAnnotate;
+
+impl InputFormat for CallgrindAnnotate {
+    fn name(&self) -> &'static str {
+        "callgrind_annotate"
+    }
+
+    fn detect(&self, _: &Path) -> bool {
+        true
+    }
+
+    fn is_sample(&self, _config: &Args) -> bool {
+        true
+    }
+
+    fn load(&self, path: &Path, config: &Args) -> anyhow::Result<Vec<Run>> {
+        Ok(vec![Run::from_callgrind_annotate_file(
+            path,
+            &config.string_replace,
+            config.input_limits(),
+        )?])
+    }
+}
+
+/// Limits to enforce on untrusted inputs.
+#[derive(Default, Debug, Clone, Copy)]
+pub struct InputLimits {
+    /// The maximum size of an input, in bytes.
+    pub max_size: Option<u64>,
+    /// The maximum number of symbols in an input.
+    pub max_symbols: Option<usize>,
+}
+
+/// A reader which stops reading once a given number of bytes has been read past.
 ///
-/// This line is just after the `Ir` block and starts with the total `Ir` count. Numbers are
-/// "delimited" with commas since they are large (e.g.: 14,418,621,168).
-fn parse_total_ir_line(line: &str) -> u64 {
-    let word = line.trim().split(' ').next().unwrap();
-    let count = word
-        .chars()
-        // This filter ignore commas.
+/// Since lines are read until a newline is found, this also bounds the memory used to read a
+/// single line.
+pub struct LimitedReader<R> {
+    /// The underlying reader.
+    inner: R,
+    /// The number of bytes that may still be read, if limited.
+    remaining: Option<u64>,
+    /// Whether the limit was exceeded.
+    exceeded: bool,
+}
+
+impl<R: Read> LimitedReader<R> {
+    /// Wrap `inner` so that at most `limit` bytes are read from it, if any.
+    pub fn new(inner: R, limit: Option<u64>) -> Self {
+        Self {
+            inner,
+            remaining: limit,
+            exceeded: false,
+        }
+    }
+
+    /// Return whether the input is larger than the limit.
+    pub fn exceeded(&self) -> bool {
+        self.exceeded
+    }
+}
+
+impl<R: Read> Read for LimitedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let Some(remaining) = self.remaining else {
+            return self.inner.read(buf);
+        };
+        // Read one byte past the limit so that we know whether there is more.
+        let max = buf
+            .len()
+            .min(usize::try_from(remaining + 1).unwrap_or(usize::MAX));
+        let n = self.inner.read(&mut buf[..max])?;
+        if n as u64 > remaining {
+            self.exceeded = true;
+            self.remaining = Some(0);
+            return Ok(remaining as usize);
+        }
+        self.remaining = Some(remaining - n as u64);
+        Ok(n)
+    }
+}
+
+/// Parse a count.
+///
+/// Numbers are "delimited" with commas since they are large (e.g.: 14,418,621,168). A count of 0
+/// may be shown as a `.`. Counts that do not fit a `u64` saturate.
+fn parse_count(word: &str) -> u64 {
+    word.chars()
+        // This filter ignore commas (and the `.` of null counts).
         .filter_map(|c| c.to_digit(10))
         // This is akin to `str::parse::<u64>`.
-        .fold(0, |sum, digit| sum * 10 + u64::from(digit));
-    count
+        .fold(0, |sum: u64, digit| {
+            sum.saturating_mul(10).saturating_add(u64::from(digit))
+        })
+}
+
+/// Split the first word off `s`, ignoring leading spaces.
+///
+/// Return the word and the rest of `s`, starting with the space that follows the word.
+fn next_word(s: &str) -> Option<(&str, &str)> {
+    let s = s.trim_start_matches(' ');
+    if s.is_empty() {
+        return None;
+    }
+    let end = s.find(' ').unwrap_or(s.len());
+    Some(s.split_at(end))
 }
 
-/// Parse an IR line for a particular symbol.
+/// Parse the counts at the beginning of a line into `counts` and return the rest of the line.
+///
+/// The line starts with one count per event, each of which may be followed by a percentage:
+/// ```no_compile
+/// <ir> (xx.xx%) <dr> (xx.xx%) . <rest>
+/// ```
+///
+/// There may be leading spaces to the counts and spaces in the percentages. `counts` is cleared
+/// first, so that the same buffer can be used for every line.
+fn parse_counts<'a>(line: &'a str, n_events: usize, counts: &mut Vec<u64>) -> &'a str {
+    counts.clear();
+    let mut rest = line.trim();
+    for _ in 0..n_events {
+        let Some((word, after)) = next_word(rest) else {
+            break;
+        };
+        counts.push(parse_count(word));
+        rest = after;
+        // We then skip until the word ends with `)`, effectively skipping over the percentage.
+        if next_word(rest).is_some_and(|(word, _)| word.starts_with('(')) {
+            while let Some((word, after)) = next_word(rest) {
+                rest = after;
+                if word.ends_with(')') {
+                    break;
+                }
+            }
+        }
+    }
+    counts.resize(n_events, 0);
+    rest
+}
+
+/// Parse the totals line.
+///
+/// This line is just after the events block and starts with the total count of each event.
+fn parse_totals_line(line: &str, n_events: usize) -> Vec<u64> {
+    let mut counts = Vec::with_capacity(n_events);
+    parse_counts(line, n_events, &mut counts);
+    counts
+}
+
+/// Parse a counts line for a particular symbol into `counts` and return the symbol.
 ///
 /// The line is of the form:
 /// ```no_compile
-/// <ir> (xx.xx%) <loc>:<sym> [<file>]
+/// <ir> (xx.xx%) [<count> (xx.xx%)...] <loc>:<sym> [<file>]
 /// ```
 ///
-/// There may be leading spaces to `ir`, spaces in the percentage and even in `loc`.
-fn parse_fn_ir_line(line: &str) -> (String, u64) {
-    // We ignore empty words (leading and trailing spaces as well).
-    let mut words = line.trim().split(' ').filter(|word| !word.is_empty());
-    // First word is `<ir>`.
-    let ir_str = words.next().unwrap();
-    let ir_str = ir_str
-        .chars()
-        .filter_map(|c| c.to_digit(10))
-        .fold(0, |sum, digit| sum * 10 + u64::from(digit));
+/// There may be leading spaces to counts, spaces in the percentages and even in `loc`. The symbol
+/// is borrowed from `line`, unless it has runs of spaces which must be collapsed.
+fn parse_fn_line<'a>(line: &'a str, n_events: usize, counts: &mut Vec<u64>) -> Cow<'a, str> {
+    let rest = parse_counts(line, n_events, counts).trim_start_matches(' ');
 
-    // We then skip until the word ends with `)`, effectively skipping over the percentage.
-    let words = words.skip_while(|word| !word.ends_with(')')).skip(1);
     // We then take words until one starts with `[`. This takes both `<loc>:<sym>`.
-    // Joining with space allows us to rebuild constructs such as:
+    // Spaces between words are kept (collapsed into one), which allows us to rebuild constructs
+    // such as:
     // ```
     // <yaml_rust2::parser::Event as core::cmp::PartialEq>::eq`
     //                           ^  ^
     //                      These spaces are a pain
     // ```
-    let loc = words.take_while(|word| !word.starts_with('[')).join(" ");
+    let end = rest
+        .match_indices('[')
+        .map(|(i, _)| i)
+        .find(|i| *i == 0 || rest.as_bytes()[i - 1] == b' ')
+        .unwrap_or(rest.len());
+    let loc = rest[..end].trim_end_matches(' ');
     // We ignore every character until we reach the `:` that precedes `<sym>` and consume that one
     // as well. Hurray, we found our symbol.
-    let loc = loc.chars().skip_while(|c| *c != ':').skip(1).collect();
+    let symbol = loc.split_once(':').map_or("", |(_, symbol)| symbol);
+    if symbol.contains("  ") {
+        Cow::Owned(symbol.split(' ').filter(|word| !word.is_empty()).join(" "))
+    } else {
+        Cow::Borrowed(symbol)
+    }
+}
+
+/// A reader which reads its input line by line into a single buffer.
+///
+/// Memory is thus bounded by the longest line rather than by the size of the input. Bytes which
+/// are not valid UTF-8 (e.g. in the symbols of profiles converted from other languages) are
+/// replaced with `U+FFFD`, rather than failing the whole input.
+pub(crate) struct LineReader<R> {
+    /// The underlying reader.
+    input: R,
+    /// The bytes of the last line that was read.
+    bytes: Vec<u8>,
+    /// The last line that was read.
+    line: String,
+    /// The number of lines read so far.
+    number: usize,
+}
 
-    (loc, ir_str)
+impl<R: BufRead> LineReader<R> {
+    /// Read lines from `input`.
+    pub(crate) fn new(input: R) -> Self {
+        Self {
+            input,
+            bytes: vec![],
+            line: String::new(),
+            number: 0,
+        }
+    }
+
+    /// Read the next line and return it without its line terminator, along with its number
+    /// (1-indexed). Return `None` at the end of the input.
+    pub(crate) fn next_line(&mut self) -> Result<Option<(usize, &str)>> {
+        self.bytes.clear();
+        self.line.clear();
+        if self.input.read_until(b'\n', &mut self.bytes)? == 0 {
+            return Ok(None);
+        }
+        match std::str::from_utf8(&self.bytes) {
+            Ok(line) => self.line.push_str(line),
+            Err(_) => self.line.push_str(&String::from_utf8_lossy(&self.bytes)),
+        }
+        self.number += 1;
+        let line = self.line.strip_suffix('\n').unwrap_or(&self.line);
+        Ok(Some((self.number, line.strip_suffix('\r').unwrap_or(line))))
+    }
 }
 
 /// Parse a `callgrind_annotate` file and return a `Run` from it.
-pub fn parse<R: std::io::BufRead>(input: R, replacements: &[StringReplacement]) -> Run {
+///
+/// All events shown in the file are parsed. The `Ir` event (or the first event if `Ir` is not
+/// shown) is the instruction count of the run. Other events are stored in [`Run::events`].
+///
+/// The input is streamed: it is read line by line and symbols are added to the run as they are
+/// read, so that memory is bounded by the number of symbols rather than by the size of the
+/// input. Symbols are borrowed from the line being read until they are added to the run, so that
+/// only new symbols are allocated. Reading stops at the end of the table of symbols; the rest of
+/// the input (e.g. annotated sources) is not read. Parsing fails if the run has more than
+/// `max_symbols` symbols.
+///
+/// Errors have no path; see [`Error::in_file`].
+///
+/// # Errors
+/// Returns an error if the input cannot be read, is not a `callgrind_annotate` output or has too
+/// many symbols.
+pub fn parse<R: BufRead>(
+    input: R,
+    replacements: &[StringReplacement],
+    max_symbols: Option<usize>,
+) -> Result<Run> {
+    let no_totals = || Error::FormatMismatch {
+        path: None,
+        expected: "a `callgrind_annotate` output",
+        reason: "No total IR line".to_string(),
+    };
+    let mut lines = LineReader::new(input);
+
+    // Find the events that are shown, then the header of the totals block, which starts with the
+    // name of the first event.
+    let mut events = vec![IR_EVENT.to_string()];
+    let mut command = None;
+    let mut simulation = None;
+    loop {
+        let Some((_, line)) = lines.next_line()? else {
+            return Err(no_totals());
+        };
+        if let Some(target) = line.strip_prefix("Profiled target:") {
+            // The target is followed by its PID, e.g. `./bench (PID: 1234, part: 1)`.
+            let target = target
+                .rsplit_once(" (PID:")
+                .map_or(target, |(target, _)| target);
+            command = Some(target.trim().to_string());
+        } else if let Some(recorded) = line.strip_prefix("Events recorded:") {
+            simulation = Some(simulation_options(
+                &recorded.split_whitespace().collect_vec(),
+            ));
+        } else if let Some(shown) = line.strip_prefix("Events shown:") {
+            let shown = shown.split_whitespace().map(str::to_string).collect_vec();
+            if !shown.is_empty() {
+                events = shown;
+            }
+        } else if line.starts_with(&events[0]) {
+            break;
+        }
+    }
+    let n_events = events.len();
+    let ir_index = events
+        .iter()
+        .position(|event| event == IR_EVENT)
+        .unwrap_or(0);
+    let first_event = events[0].clone();
+
     let mut run = Run::new();
-    let mut lines = input
-        .lines()
-        .map_while(std::result::Result::ok)
-        .skip_while(|line| !line.starts_with("Ir"))
-        .skip(2);
-    run.total_ir = parse_total_ir_line(&lines.next().unwrap());
-
-    for (symbol, ir) in lines
-        .skip_while(|line| !line.starts_with("Ir"))
-        .skip(2)
-        .take_while(|line| line.trim().chars().next().unwrap_or('\0').is_ascii_digit())
-        .map(|line| parse_fn_ir_line(&line))
-    {
+    run.metadata.command = command;
+    run.metadata.simulation = simulation;
+    run.events = events
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| *i != ir_index)
+        .map(|(_, event)| event.clone())
+        .collect();
+    // Split the counts of a line into its IR count and the counts of other events.
+    let split_counts = |counts: &mut Vec<u64>| counts.remove(ir_index);
+
+    // Skip the line of dashes below the header.
+    lines.next_line()?;
+    let Some((number, line)) = lines.next_line()? else {
+        return Err(no_totals());
+    };
+    if !line.trim_start().starts_with(|c: char| c.is_ascii_digit()) {
+        return Err(Error::Parse {
+            path: None,
+            line: number,
+            message: format!("Expected the total counts of {}", events.join(" ")),
+        });
+    }
+    run.event_totals = parse_totals_line(line, n_events);
+    run.total_ir = split_counts(&mut run.event_totals);
+
+    // Find the header of the table of symbols, then skip the line of dashes below it.
+    while let Some((_, line)) = lines.next_line()? {
+        if line.starts_with(&first_event) {
+            break;
+        }
+    }
+    lines.next_line()?;
+
+    // The table ends at the first line that does not start with a count. Symbols are borrowed
+    // from the line and the counts are parsed into the same buffer, so that nothing is allocated
+    // for a line unless its symbol is new or replaced.
+    let mut counts = Vec::with_capacity(n_events);
+    while let Some((_, line)) = lines.next_line()? {
+        let c = line.trim().chars().next().unwrap_or('\0');
+        if !(c.is_ascii_digit() || c == '.') {
+            break;
+        }
+        let symbol = parse_fn_line(line, n_events, &mut counts);
         let symbol = replacements
             .iter()
-            .fold(Cow::Owned(symbol), |symbol, replacement| {
-                replacement.perform(symbol)
-            });
-        run.add_ir(&symbol, ir);
+            .fold(symbol, |symbol, replacement| replacement.perform(symbol));
+        let ir = split_counts(&mut counts);
+        run.add_counts(&symbol, ir, &counts);
+        if let Some(max) = max_symbols {
+            if run.symbols.len() > max {
+                return Err(Error::TooManySymbols {
+                    path: None,
+                    max_symbols: max,
+                });
+            }
+        }
     }
 
-    run
+    Ok(run)
 }
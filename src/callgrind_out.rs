@@ -0,0 +1,336 @@
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    fs::File,
+    io::{BufRead, BufReader},
+    path::Path,
+};
+
+use crate::{
+    args::{Args, StringReplacement},
+    callgrind::{simulation_options, LimitedReader, LineReader, IR_EVENT},
+    error::{Error, Result},
+    format::InputFormat,
+    runs::Run,
+};
+
+/// The suffix of the symbols which hold the code inlined into a function (see
+/// `--split-inlined`).
+pub const INLINED_SUFFIX: &str = " (inlined)";
+
+/// The keys of the header lines of the callgrind format, one of which starts files which do not
+/// start with `# callgrind format` (e.g. `version:` for callgrind itself, `event:` for
+/// `pyprof2calltree` or `positions:` for `pprof -callgrind`).
+const HEADER_KEYS: [&str; 9] = [
+    "version",
+    "creator",
+    "pid",
+    "cmd",
+    "part",
+    "desc",
+    "positions",
+    "event",
+    "events",
+];
+
+/// Raw `callgrind.out` files, as written by `valgrind --tool=callgrind`, each of which holds a
+/// single sample.
+///
+/// They are detected by their first line, which is either `# callgrind format` or a header line
+/// of the format (e.g. `version:` or `events:`), so that profiles converted to the callgrind
+/// format from other languages (e.g. by `pyprof2calltree` or `pprof -callgrind`) are detected as
+/// well.
+pub struct CallgrindOut;
+
+impl InputFormat for CallgrindOut {
+    fn name(&self) -> &'static str {
+        "callgrind.out"
+    }
+
+    fn detect(&self, path: &Path) -> bool {
+        let Ok(file) = File::open(path) else {
+            return false;
+        };
+        let mut first_line = String::new();
+        // Only the first line is read, however long the file is.
+        let mut reader = BufReader::new(LimitedReader::new(file, Some(256)));
+        if reader.read_line(&mut first_line).is_err() {
+            return false;
+        }
+        let first_line = first_line.trim_end();
+        first_line == "# callgrind format"
+            || first_line
+                .split_once(':')
+                .is_some_and(|(key, _)| HEADER_KEYS.contains(&key.trim()))
+    }
+
+    fn is_sample(&self, _config: &Args) -> bool {
+        true
+    }
+
+    fn load(&self, path: &Path, config: &Args) -> anyhow::Result<Vec<Run>> {
+        let limits = config.input_limits();
+        let file = File::open(path).map_err(|error| Error::from(error).in_file(path))?;
+        let mut reader = LimitedReader::new(file, limits.max_size);
+        let run = parse(
+            BufReader::new(&mut reader),
+            &config.string_replace,
+            limits.max_symbols,
+            config.split_inlined,
+        );
+        if reader.exceeded() {
+            return Err(Error::TooLarge {
+                path: Some(path.to_path_buf()),
+                max_size: limits.max_size.unwrap_or_default(),
+            }
+            .into());
+        }
+        Ok(vec![run.map_err(|error| error.in_file(path))?])
+    }
+}
+
+/// Parse a raw `callgrind.out` file and return a `Run` from it.
+///
+/// The counts of a symbol are its self cost: the costs of the calls it makes are left out. The
+/// `Ir` event (or the first event if there is no `Ir`) is the instruction count of the run.
+/// Other events are stored in [`Run::events`].
+///
+/// With `split_inlined`, the costs of a function which are in another file than that of the
+/// function (as per the `fi=` and `fe=` lines) are code inlined into it, and are attributed to
+/// the function suffixed with [`INLINED_SUFFIX`] instead.
+///
+/// Errors have no path; see [`Error::in_file`].
+///
+/// # Errors
+/// Returns an error if the input cannot be read, is not a `callgrind.out` file or has more than
+/// `max_symbols` symbols.
+pub fn parse<R: BufRead>(
+    input: R,
+    replacements: &[StringReplacement],
+    max_symbols: Option<usize>,
+    split_inlined: bool,
+) -> Result<Run> {
+    let mismatch = |reason: &str| Error::FormatMismatch {
+        path: None,
+        expected: "a `callgrind.out` file",
+        reason: reason.to_string(),
+    };
+    let mut lines = LineReader::new(input);
+    let mut run = Run::new();
+    let mut ir_index = None;
+    let mut n_positions = 1;
+    let mut totals = None;
+    let mut location = Location::default();
+    // The cost line that follows a `calls=` line is the inclusive cost of the call.
+    let mut is_call_cost = false;
+    let mut counts = vec![];
+
+    while let Some((number, line)) = lines.next_line()? {
+        let parse_error = |message: String| Error::Parse {
+            path: None,
+            line: number,
+            message,
+        };
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if line.starts_with(|c: char| c.is_ascii_digit() || matches!(c, '+' | '-' | '*')) {
+            let Some(ir_index) = ir_index else {
+                return Err(mismatch("Costs before the `events:` line"));
+            };
+            if std::mem::take(&mut is_call_cost) {
+                continue;
+            }
+            let Some(symbol) = location.symbol(split_inlined) else {
+                return Err(parse_error("Cost outside of a function".to_string()));
+            };
+            parse_costs(line, n_positions, run.events.len() + 1, &mut counts)
+                .map_err(parse_error)?;
+            let ir = counts.remove(ir_index);
+            run.add_counts(&symbol, ir, &counts);
+            if let Some(max) = max_symbols {
+                if run.symbols.len() > max {
+                    return Err(Error::TooManySymbols {
+                        path: None,
+                        max_symbols: max,
+                    });
+                }
+            }
+            continue;
+        }
+
+        let Some((key, value)) = line.split_once(['=', ':']) else {
+            return Err(parse_error(format!("Unexpected line {line:?}")));
+        };
+        let value = value.trim();
+        match key {
+            "events" => {
+                let (index, events) = split_events(value);
+                run.metadata.simulation = Some(simulation_options(&events));
+                run.events = events;
+                ir_index = Some(index);
+            }
+            "positions" => n_positions = value.split_whitespace().count().max(1),
+            "totals" | "summary" => {
+                let mut event_totals = vec![];
+                parse_costs(value, 0, run.events.len() + 1, &mut event_totals)
+                    .map_err(parse_error)?;
+                totals = Some(event_totals);
+            }
+            "calls" => is_call_cost = true,
+            "cmd" => run.metadata.command = Some(value.to_string()),
+            _ => location.update(key, value, replacements),
+        }
+    }
+    let Some(ir_index) = ir_index else {
+        return Err(mismatch("No `events:` line"));
+    };
+
+    // Without totals, the totals are the sums of the self costs of the symbols.
+    if let Some(mut event_totals) = totals {
+        run.total_ir = event_totals.remove(ir_index);
+        run.event_totals = event_totals;
+    } else {
+        sum_self_costs(&mut run);
+    }
+    Ok(run)
+}
+
+/// Split the events of an `events:` line into the index of the `Ir` event (or 0 if there is no
+/// `Ir`) and the other events, in order.
+pub(crate) fn split_events(events: &str) -> (usize, Vec<String>) {
+    let events = events.split_whitespace().collect::<Vec<_>>();
+    let index = events
+        .iter()
+        .position(|event| *event == IR_EVENT)
+        .unwrap_or(0);
+    let others = events
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| *i != index)
+        .map(|(_, event)| (*event).to_string())
+        .collect();
+    (index, others)
+}
+
+/// Set the totals of `run` to the sums of the counts of its symbols, for inputs which have no
+/// `totals:` or `summary:` line.
+pub(crate) fn sum_self_costs(run: &mut Run) {
+    run.total_ir = run
+        .symbols
+        .iter()
+        .fold(0, |total: u64, symbol| total.saturating_add(symbol.ir));
+    run.event_totals = (0..run.events.len())
+        .map(|i| {
+            run.symbols.iter().fold(0, |total: u64, symbol| {
+                total.saturating_add(symbol.events.get(i).copied().unwrap_or_default())
+            })
+        })
+        .collect();
+}
+
+/// Parse a cost line into `counts`: `n_positions` positions, followed by up to `n_events`
+/// counts.
+///
+/// Positions are ignored. Missing counts are 0.
+fn parse_costs(
+    line: &str,
+    n_positions: usize,
+    n_events: usize,
+    counts: &mut Vec<u64>,
+) -> std::result::Result<(), String> {
+    counts.clear();
+    for word in line.split_whitespace().skip(n_positions) {
+        let Ok(count) = word.parse::<u64>() else {
+            return Err(format!("Invalid count {word:?}"));
+        };
+        counts.push(count);
+    }
+    counts.resize(n_events, 0);
+    Ok(())
+}
+
+/// Where the costs being read are, as set by the `fl=`, `fi=`, `fe=` and `fn=` lines.
+#[derive(Default)]
+struct Location {
+    /// Compressed names of files, by id.
+    files: HashMap<String, String>,
+    /// Compressed names of functions, by id.
+    functions: HashMap<String, String>,
+    /// The file of the current function.
+    file: String,
+    /// The file the code inlined into the current function comes from, if any.
+    inline_file: Option<String>,
+    /// The current function, with replacements performed.
+    function: Option<String>,
+}
+
+impl Location {
+    /// Update the location from a `key=value` line. Lines which are not about locations are
+    /// ignored.
+    fn update(&mut self, key: &str, value: &str, replacements: &[StringReplacement]) {
+        match key {
+            "fl" => {
+                self.file = resolve(&mut self.files, value);
+                self.inline_file = None;
+            }
+            "fi" | "fe" => self.inline_file = Some(resolve(&mut self.files, value)),
+            "fn" => {
+                let name = resolve(&mut self.functions, value);
+                let name = replacements
+                    .iter()
+                    .fold(name.into(), |name, replacement| replacement.perform(name));
+                self.function = Some(name.into_owned());
+                self.inline_file = None;
+            }
+            // Called files and functions may define compressed names.
+            "cfi" | "cfl" => {
+                resolve(&mut self.files, value);
+            }
+            "cfn" => {
+                resolve(&mut self.functions, value);
+            }
+            _ => {}
+        }
+    }
+
+    /// Return the symbol the costs are attributed to, or `None` outside of a function.
+    ///
+    /// With `split_inlined`, costs of code inlined from another file are attributed to the
+    /// function suffixed with [`INLINED_SUFFIX`].
+    fn symbol(&self, split_inlined: bool) -> Option<Cow<'_, str>> {
+        let function = self.function.as_deref()?;
+        let is_inlined = self
+            .inline_file
+            .as_ref()
+            .is_some_and(|inline| *inline != self.file);
+        Some(if split_inlined && is_inlined {
+            Cow::Owned(format!("{function}{INLINED_SUFFIX}"))
+        } else {
+            Cow::Borrowed(function)
+        })
+    }
+}
+
+/// Resolve a possibly compressed name: `(<id>) <name>` defines `id` as `name`, and `(<id>)`
+/// refers to it.
+///
+/// Ids are numbers: names which merely start with parentheses (e.g.
+/// `(anonymous namespace)::parse`) are not compressed. Undefined ids resolve to themselves.
+fn resolve(names: &mut HashMap<String, String>, value: &str) -> String {
+    let Some((id, name)) = value
+        .strip_prefix('(')
+        .and_then(|value| value.split_once(')'))
+        .filter(|(id, _)| !id.is_empty() && id.bytes().all(|byte| byte.is_ascii_digit()))
+    else {
+        return value.to_string();
+    };
+    let name = name.trim();
+    if name.is_empty() {
+        names.get(id).cloned().unwrap_or_else(|| format!("({id})"))
+    } else {
+        names.insert(id.to_string(), name.to_string());
+        name.to_string()
+    }
+}
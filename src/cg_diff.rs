@@ -0,0 +1,195 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufRead, BufReader},
+    path::Path,
+};
+
+use crate::{
+    args::{Args, StringReplacement},
+    callgrind::{simulation_options, LimitedReader, LineReader},
+    callgrind_out::{split_events, sum_self_costs},
+    error::{Error, Result},
+    format::InputFormat,
+    runs::Run,
+};
+
+/// The prefix of the first line of the files written by `cg_diff`, followed by the names of the
+/// compared files.
+const FILES_COMPARED: &str = "desc: Files compared:";
+
+/// The outputs of valgrind's `cg_diff`, which hold the differences between two profiles (which
+/// `cg_annotate --diff` also reads).
+///
+/// They are detected by their first line, `desc: Files compared: <old>; <new>`. Each file is
+/// loaded as two runs (see [`parse`]).
+pub struct CgDiff;
+
+impl InputFormat for CgDiff {
+    fn name(&self) -> &'static str {
+        "cg_diff"
+    }
+
+    fn detect(&self, path: &Path) -> bool {
+        let Ok(file) = File::open(path) else {
+            return false;
+        };
+        let mut first_line = String::new();
+        // Only the first line is read, however long the file is.
+        let mut reader = BufReader::new(LimitedReader::new(file, Some(1024)));
+        if reader.read_line(&mut first_line).is_err() {
+            return false;
+        }
+        first_line.starts_with(FILES_COMPARED)
+    }
+
+    fn is_sample(&self, _config: &Args) -> bool {
+        false
+    }
+
+    fn load(&self, path: &Path, config: &Args) -> anyhow::Result<Vec<Run>> {
+        let limits = config.input_limits();
+        let file = File::open(path).map_err(|error| Error::from(error).in_file(path))?;
+        let mut reader = LimitedReader::new(file, limits.max_size);
+        let runs = parse(
+            BufReader::new(&mut reader),
+            &config.string_replace,
+            limits.max_symbols,
+        );
+        if reader.exceeded() {
+            return Err(Error::TooLarge {
+                path: Some(path.to_path_buf()),
+                max_size: limits.max_size.unwrap_or_default(),
+            }
+            .into());
+        }
+        Ok(runs.map_err(|error| error.in_file(path))?)
+    }
+}
+
+/// Parse the output of `cg_diff` and return two runs from it, named after the compared files.
+///
+/// `cg_diff` only keeps the signed difference of each count, so that the counts of the profiles
+/// cannot be recovered. Instead, the change of each count of a symbol is put in the run it is
+/// the highest in: a decrease is the count of the symbol in the first run (and 0 in the second),
+/// and an increase its count in the second run (and 0 in the first). Differences are thus those
+/// of the profiles, but percentages are not. As with `callgrind.out` files, the `Ir` event (or
+/// the first event if there is no `Ir`) is the instruction count. The totals of each run are the
+/// sums of the counts of its symbols.
+///
+/// Errors have no path; see [`Error::in_file`].
+///
+/// # Errors
+/// Returns an error if the input cannot be read, is not an output of `cg_diff` or has more than
+/// `max_symbols` symbols.
+pub fn parse<R: BufRead>(
+    input: R,
+    replacements: &[StringReplacement],
+    max_symbols: Option<usize>,
+) -> Result<Vec<Run>> {
+    let mismatch = |reason: &str| Error::FormatMismatch {
+        path: None,
+        expected: "an output of `cg_diff`",
+        reason: reason.to_string(),
+    };
+    let mut lines = LineReader::new(input);
+    let mut names = None;
+    let mut events: Option<(usize, Vec<String>)> = None;
+    let mut function: Option<String> = None;
+    // The signed change of each count (`Ir` first) of each symbol, in order of appearance.
+    let mut changes: Vec<(String, Vec<i64>)> = vec![];
+    let mut positions = HashMap::new();
+
+    while let Some((number, line)) = lines.next_line()? {
+        let parse_error = |message: String| Error::Parse {
+            path: None,
+            line: number,
+            message,
+        };
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(files) = line.strip_prefix(FILES_COMPARED) {
+            let (old, new) = files.split_once(';').unwrap_or((files, ""));
+            names = Some([old.trim().to_string(), new.trim().to_string()]);
+        } else if line.starts_with(|c: char| c.is_ascii_digit()) {
+            let Some((ir_index, events)) = &events else {
+                return Err(mismatch("Costs before the `events:` line"));
+            };
+            let Some(symbol) = &function else {
+                return Err(parse_error("Cost outside of a function".to_string()));
+            };
+            let mut counts = parse_changes(line, events.len() + 1).map_err(parse_error)?;
+            let ir = counts.remove(*ir_index);
+            counts.insert(0, ir);
+            let position = *positions.entry(symbol.clone()).or_insert_with(|| {
+                changes.push((symbol.clone(), vec![0; counts.len()]));
+                changes.len() - 1
+            });
+            if let Some(max) = max_symbols {
+                if changes.len() > max {
+                    return Err(Error::TooManySymbols {
+                        path: None,
+                        max_symbols: max,
+                    });
+                }
+            }
+            for (total, change) in changes[position].1.iter_mut().zip(counts) {
+                *total = total.saturating_add(change);
+            }
+        } else if let Some(value) = line.strip_prefix("events:") {
+            events = Some(split_events(value));
+        } else if let Some(name) = line.strip_prefix("fn=") {
+            let name = replacements
+                .iter()
+                .fold(name.trim().into(), |name, replacement| {
+                    replacement.perform(name)
+                });
+            function = Some(name.into_owned());
+        } else if !line.contains([':', '=']) {
+            return Err(parse_error(format!("Unexpected line {line:?}")));
+        }
+        // Other lines (`cmd:`, `summary:`, `fl=`, ...) do not matter.
+    }
+    let Some((_, events)) = events else {
+        return Err(mismatch("No `events:` line"));
+    };
+    let [old, new] = names.unwrap_or_default();
+
+    let mut runs = [Run::new_named(old), Run::new_named(new)];
+    for run in &mut runs {
+        run.events.clone_from(&events);
+        run.metadata.simulation = Some(simulation_options(&events));
+    }
+    for (symbol, changes) in changes {
+        let [decreases, increases] = [-1, 1].map(|sign: i64| {
+            changes
+                .iter()
+                .map(|change| change.saturating_mul(sign).max(0).unsigned_abs())
+                .collect::<Vec<_>>()
+        });
+        runs[0].add_counts(&symbol, decreases[0], &decreases[1..]);
+        runs[1].add_counts(&symbol, increases[0], &increases[1..]);
+    }
+    for run in &mut runs {
+        sum_self_costs(run);
+    }
+    Ok(runs.into())
+}
+
+/// Parse a cost line of `cg_diff`, a line number followed by up to `n_events` signed changes.
+///
+/// Missing changes are 0.
+fn parse_changes(line: &str, n_events: usize) -> std::result::Result<Vec<i64>, String> {
+    let mut changes = line
+        .split_whitespace()
+        .skip(1)
+        .map(|word| {
+            word.parse::<i64>()
+                .map_err(|_| format!("Invalid change {word:?}"))
+        })
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    changes.resize(n_events, 0);
+    Ok(changes)
+}
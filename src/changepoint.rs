@@ -0,0 +1,129 @@
+use crate::{args::Percentage, runs::Records};
+
+/// A jump in the IR count of a series (the total IR or a symbol) from one run to the next.
+///
+/// Counts on either side of the jump are the means of the runs of the segments the series is
+/// split into (see [`changepoints_of`]), so that a single noisy run does not hide a jump.
+pub struct Changepoint<'a> {
+    /// The name of the symbol, or `None` for the total IR.
+    pub symbol: Option<&'a str>,
+    /// The index of the first run after the jump.
+    pub run: usize,
+    /// The mean IR count of the runs of the segment before the jump.
+    pub before: u64,
+    /// The mean IR count of the runs of the segment after the jump.
+    pub after: u64,
+}
+
+impl Changepoint<'_> {
+    /// Return the signed change of the IR count, in percent of the count before the jump.
+    ///
+    /// A change from 0 is considered a 100% increase.
+    pub fn percent(&self) -> f64 {
+        relative_change(self.before as f64, self.after as f64)
+    }
+}
+
+/// Find the runs at which the total IR and the IR of each symbol jumped up by more than
+/// `threshold` (see `--find-regressions`).
+///
+/// Jumps of the total IR are returned first, followed by those of the symbols, in the order of
+/// the symbols in `records`. Jumps of a series are in the order of the runs.
+pub fn find_changepoints(records: &Records, threshold: Percentage) -> Vec<Changepoint<'_>> {
+    let series = std::iter::once((None, records.runs_total_irs.as_slice())).chain(
+        records
+            .symbols
+            .iter()
+            .map(|symbol| (Some(&*symbol.name), symbol.irs.as_slice())),
+    );
+    series
+        .flat_map(|(symbol, irs)| {
+            let changepoints = changepoints_of(irs, threshold);
+            // Each segment runs from one changepoint to the next.
+            let bounds = std::iter::once(0)
+                .chain(changepoints.iter().copied())
+                .chain(std::iter::once(irs.len()))
+                .collect::<Vec<_>>();
+            bounds
+                .windows(3)
+                .map(|window| Changepoint {
+                    symbol,
+                    run: window[1],
+                    before: mean(&irs[window[0]..window[1]]).round() as u64,
+                    after: mean(&irs[window[1]..window[2]]).round() as u64,
+                })
+                .filter(|changepoint| changepoint.after > changepoint.before)
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Return the indices at which the mean of `values` shifts by more than `threshold`, in
+/// ascending order.
+///
+/// This is a binary segmentation: `values` are split where the split explains most of their
+/// variance, as long as the means on either side differ by more than `threshold`, and each side
+/// is then split in turn. An index is that of the first value after the shift, so that it is
+/// never 0.
+///
+/// ```
+/// # use callgrind_differ::{args::Percentage, changepoint::changepoints_of};
+/// let irs = [100, 101, 99, 100, 120, 121, 119, 90, 91];
+/// assert_eq!(changepoints_of(&irs, Percentage(5.0)), [4, 7]);
+/// ```
+pub fn changepoints_of(values: &[u64], threshold: Percentage) -> Vec<usize> {
+    let mut changepoints = vec![];
+    split(values, 0, threshold, &mut changepoints);
+    changepoints.sort_unstable();
+    changepoints
+}
+
+/// Split `values`, which start at index `offset` of the whole series, as per [`changepoints_of`].
+fn split(values: &[u64], offset: usize, threshold: Percentage, changepoints: &mut Vec<usize>) {
+    let n = values.len();
+    if n < 2 {
+        return;
+    }
+    let total = values.iter().map(|x| *x as f64).sum::<f64>();
+    let mut left = 0.0;
+    let mut best = None;
+    for k in 1..n {
+        left += values[k - 1] as f64;
+        let mean_left = left / k as f64;
+        let mean_right = (total - left) / (n - k) as f64;
+        // The reduction of the sum of squared errors when splitting at `k`.
+        let gain = (k * (n - k)) as f64 / n as f64 * (mean_left - mean_right).powi(2);
+        if best.is_none_or(|(_, best_gain, _, _)| gain > best_gain) {
+            best = Some((k, gain, mean_left, mean_right));
+        }
+    }
+    let Some((k, _, mean_left, mean_right)) = best else {
+        return;
+    };
+    if relative_change(mean_left, mean_right).abs() <= threshold.0 {
+        return;
+    }
+    changepoints.push(offset + k);
+    split(&values[..k], offset, threshold, changepoints);
+    split(&values[k..], offset + k, threshold, changepoints);
+}
+
+/// Return the mean of `values`.
+fn mean(values: &[u64]) -> f64 {
+    values.iter().map(|x| *x as f64).sum::<f64>() / values.len() as f64
+}
+
+/// Return the signed change from `before` to `after`, in percent of `before`.
+///
+/// A change from 0 is considered a 100% increase.
+fn relative_change(before: f64, after: f64) -> f64 {
+    if before == 0.0 {
+        if after > 0.0 {
+            100.0
+        } else {
+            0.0
+        }
+    } else {
+        (after - before) * 100.0 / before
+    }
+}
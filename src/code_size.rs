@@ -0,0 +1,249 @@
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, Read},
+    path::Path,
+};
+
+use crate::{
+    args::{Args, StringReplacement},
+    callgrind::{LimitedReader, LineReader},
+    error::{Error, Result},
+    format::InputFormat,
+    json,
+    runs::Run,
+};
+
+/// The unit of the counts of `cargo llvm-lines`.
+pub const LINES_UNIT: &str = "lines";
+
+/// The unit of the counts of `cargo bloat`.
+pub const BYTES_UNIT: &str = "bytes";
+
+/// The name of the row of `cargo llvm-lines` holding the totals.
+const TOTAL_ROW: &str = "(TOTAL)";
+
+/// The outputs of `cargo llvm-lines`, which hold the number of lines of LLVM IR generated for
+/// each function.
+///
+/// They are detected by their header, whose first line starts with `Lines` and ends with
+/// `Function name`.
+pub struct LlvmLines;
+
+impl InputFormat for LlvmLines {
+    fn name(&self) -> &'static str {
+        "cargo llvm-lines"
+    }
+
+    fn detect(&self, path: &Path) -> bool {
+        first_bytes(path).is_some_and(|start| {
+            start
+                .lines()
+                .find(|line| !line.trim().is_empty())
+                .is_some_and(|line| {
+                    let line = line.trim();
+                    line.starts_with("Lines") && line.ends_with("Function name")
+                })
+        })
+    }
+
+    fn is_sample(&self, _config: &Args) -> bool {
+        true
+    }
+
+    fn load(&self, path: &Path, config: &Args) -> anyhow::Result<Vec<Run>> {
+        let limits = config.input_limits();
+        let file = File::open(path).map_err(|error| Error::from(error).in_file(path))?;
+        let mut reader = LimitedReader::new(file, limits.max_size);
+        let run = parse_llvm_lines(
+            BufReader::new(&mut reader),
+            &config.string_replace,
+            limits.max_symbols,
+        );
+        if reader.exceeded() {
+            return Err(Error::TooLarge {
+                path: Some(path.to_path_buf()),
+                max_size: limits.max_size.unwrap_or_default(),
+            }
+            .into());
+        }
+        Ok(vec![run.map_err(|error| error.in_file(path))?])
+    }
+}
+
+/// The JSON outputs of `cargo bloat --message-format json`, which hold the size of the code of
+/// each function (or of each crate, with `--crates`) of a binary.
+///
+/// They are detected as JSON objects with a `text-section-size`.
+pub struct CargoBloat;
+
+impl InputFormat for CargoBloat {
+    fn name(&self) -> &'static str {
+        "cargo bloat"
+    }
+
+    fn detect(&self, path: &Path) -> bool {
+        first_bytes(path).is_some_and(|start| {
+            start.trim_start().starts_with('{') && start.contains("\"text-section-size\"")
+        })
+    }
+
+    fn is_sample(&self, _config: &Args) -> bool {
+        true
+    }
+
+    fn load(&self, path: &Path, config: &Args) -> anyhow::Result<Vec<Run>> {
+        let limits = config.input_limits();
+        let file = File::open(path).map_err(|error| Error::from(error).in_file(path))?;
+        let mut reader = LimitedReader::new(file, limits.max_size);
+        let mut contents = String::new();
+        let read = reader.read_to_string(&mut contents);
+        if reader.exceeded() {
+            return Err(Error::TooLarge {
+                path: Some(path.to_path_buf()),
+                max_size: limits.max_size.unwrap_or_default(),
+            }
+            .into());
+        }
+        read.map_err(|error| Error::from(error).in_file(path))?;
+        let run = parse_cargo_bloat(&contents, &config.string_replace, limits.max_symbols);
+        Ok(vec![run.map_err(|error| error.in_file(path))?])
+    }
+}
+
+/// Parse the output of `cargo llvm-lines` and return a `Run` from it, in [`LINES_UNIT`].
+///
+/// The count of each function is its number of lines of LLVM IR, for all its copies
+/// (monomorphizations). The `(TOTAL)` row is the total of the run.
+///
+/// Errors have no path; see [`Error::in_file`].
+///
+/// # Errors
+/// Returns an error if the input cannot be read, a row is not well-formed or there are more
+/// than `max_symbols` functions.
+pub fn parse_llvm_lines<R: BufRead>(
+    input: R,
+    replacements: &[StringReplacement],
+    max_symbols: Option<usize>,
+) -> Result<Run> {
+    let mut lines = LineReader::new(input);
+    let mut run = Run::new();
+    run.metadata.unit = Some(LINES_UNIT.to_string());
+    let mut total = None;
+
+    while let Some((number, line)) = lines.next_line()? {
+        let parse_error = |message: String| Error::Parse {
+            path: None,
+            line: number,
+            message,
+        };
+        let line = line.trim();
+        // Skip the header and its underline.
+        if line.is_empty() || line.starts_with("Lines") || line.starts_with('-') {
+            continue;
+        }
+        let Some((count, rest)) = line.split_once(char::is_whitespace) else {
+            return Err(parse_error(format!("Unexpected line {line:?}")));
+        };
+        let Ok(count) = count.parse::<u64>() else {
+            return Err(parse_error(format!("Invalid count {count:?}")));
+        };
+        let rest = rest.trim();
+        if rest.ends_with(TOTAL_ROW) {
+            total = Some(count);
+            continue;
+        }
+        // The count is followed by its percentages, the number of copies and their percentages,
+        // e.g. `1395 (4.5%,  4.5%)      1 (0.1%,  0.1%)  <name>`.
+        let Some(name) = rest
+            .splitn(3, ')')
+            .nth(2)
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+        else {
+            return Err(parse_error(format!("Unexpected line {line:?}")));
+        };
+        let name = replacements
+            .iter()
+            .fold(name.into(), |name, replacement| replacement.perform(name));
+        run.add_ir(&name, count);
+        if let Some(max) = max_symbols {
+            if run.symbols.len() > max {
+                return Err(Error::TooManySymbols {
+                    path: None,
+                    max_symbols: max,
+                });
+            }
+        }
+    }
+    run.total_ir = total.unwrap_or_else(|| {
+        run.symbols
+            .iter()
+            .fold(0, |total: u64, symbol| total.saturating_add(symbol.ir))
+    });
+    Ok(run)
+}
+
+/// Parse the JSON output of `cargo bloat` and return a `Run` from it, in [`BYTES_UNIT`].
+///
+/// The count of each function (or crate) is the size of its code. As `cargo bloat` only lists
+/// the largest functions by default (see its `-n` option), the total of the run is the sum of the
+/// sizes of those that are listed rather than the size of the `.text` section.
+///
+/// Errors have no path; see [`Error::in_file`].
+///
+/// # Errors
+/// Returns an error if the input lists neither functions nor crates, an entry has no name or
+/// size, or there are more than `max_symbols` entries.
+pub fn parse_cargo_bloat(
+    input: &str,
+    replacements: &[StringReplacement],
+    max_symbols: Option<usize>,
+) -> Result<Run> {
+    let mismatch = |reason: String| Error::FormatMismatch {
+        path: None,
+        expected: "an output of `cargo bloat --message-format json`",
+        reason,
+    };
+    let Some(entries) =
+        json::objects(input, "functions").or_else(|| json::objects(input, "crates"))
+    else {
+        return Err(mismatch("No `functions` nor `crates`".to_string()));
+    };
+    if let Some(max) = max_symbols {
+        if entries.len() > max {
+            return Err(Error::TooManySymbols {
+                path: None,
+                max_symbols: max,
+            });
+        }
+    }
+    let mut run = Run::new();
+    run.metadata.unit = Some(BYTES_UNIT.to_string());
+    for entry in entries {
+        let (Some(name), Some(size)) = (json::string(entry, "name"), json::number(entry, "size"))
+        else {
+            return Err(mismatch(format!("Invalid entry {entry}")));
+        };
+        let name = replacements
+            .iter()
+            .fold(name.as_str().into(), |name, replacement| {
+                replacement.perform(name)
+            });
+        run.add_ir(&name, size as u64);
+    }
+    run.total_ir = run
+        .symbols
+        .iter()
+        .fold(0, |total: u64, symbol| total.saturating_add(symbol.ir));
+    Ok(run)
+}
+
+/// Return the first bytes of the file at `path`, or `None` if it cannot be read.
+pub(crate) fn first_bytes(path: &Path) -> Option<String> {
+    let file = File::open(path).ok()?;
+    let mut start = vec![];
+    LimitedReader::new(file, Some(1024))
+        .read_to_end(&mut start)
+        .ok()?;
+    Some(String::from_utf8_lossy(&start).into_owned())
+}
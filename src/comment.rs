@@ -0,0 +1,158 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+};
+
+use anyhow::{Context, Result};
+
+use crate::{
+    args::Args,
+    diff::{DiffCell, DiffTable},
+    regression::Regression,
+    summary::Summary,
+};
+
+/// Write a Markdown report, meant to be posted as a pull request comment, to `--export-pr-comment`.
+///
+/// The report compares the last column to its reference. It starts with hidden HTML comments
+/// that bots can use:
+///   * If `--comment-marker` is set, `<!-- callgrind_differ:<token> -->`, so that a bot can find
+///     the comment it previously posted and update it.
+///   * A front-matter block with the headline numbers, one `key: value` per line. Keys are never
+///     removed nor renamed.
+///
+/// It is followed by the total IR and a table of the symbols whose IR changed, the header of which
+/// shows the note of the run of the last column, if any, when hovered.
+///
+/// # Errors
+/// Returns an error if the report cannot be written.
+pub fn export_pr_comment(
+    config: &Args,
+    table: &DiffTable,
+    regressions: &[Regression],
+) -> Result<()> {
+    let path = &config.export_pr_comment;
+    let mut writer =
+        BufWriter::new(File::create(path).with_context(|| format!("Failed to create {path}"))?);
+    write_pr_comment(config, table, regressions, &mut writer)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Write the report described in [`export_pr_comment`] into `w`.
+///
+/// # Errors
+/// Returns an error if writing to `w` fails.
+pub fn write_pr_comment<W: Write>(
+    config: &Args,
+    table: &DiffTable,
+    regressions: &[Regression],
+    w: &mut W,
+) -> Result<()> {
+    let column = table.last_column();
+    let summary = Summary::new(table, regressions);
+    let total = table.total[column];
+    let (total_ir, reference_total_ir) = (summary.total_ir, summary.reference_total_ir);
+    let n_failures = summary.n_failures();
+
+    // Hidden, machine-readable part.
+    if let Some(marker) = &config.comment_marker {
+        writeln!(w, "<!-- callgrind_differ:{marker} -->")?;
+    }
+    writeln!(w, "<!--")?;
+    writeln!(w, "reference_total_ir: {reference_total_ir}")?;
+    writeln!(w, "total_ir: {total_ir}")?;
+    writeln!(w, "total_ir_diff: {}", signed_diff(&total))?;
+    writeln!(w, "total_ir_percent: {}", signed_percent(&total))?;
+    writeln!(w, "symbols_increased: {}", summary.symbols_increased)?;
+    writeln!(w, "symbols_decreased: {}", summary.symbols_decreased)?;
+    writeln!(w, "regressions: {}", regressions.len())?;
+    writeln!(w, "failures: {n_failures}")?;
+    writeln!(w, "-->")?;
+
+    // Human-readable part.
+    writeln!(w, "## Instruction count report")?;
+    writeln!(w)?;
+    writeln!(
+        w,
+        "**Total IR:** {reference_total_ir} → {total_ir} ({}, {}%)",
+        signed_diff(&total),
+        signed_percent(&total)
+    )?;
+    writeln!(w)?;
+    if n_failures != 0 {
+        writeln!(
+            w,
+            ":x: {n_failures} symbol(s) regressed beyond their allowed threshold."
+        )?;
+        writeln!(w)?;
+    }
+    let changed = table
+        .rows
+        .iter()
+        .filter(|row| row.cells[column].diff() != 0)
+        .collect::<Vec<_>>();
+    if changed.is_empty() {
+        writeln!(w, "No symbol changed.")?;
+        return Ok(());
+    }
+    // The note of the run, if any, is shown when hovering the header of its column.
+    let current = match summary.note {
+        Some(note) => format!("<abbr title=\"{}\">Current</abbr>", html_attribute(note)),
+        None => "Current".to_string(),
+    };
+    writeln!(w, "| Symbol | Reference | {current} | Diff | % |")?;
+    writeln!(w, "|:-------|----------:|--------:|-----:|--:|")?;
+    for row in changed {
+        let cell = &row.cells[column];
+        // Symbols renamed in the history show their former names when hovered.
+        let name = format!("`{}`", row.name().replace('|', "\\|").replace('`', "'"));
+        let name = if row.symbol.renamed_from.is_empty() {
+            name
+        } else {
+            format!(
+                "<abbr title=\"Formerly {}\">{name}</abbr>",
+                html_attribute(&row.symbol.renamed_from.join(", "))
+            )
+        };
+        writeln!(
+            w,
+            "| {name} | {} | {} | {} | {}% |",
+            cell.reference_ir(),
+            cell.ir,
+            signed_diff(cell),
+            signed_percent(cell)
+        )?;
+    }
+    Ok(())
+}
+
+/// Escape `s` to be the value of a quoted HTML attribute.
+pub(crate) fn html_attribute(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('|', "&#124;")
+}
+
+/// Format the difference between the IR count of `cell` and its reference with an explicit sign.
+pub(crate) fn signed_diff(cell: &DiffCell) -> String {
+    if cell.is_decrease() {
+        format!("-{}", cell.abs_diff())
+    } else {
+        format!("+{}", cell.abs_diff())
+    }
+}
+
+/// Format the change of the IR count of `cell` as a percentage with an explicit sign.
+///
+/// A change from 0 is shown as a 100% increase.
+pub(crate) fn signed_percent(cell: &DiffCell) -> String {
+    let percent = cell.percent();
+    if cell.is_decrease() {
+        format!("-{:.3}", -percent)
+    } else {
+        format!("+{percent:.3}")
+    }
+}
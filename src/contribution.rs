@@ -0,0 +1,154 @@
+use crate::{diff::DiffTable, runs::Records};
+
+/// The change of the total IR of the last column to its reference, broken down by symbol (see
+/// `--explain-total`).
+pub struct TotalChange<'a> {
+    /// The signed difference of the total IR.
+    pub diff: i128,
+    /// The changes of the symbols, the largest (in absolute value) first.
+    pub contributions: Vec<Contribution<'a>>,
+    /// The part of [`Self::diff`] which is not accounted for by the symbols, e.g. because
+    /// `callgrind_annotate` omitted those below its threshold.
+    pub unaccounted: i128,
+    /// [`Self::unaccounted`], in percent of the difference of the total IR, so that the shares of
+    /// the symbols and of the unaccounted part sum to 100%.
+    pub unaccounted_share: f64,
+}
+
+/// The part a symbol had in the change of the total IR.
+pub struct Contribution<'a> {
+    /// The name of the symbol.
+    pub symbol: &'a str,
+    /// The signed difference of the IR of the symbol.
+    pub diff: i128,
+    /// [`Self::diff`], in percent of the difference of the total IR.
+    ///
+    /// Symbols which changed in the other direction than the total have a negative share, and
+    /// shares may exceed 100% when they cancel out.
+    pub share: f64,
+    /// The sum of the shares of this symbol and of those before it.
+    pub cumulative_share: f64,
+    /// Whether the symbol was not measured in the run or in its reference (see
+    /// [`crate::diff::DiffCell::unmeasured`]), i.e. it appeared or vanished. Its count there is
+    /// taken as 0, so that [`Self::diff`] is the count it has in the other.
+    pub unmeasured: bool,
+}
+
+impl Contribution<'_> {
+    /// Return whether the symbol appeared in the run, i.e. it was only measured there.
+    pub fn appeared(&self) -> bool {
+        self.unmeasured && self.diff > 0
+    }
+
+    /// Return whether the symbol vanished from the run, i.e. it was only measured in its reference.
+    pub fn vanished(&self) -> bool {
+        self.unmeasured && self.diff < 0
+    }
+}
+
+impl<'a> TotalChange<'a> {
+    /// Break down the change of the total IR of the last column of `table` by symbol.
+    ///
+    /// Symbols whose IR did not change are left out. Symbols which appeared or vanished are
+    /// listed with their whole count, though it is unknown in the run which does not list them.
+    /// If the total IR did not change, shares are 0.
+    pub fn new(table: &DiffTable<'a>) -> Self {
+        let column = table.last_column();
+        let diff = table.total[column].diff();
+        let share_of = |symbol_diff: i128| {
+            if diff == 0 {
+                0.0
+            } else {
+                symbol_diff as f64 * 100.0 / diff as f64
+            }
+        };
+
+        let mut contributions = table
+            .rows
+            .iter()
+            .map(|row| {
+                let cell = &row.cells[column];
+                // Unmeasured cells are unchanged; their counts are compared nonetheless.
+                let reference = cell.reference.unwrap_or(cell.ir);
+                let diff = i128::from(cell.ir) - i128::from(reference);
+                (&*row.symbol.name, diff, cell.unmeasured)
+            })
+            .filter(|(_, diff, _)| *diff != 0)
+            .map(|(symbol, diff, unmeasured)| Contribution {
+                symbol,
+                diff,
+                share: share_of(diff),
+                cumulative_share: 0.0,
+                unmeasured,
+            })
+            .collect::<Vec<_>>();
+        // Names break ties so that the order does not depend on `--sort-by`.
+        contributions.sort_unstable_by(|a, b| {
+            b.diff
+                .unsigned_abs()
+                .cmp(&a.diff.unsigned_abs())
+                .then_with(|| a.symbol.cmp(b.symbol))
+        });
+        let mut cumulative_share = 0.0;
+        for contribution in &mut contributions {
+            cumulative_share += contribution.share;
+            contribution.cumulative_share = cumulative_share;
+        }
+
+        let accounted = contributions
+            .iter()
+            .map(|contribution| contribution.diff)
+            .sum::<i128>();
+        let unaccounted = diff - accounted;
+        Self {
+            diff,
+            contributions,
+            unaccounted,
+            unaccounted_share: share_of(unaccounted),
+        }
+    }
+}
+
+/// A symbol whose IR count changed from one run to the next.
+pub struct Mover<'a> {
+    /// The name of the symbol.
+    pub symbol: &'a str,
+    /// The IR count of the symbol in the earlier run.
+    pub before: u64,
+    /// The IR count of the symbol in the later run.
+    pub after: u64,
+}
+
+impl Mover<'_> {
+    /// Return the signed difference of the IR count.
+    pub fn diff(&self) -> i128 {
+        i128::from(self.after) - i128::from(self.before)
+    }
+}
+
+/// Return the (at most) `k` symbols whose IR count changed the most in absolute value from the
+/// run at `column - 1` to the run at `column`, the largest change first (see `--movers`).
+///
+/// # Panics
+/// Panics if `column` is 0 or not a column of `records`.
+pub fn top_movers(records: &Records, column: usize, k: usize) -> Vec<Mover<'_>> {
+    let mut movers = records
+        .symbols
+        .iter()
+        .map(|symbol| Mover {
+            symbol: &symbol.name,
+            before: symbol.irs[column - 1],
+            after: symbol.irs[column],
+        })
+        .filter(|mover| mover.before != mover.after)
+        .collect::<Vec<_>>();
+    // Names break ties so that the order does not depend on `--sort-by`.
+    movers.sort_unstable_by(|a, b| {
+        b.diff()
+            .unsigned_abs()
+            .cmp(&a.diff().unsigned_abs())
+            .then_with(|| a.symbol.cmp(b.symbol))
+    });
+    movers.truncate(k);
+    movers
+}
@@ -0,0 +1,77 @@
+use crate::runs::Records;
+
+/// How strongly the IR count of a symbol moves with that of another, across runs.
+pub struct Correlation<'a> {
+    /// The name of the symbol.
+    pub symbol: &'a str,
+    /// The Pearson correlation coefficient of the IR counts of both symbols, from -1 (one
+    /// decreases when the other increases) to 1 (both increase together).
+    pub coefficient: f64,
+}
+
+/// Rank the other symbols of `records` by how strongly their IR count correlates with that of
+/// `symbol` across runs (see `--correlate`).
+///
+/// Symbols are ranked by the absolute value of their coefficient, the strongest first, as a
+/// symbol moving against `symbol` (e.g. code inlined into it) is as telling as one moving with
+/// it. Symbols whose IR count never changes have no correlation and are left out, as are all
+/// symbols if that of `symbol` never changes. Returns `None` if there is no such symbol.
+pub fn correlate<'a>(records: &'a Records, symbol: &str) -> Option<Vec<Correlation<'a>>> {
+    let target = records
+        .symbols
+        .iter()
+        .find(|candidate| &*candidate.name == symbol)?;
+    let mut correlations = records
+        .symbols
+        .iter()
+        .filter(|other| other.name != target.name)
+        .filter_map(|other| {
+            Some(Correlation {
+                symbol: &other.name,
+                coefficient: correlation_of(&target.irs, &other.irs)?,
+            })
+        })
+        .collect::<Vec<_>>();
+    // Names break ties so that the order does not depend on `--sort-by`.
+    correlations.sort_unstable_by(|a, b| {
+        b.coefficient
+            .abs()
+            .total_cmp(&a.coefficient.abs())
+            .then_with(|| a.symbol.cmp(b.symbol))
+    });
+    Some(correlations)
+}
+
+/// Return the Pearson correlation coefficient of `a` and `b`, which have the same length.
+///
+/// Returns `None` if either is constant, in which case they are not correlated.
+///
+/// ```
+/// # use callgrind_differ::correlation::correlation_of;
+/// assert_eq!(correlation_of(&[1, 2, 3], &[10, 20, 30]), Some(1.0));
+/// assert_eq!(correlation_of(&[1, 2, 3], &[30, 20, 10]), Some(-1.0));
+/// assert_eq!(correlation_of(&[1, 2, 3], &[5, 5, 5]), None);
+/// ```
+pub fn correlation_of(a: &[u64], b: &[u64]) -> Option<f64> {
+    let n = a.len() as f64;
+    let mean_a = a.iter().map(|x| *x as f64).sum::<f64>() / n;
+    let mean_b = b.iter().map(|x| *x as f64).sum::<f64>() / n;
+    let (covariance, variance_a, variance_b) = a
+        .iter()
+        .zip(b)
+        .map(|(x, y)| (*x as f64 - mean_a, *y as f64 - mean_b))
+        .fold(
+            (0.0, 0.0, 0.0),
+            |(covariance, variance_a, variance_b), (dx, dy)| {
+                (
+                    covariance + dx * dy,
+                    variance_a + dx * dx,
+                    variance_b + dy * dy,
+                )
+            },
+        );
+    if variance_a <= 0.0 || variance_b <= 0.0 {
+        return None;
+    }
+    Some((covariance / (variance_a * variance_b).sqrt()).clamp(-1.0, 1.0))
+}
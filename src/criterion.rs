@@ -0,0 +1,159 @@
+use std::{
+    fs::File,
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    args::Args,
+    callgrind::{InputLimits, LimitedReader},
+    error::{Error, Result},
+    format::InputFormat,
+    json,
+    runs::Run,
+};
+
+/// The path, relative to the directory of a benchmark, of the estimates of its latest run.
+const ESTIMATES_PATH: &str = "new/estimates.json";
+
+/// The path, relative to the directory of a benchmark, of the description of its latest run.
+const BENCHMARK_PATH: &str = "new/benchmark.json";
+
+/// The unit of the estimates of Criterion.rs.
+const UNIT: &str = "ns";
+
+/// The output directories of Criterion.rs (e.g. `target/criterion`), which hold the wall times
+/// of benchmarks.
+///
+/// They are detected as directories holding at least one benchmark, i.e. a directory with a
+/// `new/estimates.json` file. Each directory is loaded as a single run (see [`load`]).
+pub struct Criterion;
+
+impl InputFormat for Criterion {
+    fn name(&self) -> &'static str {
+        "Criterion.rs"
+    }
+
+    fn detect(&self, path: &Path) -> bool {
+        path.is_dir() && find_benchmarks(path).is_ok_and(|benchmarks| !benchmarks.is_empty())
+    }
+
+    fn is_sample(&self, _config: &Args) -> bool {
+        false
+    }
+
+    fn load(&self, path: &Path, config: &Args) -> anyhow::Result<Vec<Run>> {
+        Ok(vec![load(path, config.input_limits())?])
+    }
+}
+
+/// Load the latest run of each benchmark of a Criterion.rs output directory as a run named after
+/// the directory.
+///
+/// Each symbol of the run is a benchmark, named after its ID (e.g. `parse/large` for the `large`
+/// benchmark of the `parse` group). Its count is the mean of its wall time, in nanoseconds, and
+/// its standard deviation that estimated by Criterion.rs. The unit of the run is thus `ns`.
+///
+/// The `max_size` of `limits` applies to each file read, and its `max_symbols` to the number of
+/// benchmarks.
+///
+/// # Errors
+/// Returns an error if a file cannot be read or is too large, estimates are missing from an
+/// `estimates.json` file or there are too many benchmarks.
+pub fn load(dir: &Path, limits: InputLimits) -> Result<Run> {
+    let benchmarks = find_benchmarks(dir)?;
+    if let Some(max) = limits.max_symbols {
+        if benchmarks.len() > max {
+            return Err(Error::TooManySymbols {
+                path: Some(dir.to_path_buf()),
+                max_symbols: max,
+            });
+        }
+    }
+    let name = dir.file_name().map_or_else(
+        || dir.display().to_string(),
+        |name| name.to_string_lossy().into_owned(),
+    );
+    let mut run = Run::new_named(name);
+    run.metadata.unit = Some(UNIT.to_string());
+    for benchmark in benchmarks {
+        let estimates_path = benchmark.join(ESTIMATES_PATH);
+        let estimates = read(&estimates_path, limits.max_size)?;
+        let estimate = |key: &str| {
+            json::value(&estimates, key)
+                .and_then(|estimate| json::number(estimate, "point_estimate"))
+                .ok_or_else(|| Error::FormatMismatch {
+                    path: Some(estimates_path.clone()),
+                    expected: "a Criterion.rs `estimates.json` file",
+                    reason: format!("No `{key}` estimate"),
+                })
+        };
+        let (mean, stddev) = (estimate("mean")?, estimate("std_dev")?);
+        // The ID of the benchmark is recorded next to its estimates since Criterion.rs 0.3; the
+        // directory is named after a sanitized version of it.
+        let id = read(&benchmark.join(BENCHMARK_PATH), limits.max_size)
+            .ok()
+            .and_then(|description| json::string(&description, "full_id"))
+            .unwrap_or_else(|| {
+                benchmark
+                    .strip_prefix(dir)
+                    .unwrap_or(&benchmark)
+                    .to_string_lossy()
+                    .into_owned()
+            });
+        run.add_ir(&id, mean.max(0.0).round() as u64);
+        if let Some(symbol) = run.symbols.iter_mut().find(|symbol| *symbol.name == *id) {
+            symbol.stddev = stddev;
+        }
+    }
+    run.total_ir = run
+        .symbols
+        .iter()
+        .fold(0, |total: u64, symbol| total.saturating_add(symbol.ir));
+    Ok(run)
+}
+
+/// Return the directories of the benchmarks in `dir`, sorted by path.
+///
+/// A benchmark is a directory with a `new/estimates.json` file. Benchmarks are not nested: the
+/// directories of a benchmark are not searched.
+///
+/// # Errors
+/// Returns an error if a directory cannot be read.
+fn find_benchmarks(dir: &Path) -> Result<Vec<PathBuf>> {
+    if dir.join(ESTIMATES_PATH).is_file() {
+        return Ok(vec![dir.to_path_buf()]);
+    }
+    let io_error = |source| Error::Io {
+        path: Some(dir.to_path_buf()),
+        source,
+    };
+    let mut benchmarks = vec![];
+    for entry in std::fs::read_dir(dir).map_err(io_error)? {
+        let path = entry.map_err(io_error)?.path();
+        if path.is_dir() {
+            benchmarks.extend(find_benchmarks(&path)?);
+        }
+    }
+    benchmarks.sort();
+    Ok(benchmarks)
+}
+
+/// Read the file at `path` to a string, which must be at most `max_size` bytes, if limited.
+///
+/// # Errors
+/// Returns an error if the file cannot be read or is too large.
+fn read(path: &Path, max_size: Option<u64>) -> Result<String> {
+    let file = File::open(path).map_err(|error| Error::from(error).in_file(path))?;
+    let mut reader = LimitedReader::new(file, max_size);
+    let mut contents = String::new();
+    let read = reader.read_to_string(&mut contents);
+    if reader.exceeded() {
+        return Err(Error::TooLarge {
+            path: Some(path.to_path_buf()),
+            max_size: max_size.unwrap_or_default(),
+        });
+    }
+    read.map_err(|error| Error::from(error).in_file(path))?;
+    Ok(contents)
+}
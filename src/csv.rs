@@ -0,0 +1,177 @@
+use std::io::{self, BufRead, Write};
+
+use crate::{
+    args::StringReplacement,
+    callgrind::LineReader,
+    error::{Error, Result},
+    runs::{Records, Run},
+};
+
+/// Parse a CSV file and return its runs, one per column of counts.
+///
+/// Each row is a symbol: its name, then its IR count in each run. The first row is a header if
+/// and only if its first cell contains `name` and its second cell cannot be parsed as an integer;
+/// the other cells of the header are then the names of the runs. Cells may be quoted with `"`,
+/// in which case `""` stands for a `"`, so that symbols may hold commas.
+///
+/// CSVs hold no total IR: the total IR of each run is the sum of the counts of its symbols.
+///
+/// Errors have no path; see [`Error::in_file`].
+///
+/// # Errors
+/// Returns an error if the input cannot be read, a row does not have as many cells as the first
+/// one, a count is not an integer or there are more than `max_symbols` symbols.
+pub fn parse<R: BufRead>(
+    input: R,
+    replacements: &[StringReplacement],
+    max_symbols: Option<usize>,
+) -> Result<Vec<Run>> {
+    let mut lines = LineReader::new(input);
+    let mut runs: Option<Vec<Run>> = None;
+
+    while let Some((number, line)) = lines.next_line()? {
+        let parse_error = |message: String| Error::Parse {
+            path: None,
+            line: number,
+            message,
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let cells = split_cells(line).map_err(parse_error)?;
+        let Some((symbol, counts)) = cells.split_first() else {
+            continue;
+        };
+        let is_header = symbol.contains("name")
+            && counts
+                .first()
+                .is_some_and(|count| count.parse::<u64>().is_err());
+        if runs.is_none() && is_header {
+            runs = Some(counts.iter().cloned().map(Run::new_named).collect());
+            continue;
+        }
+        let runs = runs.get_or_insert_with(|| counts.iter().map(|_| Run::new()).collect());
+        add_row(runs, symbol, counts, replacements).map_err(parse_error)?;
+        if let Some(max) = max_symbols {
+            if runs.first().is_some_and(|run| run.symbols.len() > max) {
+                return Err(Error::TooManySymbols {
+                    path: None,
+                    max_symbols: max,
+                });
+            }
+        }
+    }
+
+    let mut runs = runs.unwrap_or_default();
+    for run in &mut runs {
+        run.total_ir = run
+            .symbols
+            .iter()
+            .fold(0, |total: u64, symbol| total.saturating_add(symbol.ir));
+    }
+    Ok(runs)
+}
+
+/// Write the IR counts of `records` as a CSV that [`parse`] reads back.
+///
+/// The first row is a header naming the runs; each other row is a symbol, with its IR count in
+/// each run. Cells are quoted where needed. A run named after an integer is not recognized as a
+/// header by [`parse`], nor is a first run with an IR count of 0.
+///
+/// # Errors
+/// Returns an error if writing fails.
+pub fn write<W: Write>(records: &Records, mut writer: W) -> io::Result<()> {
+    write!(writer, "name")?;
+    for name in &records.run_names {
+        write!(writer, ",{}", quote(name))?;
+    }
+    writeln!(writer)?;
+    for symbol in &records.symbols {
+        write!(writer, "{}", quote(&symbol.name))?;
+        for ir in &symbol.irs {
+            write!(writer, ",{ir}")?;
+        }
+        writeln!(writer)?;
+    }
+    Ok(())
+}
+
+/// Quote a cell if it would otherwise not be read back as is by [`split_cells`].
+fn quote(cell: &str) -> String {
+    if cell.contains([',', '"']) || cell.trim() != cell {
+        format!("\"{}\"", cell.replace('"', "\"\""))
+    } else {
+        cell.to_string()
+    }
+}
+
+/// Add the counts of a row to each run.
+fn add_row(
+    runs: &mut [Run],
+    symbol: &str,
+    counts: &[String],
+    replacements: &[StringReplacement],
+) -> std::result::Result<(), String> {
+    if counts.len() != runs.len() {
+        return Err(format!(
+            "Expected {} counts, got {}",
+            runs.len(),
+            counts.len()
+        ));
+    }
+    let symbol = replacements
+        .iter()
+        .fold(symbol.into(), |symbol, replacement| {
+            replacement.perform(symbol)
+        });
+    for (run, count) in runs.iter_mut().zip(counts) {
+        let Ok(ir) = count.parse::<u64>() else {
+            return Err(format!("Invalid count {count:?}"));
+        };
+        run.add_ir(&symbol, ir);
+    }
+    Ok(())
+}
+
+/// Split a line into its cells, unquoting them. Unquoted cells are trimmed.
+fn split_cells(line: &str) -> std::result::Result<Vec<String>, String> {
+    let mut cells = vec![];
+    let mut chars = line.chars().peekable();
+    loop {
+        let mut cell = String::new();
+        // Whether the cell is the last of the line.
+        let mut is_last = true;
+        if chars.peek() == Some(&'"') {
+            chars.next();
+            loop {
+                match chars.next() {
+                    Some('"') if chars.peek() == Some(&'"') => {
+                        chars.next();
+                        cell.push('"');
+                    }
+                    Some('"') => break,
+                    Some(c) => cell.push(c),
+                    None => return Err("Unterminated quoted cell".to_string()),
+                }
+            }
+            match chars.next() {
+                Some(',') => is_last = false,
+                None => {}
+                Some(c) => return Err(format!("Unexpected {c:?} after a quoted cell")),
+            }
+        } else {
+            for c in chars.by_ref() {
+                if c == ',' {
+                    is_last = false;
+                    break;
+                }
+                cell.push(c);
+            }
+            cell = cell.trim().to_string();
+        }
+        cells.push(cell);
+        if is_last {
+            return Ok(cells);
+        }
+    }
+}
@@ -0,0 +1,345 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use anyhow::{Context, Result};
+
+use crate::{
+    comment::{html_attribute, signed_diff, signed_percent},
+    diff::{DiffCell, DiffRow, DiffTable},
+    graph,
+    history::format_timestamp,
+    regression::{Regression, Severity},
+};
+
+/// The name of the directory of a dashboard in which the pages of the symbols are.
+const SYMBOLS_DIR_NAME: &str = "symbols";
+
+/// The number of characters commit hashes are abbreviated to.
+const COMMIT_LENGTH: usize = 12;
+
+/// The style sheet of the pages of a dashboard.
+const STYLE: &str = "body { font-family: sans-serif; margin: 2em auto; max-width: 1000px; } \
+    table { border-collapse: collapse; } \
+    th, td { padding: 0.2em 0.6em; border-bottom: 1px solid #ddd; text-align: left; } \
+    td.count { text-align: right; font-family: monospace; } \
+    .increase { color: #b00; } .decrease { color: #080; } \
+    .error { background: #fdd; } .warning { background: #ffd; }";
+
+/// Write a static HTML site of the runs of the history to `out_dir`, for `history dashboard`.
+///
+/// The `index.html` page shows the trend of the total IR and of the heaviest symbols (see
+/// [`graph::write_graph`]), the regressions of the last run, the symbols whose IR changed in the
+/// last run compared to its reference, and the runs. Each symbol has a page of its own in
+/// `symbols/`, with the graph of its IR and its IR in each run. Pages only link to each other
+/// with relative links, so that the site can be published as is (e.g. to GitHub Pages).
+///
+/// # Errors
+/// Returns an error if a directory or a page cannot be written.
+pub fn export_dashboard(
+    out_dir: &str,
+    table: &DiffTable,
+    regressions: &[Regression],
+) -> Result<()> {
+    let dir = Path::new(out_dir);
+    let symbols_dir = dir.join(SYMBOLS_DIR_NAME);
+    std::fs::create_dir_all(&symbols_dir)
+        .with_context(|| format!("Failed to create {}", symbols_dir.display()))?;
+    let pages = table
+        .rows
+        .iter()
+        .enumerate()
+        .map(|(i, row)| (row.name(), format!("{SYMBOLS_DIR_NAME}/{i}.html")))
+        .collect::<HashMap<_, _>>();
+    write_page(&dir.join("index.html"), "Instruction counts", |w| {
+        write_index(table, regressions, &pages, w)
+    })?;
+    for (i, row) in table.rows.iter().enumerate() {
+        write_page(&symbols_dir.join(format!("{i}.html")), row.name(), |w| {
+            write_symbol_page(table, row, regressions, w)
+        })?;
+    }
+    Ok(())
+}
+
+/// Write a page titled `title` to `path`, its body written with `write_body`.
+///
+/// # Errors
+/// Returns an error if the page cannot be written.
+fn write_page<F>(path: &Path, title: &str, write_body: F) -> Result<()>
+where
+    F: FnOnce(&mut BufWriter<File>) -> Result<()>,
+{
+    let mut writer = BufWriter::new(
+        File::create(path).with_context(|| format!("Failed to create {}", path.display()))?,
+    );
+    writeln!(writer, "<!DOCTYPE html>")?;
+    writeln!(writer, "<html lang=\"en\">")?;
+    writeln!(writer, "<head>")?;
+    writeln!(writer, "<meta charset=\"utf-8\">")?;
+    writeln!(writer, "<title>{}</title>", html_attribute(title))?;
+    writeln!(writer, "<style>{STYLE}</style>")?;
+    writeln!(writer, "</head>")?;
+    writeln!(writer, "<body>")?;
+    write_body(&mut writer)?;
+    writeln!(writer, "</body>")?;
+    writeln!(writer, "</html>")?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Write the body of the `index.html` page of a dashboard into `w`, linking to the page of each
+/// symbol of `pages`.
+///
+/// # Errors
+/// Returns an error if writing to `w` fails.
+fn write_index<W: Write>(
+    table: &DiffTable,
+    regressions: &[Regression],
+    pages: &HashMap<&str, String>,
+    w: &mut W,
+) -> Result<()> {
+    let column = table.last_column();
+    let total = &table.total[column];
+    writeln!(w, "<h1>Instruction counts</h1>")?;
+    writeln!(
+        w,
+        "<p>{} run(s), the latest being {}.</p>",
+        table.n_runs(),
+        run_description(table, column)
+    )?;
+    writeln!(w, "<h2>Total IR</h2>")?;
+    writeln!(
+        w,
+        "<p>{} → {} ({}, {}%)</p>",
+        total.reference_ir(),
+        total.ir,
+        signed_diff(total),
+        signed_percent(total)
+    )?;
+    graph::write_graph(table, w)?;
+
+    writeln!(w, "<h2>Regressions</h2>")?;
+    let all = regressions.iter().collect::<Vec<_>>();
+    write_regressions(&all, |symbol| pages.get(symbol).cloned(), w)?;
+
+    writeln!(w, "<h2>Latest changes</h2>")?;
+    let changed = table
+        .rows
+        .iter()
+        .filter(|row| row.cells[column].diff() != 0)
+        .collect::<Vec<_>>();
+    if changed.is_empty() {
+        writeln!(w, "<p>No symbol changed.</p>")?;
+    } else {
+        writeln!(
+            w,
+            "<table><tr><th>Symbol</th><th>Reference</th><th>Current</th><th>Diff</th><th>%</th></tr>"
+        )?;
+        for row in changed {
+            let severity = regressions
+                .iter()
+                .filter(|regression| regression.is_ir() && regression.symbol == row.name())
+                .map(|regression| regression.severity)
+                .max();
+            let cell = &row.cells[column];
+            writeln!(
+                w,
+                "<tr{}><td><a href=\"{}\"><code>{}</code></a></td><td class=\"count\">{}</td><td class=\"count\">{}</td>{}</tr>",
+                severity.map_or(String::new(), |severity| format!(
+                    " class=\"{}\"",
+                    severity_class(severity)
+                )),
+                pages[row.name()],
+                html_attribute(row.name()),
+                cell.reference_ir(),
+                cell.ir,
+                diff_cells(cell)
+            )?;
+        }
+        writeln!(w, "</table>")?;
+    }
+
+    writeln!(w, "<h2>Runs</h2>")?;
+    writeln!(
+        w,
+        "<table><tr><th>Run</th><th>Date</th><th>Commit</th><th>Total IR</th><th>Note</th></tr>"
+    )?;
+    for (i, total) in table.total.iter().enumerate() {
+        let metadata = &table.records.runs_metadata[i];
+        writeln!(
+            w,
+            "<tr><td>{}</td><td>{}</td><td><code>{}</code></td><td class=\"count\">{}</td><td>{}</td></tr>",
+            html_attribute(&table.records.run_names[i]),
+            metadata.timestamp.map(format_timestamp).unwrap_or_default(),
+            metadata
+                .commit
+                .as_deref()
+                .map(|commit| commit.chars().take(COMMIT_LENGTH).collect::<String>())
+                .unwrap_or_default(),
+            total.ir,
+            html_attribute(metadata.note.as_deref().unwrap_or_default())
+        )?;
+    }
+    writeln!(w, "</table>")?;
+
+    writeln!(w, "<h2>Symbols</h2>")?;
+    writeln!(w, "<ul>")?;
+    for row in &table.rows {
+        writeln!(
+            w,
+            "<li><a href=\"{}\"><code>{}</code></a></li>",
+            pages[row.name()],
+            html_attribute(row.name())
+        )?;
+    }
+    writeln!(w, "</ul>")?;
+    Ok(())
+}
+
+/// Write the body of the page of the symbol of `row` into `w`.
+///
+/// # Errors
+/// Returns an error if writing to `w` fails.
+fn write_symbol_page<W: Write>(
+    table: &DiffTable,
+    row: &DiffRow,
+    regressions: &[Regression],
+    w: &mut W,
+) -> Result<()> {
+    writeln!(w, "<p><a href=\"../index.html\">Dashboard</a></p>")?;
+    writeln!(w, "<h1><code>{}</code></h1>", html_attribute(row.name()))?;
+    if !row.symbol.renamed_from.is_empty() {
+        writeln!(
+            w,
+            "<p>Formerly {}.</p>",
+            row.symbol
+                .renamed_from
+                .iter()
+                .map(|name| format!("<code>{}</code>", html_attribute(name)))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )?;
+    }
+    let regressions = regressions
+        .iter()
+        .filter(|regression| regression.symbol == row.name())
+        .collect::<Vec<_>>();
+    if !regressions.is_empty() {
+        writeln!(w, "<h2>Regressions</h2>")?;
+        write_regressions(&regressions, |_| None, w)?;
+    }
+    writeln!(w, "<h2>IR</h2>")?;
+    graph::write_symbol_graph(table, row, w)?;
+    writeln!(
+        w,
+        "<table><tr><th>Run</th><th>Date</th><th>IR</th><th>Diff</th><th>%</th></tr>"
+    )?;
+    for (i, cell) in row.cells.iter().enumerate() {
+        let diff = if table.is_reference(i) {
+            "<td></td><td></td>".to_string()
+        } else {
+            diff_cells(cell)
+        };
+        writeln!(
+            w,
+            "<tr><td>{}</td><td>{}</td><td class=\"count\">{}</td>{diff}</tr>",
+            html_attribute(&table.records.run_names[i]),
+            table.records.runs_metadata[i]
+                .timestamp
+                .map(format_timestamp)
+                .unwrap_or_default(),
+            cell.ir,
+        )?;
+    }
+    writeln!(w, "</table>")?;
+    Ok(())
+}
+
+/// Write a list of `regressions` into `w`, linking each symbol to the page `page_of` returns for
+/// it, if any.
+///
+/// # Errors
+/// Returns an error if writing to `w` fails.
+fn write_regressions<W, F>(regressions: &[&Regression], page_of: F, w: &mut W) -> Result<()>
+where
+    W: Write,
+    F: Fn(&str) -> Option<String>,
+{
+    if regressions.is_empty() {
+        writeln!(w, "<p>No regression.</p>")?;
+        return Ok(());
+    }
+    writeln!(w, "<ul>")?;
+    for regression in regressions {
+        let symbol = format!("<code>{}</code>", html_attribute(regression.symbol));
+        let symbol = match page_of(regression.symbol) {
+            Some(page) => format!("<a href=\"{page}\">{symbol}</a>"),
+            None => symbol,
+        };
+        writeln!(
+            w,
+            "<li class=\"{}\">{symbol}: {} {} → {} (+{:.3}%)</li>",
+            severity_class(regression.severity),
+            regression.event,
+            regression.reference_ir,
+            regression.ir,
+            regression.percent
+        )?;
+    }
+    writeln!(w, "</ul>")?;
+    Ok(())
+}
+
+/// Return the difference and percentage cells of a table row for `cell`, colored by the
+/// direction of the change.
+fn diff_cells(cell: &DiffCell) -> String {
+    let class = if cell.is_increase() {
+        "count increase"
+    } else if cell.is_decrease() {
+        "count decrease"
+    } else {
+        "count"
+    };
+    format!(
+        "<td class=\"{class}\">{}</td><td class=\"{class}\">{}%</td>",
+        signed_diff(cell),
+        signed_percent(cell)
+    )
+}
+
+/// Return the CSS class of the elements showing a regression of the given severity.
+fn severity_class(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Warning => "warning",
+        Severity::Error => "error",
+    }
+}
+
+/// Describe the run of the given column: its name, date and commit, if known.
+fn run_description(table: &DiffTable, column: usize) -> String {
+    let metadata = &table.records.runs_metadata[column];
+    let mut description = format!(
+        "<b>{}</b>",
+        html_attribute(&table.records.run_names[column])
+    );
+    let details = [
+        metadata.timestamp.map(format_timestamp),
+        metadata.commit.as_deref().map(|commit| {
+            format!(
+                "commit <code>{}</code>",
+                commit.chars().take(COMMIT_LENGTH).collect::<String>()
+            )
+        }),
+    ]
+    .into_iter()
+    .flatten()
+    .collect::<Vec<_>>();
+    if !details.is_empty() {
+        description = format!("{description} ({})", details.join(", "));
+    }
+    description
+}
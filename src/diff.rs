@@ -0,0 +1,404 @@
+use std::ops::Range;
+
+use itertools::Itertools;
+
+use crate::{
+    args::{Args, RelativeTo, SortByField, SortByOrder},
+    runs::{mean_of, Records, RecordsSymbol},
+};
+
+/// The percentage of increase from which a change is shown as a ratio instead.
+const RATIO_THRESHOLD_PERCENT: f64 = 1000.0;
+
+/// The differences between each run of a [`Records`] and its reference.
+///
+/// This is the single place where references are resolved (see [`ReferenceResolver`]) and changes
+/// computed. The table and all exporters render from it, so that they never disagree.
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct DiffTable<'a> {
+    /// The records the table is computed from.
+    pub records: &'a Records,
+    /// The indices of the reference columns of each run.
+    pub references: Vec<Range<usize>>,
+    /// The total IR of each run, compared to its reference.
+    pub total: Vec<DiffCell>,
+    /// One row per symbol, in the order of [`Records::symbols`] (or by impact, with
+    /// `--sort-by=impact`).
+    pub rows: Vec<DiffRow<'a>>,
+}
+
+/// The differences between the IR counts of a symbol in each run and its reference.
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct DiffRow<'a> {
+    /// The symbol.
+    pub symbol: &'a RecordsSymbol,
+    /// One cell per run.
+    pub cells: Vec<DiffCell>,
+}
+
+/// An IR count, compared to its reference.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DiffCell {
+    /// The IR count.
+    pub ir: u64,
+    /// The reference IR count, or `None` if the cell is in its own reference column.
+    pub reference: Option<u64>,
+    /// Whether the change is within the noise band (see `--noise-band`).
+    pub noisy: bool,
+    /// Whether the change is larger than `--significance-sigmas` times the pooled standard
+    /// deviation of the column and its reference (see [`RecordsSymbol::is_significant`]).
+    pub significant: bool,
+    /// Whether the symbol was not measured in the run or in its reference (see
+    /// [`RecordsSymbol::measured`]). The change is then unknown, and the cell is unchanged.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub unmeasured: bool,
+}
+
+/// How a [`DiffCell`] changed relative to its reference.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Relative {
+    /// The count did not change.
+    Unchanged,
+    /// The count changed by the given percentage of the reference (negative for a decrease).
+    Percent(f64),
+    /// The count increased so much that it is better expressed as a ratio of the reference
+    /// (e.g. `12.5` for 12.5 times the reference).
+    Ratio(f64),
+}
+
+/// How the reference of each run is found.
+///
+/// `--relative-to` is resolved by [`RelativeTo`]. Other strategies may be implemented and given
+/// to [`DiffTable::with_resolver`], e.g. comparing each run to the best one so far:
+/// ```
+/// # use std::ops::Range;
+/// # use callgrind_differ::{diff::ReferenceResolver, runs::Records};
+/// struct BestSoFar;
+///
+/// impl ReferenceResolver for BestSoFar {
+///     fn reference_columns(&self, records: &Records, column: usize) -> Range<usize> {
+///         let best = (0..column.max(1))
+///             .min_by_key(|i| records.runs_total_irs[*i])
+///             .unwrap();
+///         best..best + 1
+///     }
+/// }
+/// ```
+pub trait ReferenceResolver {
+    /// Return the indices of the columns of `records` that the column at index `column` is
+    /// compared to.
+    ///
+    /// The reference value is the mean of these columns, of which there must be at least one. If
+    /// the range is `column..column + 1`, the column is its own reference.
+    fn reference_columns(&self, records: &Records, column: usize) -> Range<usize>;
+}
+
+impl ReferenceResolver for RelativeTo {
+    fn reference_columns(&self, records: &Records, column: usize) -> Range<usize> {
+        if let RelativeTo::Pinned = self {
+            let pinned = records
+                .runs_metadata
+                .iter()
+                .rposition(|metadata| metadata.pinned)
+                .unwrap_or(0);
+            return pinned..pinned + 1;
+        }
+        RelativeTo::reference_columns(*self, column, records.n_runs())
+    }
+}
+
+impl<'a> DiffTable<'a> {
+    /// Compute the differences of all runs of `records` to their reference, as per
+    /// `--relative-to`.
+    pub fn new(config: &Args, records: &'a Records) -> Self {
+        Self::with_resolver(config, records, &config.relative_to)
+    }
+
+    /// Compute the differences of all runs of `records` to the reference found by `resolver`.
+    ///
+    /// A run whose reference holds runs of another unit (see [`crate::runs::RunMetadata::unit`])
+    /// is its own reference instead, so that wall times are not compared to instruction counts.
+    ///
+    /// # Panics
+    /// Panics if `resolver` returns an empty range or columns that do not exist.
+    pub fn with_resolver(
+        config: &Args,
+        records: &'a Records,
+        resolver: &dyn ReferenceResolver,
+    ) -> Self {
+        let n_runs = records.n_runs();
+        // Counts in different units (see `RunMetadata::unit`) are not compared.
+        let unit = |i: usize| records.runs_metadata[i].unit.as_deref();
+        let references = (0..n_runs)
+            .map(|i| {
+                let reference = resolver.reference_columns(records, i);
+                if reference.clone().all(|column| unit(column) == unit(i)) {
+                    reference
+                } else {
+                    i..i + 1
+                }
+            })
+            .collect::<Vec<_>>();
+        let is_reference = |i: usize| references[i] == (i..i + 1);
+
+        let total = (0..n_runs)
+            .map(|i| {
+                DiffCell::exact(
+                    records.runs_total_irs[i],
+                    (!is_reference(i)).then(|| records.reference_total_ir(references[i].clone())),
+                )
+            })
+            .collect();
+        let rows = records
+            .symbols
+            .iter()
+            .map(|symbol| DiffRow {
+                symbol,
+                cells: (0..n_runs)
+                    .map(|i| {
+                        let reference = &references[i];
+                        let unmeasured = !symbol.is_measured(i, reference.clone());
+                        DiffCell {
+                            ir: symbol.irs[i],
+                            reference: (!is_reference(i))
+                                .then(|| symbol.reference_ir(reference.clone())),
+                            noisy: !is_reference(i)
+                                && symbol.is_within_noise(i, reference.clone(), config.noise_band),
+                            significant: !is_reference(i)
+                                && !unmeasured
+                                && symbol.is_significant(
+                                    i,
+                                    reference.clone(),
+                                    config.significance_sigmas,
+                                ),
+                            unmeasured,
+                        }
+                    })
+                    .collect(),
+            })
+            .collect::<Vec<_>>();
+
+        let mut table = Self {
+            records,
+            references,
+            total,
+            rows,
+        };
+        if matches!(config.sort_by.field, SortByField::Impact) {
+            table.sort_by_impact(config.sort_by.order);
+        }
+        table
+    }
+
+    /// Return the number of runs in the table.
+    pub fn n_runs(&self) -> usize {
+        self.total.len()
+    }
+
+    /// Return whether the column at index `i` is its own reference.
+    pub fn is_reference(&self, i: usize) -> bool {
+        self.references[i] == (i..i + 1)
+    }
+
+    /// Return the count of the event at `event` (an index in [`Records::events`]) for each
+    /// symbol in the column at index `column`, compared to its reference.
+    ///
+    /// Events are not merged from multiple samples, hence the cells are [exact](DiffCell::exact).
+    pub fn event_cells(
+        &self,
+        event: usize,
+        column: usize,
+    ) -> impl Iterator<Item = (&'a RecordsSymbol, DiffCell)> + '_ {
+        let reference = self.references[column].clone();
+        let is_reference = self.is_reference(column);
+        self.records.symbols.iter().map(move |symbol| {
+            let counts = &symbol.events[event];
+            let mut cell = DiffCell::exact(
+                counts[column],
+                (!is_reference).then(|| mean_of(&counts[reference.clone()])),
+            );
+            if !symbol.is_measured(column, reference.clone()) {
+                cell.unmeasured = true;
+                cell.significant = false;
+            }
+            (symbol, cell)
+        })
+    }
+
+    /// Sort the rows by the [impact](DiffCell::impact) of the change of their last column.
+    ///
+    /// Rows with the same impact are sorted by ascending name, whichever the order.
+    fn sort_by_impact(&mut self, order: SortByOrder) {
+        let Some(column) = self.n_runs().checked_sub(1) else {
+            return;
+        };
+        let total = self.total[column];
+        // Impacts are computed once per row rather than once per comparison.
+        let mut rows = std::mem::take(&mut self.rows)
+            .into_iter()
+            .map(|row| (row.cells[column].impact(&total), row))
+            .collect::<Vec<_>>();
+        rows.sort_unstable_by(|(a_impact, a), (b_impact, b)| {
+            let ordering = a_impact.total_cmp(b_impact);
+            match order {
+                SortByOrder::Ascending => ordering,
+                SortByOrder::Descending => ordering.reverse(),
+            }
+            .then_with(|| a.name().cmp(b.name()))
+        });
+        self.rows = rows.into_iter().map(|(_, row)| row).collect();
+    }
+
+    /// Return the rank of the symbol of each row by IR count over the runs of `columns`, from 1
+    /// for the hottest symbol (see `--show=rank`).
+    ///
+    /// Rows are ranked by the sum of their IR counts over `columns`, which ranks them as their
+    /// mean would. Rows with the same IR count share the same rank, and rows with no IR count have
+    /// none.
+    pub fn ranks(&self, columns: Range<usize>) -> Vec<Option<usize>> {
+        let irs = self
+            .rows
+            .iter()
+            .map(|row| {
+                row.symbol.irs[columns.clone()]
+                    .iter()
+                    .map(|ir| u128::from(*ir))
+                    .sum::<u128>()
+            })
+            .collect::<Vec<_>>();
+        let mut order = (0..irs.len()).collect::<Vec<_>>();
+        order.sort_unstable_by(|a, b| irs[*b].cmp(&irs[*a]));
+        let mut ranks = vec![None; irs.len()];
+        for (position, row) in order.iter().copied().enumerate() {
+            if irs[row] == 0 {
+                break;
+            }
+            ranks[row] = Some(match position {
+                0 => 1,
+                _ if irs[order[position - 1]] == irs[row] => {
+                    ranks[order[position - 1]].unwrap_or(1)
+                }
+                _ => position + 1,
+            });
+        }
+        ranks
+    }
+
+    /// Return the index of the last column, i.e. the most recent run.
+    ///
+    /// # Panics
+    /// This function panics if the table has no run.
+    pub fn last_column(&self) -> usize {
+        assert!(self.n_runs() != 0, "The table has no run");
+        self.n_runs() - 1
+    }
+}
+
+impl DiffRow<'_> {
+    /// Return the name of the symbol.
+    pub fn name(&self) -> &str {
+        &self.symbol.name
+    }
+
+    /// Return whether the IR count of the symbol is the same in all runs.
+    pub fn is_unchanged(&self) -> bool {
+        self.cells.iter().map(|cell| cell.ir).all_equal()
+    }
+}
+
+impl DiffCell {
+    /// Create a cell of counts which were not merged from multiple samples: it is never within
+    /// the noise band, and any change is significant.
+    pub fn exact(ir: u64, reference: Option<u64>) -> Self {
+        Self {
+            ir,
+            reference,
+            noisy: false,
+            significant: reference.is_some_and(|reference| reference != ir),
+            unmeasured: false,
+        }
+    }
+
+    /// Return the reference IR count, which is the IR count itself in a reference column or if
+    /// the cell is [unmeasured](Self::unmeasured).
+    pub fn reference_ir(&self) -> u64 {
+        if self.unmeasured {
+            self.ir
+        } else {
+            self.reference.unwrap_or(self.ir)
+        }
+    }
+
+    /// Return the signed difference between the IR count and its reference.
+    pub fn diff(&self) -> i128 {
+        i128::from(self.ir) - i128::from(self.reference_ir())
+    }
+
+    /// Return the absolute difference between the IR count and its reference.
+    pub fn abs_diff(&self) -> u64 {
+        self.ir.abs_diff(self.reference_ir())
+    }
+
+    /// Return whether the IR count is higher than its reference.
+    pub fn is_increase(&self) -> bool {
+        self.ir > self.reference_ir()
+    }
+
+    /// Return whether the IR count is lower than its reference.
+    pub fn is_decrease(&self) -> bool {
+        self.ir < self.reference_ir()
+    }
+
+    /// Return the share of `total` (the total IR of the same column) of the IR count, in percent.
+    ///
+    /// This is the higher of the shares of the IR count and of its reference, so that a symbol
+    /// which vanished keeps the share it had. A total of 0 has no share.
+    pub fn share_of(&self, total: &DiffCell) -> f64 {
+        let share = |ir: u64, total: u64| {
+            if total == 0 {
+                0.0
+            } else {
+                ir as f64 * 100.0 / total as f64
+            }
+        };
+        share(self.ir, total.ir).max(share(self.reference_ir(), total.reference_ir()))
+    }
+
+    /// Return the impact of the change of the IR count: its absolute difference, weighted by the
+    /// [share](Self::share_of) of `total` (the total IR of the same column) of the symbol.
+    ///
+    /// A 2% change of a symbol taking half the total IR thus outranks a 300% change of a symbol
+    /// taking a thousandth of it.
+    pub fn impact(&self, total: &DiffCell) -> f64 {
+        self.abs_diff() as f64 * self.share_of(total) / 100.0
+    }
+
+    /// Return the signed change of the IR count, in percent of its reference.
+    ///
+    /// A change from 0 is considered a 100% increase.
+    pub fn percent(&self) -> f64 {
+        let reference = self.reference_ir();
+        if self.ir == reference {
+            0.0
+        } else if reference == 0 {
+            100.0
+        } else {
+            self.diff() as f64 * 100.0 / reference as f64
+        }
+    }
+
+    /// Return the change of the IR count as it should be shown: a percentage, or a ratio if the
+    /// increase is too high for a percentage to be readable.
+    pub fn relative(&self) -> Relative {
+        let percent = self.percent();
+        if self.ir == self.reference_ir() {
+            Relative::Unchanged
+        } else if percent >= RATIO_THRESHOLD_PERCENT {
+            Relative::Ratio(percent / 100.0)
+        } else {
+            Relative::Percent(percent)
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+};
+
+use anyhow::{Context, Result};
+
+use crate::{
+    args::Args,
+    callgrind::IR_EVENT,
+    diff::{DiffCell, DiffTable},
+    runs::mean_of,
+};
+
+/// Write the changes of the last column compared to its reference as a callgrind profile to
+/// `--export-callgrind-diff`, so that they can be explored in `kcachegrind` or `qcachegrind`.
+///
+/// Costs of a callgrind profile are unsigned. Each event (`Ir` first, then the other events of
+/// the records) is split into two: the increase of its count (e.g. `IrInc`) and its decrease
+/// (e.g. `IrDec`), one of which is 0 for each symbol. Each symbol whose counts changed has a
+/// single cost line, its self cost, in an unknown file (`???`). The profile has no `cfn=` nor
+/// `calls=` line, as runs do not keep the call graph (nor the files) of their inputs.
+///
+/// # Errors
+/// Returns an error if the profile cannot be written.
+pub fn export_callgrind_diff(config: &Args, table: &DiffTable) -> Result<()> {
+    let path = &config.export_callgrind_diff;
+    let mut writer =
+        BufWriter::new(File::create(path).with_context(|| format!("Failed to create {path}"))?);
+    write_callgrind_diff(table, &mut writer)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Write the profile described in [`export_callgrind_diff`] into `w`.
+///
+/// # Errors
+/// Returns an error if writing to `w` fails.
+pub fn write_callgrind_diff<W: Write>(table: &DiffTable, w: &mut W) -> Result<()> {
+    let records = table.records;
+    let column = table.last_column();
+    let events = std::iter::once(IR_EVENT)
+        .chain(records.events.iter().map(String::as_str))
+        .collect::<Vec<_>>();
+    let reference = table.references[column].clone();
+    let is_reference = table.is_reference(column);
+
+    // The increase and decrease of each event, for each changed symbol.
+    let mut costs = vec![];
+    for row in &table.rows {
+        // Events are not merged from multiple samples (see `DiffTable::event_cells`).
+        let cells = std::iter::once(row.cells[column])
+            .chain(row.symbol.events.iter().map(|counts| {
+                DiffCell::exact(
+                    counts[column],
+                    (!is_reference).then(|| mean_of(&counts[reference.clone()])),
+                )
+            }))
+            .collect::<Vec<_>>();
+        if cells.iter().all(|cell| cell.diff() == 0) {
+            continue;
+        }
+        costs.push((row.name(), cells.iter().flat_map(split).collect::<Vec<_>>()));
+    }
+    let summary = (0..events.len() * 2)
+        .map(|i| {
+            costs
+                .iter()
+                .fold(0, |total: u64, (_, costs)| total.saturating_add(costs[i]))
+        })
+        .collect::<Vec<_>>();
+
+    writeln!(w, "# callgrind format")?;
+    writeln!(w, "version: 1")?;
+    writeln!(w, "creator: callgrind_differ")?;
+    let name = &records.run_names[column];
+    if name.is_empty() {
+        writeln!(w, "desc: Changes of run {column} compared to its reference")?;
+    } else {
+        writeln!(w, "desc: Changes of {name} compared to its reference")?;
+    }
+    if let Some(command) = &records.runs_metadata[column].command {
+        writeln!(w, "cmd: {command}")?;
+    }
+    writeln!(w, "positions: line")?;
+    for event in &events {
+        writeln!(w, "event: {event}Inc : {event} increase")?;
+        writeln!(w, "event: {event}Dec : {event} decrease")?;
+    }
+    writeln!(
+        w,
+        "events: {}",
+        events
+            .iter()
+            .map(|event| format!("{event}Inc {event}Dec"))
+            .collect::<Vec<_>>()
+            .join(" ")
+    )?;
+    writeln!(w, "summary: {}", join(&summary))?;
+    writeln!(w)?;
+    writeln!(w, "fl=???")?;
+    for (name, costs) in costs {
+        writeln!(w, "fn={name}")?;
+        writeln!(w, "0 {}", join(&costs))?;
+    }
+    Ok(())
+}
+
+/// Split the change of `cell` into its increase and its decrease, one of which is 0.
+fn split(cell: &DiffCell) -> [u64; 2] {
+    if cell.is_decrease() {
+        [0, cell.abs_diff()]
+    } else {
+        [cell.abs_diff(), 0]
+    }
+}
+
+/// Join counts with spaces.
+fn join(counts: &[u64]) -> String {
+    counts
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
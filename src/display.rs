@@ -1,10 +1,30 @@
-use itertools::Itertools;
+use std::{
+    cmp::Ordering,
+    io::{self, BufWriter, Write},
+};
 
-use crate::args::{Args, RelativeTo, Show};
-use crate::runs::{Records, RecordsSymbol};
+use crate::args::{Args, Show};
+use crate::diff::{DiffCell, DiffRow, DiffTable, Relative};
+use crate::runs::{slope_of, IrStats, Records, RecordsSymbol};
 
-pub fn display(config: &Args, records: &Records) {
-    Displayer::new(config, records).display();
+/// Display the differences as a table on the standard output.
+///
+/// The output is buffered and the standard output is locked for the duration of the display.
+///
+/// # Errors
+/// Returns an error if writing to the standard output fails.
+pub fn display(config: &Args, table: &DiffTable) -> io::Result<()> {
+    let mut w = BufWriter::new(io::stdout().lock());
+    display_to(config, table, &mut w)?;
+    w.flush()
+}
+
+/// Display the differences as a table into `w`.
+///
+/// # Errors
+/// Returns an error if writing to `w` fails.
+pub fn display_to<W: Write>(config: &Args, table: &DiffTable, w: &mut W) -> io::Result<()> {
+    Displayer::new(config, table).display(w)
 }
 
 /// The width of the `percent_diff` column (`+ 12.345%`).
@@ -18,200 +38,535 @@ pub fn display(config: &Args, records: &Records) {
 /// When this is expressed as a ratio, this will create a shift if the ratio is 1000x or higher.
 const PERCENTDIFF_WIDTH: u32 = 9;
 
+/// The width of a rate of `--show=<metric>` (`100.00%`).
+const RATE_WIDTH: u32 = 7;
+
+/// The width of the difference of a rate of `--show=<metric>`, in percentage points
+/// (`+100.00pp`).
+const RATE_DIFF_WIDTH: u32 = 9;
+
+/// The rank difference of `--show=rank` shown for symbols which had no rank in the reference.
+const NEW_RANK: &str = "new";
+
+/// What is shown in place of the counts of a symbol that was not measured in a run, and of the
+/// changes they would make (see [`RecordsSymbol::measured`]).
+const UNMEASURED: char = '·';
+
 /// The name of the "symbol" for the row that contains the total IR for runs.
 const TOTAL_IR_ROW_NAME: &str = "Total IR";
 
-/// Context for displaying a [`Records`].
+/// The names of the columns of `--show=stats`, in order.
+const STATS_COLUMNS: [&str; 4] = ["min", "max", "mean", "stddev"];
+
+/// The name of the column of `--show=trend`.
+const TREND_COLUMN: &str = "trend";
+
+/// The name of the column of `--show=cumulative`.
+const CUMULATIVE_COLUMN: &str = "cumulative";
+
+/// Context for displaying a [`DiffTable`].
 struct Displayer<'a> {
     /// The program configuration.
     config: &'a Args,
-    /// The records to display.
-    records: &'a Records,
+    /// The differences to display.
+    table: &'a DiffTable<'a>,
     /// The length of the longest symbol.
     max_symbol_width: u32,
     /// The length (in digits) of the highest `total_ir`.
     max_total_ir_width: u8,
+    /// The width that the reference column takes in-between the ` | `.
+    reference_width: u32,
     /// The width that a column takes in-between the ` | `.
     run_width: u32,
+    /// The width of each column of `--show=stats`, or 0 if they are not shown.
+    stats_width: u32,
+    /// The width of the slope in the column of `--show=trend` (without the arrow), or 0 if it is
+    /// not shown.
+    trend_width: u32,
+    /// The width of the column of `--show=cumulative`, or 0 if it is not shown.
+    cumulative_width: u32,
+    /// The width of a rank of `--show=rank` (`#<rank>`), or 0 if it is not shown.
+    rank_width: u32,
+    /// The rank of each row in each column, if `--show=rank`.
+    ranks: Vec<Vec<Option<usize>>>,
+    /// The rank of each row in the reference of each column, if `--show=rank`.
+    reference_ranks: Vec<Vec<Option<usize>>>,
     /// The total width of a line.
     line_width: u32,
-    /// The index of the reference column.
-    reference_column: u32,
 }
 
 impl<'a> Displayer<'a> {
     /// Create a new [`Displayer`].
-    fn new(config: &'a Args, records: &'a Records) -> Self {
+    fn new(config: &'a Args, table: &'a DiffTable<'a>) -> Self {
         let mut ret = Self {
             config,
-            records,
-            max_symbol_width: get_max_symbol_length(records, config.all),
-            max_total_ir_width: get_highest_total_ir_length(records),
+            table,
+            max_symbol_width: get_max_symbol_length(table, config.all),
+            max_total_ir_width: get_highest_total_ir_length(table.records),
+            reference_width: 0,
             run_width: 0,
+            stats_width: 0,
+            trend_width: 0,
+            cumulative_width: 0,
+            rank_width: 0,
+            ranks: vec![],
+            reference_ranks: vec![],
             line_width: 0,
-            reference_column: 0,
         };
+        if config.show_rank() {
+            ret.ranks = (0..table.n_runs())
+                .map(|column| table.ranks(column..column + 1))
+                .collect();
+            ret.reference_ranks = table
+                .references
+                .iter()
+                .map(|columns| table.ranks(columns.clone()))
+                .collect();
+        }
         ret.compute_widths();
-
-        ret.reference_column = match &config.relative_to {
-            RelativeTo::First => 0,
-            RelativeTo::Last => (records.n_runs() - 1) as u32,
-            RelativeTo::Previous => u32::MAX,
-            RelativeTo::Column(x) => *x,
-        };
-
         ret
     }
 
-    /// Display the [`Records`] on the standard output.
-    fn display(&self) {
-        self.show_header();
-        self.show_delimitation_line();
-        self.show_total_ir_line();
-        self.show_delimitation_line();
-        for symbol in &self.records.symbols {
-            if self.config.all || !symbol.irs.iter().all_equal() {
-                self.show_symbol_row(symbol);
+    /// Display the [`DiffTable`] into `w`.
+    fn display<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        self.show_header(w)?;
+        self.show_delimitation_line(w)?;
+        self.show_total_ir_line(w)?;
+        self.show_delimitation_line(w)?;
+        for (i, row) in self.table.rows.iter().enumerate() {
+            if self.config.all || !row.is_unchanged() {
+                self.show_symbol_row(w, i, row)?;
             }
         }
+        Ok(())
     }
 
     /// Show the header line.
-    fn show_header(&self) {
-        print!("Symbol");
-        print_n(' ', self.max_symbol_width as usize - "Symbol".len());
-        for (i, col_name) in self.records.run_names.iter().enumerate() {
-            print!(" | ");
-            if self.is_ref_column(i) {
-                print_centered(col_name, self.max_total_ir_width as usize);
+    fn show_header<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        write!(w, "Symbol")?;
+        write_n(w, ' ', self.max_symbol_width as usize - "Symbol".len())?;
+        for (i, col_name) in self.table.records.run_names.iter().enumerate() {
+            write!(w, " | ")?;
+            let col_name = match &self.table.records.runs_metadata[i].unit {
+                Some(unit) if col_name.is_empty() => unit.clone(),
+                Some(unit) => format!("{col_name} ({unit})"),
+                None => col_name.clone(),
+            };
+            if self.table.is_reference(i) {
+                write_centered(w, &col_name, self.reference_width as usize)?;
             } else {
-                print_centered(col_name, self.run_width as usize);
+                write_centered(w, &col_name, self.run_width as usize)?;
             }
         }
-        println!();
+        if self.stats_width != 0 {
+            for name in STATS_COLUMNS {
+                write!(w, " | ")?;
+                write_centered(w, name, self.stats_width as usize)?;
+            }
+        }
+        if self.trend_width != 0 {
+            write!(w, " | ")?;
+            write_centered(w, TREND_COLUMN, self.trend_width as usize + 2)?;
+        }
+        if self.cumulative_width != 0 {
+            write!(w, " | ")?;
+            write_centered(w, CUMULATIVE_COLUMN, self.cumulative_width as usize)?;
+        }
+        writeln!(w)?;
+        Ok(())
     }
 
     /// Show a `---+----+---` line as a horizontal separation.
-    fn show_delimitation_line(&self) {
-        print_n('-', self.max_symbol_width as usize);
-        for i in 0..self.records.run_names.len() {
-            print!("-+-");
-            if self.is_ref_column(i) {
-                print_n('-', self.max_total_ir_width as usize);
+    fn show_delimitation_line<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        write_n(w, '-', self.max_symbol_width as usize)?;
+        for i in 0..self.table.records.run_names.len() {
+            write!(w, "-+-")?;
+            if self.table.is_reference(i) {
+                write_n(w, '-', self.reference_width as usize)?;
             } else {
-                print_n('-', self.run_width as usize);
+                write_n(w, '-', self.run_width as usize)?;
             }
         }
-        println!();
+        if self.stats_width != 0 {
+            for _ in STATS_COLUMNS {
+                write!(w, "-+-")?;
+                write_n(w, '-', self.stats_width as usize)?;
+            }
+        }
+        if self.trend_width != 0 {
+            write!(w, "-+-")?;
+            write_n(w, '-', self.trend_width as usize + 2)?;
+        }
+        if self.cumulative_width != 0 {
+            write!(w, "-+-")?;
+            write_n(w, '-', self.cumulative_width as usize)?;
+        }
+        writeln!(w)?;
+        Ok(())
     }
 
     /// Show the "Total IR" line.
-    fn show_total_ir_line(&self) {
-        print_left(TOTAL_IR_ROW_NAME, self.max_symbol_width as usize);
-        for (i, ir) in self.records.runs_total_irs.iter().enumerate() {
-            let s = ir.to_string();
-            print!(" | ");
-            if self.is_ref_column(i) {
-                print_right(&s, self.max_total_ir_width as usize);
+    fn show_total_ir_line<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        self.show_name(w, TOTAL_IR_ROW_NAME, &self.table.records.runs_total_irs)?;
+        // Total IR counts are always known.
+        let measured = vec![true; self.table.n_runs()];
+        for (column, cell) in self.table.total.iter().enumerate() {
+            write!(w, " | ")?;
+            if cell.reference.is_none() {
+                self.show_symbol_ir(w, cell.ir)?;
             } else {
-                let reference_ir = self.get_reference_total_ir_for(i);
-                self.show_run_details(*ir, reference_ir);
+                let irs = &self.table.records.runs_total_irs;
+                self.show_run_details(w, cell, irs, &measured, column)?;
             }
+            self.show_rank(w, None, column)?;
+            self.show_metrics(w, None, column)?;
         }
-        println!();
+        if self.stats_width != 0 {
+            self.show_stats(w, IrStats::of(&self.table.records.runs_total_irs))?;
+        }
+        if self.trend_width != 0 {
+            self.show_trend(w, slope_of(&self.table.records.runs_total_irs))?;
+        }
+        if self.cumulative_width != 0 {
+            self.show_cumulative(w, &self.table.records.runs_total_irs, &measured)?;
+        }
+        writeln!(w)?;
+        Ok(())
     }
 
-    /// Display the row with details for a single symbol.
-    fn show_symbol_row(&self, symbol: &RecordsSymbol) {
-        print_left(&symbol.name, self.max_symbol_width as usize);
-        for (i, ir) in symbol.irs.iter().enumerate() {
-            print!(" | ");
-            if self.is_ref_column(i) {
+    /// Display the row with details for a single symbol, that of the row at index `i`.
+    fn show_symbol_row<W: Write>(&self, w: &mut W, i: usize, row: &DiffRow) -> io::Result<()> {
+        self.show_name(w, row.name(), &row.symbol.irs)?;
+        for (column, cell) in row.cells.iter().enumerate() {
+            write!(w, " | ")?;
+            if cell.reference.is_none() {
                 // If it's the reference column, just print the IR count.
-                self.show_symbol_ir(*ir);
+                if row.symbol.measured[column] {
+                    self.show_symbol_ir(w, cell.ir)?;
+                } else {
+                    write_unmeasured(w, self.max_total_ir_width as usize)?;
+                }
             } else {
-                let reference_ir = self.get_reference_ir_for(i, symbol);
-                self.show_run_details(*ir, reference_ir);
+                let measured = &row.symbol.measured;
+                self.show_run_details(w, cell, &row.symbol.irs, measured, column)?;
             }
+            self.show_rank(w, Some(i), column)?;
+            self.show_metrics(w, Some(row.symbol), column)?;
+        }
+        if self.stats_width != 0 {
+            self.show_stats(w, row.symbol.ir_stats())?;
+        }
+        if self.trend_width != 0 {
+            self.show_trend(w, row.symbol.ir_slope())?;
+        }
+        if self.cumulative_width != 0 {
+            self.show_cumulative(w, &row.symbol.irs, &row.symbol.measured)?;
+        }
+        writeln!(w)?;
+        Ok(())
+    }
+
+    /// Display the name of a row, preceded by the mark of `--show=stability` of its IR counts
+    /// `irs`, if shown.
+    fn show_name<W: Write>(&self, w: &mut W, name: &str, irs: &[u64]) -> io::Result<()> {
+        if !self.config.show_stability() {
+            return write_left(w, name, self.max_symbol_width as usize);
+        }
+        let mark = if irs.is_empty() {
+            ' '
+        } else {
+            IrStats::of(irs).stability().mark()
+        };
+        // The mark is a single character wide, however many bytes it takes.
+        write!(w, "{mark} ")?;
+        write_left(w, name, self.max_symbol_width as usize - 2)
+    }
+
+    /// Display the columns of `--show=stats`.
+    ///
+    /// The standard deviation is rounded to the nearest integer, as IR counts are.
+    fn show_stats<W: Write>(&self, w: &mut W, stats: IrStats) -> io::Result<()> {
+        for value in [
+            stats.min,
+            stats.max,
+            stats.mean,
+            stats.stddev.round() as u64,
+        ] {
+            write!(w, " | ")?;
+            write_right(w, &value.to_string(), self.stats_width as usize)?;
+        }
+        Ok(())
+    }
+
+    /// Display the column of `--show=trend`: the slope, followed by an arrow of its direction.
+    ///
+    /// Increasing trends are shown red and decreasing ones green.
+    fn show_trend<W: Write>(&self, w: &mut W, slope: f64) -> io::Result<()> {
+        write!(w, " | ")?;
+        let (color, arrow) = match trend_direction(slope) {
+            Ordering::Greater => ("\x1B[31m", '↑'),
+            Ordering::Less => ("\x1B[32m", '↓'),
+            Ordering::Equal => ("", '→'),
+        };
+        write!(w, "{color}")?;
+        write_right(w, &format_slope(slope), self.trend_width as usize)?;
+        write!(w, " {arrow}")?;
+        if !color.is_empty() {
+            write!(w, "\x1B[0m")?;
         }
-        println!();
+        Ok(())
+    }
+
+    /// Display the column of `--show=cumulative`: the change from the first of `irs` to the last,
+    /// as a difference and a percentage, unless either was not `measured`.
+    fn show_cumulative<W: Write>(
+        &self,
+        w: &mut W,
+        irs: &[u64],
+        measured: &[bool],
+    ) -> io::Result<()> {
+        write!(w, " | ")?;
+        write_n(
+            w,
+            ' ',
+            (self.cumulative_width - self.cumulative_content_width()) as usize,
+        )?;
+        let mut cell = DiffCell::exact(irs[irs.len() - 1], Some(irs[0]));
+        cell.unmeasured = !measured[0] || !measured[measured.len() - 1];
+        self.show_symbol_irdff(w, &cell)?;
+        write!(w, " ")?;
+        self.show_symbol_percentdff(w, &cell)
+    }
+
+    /// Return the width of the difference and percentage of `--show=cumulative`, without padding.
+    fn cumulative_content_width(&self) -> u32 {
+        self.max_total_ir_width as u32 + 1 + 1 + PERCENTDIFF_WIDTH
     }
 
-    /// Display the columns (as per `--show`) with the given details.
-    fn show_run_details(&self, ir: u64, reference_ir: u64) {
-        for (i, x) in self.config.show.iter().enumerate() {
+    /// Display the columns (as per `--show`) with the details of the given cell, that of `irs` at
+    /// index `column`.
+    ///
+    /// If the change is within the noise band, it is not highlighted. Counts which were not
+    /// `measured`, and changes involving them, are shown as [`UNMEASURED`].
+    fn show_run_details<W: Write>(
+        &self,
+        w: &mut W,
+        cell: &DiffCell,
+        irs: &[u64],
+        measured: &[bool],
+        column: usize,
+    ) -> io::Result<()> {
+        let ir_width = self.max_total_ir_width as usize;
+        for (i, x) in self.config.run_columns().enumerate() {
             if i != 0 {
                 // Print a space between that value and the previous one.
-                print!(" ");
+                write!(w, " ")?;
             }
             match x {
-                Show::IRCount => self.show_symbol_ir(ir),
-                Show::PercentageDiff => self.show_symbol_percentdff(ir, reference_ir),
-                Show::IRCountDiff => self.show_symbol_irdff(ir, reference_ir),
-                Show::All => unreachable!(),
+                Show::IRCount if !measured[column] => write_unmeasured(w, ir_width)?,
+                Show::IRCount => self.show_symbol_ir(w, cell.ir)?,
+                Show::PercentageDiff => self.show_symbol_percentdff(w, cell)?,
+                Show::IRCountDiff => self.show_symbol_irdff(w, cell)?,
+                Show::Significance => show_symbol_significance(w, cell)?,
+                Show::Acceleration
+                    if measured[column.saturating_sub(2)..=column]
+                        .iter()
+                        .any(|measured| !measured) =>
+                {
+                    write_unmeasured(w, ir_width + 1)?;
+                }
+                Show::Acceleration => self.show_acceleration(w, irs, column)?,
+                Show::All
+                | Show::Stats
+                | Show::Trend
+                | Show::Cumulative
+                | Show::Metric(_)
+                | Show::Rank
+                | Show::Stability => {
+                    unreachable!()
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Display the rank of `--show=rank` of the row at index `row` (or blanks for the total IR if
+    /// `None`) in the column at index `column`.
+    ///
+    /// The rank is followed by the number of places the symbol moved from its rank in the
+    /// reference, unless this is the reference column. Climbing (i.e. getting hotter) is shown red
+    /// and falling green.
+    fn show_rank<W: Write>(&self, w: &mut W, row: Option<usize>, column: usize) -> io::Result<()> {
+        if self.rank_width == 0 {
+            return Ok(());
+        }
+        let is_reference = self.table.is_reference(column);
+        let Some(row) = row else {
+            let width = if is_reference {
+                1 + self.rank_width
+            } else {
+                2 * (1 + self.rank_width)
+            };
+            return write_n(w, ' ', width as usize);
+        };
+        let rank = self.ranks[column][row];
+        write!(w, " ")?;
+        write_right(w, &format_rank(rank), self.rank_width as usize)?;
+        if is_reference {
+            return Ok(());
+        }
+        let (color, diff) = match (rank, self.reference_ranks[column][row]) {
+            (Some(rank), Some(reference)) => match rank.cmp(&reference) {
+                Ordering::Less => ("\x1B[31m", format!("+{}", reference - rank)),
+                Ordering::Greater => ("\x1B[32m", format!("-{}", rank - reference)),
+                Ordering::Equal => ("", "- ".to_string()),
+            },
+            (Some(_), None) => ("\x1B[31m", NEW_RANK.to_string()),
+            (None, _) => ("", "- ".to_string()),
+        };
+        write!(w, " {color}")?;
+        write_right(w, &diff, self.rank_width as usize)?;
+        if !color.is_empty() {
+            write!(w, "\x1B[0m")?;
+        }
+        Ok(())
+    }
+
+    /// Display the rates of `--show=<metric>` of `symbol` (or of the whole program if `None`) in
+    /// the column at index `column`.
+    ///
+    /// Each rate is followed by its difference to that of the reference, in percentage points,
+    /// unless this is the reference column. Increases are shown red and decreases green. Rates
+    /// which cannot be computed (e.g. a symbol which made no data access) are shown as `-`.
+    fn show_metrics<W: Write>(
+        &self,
+        w: &mut W,
+        symbol: Option<&RecordsSymbol>,
+        column: usize,
+    ) -> io::Result<()> {
+        let records = self.table.records;
+        for metric in self.config.metrics() {
+            let rate = metric.rate(records, symbol, column..column + 1);
+            write!(w, " ")?;
+            write_right(w, &format_rate(rate), RATE_WIDTH as usize)?;
+            if self.table.is_reference(column) {
+                continue;
+            }
+            let reference = metric.rate(records, symbol, self.table.references[column].clone());
+            let diff = format_rate_diff(
+                rate.zip(reference)
+                    .map(|(rate, reference)| rate - reference),
+            );
+            let color = if diff.starts_with('+') {
+                "\x1B[31m"
+            } else if diff.starts_with("- ") {
+                ""
+            } else {
+                "\x1B[32m"
+            };
+            write!(w, " {color}")?;
+            write_right(w, &diff, RATE_DIFF_WIDTH as usize)?;
+            if !color.is_empty() {
+                write!(w, "\x1B[0m")?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Display the column of `--show=acceleration` of `irs` at index `column`: the difference to the
+    /// previous IR count minus the previous difference, correctly aligned.
+    ///
+    /// Accelerating regressions (and slowing improvements) are shown red, and recoveries green.
+    /// Columns with no previous difference are shown as `-`.
+    fn show_acceleration<W: Write>(&self, w: &mut W, irs: &[u64], column: usize) -> io::Result<()> {
+        let width = self.max_total_ir_width as usize;
+        let acceleration = (column >= 2).then(|| {
+            let diff = |i: usize| i128::from(irs[i]) - i128::from(irs[i - 1]);
+            diff(column) - diff(column - 1)
+        });
+        match acceleration {
+            Some(acceleration) if acceleration != 0 => {
+                let (color, sign) = if acceleration > 0 {
+                    ("\x1B[31m", '+')
+                } else {
+                    ("\x1B[32m", '-')
+                };
+                write!(w, "{color}{sign}")?;
+                write_right(w, &acceleration.unsigned_abs().to_string(), width)?;
+                write!(w, "\x1B[0m")
             }
+            _ => write_right(w, "-", width + 1),
         }
     }
 
     /// Display the IR count, correctly aligned.
-    fn show_symbol_ir(&self, ir: u64) {
+    fn show_symbol_ir<W: Write>(&self, w: &mut W, ir: u64) -> io::Result<()> {
         let s = ir.to_string();
-        print_right(&s, self.max_total_ir_width as usize);
+        write_right(w, &s, self.max_total_ir_width as usize)?;
+        Ok(())
     }
 
     /// Display the IR difference, correctly aligned.
-    fn show_symbol_irdff(&self, ir: u64, reference_ir: u64) {
-        let diff = ir.abs_diff(reference_ir);
-        if diff == 0 {
-            print_right("-", (self.max_total_ir_width + 1) as usize);
-        } else if ir > reference_ir {
+    ///
+    /// If the change is within the noise band, the difference is not highlighted.
+    fn show_symbol_irdff<W: Write>(&self, w: &mut W, cell: &DiffCell) -> io::Result<()> {
+        let diff = cell.abs_diff();
+        let noisy = cell.noisy;
+        if cell.unmeasured {
+            write_unmeasured(w, (self.max_total_ir_width + 1) as usize)?;
+        } else if diff == 0 {
+            write_right(w, "-", (self.max_total_ir_width + 1) as usize)?;
+        } else if cell.is_increase() {
             // Increase, show red.
-            print!("\x1B[31m+");
+            write!(w, "{}+", highlight("\x1B[31m", noisy))?;
             let s = format!("{diff}");
-            print_right(&s, self.max_total_ir_width as usize);
-            print!("\x1B[0m");
+            write_right(w, &s, self.max_total_ir_width as usize)?;
+            write!(w, "{}", highlight("\x1B[0m", noisy))?;
         } else {
             // Decrease, show green
-            print!("\x1B[32m-");
+            write!(w, "{}-", highlight("\x1B[32m", noisy))?;
             let s = format!("{diff}");
-            print_right(&s, self.max_total_ir_width as usize);
-            print!("\x1B[0m");
+            write_right(w, &s, self.max_total_ir_width as usize)?;
+            write!(w, "{}", highlight("\x1B[0m", noisy))?;
         }
+        Ok(())
     }
 
     /// Display the IR percentage difference, correctly aligned.
+    ///
+    /// If the change is within the noise band, the difference is not highlighted.
     #[allow(clippy::unused_self)]
-    fn show_symbol_percentdff(&self, ir: u64, reference_ir: u64) {
-        let diff = ir.abs_diff(reference_ir);
-        let percent = if reference_ir == 0 {
-            100.0
-        } else {
-            (diff as f64) * 100.0 / (reference_ir as f64)
-        };
-
-        if diff == 0 {
-            print_right("- ", PERCENTDIFF_WIDTH as usize);
-        } else if reference_ir > ir {
-            // Decrease, show green.
-            print!("\x1B[32m-");
-            let s = format!("{percent:7.3}%");
-            print_right(&s, (PERCENTDIFF_WIDTH - 1) as usize);
-            print!("\x1B[0m");
-        } else {
-            // Increase, show red
-            if percent < 1000.0 {
-                print!("\x1B[31m+");
+    fn show_symbol_percentdff<W: Write>(&self, w: &mut W, cell: &DiffCell) -> io::Result<()> {
+        let noisy = cell.noisy;
+        if cell.unmeasured {
+            write_unmeasured(w, (PERCENTDIFF_WIDTH - 1) as usize)?;
+            return write!(w, " ");
+        }
+        match cell.relative() {
+            Relative::Unchanged => write_right(w, "- ", PERCENTDIFF_WIDTH as usize)?,
+            Relative::Percent(percent) if percent < 0.0 => {
+                // Decrease, show green.
+                write!(w, "{}-", highlight("\x1B[32m", noisy))?;
+                let s = format!("{:7.3}%", -percent);
+                write_right(w, &s, (PERCENTDIFF_WIDTH - 1) as usize)?;
+                write!(w, "{}", highlight("\x1B[0m", noisy))?;
+            }
+            Relative::Percent(percent) => {
+                // Increase, show red
+                write!(w, "{}+", highlight("\x1B[31m", noisy))?;
                 let s = format!("{percent:7.3}%");
-                print_right(&s, (PERCENTDIFF_WIDTH - 1) as usize);
-            } else {
+                write_right(w, &s, (PERCENTDIFF_WIDTH - 1) as usize)?;
+                write!(w, "{}", highlight("\x1B[0m", noisy))?;
+            }
+            Relative::Ratio(ratio) => {
                 // Too high an increase, show as bold red ratio.
-                print!("\x1B[31;1m");
-                let ratio = percent / 100.0;
+                write!(w, "{}", highlight("\x1B[31;1m", noisy))?;
                 let s = format!("{ratio:7.3}x");
-                print_right(&s, PERCENTDIFF_WIDTH as usize);
+                write_right(w, &s, PERCENTDIFF_WIDTH as usize)?;
+                write!(w, "{}", highlight("\x1B[0m", noisy))?;
             }
-            print!("\x1B[0m");
         }
+        Ok(())
     }
 
     /// Compute the widths of `Self` that can't easily be initialized in [`Self::new`].
@@ -222,9 +577,21 @@ impl<'a> Displayer<'a> {
     ///                    ^^^^^^^^^^^^^^^^^^^^^ Repeated for each column other than the ref
     /// ```
     ///
-    /// The `<ir>`, `<ir-diff>` and `<%>` fields will show only if they are selected via `--show`.
+    /// The `<ir>`, `<ir-diff>` and `<%>` fields (and the significance mark and the acceleration)
+    /// will show only if they are selected via `--show`.
+    /// With `--show=stats`, the line ends with ` | <min> | <max> | <mean> | <stddev>`, and with
+    /// `--show=trend`, with ` | <slope> <arrow>`, and with `--show=cumulative`, with
+    /// ` | <ir-diff> <%>` from the first run to the last.
+    /// With `--show=stability`, `<symbol>` is preceded by `<mark> `.
+    /// With `--show=rank`, ` <rank>` is added to the reference column and ` <rank> <rank-diff>`
+    /// to the others. Each metric of `--show` then adds ` <rate>` to the reference column and
+    /// ` <rate> <rate-diff>` to the others.
     fn compute_widths(&mut self) {
         let ir_len = self.max_total_ir_width as u32;
+        if self.config.show_stability() {
+            // `<mark> ` before the name of the symbol.
+            self.max_symbol_width += 2;
+        }
 
         let ir_ref = ir_len;
         let ir = if self.config.show.contains(&Show::IRCount) {
@@ -242,60 +609,164 @@ impl<'a> Displayer<'a> {
         } else {
             0
         };
+        let significance = u32::from(self.config.show.contains(&Show::Significance));
+        let acceleration = if self.config.show.contains(&Show::Acceleration) {
+            ir_len + 1 // Account for the `+` or `-` sign.
+        } else {
+            0
+        };
+        let n_metrics = self.config.metrics().count() as u32;
+        if self.config.show_rank() {
+            // `#<rank>`, which also fits `+<rank-diff>` and `new`.
+            self.rank_width =
+                (1 + self.table.rows.len().max(1).ilog10() + 1).max(NEW_RANK.len() as u32);
+        }
+        let rank = if self.rank_width == 0 {
+            0
+        } else {
+            1 + self.rank_width
+        };
 
+        self.reference_width = ir_ref + // <ir_ref>
+            rank +                        // ` <rank>`
+            (1 + RATE_WIDTH) * n_metrics; // ` <rate>` for each metric
         self.run_width = ir + // <ir>
              ir_diff +        // <ir-diff>
              percent_diff +   // <%>
-             ((self.config.show.len() - 1) as u32); // spaces
+             significance +   // <significance>
+             acceleration +   // <acceleration>
+             ((self.config.run_columns().count() - 1) as u32) + // spaces
+             2 * rank +       // ` <rank> <rank-diff>`
+             (1 + RATE_WIDTH + 1 + RATE_DIFF_WIDTH) * n_metrics; // ` <rate> <rate-diff>` for each metric
+
+        if self.config.show_stats() && self.table.n_runs() != 0 {
+            self.stats_width = STATS_COLUMNS
+                .iter()
+                .map(|name| name.len() as u32)
+                .max()
+                .unwrap_or(0)
+                .max(ir_len);
+        }
+        // ` | <stat>` for each statistic, if shown.
+        let stats = if self.stats_width == 0 {
+            0
+        } else {
+            (3 + self.stats_width) * STATS_COLUMNS.len() as u32
+        };
+
+        if self.config.show_trend() && self.table.n_runs() != 0 {
+            let records = self.table.records;
+            self.trend_width = std::iter::once(slope_of(&records.runs_total_irs))
+                .chain(records.symbols.iter().map(RecordsSymbol::ir_slope))
+                .map(|slope| format_slope(slope).len() as u32)
+                .max()
+                .unwrap_or(0)
+                // The header must fit above the slope and its arrow.
+                .max(TREND_COLUMN.len() as u32 - 2);
+        }
+        // ` | <slope> <arrow>`, if shown.
+        let trend = if self.trend_width == 0 {
+            0
+        } else {
+            3 + self.trend_width + 2
+        };
+
+        if self.config.show_cumulative() && self.table.n_runs() != 0 {
+            self.cumulative_width = self
+                .cumulative_content_width()
+                .max(CUMULATIVE_COLUMN.len() as u32);
+        }
+        // ` | <ir-diff> <%>`, if shown.
+        let cumulative = if self.cumulative_width == 0 {
+            0
+        } else {
+            3 + self.cumulative_width
+        };
 
         self.line_width = self.max_symbol_width + // <symbol>
             3 +                 // ` | `
-            ir_ref +            // <ir_ref>
+            self.reference_width + // <ir_ref> [<rate>...]
             (3 +                // ` | `
              self.run_width) *  // <ir> <ir-diff> <%>
-            ((self.records.n_runs() - 1) as u32); // For each column other than the reference one.
+            ((self.table.n_runs() - 1) as u32) + // For each column other than the reference one.
+            stats +
+            trend +
+            cumulative;
     }
+}
 
-    /// Return whether the column at index `i` is the reference column.
-    ///
-    /// If the relative is set to previous, the reference column is considered to be the first.
-    fn is_ref_column(&self, i: usize) -> bool {
-        (i as u32) == self.reference_column || (i == 0 && self.reference_column == u32::MAX)
+/// Display whether the change of the cell is significant (see `--show=significance`).
+///
+/// This is `*` for a significant change, `~` for a change which is not, and a blank if the IR
+/// count did not change.
+fn show_symbol_significance<W: Write>(w: &mut W, cell: &DiffCell) -> io::Result<()> {
+    let mark = if cell.significant {
+        '*'
+    } else if cell.diff() != 0 {
+        '~'
+    } else {
+        ' '
+    };
+    write!(w, "{mark}")
+}
+
+/// Format a rank of `--show=rank`, or `-` if there is none.
+fn format_rank(rank: Option<usize>) -> String {
+    rank.map_or_else(|| "- ".to_string(), |rank| format!("#{rank}"))
+}
+
+/// Format a rate of `--show=<metric>`, or `-` if there is none.
+fn format_rate(rate: Option<f64>) -> String {
+    rate.map_or_else(|| "- ".to_string(), |rate| format!("{rate:.2}%"))
+}
+
+/// Format the difference of a rate of `--show=<metric>`, in percentage points.
+///
+/// Differences which round to 0 are shown as `-`, like unchanged IR counts, as are those which
+/// cannot be computed.
+fn format_rate_diff(diff: Option<f64>) -> String {
+    match diff.map(|diff| format!("{diff:+.2}pp")) {
+        Some(s) if s != "+0.00pp" && s != "-0.00pp" => s,
+        _ => "- ".to_string(),
     }
+}
 
-    /// Get the reference IR count for the given symbol and run.
-    fn get_reference_ir_for(&self, i: usize, symbol: &RecordsSymbol) -> u64 {
-        if self.reference_column == u32::MAX {
-            symbol.irs[i - 1]
-        } else {
-            symbol.irs[self.reference_column as usize]
-        }
+/// Return the direction of a trend, as it is shown by [`format_slope`].
+///
+/// Slopes which round to 0 are flat.
+fn trend_direction(slope: f64) -> Ordering {
+    let rounded = (slope * 10.0).round();
+    if rounded > 0.0 {
+        Ordering::Greater
+    } else if rounded < 0.0 {
+        Ordering::Less
+    } else {
+        Ordering::Equal
     }
+}
 
-    /// Get the reference total IR count for the given run.
-    fn get_reference_total_ir_for(&self, i: usize) -> u64 {
-        if self.reference_column == u32::MAX {
-            self.records.runs_total_irs[i - 1]
-        } else {
-            self.records.runs_total_irs[self.reference_column as usize]
-        }
+/// Format a slope with its sign and a decimal, or `0.0` if it is flat.
+fn format_slope(slope: f64) -> String {
+    match trend_direction(slope) {
+        Ordering::Equal => "0.0".to_string(),
+        _ => format!("{slope:+.1}"),
     }
 }
 
-/// Get the length of the longest symbol.
+/// Get the length of the longest symbol, in characters.
 ///
 /// If `display_all` (the `-a` option) is disabled, this will only take into account symbols for
 /// which the IR count is not the same throughout all runs.
 ///
 /// If there is no symbol to display, this returns 0.
-fn get_max_symbol_length(records: &Records, display_all: bool) -> u32 {
+fn get_max_symbol_length(table: &DiffTable, display_all: bool) -> u32 {
     const TOTAL_IR_LEN: u32 = TOTAL_IR_ROW_NAME.len() as u32;
 
-    (records
-        .symbols
+    (table
+        .rows
         .iter()
-        .filter(|record| display_all || !record.irs.iter().all_equal())
-        .map(|record| record.name.len())
+        .filter(|row| display_all || !row.is_unchanged())
+        .map(|row| row.name().chars().count())
         .max()
         .unwrap_or(0) as u32)
         .max(TOTAL_IR_LEN)
@@ -307,58 +778,84 @@ fn get_highest_total_ir_length(records: &Records) -> u8 {
         .runs_total_irs
         .iter()
         .max()
-        .map_or(1, |x| (x.ilog10() + 1) as u8)
+        .map_or(1, |x| (x.checked_ilog10().unwrap_or(0) + 1) as u8)
+}
+
+/// Return the given escape sequence, or an empty string if the value is not to be highlighted.
+fn highlight(escape: &str, noisy: bool) -> &str {
+    if noisy {
+        ""
+    } else {
+        escape
+    }
 }
 
 /// Print the string aligned to the right within the given width.
 ///
-/// Spaces are used as padding. Truncate if needed.
-fn print_right(s: &str, width: usize) {
-    if s.len() > width {
+/// Spaces are used as padding. Truncate if needed. Widths are in characters, as symbols need not
+/// be ASCII.
+fn write_right<W: Write>(w: &mut W, s: &str, width: usize) -> io::Result<()> {
+    let len = s.chars().count();
+    if len > width {
         for c in s.chars().take(width) {
-            print!("{c}");
+            write!(w, "{c}")?;
         }
     } else {
-        let padding = width - s.len();
-        print_n(' ', padding);
-        print!("{s}");
+        let padding = width - len;
+        write_n(w, ' ', padding)?;
+        write!(w, "{s}")?;
     }
+    Ok(())
 }
 
 /// Print the string aligned to the left within the given width.
 ///
-/// Spaces are used as padding. Truncate if needed.
-fn print_left(s: &str, width: usize) {
-    if s.len() > width {
+/// Spaces are used as padding. Truncate if needed. Widths are in characters, as symbols need not
+/// be ASCII.
+fn write_left<W: Write>(w: &mut W, s: &str, width: usize) -> io::Result<()> {
+    let len = s.chars().count();
+    if len > width {
         for c in s.chars().take(width) {
-            print!("{c}");
+            write!(w, "{c}")?;
         }
     } else {
-        let padding = width - s.len();
-        print!("{s}");
-        print_n(' ', padding);
+        let padding = width - len;
+        write!(w, "{s}")?;
+        write_n(w, ' ', padding)?;
     }
+    Ok(())
 }
 
 /// Print the string centered within the given width.
 ///
-/// Spaces are used as padding. Truncate if needed.
-fn print_centered(s: &str, width: usize) {
-    if s.len() > width {
+/// Spaces are used as padding. Truncate if needed. Widths are in characters, as symbols need not
+/// be ASCII.
+fn write_centered<W: Write>(w: &mut W, s: &str, width: usize) -> io::Result<()> {
+    let len = s.chars().count();
+    if len > width {
         for c in s.chars().take(width) {
-            print!("{c}");
+            write!(w, "{c}")?;
         }
     } else {
-        let padding = width - s.len();
-        print_n(' ', padding / 2);
-        print!("{s}");
-        print_n(' ', padding / 2 + padding % 2);
+        let padding = width - len;
+        write_n(w, ' ', padding / 2)?;
+        write!(w, "{s}")?;
+        write_n(w, ' ', padding / 2 + padding % 2)?;
     }
+    Ok(())
+}
+
+/// Print [`UNMEASURED`] aligned to the right within the given width.
+fn write_unmeasured<W: Write>(w: &mut W, width: usize) -> io::Result<()> {
+    // The mark is a single character wide, however many bytes it takes.
+    write_n(w, ' ', width.saturating_sub(1))?;
+    write!(w, "{UNMEASURED}")
 }
 
 /// Print `c` `n` times.
-fn print_n(c: char, n: usize) {
+fn write_n<W: Write>(w: &mut W, c: char, n: usize) -> io::Result<()> {
     for _ in 0..n {
-        print!("{c}");
+        write!(w, "{c}")?;
     }
+    Ok(())
 }
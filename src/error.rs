@@ -0,0 +1,165 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+use thiserror::Error;
+
+/// An error from loading or handling annotations.
+///
+/// Errors that relate to an input carry its path once it is known. Parsing from a reader (see
+/// [`crate::callgrind::parse`]) has no path; [`Error::in_file`] attaches it.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// An input could not be opened or read.
+    #[error("Failed to read {}", input(path.as_deref()))]
+    Io {
+        /// The path to the input, if known.
+        path: Option<PathBuf>,
+        /// The underlying error.
+        source: io::Error,
+    },
+    /// A line of an input could not be parsed.
+    #[error("{}:{line}: {message}", input(path.as_deref()))]
+    Parse {
+        /// The path to the input, if known.
+        path: Option<PathBuf>,
+        /// The number of the line (1-indexed).
+        line: usize,
+        /// What was wrong with the line.
+        message: String,
+    },
+    /// No format detects an input (see [`crate::format::InputFormats`]).
+    #[error("{}: Unknown input format", input(path.as_deref()))]
+    UnknownFormat {
+        /// The path to the input, if known.
+        path: Option<PathBuf>,
+    },
+    /// An input is not of the expected format.
+    #[error("{} is not {expected}: {reason}", input(path.as_deref()))]
+    FormatMismatch {
+        /// The path to the input, if known.
+        path: Option<PathBuf>,
+        /// The format that was expected (e.g. "a `callgrind_annotate` output").
+        expected: &'static str,
+        /// Why the input does not match the format.
+        reason: String,
+    },
+    /// A column was referenced that does not exist.
+    #[error("Invalid column {column} (got {n_columns} columns)")]
+    ColumnOutOfRange {
+        /// The index of the column (0-indexed).
+        column: usize,
+        /// The number of columns.
+        n_columns: usize,
+    },
+    /// An input is larger than allowed by `--max-input-size`.
+    #[error(
+        "{} is larger than {max_size} bytes (see `--max-input-size`)",
+        input(path.as_deref())
+    )]
+    TooLarge {
+        /// The path to the input, if known.
+        path: Option<PathBuf>,
+        /// The maximum size, in bytes.
+        max_size: u64,
+    },
+    /// A run cannot be added to records (see [`crate::runs::Records::try_add_run`]).
+    #[error("Invalid run {run:?}: {reason}")]
+    InvalidRun {
+        /// The name of the run.
+        run: String,
+        /// What is wrong with the run.
+        reason: String,
+    },
+    /// Runs with the same name would end up in the same records (see
+    /// [`crate::runs::Records::merge`]).
+    #[error("Runs named {names:?} are already in the records")]
+    DuplicateRunNames {
+        /// The names of the conflicting runs.
+        names: Vec<String>,
+    },
+    /// An invariant of records is broken (see [`crate::runs::Records::check_invariants`]).
+    #[error("Invalid records: {reason}")]
+    InvalidRecords {
+        /// Which invariant is broken.
+        reason: String,
+    },
+    /// An input has more symbols than allowed by `--max-symbols`.
+    #[error(
+        "{} has too many symbols (more than {max_symbols}, see `--max-symbols`)",
+        input(path.as_deref())
+    )]
+    TooManySymbols {
+        /// The path to the input, if known.
+        path: Option<PathBuf>,
+        /// The maximum number of symbols.
+        max_symbols: usize,
+    },
+}
+
+/// A [`Result`](std::result::Result) whose error is an [`Error`](enum@Error).
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    /// Attach the path of the input the error relates to, unless it already has one.
+    #[must_use]
+    pub fn in_file<P: AsRef<Path>>(mut self, file: P) -> Self {
+        match &mut self {
+            Self::Io { path, .. }
+            | Self::Parse { path, .. }
+            | Self::UnknownFormat { path }
+            | Self::FormatMismatch { path, .. }
+            | Self::TooLarge { path, .. }
+            | Self::TooManySymbols { path, .. } => {
+                path.get_or_insert_with(|| file.as_ref().to_path_buf());
+            }
+            Self::ColumnOutOfRange { .. }
+            | Self::InvalidRun { .. }
+            | Self::DuplicateRunNames { .. }
+            | Self::InvalidRecords { .. } => {}
+        }
+        self
+    }
+
+    /// Return the path of the input the error relates to, if any.
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            Self::Io { path, .. }
+            | Self::Parse { path, .. }
+            | Self::UnknownFormat { path }
+            | Self::FormatMismatch { path, .. }
+            | Self::TooLarge { path, .. }
+            | Self::TooManySymbols { path, .. } => path.as_deref(),
+            Self::ColumnOutOfRange { .. }
+            | Self::InvalidRun { .. }
+            | Self::DuplicateRunNames { .. }
+            | Self::InvalidRecords { .. } => None,
+        }
+    }
+
+    /// Return whether the error is that of an input which could not be read or parsed, as
+    /// opposed to one of the records the inputs were loaded into.
+    pub fn is_input(&self) -> bool {
+        matches!(
+            self,
+            Self::Io { .. }
+                | Self::Parse { .. }
+                | Self::UnknownFormat { .. }
+                | Self::FormatMismatch { .. }
+                | Self::TooLarge { .. }
+                | Self::TooManySymbols { .. }
+        )
+    }
+}
+
+/// Return how to refer to an input in an error message.
+fn input(path: Option<&Path>) -> String {
+    path.map_or_else(|| "input".to_string(), |path| path.display().to_string())
+}
+
+impl From<io::Error> for Error {
+    fn from(source: io::Error) -> Self {
+        Self::Io { path: None, source }
+    }
+}
@@ -0,0 +1,137 @@
+use std::{fs::File, io::BufReader, path::Path};
+
+use anyhow::Result;
+
+use crate::{
+    args::Args,
+    callgrind::{CallgrindAnnotate, LimitedReader},
+    callgrind_out::CallgrindOut,
+    cg_diff::CgDiff,
+    code_size::{CargoBloat, LlvmLines},
+    criterion::Criterion,
+    csv,
+    error::Error,
+    massif::Massif,
+    runs::Run,
+};
+
+/// A format of input files from which runs are loaded.
+///
+/// Formats are gathered in [`InputFormats`], which finds the format of each input.
+pub trait InputFormat: Send + Sync {
+    /// The name of the format, for error messages.
+    fn name(&self) -> &'static str;
+
+    /// Return whether the file at `path` is in this format.
+    fn detect(&self, path: &Path) -> bool;
+
+    /// Return whether each file holds a single sample of a run, which may be merged with other
+    /// samples (see `--merge`), as per the configuration. Files holding multiple runs are added to
+    /// the records as is.
+    fn is_sample(&self, config: &Args) -> bool;
+
+    /// Load the runs of the file at `path`, as per the configuration (e.g. `--string-replace`).
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be read or is not in this format.
+    fn load(&self, path: &Path, config: &Args) -> Result<Vec<Run>>;
+}
+
+/// CSV files, where each row is a symbol, each column a run and each cell an IR count (see
+/// [`csv::parse`]).
+///
+/// They are detected by their `.csv` extension.
+pub struct Csv;
+
+impl InputFormat for Csv {
+    fn name(&self) -> &'static str {
+        "CSV"
+    }
+
+    fn detect(&self, path: &Path) -> bool {
+        path.extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("csv"))
+    }
+
+    fn is_sample(&self, _config: &Args) -> bool {
+        false
+    }
+
+    fn load(&self, path: &Path, config: &Args) -> Result<Vec<Run>> {
+        let limits = config.input_limits();
+        let file = File::open(path).map_err(|error| Error::from(error).in_file(path))?;
+        let mut reader = LimitedReader::new(file, limits.max_size);
+        let runs = csv::parse(
+            BufReader::new(&mut reader),
+            &config.string_replace,
+            limits.max_symbols,
+        );
+        if reader.exceeded() {
+            return Err(Error::TooLarge {
+                path: Some(path.to_path_buf()),
+                max_size: limits.max_size.unwrap_or_default(),
+            }
+            .into());
+        }
+        Ok(runs.map_err(|error| error.in_file(path))?)
+    }
+}
+
+/// The input formats, in order of precedence.
+///
+/// The first format which detects an input is used to load it. By default, these are
+/// [`Criterion`], [`Csv`], [`CgDiff`], [`CargoBloat`], [`LlvmLines`], [`Massif`], `Pprof` (with the
+/// `pprof` feature), [`CallgrindOut`] then [`CallgrindAnnotate`], which accepts any file.
+pub struct InputFormats {
+    /// The formats, the first having the highest precedence.
+    formats: Vec<Box<dyn InputFormat>>,
+}
+
+impl Default for InputFormats {
+    fn default() -> Self {
+        Self {
+            formats: vec![
+                Box::new(Criterion),
+                Box::new(Csv),
+                Box::new(CgDiff),
+                Box::new(CargoBloat),
+                Box::new(LlvmLines),
+                Box::new(Massif),
+                #[cfg(feature = "pprof")]
+                Box::new(crate::pprof::Pprof),
+                Box::new(CallgrindOut),
+                Box::new(CallgrindAnnotate),
+            ],
+        }
+    }
+}
+
+impl InputFormats {
+    /// Add a format, with precedence over those that were already registered.
+    pub fn register<F: InputFormat + 'static>(&mut self, format: F) -> &mut Self {
+        self.formats.insert(0, Box::new(format));
+        self
+    }
+
+    /// Return the format of the file at `path`, if any.
+    pub fn find(&self, path: &Path) -> Option<&dyn InputFormat> {
+        self.formats
+            .iter()
+            .find(|format| format.detect(path))
+            .map(AsRef::as_ref)
+    }
+
+    /// Load the runs of the file at `path` with its format.
+    ///
+    /// # Errors
+    /// Returns an error if no format detects the file, or if it cannot be loaded.
+    pub fn load(&self, path: &Path, config: &Args) -> Result<Vec<Run>> {
+        let Some(format) = self.find(path) else {
+            return Err(Error::UnknownFormat {
+                path: Some(path.to_path_buf()),
+            }
+            .into());
+        };
+        format.load(path, config)
+    }
+}
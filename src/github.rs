@@ -0,0 +1,45 @@
+use std::io::{self, Write};
+
+use crate::regression::{Regression, Severity};
+
+/// Write the regressions into `out` as GitHub Actions workflow commands.
+///
+/// Each regression is printed on its own line, e.g.:
+/// ```no_compile
+/// ::error title=IR regression::parser::parse: +50000 IR (+10.000%, 500000 -> 550000)
+/// ::error title=LLd regression::parser::parse: +120 LLd (+20.000%, 600 -> 720)
+/// ```
+///
+/// # Errors
+/// Returns an error if writing to `out` fails.
+pub fn print_annotations(regressions: &[Regression], out: &mut dyn Write) -> io::Result<()> {
+    for regression in regressions {
+        let command = match regression.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        writeln!(
+            out,
+            "::{command} title={} regression::{}",
+            escape_property(regression.event_name()),
+            escape_data(&regression.describe())
+        )?;
+    }
+    Ok(())
+}
+
+/// Escape the message of a workflow command.
+///
+/// See <https://github.com/actions/toolkit/blob/main/packages/core/src/command.ts>.
+fn escape_data(s: &str) -> String {
+    s.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// Escape a property of a workflow command.
+///
+/// See <https://github.com/actions/toolkit/blob/main/packages/core/src/command.ts>.
+fn escape_property(s: &str) -> String {
+    escape_data(s).replace(':', "%3A").replace(',', "%2C")
+}
@@ -0,0 +1,95 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+};
+
+use anyhow::{Context, Result};
+use itertools::Itertools;
+
+use crate::{
+    args::Args,
+    checksum::sha256,
+    regression::{Regression, Severity},
+    summary::json_string,
+};
+
+/// Write the regressions to `--export-gitlab-codequality` as a GitLab code quality report, so
+/// that they show up in the widget of merge requests.
+///
+/// Issues are located at the first line of the last input, or of the history store if there is
+/// no input, as symbols have no known location in the sources.
+///
+/// # Errors
+/// Returns an error if the report cannot be written.
+pub fn export_codequality(config: &Args, regressions: &[Regression]) -> Result<()> {
+    let path = &config.export_gitlab_codequality;
+    let location = config.inputs.last().unwrap_or(&config.history);
+    let mut writer =
+        BufWriter::new(File::create(path).with_context(|| format!("Failed to create {path}"))?);
+    write_codequality(regressions, location, &mut writer)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Write the regressions into `w` as a GitLab code quality report, each located at the first
+/// line of `location`.
+///
+/// The report is an array with an issue per regression, e.g.:
+/// ```no_compile
+/// [
+///   {
+///     "description": "parser::parse: +50000 IR (+10.000%, 500000 -> 550000)",
+///     "check_name": "IR regression",
+///     "fingerprint": "5c1d9e…",
+///     "severity": "major",
+///     "location": {"path": "head.callgrind", "lines": {"begin": 1}}
+///   }
+/// ]
+/// ```
+/// Regressions beyond `--warn-on-regression` are `minor` issues, and those beyond
+/// `--fail-on-regression` `major` ones. The fingerprint of an issue is the SHA-256 of the symbol
+/// and the event, so that GitLab tells the regressions of a merge request from those already
+/// reported on its target branch.
+///
+/// # Errors
+/// Returns an error if writing to `w` fails.
+pub fn write_codequality<W: Write>(
+    regressions: &[Regression],
+    location: &str,
+    w: &mut W,
+) -> Result<()> {
+    writeln!(w, "[")?;
+    for (i, regression) in regressions.iter().enumerate() {
+        let severity = match regression.severity {
+            Severity::Warning => "minor",
+            Severity::Error => "major",
+        };
+        let fingerprint =
+            sha256(format!("{}\0{}", regression.symbol, regression.event).as_bytes())?
+                .iter()
+                .map(|byte| format!("{byte:02x}"))
+                .join("");
+        writeln!(w, "  {{")?;
+        writeln!(
+            w,
+            "    \"description\": {},",
+            json_string(&regression.describe())
+        )?;
+        writeln!(
+            w,
+            "    \"check_name\": {},",
+            json_string(&format!("{} regression", regression.event_name()))
+        )?;
+        writeln!(w, "    \"fingerprint\": \"{fingerprint}\",")?;
+        writeln!(w, "    \"severity\": \"{severity}\",")?;
+        writeln!(
+            w,
+            "    \"location\": {{\"path\": {}, \"lines\": {{\"begin\": 1}}}}",
+            json_string(location)
+        )?;
+        let separator = if i + 1 == regressions.len() { "" } else { "," };
+        writeln!(w, "  }}{separator}")?;
+    }
+    writeln!(w, "]")?;
+    Ok(())
+}
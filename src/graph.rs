@@ -0,0 +1,382 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+};
+
+use anyhow::{Context, Result};
+
+use itertools::Itertools;
+
+use crate::{
+    args::Args,
+    diff::{DiffRow, DiffTable},
+    history::format_timestamp,
+};
+
+/// The number of symbols plotted along with the total IR.
+const N_SYMBOLS: usize = 5;
+/// The width of the graph, in pixels.
+const WIDTH: f64 = 800.0;
+/// The height of the graph, in pixels.
+const HEIGHT: f64 = 480.0;
+/// The space around the plot, for the axes, in pixels.
+const MARGIN: f64 = 60.0;
+/// The space above the plot, for the legend, in pixels.
+const TOP: f64 = 20.0 + LEGEND_LINE * (N_SYMBOLS + 1) as f64;
+/// The height of a line of the legend, in pixels.
+const LEGEND_LINE: f64 = 14.0;
+/// The number of ticks of each axis.
+const N_TICKS: usize = 5;
+/// How many times longer than the median interval between runs an interval must be to be a gap.
+const GAP_FACTOR: u64 = 3;
+/// The colors of the series, the total IR first.
+const COLORS: [&str; N_SYMBOLS + 1] = [
+    "#000000", "#1f77b4", "#ff7f0e", "#2ca02c", "#d62728", "#9467bd",
+];
+
+/// A line of the graph.
+struct Series<'a> {
+    /// The name of the series, shown in the legend.
+    name: &'a str,
+    /// The IR count of each run.
+    irs: Vec<u64>,
+    /// The percent change of the IR count of each run since the first one with IR.
+    changes: Vec<f64>,
+}
+
+impl<'a> Series<'a> {
+    /// Create a series, unless it has no IR in any run to compare the others to.
+    fn new(name: &'a str, irs: Vec<u64>) -> Option<Self> {
+        let first = *irs.iter().find(|ir| **ir != 0)? as f64;
+        let changes = irs
+            .iter()
+            .map(|ir| (*ir as f64 - first) * 100.0 / first)
+            .collect();
+        Some(Self { name, irs, changes })
+    }
+}
+
+/// Write an SVG graph of the IR counts of the runs to `--export-graph`.
+///
+/// The graph plots the change of the total IR, and of that of the symbols with the highest IR in
+/// the last column, since the first run in which each had IR, so that they share an axis. When every run has a
+/// timestamp (e.g. when comparing runs of the history), runs are placed by date, so that periods
+/// without measurements show as such: intervals much longer than the others are dashed. Runs are
+/// evenly spaced otherwise.
+///
+/// # Errors
+/// Returns an error if the graph cannot be written.
+pub fn export_graph(config: &Args, table: &DiffTable) -> Result<()> {
+    let path = &config.export_graph;
+    let mut writer =
+        BufWriter::new(File::create(path).with_context(|| format!("Failed to create {path}"))?);
+    write_graph(table, &mut writer)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Write the graph described in [`export_graph`] into `w`.
+///
+/// # Errors
+/// Returns an error if writing to `w` fails.
+pub fn write_graph<W: Write>(table: &DiffTable, w: &mut W) -> Result<()> {
+    write_chart(table, &graph_series(table), w)
+}
+
+/// Write a graph of the IR count of the symbol of `row` alone, as [`write_graph`] does.
+///
+/// # Errors
+/// Returns an error if writing to `w` fails.
+pub fn write_symbol_graph<W: Write>(table: &DiffTable, row: &DiffRow, w: &mut W) -> Result<()> {
+    let irs = row.cells.iter().map(|cell| cell.ir).collect();
+    let series = Series::new(row.name(), irs).into_iter().collect_vec();
+    write_chart(table, &series, w)
+}
+
+/// Write a graph of `series` into `w`.
+///
+/// # Errors
+/// Returns an error if writing to `w` fails.
+fn write_chart<W: Write>(table: &DiffTable, series: &[Series], w: &mut W) -> Result<()> {
+    let plot = Plot::new(
+        table,
+        series
+            .iter()
+            .flat_map(|series| series.changes.iter().copied()),
+    );
+
+    writeln!(
+        w,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{WIDTH}" height="{HEIGHT}" viewBox="0 0 {WIDTH} {HEIGHT}" font-family="sans-serif" font-size="11">"#
+    )?;
+    writeln!(
+        w,
+        r#"<rect width="{WIDTH}" height="{HEIGHT}" fill="white"/>"#
+    )?;
+    write_axes(table, &plot, w)?;
+    for (series, color) in series.iter().zip(COLORS) {
+        write_series(table, series, color, &plot, w)?;
+    }
+    write_legend(series, w)?;
+    writeln!(w, "</svg>")?;
+    Ok(())
+}
+
+/// The axes of the graph, and how values map to pixels.
+struct Plot {
+    /// The lowest percent change of the Y axis.
+    min: f64,
+    /// The highest percent change of the Y axis.
+    max: f64,
+    /// The position of each run along the X axis, from 0 to 1.
+    xs: Vec<f64>,
+    /// Whether the interval between each run and the next is a gap in the measurements.
+    gaps: Vec<bool>,
+}
+
+impl Plot {
+    /// Create a plot of the runs of `table`, spanning 0% and all of `changes`.
+    fn new(table: &DiffTable, changes: impl Iterator<Item = f64>) -> Self {
+        let (min, max) = changes.fold((0.0_f64, 0.0_f64), |(min, max), y| (min.min(y), max.max(y)));
+        // Keep flat graphs from dividing by 0.
+        let (min, max) = if (max - min).abs() < f64::EPSILON {
+            (min - 1.0, max + 1.0)
+        } else {
+            (min, max)
+        };
+        Self {
+            min,
+            max,
+            xs: x_positions(table),
+            gaps: gaps(table),
+        }
+    }
+
+    /// Return the horizontal pixel of a position from 0 to 1 along the X axis.
+    fn x(x: f64) -> f64 {
+        MARGIN + x * (WIDTH - 2.0 * MARGIN)
+    }
+
+    /// Return the vertical pixel of a percent change.
+    fn y(&self, y: f64) -> f64 {
+        HEIGHT - MARGIN - (y - self.min) / (self.max - self.min) * (HEIGHT - MARGIN - TOP)
+    }
+}
+
+/// Write the axes of the graph, their ticks and labels.
+///
+/// # Errors
+/// Returns an error if writing to `w` fails.
+fn write_axes<W: Write>(table: &DiffTable, plot: &Plot, w: &mut W) -> Result<()> {
+    let (left, right, bottom) = (Plot::x(0.0), Plot::x(1.0), HEIGHT - MARGIN);
+    writeln!(
+        w,
+        r#"<line x1="{left}" y1="{bottom}" x2="{right}" y2="{bottom}" stroke="black"/>"#
+    )?;
+    writeln!(
+        w,
+        r#"<line x1="{left}" y1="{TOP}" x2="{left}" y2="{bottom}" stroke="black"/>"#
+    )?;
+    for i in 0..N_TICKS {
+        let change = plot.min + i as f64 / (N_TICKS - 1) as f64 * (plot.max - plot.min);
+        let y = plot.y(change);
+        writeln!(
+            w,
+            r#"<line x1="{left}" y1="{y:.1}" x2="{right}" y2="{y:.1}" stroke="gainsboro"/>"#
+        )?;
+        writeln!(
+            w,
+            r#"<text x="{:.1}" y="{:.1}" text-anchor="end">{change:+.1}%</text>"#,
+            left - 5.0,
+            y + 4.0,
+        )?;
+    }
+    let x_labels = match time_axis(table) {
+        Some((first, last)) => (0..N_TICKS)
+            .map(|i| {
+                let fraction = i as f64 / (N_TICKS - 1) as f64;
+                let timestamp = first + ((last - first) as f64 * fraction) as u64;
+                // Only the date part of the timestamp.
+                let mut label = format_timestamp(timestamp);
+                label.truncate(10);
+                (fraction, label)
+            })
+            .collect::<Vec<_>>(),
+        None => plot
+            .xs
+            .iter()
+            .enumerate()
+            .step_by(plot.xs.len().div_ceil(N_TICKS * 2).max(1))
+            .map(|(i, x)| (*x, run_label(table, i)))
+            .collect(),
+    };
+    for (x, label) in x_labels {
+        writeln!(
+            w,
+            r#"<text x="{:.1}" y="{:.1}" text-anchor="middle">{}</text>"#,
+            Plot::x(x),
+            bottom + 16.0,
+            escape(&label)
+        )?;
+    }
+    Ok(())
+}
+
+/// Write the line and points of a series.
+///
+/// # Errors
+/// Returns an error if writing to `w` fails.
+fn write_series<W: Write>(
+    table: &DiffTable,
+    series: &Series,
+    color: &str,
+    plot: &Plot,
+    w: &mut W,
+) -> Result<()> {
+    for (i, gap) in plot.gaps.iter().enumerate() {
+        let dash = if *gap {
+            r#" stroke-dasharray="4 4""#
+        } else {
+            ""
+        };
+        writeln!(
+            w,
+            r#"<line x1="{:.1}" y1="{:.1}" x2="{:.1}" y2="{:.1}" stroke="{color}" stroke-width="1.5"{dash}/>"#,
+            Plot::x(plot.xs[i]),
+            plot.y(series.changes[i]),
+            Plot::x(plot.xs[i + 1]),
+            plot.y(series.changes[i + 1]),
+        )?;
+    }
+    for (i, (x, y)) in plot.xs.iter().zip(&series.changes).enumerate() {
+        let when = table.records.runs_metadata[i]
+            .timestamp
+            .map(|timestamp| format!(" ({})", format_timestamp(timestamp)))
+            .unwrap_or_default();
+        writeln!(
+            w,
+            r#"<circle cx="{:.1}" cy="{:.1}" r="3" fill="{color}"><title>{}{when}: {} IR ({y:+.2}%)</title></circle>"#,
+            Plot::x(*x),
+            plot.y(*y),
+            escape(&run_label(table, i)),
+            series.irs[i],
+        )?;
+    }
+    Ok(())
+}
+
+/// Write the legend of the graph, above the plot.
+///
+/// # Errors
+/// Returns an error if writing to `w` fails.
+fn write_legend<W: Write>(series: &[Series], w: &mut W) -> Result<()> {
+    for (i, (series, color)) in series.iter().zip(COLORS).enumerate() {
+        let y = 15.0 + LEGEND_LINE * i as f64;
+        let name = if series.name.chars().count() > 60 {
+            format!("{}…", series.name.chars().take(59).collect::<String>())
+        } else {
+            series.name.to_string()
+        };
+        writeln!(
+            w,
+            r#"<rect x="{MARGIN}" y="{:.1}" width="10" height="10" fill="{color}"/><text x="{:.1}" y="{y:.1}">{}</text>"#,
+            y - 9.0,
+            MARGIN + 15.0,
+            escape(&name)
+        )?;
+    }
+    Ok(())
+}
+
+/// Return the series of the graph: the total IR, then that of the [`N_SYMBOLS`] symbols with the
+/// highest IR in the last column (by name, if tied).
+fn graph_series<'a>(table: &'a DiffTable) -> Vec<Series<'a>> {
+    let column = table.total.len().saturating_sub(1);
+    let mut rows = table.rows.iter().collect::<Vec<_>>();
+    rows.sort_by(|a, b| {
+        b.cells[column]
+            .ir
+            .cmp(&a.cells[column].ir)
+            .then_with(|| a.name().cmp(b.name()))
+    });
+    let symbols = rows
+        .into_iter()
+        .filter_map(|row| Series::new(row.name(), row.cells.iter().map(|cell| cell.ir).collect()))
+        .take(N_SYMBOLS);
+    Series::new("Total", table.total.iter().map(|cell| cell.ir).collect())
+        .into_iter()
+        .chain(symbols)
+        .collect()
+}
+
+/// Return the timestamps of the first and last runs, if all runs have one and they span some
+/// time.
+fn time_axis(table: &DiffTable) -> Option<(u64, u64)> {
+    let timestamps = timestamps(table)?;
+    let (first, last) = (*timestamps.iter().min()?, *timestamps.iter().max()?);
+    (first < last).then_some((first, last))
+}
+
+/// Return the timestamp of each run, if all runs have one.
+fn timestamps(table: &DiffTable) -> Option<Vec<u64>> {
+    table
+        .records
+        .runs_metadata
+        .iter()
+        .map(|metadata| metadata.timestamp)
+        .collect()
+}
+
+/// Return the position of each run along the X axis, from 0 to 1.
+fn x_positions(table: &DiffTable) -> Vec<f64> {
+    let n_runs = table.total.len();
+    match (time_axis(table), timestamps(table)) {
+        (Some((first, last)), Some(timestamps)) => timestamps
+            .iter()
+            .map(|timestamp| (timestamp - first) as f64 / (last - first) as f64)
+            .collect(),
+        _ if n_runs == 1 => vec![0.5],
+        _ => (0..n_runs)
+            .map(|i| i as f64 / (n_runs - 1) as f64)
+            .collect(),
+    }
+}
+
+/// Return whether the interval between each run and the next is a gap in the measurements, that
+/// is, it is more than [`GAP_FACTOR`] times as long as the median interval.
+///
+/// Without timestamps, no interval is a gap.
+fn gaps(table: &DiffTable) -> Vec<bool> {
+    let n_intervals = table.total.len().saturating_sub(1);
+    let Some(timestamps) = timestamps(table).filter(|_| time_axis(table).is_some()) else {
+        return vec![false; n_intervals];
+    };
+    let intervals = timestamps
+        .windows(2)
+        .map(|pair| pair[1].abs_diff(pair[0]))
+        .collect::<Vec<_>>();
+    let mut sorted = intervals.clone();
+    sorted.sort_unstable();
+    let median = sorted.get(sorted.len() / 2).copied().unwrap_or_default();
+    intervals
+        .iter()
+        .map(|interval| median != 0 && *interval > median * GAP_FACTOR)
+        .collect()
+}
+
+/// Return a label for the run of column `i`: its name, or its index if it has none.
+fn run_label(table: &DiffTable, i: usize) -> String {
+    let name = &table.records.run_names[i];
+    if name.is_empty() {
+        format!("run {i}")
+    } else {
+        name.clone()
+    }
+}
+
+/// Escape `s` to be text of an SVG element.
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
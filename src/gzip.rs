@@ -0,0 +1,435 @@
+use crate::error::{Error, Result};
+
+/// The bytes gzip files start with.
+pub const MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// The flag of a gzip header telling that it has extra fields.
+const FEXTRA: u8 = 0x04;
+/// The flag of a gzip header telling that it has the name of the compressed file.
+const FNAME: u8 = 0x08;
+/// The flag of a gzip header telling that it has a comment.
+const FCOMMENT: u8 = 0x10;
+/// The flag of a gzip header telling that it has a CRC of the header.
+const FHCRC: u8 = 0x02;
+
+/// The base lengths of the length codes 257 to 285.
+const LENGTH_BASES: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+/// The number of extra bits of the length codes 257 to 285.
+const LENGTH_EXTRA_BITS: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+/// The base distances of the distance codes.
+const DISTANCE_BASES: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+/// The number of extra bits of the distance codes.
+const DISTANCE_EXTRA_BITS: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+/// The order in which the lengths of the code length codes of a dynamic block are stored.
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+/// The CRC-32 of each byte, as gzip computes it (that of ISO 3309).
+const CRC_TABLE: [u32; 256] = crc_table();
+
+/// How much of the decompressed data is wanted.
+#[derive(Clone, Copy)]
+enum Wanted {
+    /// All of it, which must be at most the given number of bytes, if limited.
+    All(Option<u64>),
+    /// Its first bytes only, up to the given number.
+    Prefix(usize),
+}
+
+impl Wanted {
+    /// Return whether `output` holds all of the wanted data, or an error if it is larger than
+    /// allowed.
+    fn is_done(self, output: &[u8]) -> Result<bool> {
+        match self {
+            Self::All(Some(max)) if output.len() as u64 > max => Err(Error::TooLarge {
+                path: None,
+                max_size: max,
+            }),
+            Self::All(_) => Ok(false),
+            Self::Prefix(length) => Ok(output.len() >= length),
+        }
+    }
+}
+
+/// Decompress the first member of a gzip file (RFC 1952), which must be at most `max_size` bytes
+/// once decompressed, if limited.
+///
+/// The CRC-32 and the size of the decompressed data are checked against the trailer of the
+/// member, as is the CRC of the header if it has one.
+///
+/// ```
+/// # use callgrind_differ::gzip;
+/// // `echo -n hello | gzip`
+/// let data = [
+///     0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x03, 0xcb, 0x48, 0xcd, 0xc9, 0xc9,
+///     0x07, 0x00, 0x86, 0xa6, 0x10, 0x36, 0x05, 0x00, 0x00, 0x00,
+/// ];
+/// assert_eq!(gzip::decompress(&data, None).unwrap(), b"hello");
+/// assert!(gzip::decompress(&data, Some(4)).is_err());
+/// ```
+///
+/// Errors have no path; see [`Error::in_file`].
+///
+/// # Errors
+/// Returns an error if `data` is not a gzip file, is truncated or corrupt, or is larger than
+/// `max_size` bytes once decompressed.
+pub fn decompress(data: &[u8], max_size: Option<u64>) -> Result<Vec<u8>> {
+    let mut bits = Bits::new(&data[header_size(data)?..]);
+    let output = inflate(&mut bits, Wanted::All(max_size))?;
+    let trailer = bits.take(8).map_err(|_| corrupt("Truncated trailer"))?;
+    if u32::from_le_bytes([trailer[0], trailer[1], trailer[2], trailer[3]]) != crc32(&output) {
+        return Err(corrupt("The CRC of the data does not match"));
+    }
+    // The size is that modulo 2^32.
+    if u64::from(u32::from_le_bytes([
+        trailer[4], trailer[5], trailer[6], trailer[7],
+    ])) != output.len() as u64 % (1 << 32)
+    {
+        return Err(corrupt("The size of the data does not match"));
+    }
+    Ok(output)
+}
+
+/// Decompress the first `length` bytes (or fewer, if there are not as many) of the first member
+/// of a gzip file, which may be truncated after them, e.g. to tell the format of the data from
+/// a prefix of the file.
+///
+/// Errors have no path; see [`Error::in_file`].
+///
+/// # Errors
+/// Returns an error if `data` is not a gzip file, or is truncated or corrupt before the first
+/// `length` bytes of the data.
+pub fn decompress_prefix(data: &[u8], length: usize) -> Result<Vec<u8>> {
+    let mut output = inflate(
+        &mut Bits::new(&data[header_size(data)?..]),
+        Wanted::Prefix(length),
+    )?;
+    output.truncate(length);
+    Ok(output)
+}
+
+/// Return the size of the header of the gzip file `data`, after which its compressed data
+/// starts.
+fn header_size(data: &[u8]) -> Result<usize> {
+    if data.len() < 10 || data[..2] != MAGIC {
+        return Err(corrupt("No gzip header"));
+    }
+    if data[2] != 8 {
+        return Err(corrupt("Unknown compression method"));
+    }
+    let flags = data[3];
+    let mut start = 10;
+    if flags & FEXTRA != 0 {
+        let length = data
+            .get(start..start + 2)
+            .ok_or_else(|| corrupt("Truncated header"))?;
+        start += 2 + usize::from(u16::from_le_bytes([length[0], length[1]]));
+    }
+    for flag in [FNAME, FCOMMENT] {
+        if flags & flag != 0 {
+            let end = data
+                .get(start..)
+                .and_then(|rest| rest.iter().position(|byte| *byte == 0))
+                .ok_or_else(|| corrupt("Truncated header"))?;
+            start += end + 1;
+        }
+    }
+    if flags & FHCRC != 0 {
+        let crc = data
+            .get(start..start + 2)
+            .ok_or_else(|| corrupt("Truncated header"))?;
+        // That of the header is the lower half of its CRC-32.
+        if u32::from(u16::from_le_bytes([crc[0], crc[1]])) != crc32(&data[..start]) & 0xffff {
+            return Err(corrupt("The CRC of the header does not match"));
+        }
+        start += 2;
+    }
+    if start > data.len() {
+        return Err(corrupt("Truncated header"));
+    }
+    Ok(start)
+}
+
+/// Decompress DEFLATE data (RFC 1951) from `bits`, as much of it as is wanted. The rest of the
+/// data (e.g. a gzip trailer) starts at the byte of `bits` after the last block, if all of it
+/// was wanted.
+fn inflate(bits: &mut Bits, wanted: Wanted) -> Result<Vec<u8>> {
+    let mut output = vec![];
+    loop {
+        let is_last = bits.read(1)? == 1;
+        match bits.read(2)? {
+            0 => {
+                bits.align();
+                let header = bits.take(4)?;
+                let length = u16::from_le_bytes([header[0], header[1]]);
+                if length != !u16::from_le_bytes([header[2], header[3]]) {
+                    return Err(corrupt("Invalid length of a stored block"));
+                }
+                output.extend_from_slice(bits.take(length.into())?);
+            }
+            1 => {
+                let (literals, distances) = fixed_codes();
+                inflate_block(bits, &literals, &distances, &mut output, wanted)?;
+            }
+            2 => {
+                let (literals, distances) = dynamic_codes(bits)?;
+                inflate_block(bits, &literals, &distances, &mut output, wanted)?;
+            }
+            _ => return Err(corrupt("Invalid block type")),
+        }
+        if wanted.is_done(&output)? {
+            return Ok(output);
+        }
+        if is_last {
+            bits.align();
+            return Ok(output);
+        }
+    }
+}
+
+/// Decompress a block compressed with the given codes into `output`, until its end or until
+/// `output` holds all of the wanted data.
+fn inflate_block(
+    bits: &mut Bits,
+    literals: &Huffman,
+    distances: &Huffman,
+    output: &mut Vec<u8>,
+    wanted: Wanted,
+) -> Result<()> {
+    loop {
+        let symbol = usize::from(literals.decode(bits)?);
+        match symbol {
+            0..=255 => output.push(symbol as u8),
+            256 => return Ok(()),
+            _ => {
+                let code = symbol - 257;
+                let (Some(base), Some(extra)) =
+                    (LENGTH_BASES.get(code), LENGTH_EXTRA_BITS.get(code))
+                else {
+                    return Err(corrupt("Invalid length code"));
+                };
+                let length = usize::from(*base) + bits.read(*extra)? as usize;
+                let code = usize::from(distances.decode(bits)?);
+                let (Some(base), Some(extra)) =
+                    (DISTANCE_BASES.get(code), DISTANCE_EXTRA_BITS.get(code))
+                else {
+                    return Err(corrupt("Invalid distance code"));
+                };
+                let distance = usize::from(*base) + bits.read(*extra)? as usize;
+                let Some(start) = output.len().checked_sub(distance) else {
+                    return Err(corrupt("Distance beyond the start of the data"));
+                };
+                // The copy may overlap what it appends, so bytes are copied one at a time.
+                for i in start..start + length {
+                    output.push(output[i]);
+                }
+            }
+        }
+        if wanted.is_done(output)? {
+            return Ok(());
+        }
+    }
+}
+
+/// Compute [`CRC_TABLE`].
+const fn crc_table() -> [u32; 256] {
+    let mut table = [0; 256];
+    let mut byte = 0;
+    while byte < 256 {
+        let mut crc = byte as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 {
+                0xedb8_8320 ^ (crc >> 1)
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[byte] = crc;
+        byte += 1;
+    }
+    table
+}
+
+/// Return the CRC-32 of `data`, as gzip computes it.
+fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0, |crc, byte| {
+        CRC_TABLE[((crc ^ u32::from(*byte)) & 0xff) as usize] ^ (crc >> 8)
+    })
+}
+
+/// Return the codes of the literals and lengths, and of the distances, of fixed blocks.
+fn fixed_codes() -> (Huffman, Huffman) {
+    let mut lengths = [0; 288];
+    lengths[..144].fill(8);
+    lengths[144..256].fill(9);
+    lengths[256..280].fill(7);
+    lengths[280..].fill(8);
+    (Huffman::new(&lengths), Huffman::new(&[5; 30]))
+}
+
+/// Read the codes of the literals and lengths, and of the distances, of a dynamic block.
+fn dynamic_codes(bits: &mut Bits) -> Result<(Huffman, Huffman)> {
+    let n_literals = bits.read(5)? as usize + 257;
+    let n_distances = bits.read(5)? as usize + 1;
+    let n_code_lengths = bits.read(4)? as usize + 4;
+    let mut code_lengths = [0; 19];
+    for i in &CODE_LENGTH_ORDER[..n_code_lengths] {
+        code_lengths[*i] = bits.read(3)? as u8;
+    }
+    let code_lengths = Huffman::new(&code_lengths);
+
+    let mut lengths = Vec::with_capacity(n_literals + n_distances);
+    while lengths.len() < n_literals + n_distances {
+        let (length, repeat) = match code_lengths.decode(bits)? {
+            length @ 0..=15 => (length as u8, 1),
+            16 => {
+                let Some(previous) = lengths.last() else {
+                    return Err(corrupt("Repeated code length without a previous one"));
+                };
+                (*previous, 3 + bits.read(2)?)
+            }
+            17 => (0, 3 + bits.read(3)?),
+            _ => (0, 11 + bits.read(7)?),
+        };
+        if lengths.len() + repeat as usize > n_literals + n_distances {
+            return Err(corrupt("Too many code lengths"));
+        }
+        lengths.extend(std::iter::repeat_n(length, repeat as usize));
+    }
+    if lengths[256] == 0 {
+        return Err(corrupt("No end of block code"));
+    }
+    Ok((
+        Huffman::new(&lengths[..n_literals]),
+        Huffman::new(&lengths[n_literals..]),
+    ))
+}
+
+/// A canonical Huffman code.
+struct Huffman {
+    /// The number of codes of each length in bits.
+    counts: [u16; 16],
+    /// The symbols, by code.
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    /// Build the code whose symbols have the given lengths in bits, 0 for unused symbols.
+    fn new(lengths: &[u8]) -> Self {
+        let mut counts = [0; 16];
+        for length in lengths {
+            counts[usize::from(*length)] += 1;
+        }
+        counts[0] = 0;
+        let mut offsets = [0; 16];
+        for length in 1..15 {
+            offsets[length + 1] = offsets[length] + counts[length];
+        }
+        let mut symbols = vec![0; lengths.len()];
+        for (symbol, length) in lengths
+            .iter()
+            .enumerate()
+            .filter(|(_, length)| **length != 0)
+        {
+            let offset = &mut offsets[usize::from(*length)];
+            symbols[usize::from(*offset)] = symbol as u16;
+            *offset += 1;
+        }
+        Self { counts, symbols }
+    }
+
+    /// Read a symbol from `bits`.
+    fn decode(&self, bits: &mut Bits) -> Result<u16> {
+        // The first code of the current length, and the index of its symbol.
+        let (mut code, mut first, mut index) = (0, 0, 0);
+        for count in &self.counts[1..] {
+            let count = usize::from(*count);
+            code |= bits.read(1)? as usize;
+            if code < first + count {
+                return Ok(self.symbols[index + code - first]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(corrupt("Invalid code"))
+    }
+}
+
+/// A reader of the bits of DEFLATE data, least significant bit first.
+struct Bits<'a> {
+    /// The data.
+    data: &'a [u8],
+    /// The position of the next byte to read in `data`.
+    position: usize,
+    /// The bits read from `data` but not consumed yet.
+    buffer: u32,
+    /// The number of bits in `buffer`.
+    count: u8,
+}
+
+impl<'a> Bits<'a> {
+    /// Read the bits of `data`.
+    fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            position: 0,
+            buffer: 0,
+            count: 0,
+        }
+    }
+
+    /// Read an `n`-bit number, `n` being at most 16.
+    fn read(&mut self, n: u8) -> Result<u32> {
+        while self.count < n {
+            let Some(byte) = self.data.get(self.position) else {
+                return Err(corrupt("Truncated data"));
+            };
+            self.buffer |= u32::from(*byte) << self.count;
+            self.position += 1;
+            self.count += 8;
+        }
+        let value = self.buffer & ((1 << n) - 1);
+        self.buffer >>= n;
+        self.count -= n;
+        Ok(value)
+    }
+
+    /// Skip to the next byte boundary.
+    fn align(&mut self) {
+        self.buffer = 0;
+        self.count = 0;
+    }
+
+    /// Read `n` bytes, from a byte boundary.
+    fn take(&mut self, n: usize) -> Result<&'a [u8]> {
+        let bytes = self
+            .data
+            .get(self.position..self.position + n)
+            .ok_or_else(|| corrupt("Truncated data"))?;
+        self.position += n;
+        Ok(bytes)
+    }
+}
+
+/// Return the error of corrupt gzip data.
+fn corrupt(reason: &str) -> Error {
+    Error::FormatMismatch {
+        path: None,
+        expected: "a gzip file",
+        reason: reason.to_string(),
+    }
+}
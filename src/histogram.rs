@@ -0,0 +1,53 @@
+use crate::diff::DiffTable;
+
+/// The labels of the buckets of a [`Histogram`], in order.
+pub const BUCKET_LABELS: [&str; 5] = ["< -10%", "-10..-1%", "±1%", "+1..+10%", "> +10%"];
+
+/// The distribution of the changes of the IR counts of the symbols in the last column of a table
+/// (see `--histogram`).
+///
+/// Symbols are counted in the buckets of [`BUCKET_LABELS`] by the percentage of their change to
+/// their reference, which tells whether a change is broad or localized to a few symbols.
+pub struct Histogram {
+    /// The number of symbols in each bucket.
+    pub counts: [usize; BUCKET_LABELS.len()],
+}
+
+impl Histogram {
+    /// Count the changes of the symbols of the last column of `table`.
+    ///
+    /// Symbols which are in neither the column nor its reference are left out. Symbols which are
+    /// new to the column count as a 100% increase.
+    pub fn new(table: &DiffTable) -> Self {
+        let column = table.last_column();
+        let mut counts = [0; BUCKET_LABELS.len()];
+        for cell in table.rows.iter().map(|row| &row.cells[column]) {
+            if cell.ir != 0 || cell.reference_ir() != 0 {
+                counts[bucket_of(cell.percent())] += 1;
+            }
+        }
+        Self { counts }
+    }
+
+    /// Return the total number of symbols.
+    pub fn total(&self) -> usize {
+        self.counts.iter().sum()
+    }
+}
+
+/// Return the index in [`BUCKET_LABELS`] of the bucket of a change of `percent`.
+///
+/// Bounds belong to the bucket closest to ±1%.
+fn bucket_of(percent: f64) -> usize {
+    if percent < -10.0 {
+        0
+    } else if percent < -1.0 {
+        1
+    } else if percent <= 1.0 {
+        2
+    } else if percent <= 10.0 {
+        3
+    } else {
+        4
+    }
+}
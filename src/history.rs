@@ -0,0 +1,1807 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Display,
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    process::Command,
+    sync::OnceLock,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{bail, Context, Result};
+use itertools::Itertools;
+
+use crate::{
+    alias::{self, Alias},
+    args::{HistoryCommand, OnDuplicate, Percentage, RunRange},
+    hook::Hooks,
+    preset::Preset,
+    retention::Retention,
+    runs::{Run, RunMetadata},
+};
+
+/// The extension of the files in which runs are stored.
+const RUN_FILE_EXTENSION: &str = "run";
+
+/// The name of the file used to lock the store.
+const LOCK_FILE_NAME: &str = ".lock";
+
+/// The name of the directory of a store in which the histories of its targets are.
+const TARGETS_DIR_NAME: &str = "targets";
+
+/// The name of the configuration file of a store.
+const CONFIG_FILE_NAME: &str = "config";
+
+/// The name of the file of a store in which compacted runs are stored.
+const COMPACTED_FILE_NAME: &str = "compacted";
+
+/// The name of the file of a store in which the symbol aliases applied to its runs are recorded.
+const ALIASES_FILE_NAME: &str = "aliases";
+
+/// The name of the file of a store which holds the id of the next run appended to it.
+const NEXT_ID_FILE_NAME: &str = "next_id";
+
+/// A persistent store of runs.
+///
+/// The store is a directory in which each run is stored in its own file, named after its id
+/// (e.g. `00000003.run`). Ids are increasing, which means that the most recent run is the one
+/// with the highest id. The `next_id` file of the store holds the id of the next run appended to
+/// it, so that the ids of removed runs are never given to other runs.
+///
+/// A run file is made of a header and a body, separated by an empty line:
+/// ```no_compile
+/// name: v1.3
+/// commit: 0123456789abcdef0123456789abcdef01234567
+/// branch: main
+/// tag: v1.3.0
+/// timestamp: 1711100000
+/// benchmark: parse
+/// version: v1.3
+/// iterations: 1000
+/// command: ./target/release/benchmarks parse
+/// note: Switched to hashbrown
+/// checksum: 9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08
+/// pinned: true
+/// rustc: rustc 1.77.0 (aedd173a2 2024-03-17)
+/// target_triple: x86_64-unknown-linux-gnu
+/// cpu: AMD Ryzen 7 5800X 8-Core Processor
+/// valgrind: valgrind-3.22.0
+/// simulation: --cache-sim=yes --branch-sim=no
+/// unit: ns
+/// origin: ci-runner-2
+/// total_ir: 14418621168
+/// events: Dr D1mr
+/// event_totals: 4213377121,1301
+///
+/// 1516457,402113,12 yaml_rust2::scanner::Scanner<T>::fetch_more_tokens
+/// 1210447,390017,0 yaml_rust2::parser::Parser<T>::next_token
+/// ```
+/// Header keys with no value (and `pinned` for runs which are not) are omitted. Newlines in
+/// notes are replaced with spaces, and names cannot hold any. Each line of the body is the IR count of a symbol, followed by
+/// the counts of the other events (if any) separated by commas, a space and the name of the
+/// symbol.
+///
+/// Old runs may be compacted (see [`Self::compact`]) into the `compacted` file of the store,
+/// which holds the number of runs, the header of each run preceded by its id, and a line per
+/// symbol with its counts in each run, separated by `;` (`-` if the symbol is not in the run):
+/// ```no_compile
+/// runs: 2
+///
+/// id: 0
+/// name: v1.2
+/// total_ir: 14418621168
+///
+/// id: 1
+/// name: v1.3
+/// total_ir: 14403527007
+///
+/// 1516457;1498305 yaml_rust2::scanner::Scanner<T>::fetch_more_tokens
+/// -;1210447 yaml_rust2::parser::Parser<T>::next_token
+/// ```
+/// Loading a range of runs then reads only the headers of the other runs, and the counts of all
+/// compacted runs in a single file rather than a file per run.
+///
+/// The histories of the targets of a store (see `--target`), e.g. different benchmarks, are
+/// stores of their own, in `targets/<target>` within the directory of the store.
+///
+/// The `config` file of the store, if any, declares which runs are kept (see [`Retention`]). It
+/// is applied whenever runs are appended. Targets use the `config` file of their own directory,
+/// if any, and that of the store otherwise.
+///
+/// The `aliases` file of the store, if any, records the symbol renames applied to its runs (see
+/// [`Self::alias`]), as an alias file does (see [`Alias`]). They are applied to the runs
+/// appended since as well.
+///
+/// Multiple processes (e.g. concurrent CI jobs) may use the same store. Mutations take an
+/// exclusive lock on the store and reads a shared one. Files are written to a temporary file
+/// which is then renamed, so that a crashed writer never leaves a partially written run behind.
+pub struct History {
+    /// The path to the directory of the store.
+    path: PathBuf,
+    /// The path to the configuration file of the store.
+    config_path: PathBuf,
+}
+
+/// An inconsistency found in a [`History`] by [`History::check`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Inconsistency {
+    /// A temporary file left behind by a process that crashed while writing it.
+    StaleTemporaryFile(PathBuf),
+    /// The file of a compacted run, left behind by a process that crashed while compacting it.
+    CompactedRunFile(PathBuf),
+    /// A run file that cannot be read.
+    UnreadableRun {
+        /// The path to the file.
+        path: PathBuf,
+        /// Why the file cannot be read.
+        error: String,
+    },
+    /// A compacted file that cannot be read.
+    UnreadableCompacted(String),
+    /// A run whose counts contradict each other or its header.
+    InvalidRun {
+        /// The id of the run.
+        id: u64,
+        /// What is wrong with the run.
+        reason: String,
+    },
+    /// A run loaded from the same file as another (see `--on-duplicate`). This is only a notice,
+    /// as runs may be appended again on purpose with `--on-duplicate=allow`.
+    DuplicateRun {
+        /// The id of the run.
+        id: u64,
+        /// The id of the oldest run loaded from the same file.
+        duplicate_of: u64,
+    },
+}
+
+impl Inconsistency {
+    /// Return whether [`History::check`] repairs the inconsistency.
+    ///
+    /// Stale files are removed, and unreadable run files are renamed with a `.corrupt` extension.
+    /// Other inconsistencies are left to be repaired by hand (e.g. with `history remove`).
+    pub fn is_repairable(&self) -> bool {
+        matches!(
+            self,
+            Self::StaleTemporaryFile(_) | Self::CompactedRunFile(_) | Self::UnreadableRun { .. }
+        )
+    }
+
+    /// Return whether the inconsistency is only a notice, which does not make `history fsck`
+    /// fail (see [`Self::DuplicateRun`]).
+    pub fn is_notice(&self) -> bool {
+        matches!(self, Self::DuplicateRun { .. })
+    }
+}
+
+impl Display for Inconsistency {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::StaleTemporaryFile(path) => {
+                write!(f, "{}: stale temporary file", path.display())
+            }
+            Self::CompactedRunFile(path) => {
+                write!(
+                    f,
+                    "{}: the run is also in the compacted file",
+                    path.display()
+                )
+            }
+            // The error names the file already.
+            Self::UnreadableRun { error, .. } => write!(f, "{error}"),
+            Self::UnreadableCompacted(error) => write!(f, "{COMPACTED_FILE_NAME}: {error}"),
+            Self::InvalidRun { id, reason } => write!(f, "Run {id}: {reason}"),
+            Self::DuplicateRun { id, duplicate_of } => write!(
+                f,
+                "Run {id}: loaded from the same file as run {duplicate_of}"
+            ),
+        }
+    }
+}
+
+/// A run that is stored in a [`History`].
+pub struct StoredRun {
+    /// The id of the run within the store.
+    pub id: u64,
+    /// The run itself.
+    pub run: Run,
+}
+
+impl History {
+    /// Open the history store at the given path, creating it if it doesn't exist.
+    ///
+    /// # Errors
+    /// Returns an error if the directory cannot be created.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        std::fs::create_dir_all(&path)
+            .with_context(|| format!("Failed to create history at {}", path.display()))?;
+        let config_path = path.join(CONFIG_FILE_NAME);
+        Ok(Self { path, config_path })
+    }
+
+    /// Open the history of the given target of the store at the given path, or the history of
+    /// the store itself if `target` is empty, creating it if it doesn't exist.
+    ///
+    /// # Errors
+    /// Returns an error if the directory cannot be created.
+    pub fn open_target<P: AsRef<Path>>(path: P, target: &str) -> Result<Self> {
+        if target.is_empty() {
+            return Self::open(path);
+        }
+        let mut history = Self::open(path.as_ref().join(TARGETS_DIR_NAME).join(target))?;
+        if !history.config_path.exists() {
+            history.config_path = path.as_ref().join(CONFIG_FILE_NAME);
+        }
+        Ok(history)
+    }
+
+    /// Return the names of the targets which have a history in the store, sorted.
+    ///
+    /// # Errors
+    /// Returns an error if the directory of the targets cannot be read.
+    pub fn targets(&self) -> Result<Vec<String>> {
+        let path = self.path.join(TARGETS_DIR_NAME);
+        if !path.is_dir() {
+            return Ok(vec![]);
+        }
+        let mut targets = vec![];
+        for entry in std::fs::read_dir(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?
+        {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() {
+                if let Some(name) = entry.file_name().to_str() {
+                    targets.push(name.to_string());
+                }
+            }
+        }
+        targets.sort_unstable();
+        Ok(targets)
+    }
+
+    /// Return the number of runs in the store.
+    ///
+    /// # Errors
+    /// Returns an error if the directory of the store cannot be read.
+    pub fn n_runs(&self) -> Result<usize> {
+        Ok(self.run_ids()?.len())
+    }
+
+    /// Load all runs from the store, from the oldest to the most recent.
+    ///
+    /// # Errors
+    /// Returns an error if the store cannot be locked or a run cannot be read.
+    pub fn runs(&self) -> Result<Vec<StoredRun>> {
+        self.runs_in(&RunRange::default())
+    }
+
+    /// Load all runs from the store, with their metadata and total IR but none of their symbols,
+    /// from the oldest to the most recent.
+    ///
+    /// # Errors
+    /// Returns an error if the store cannot be locked or a run cannot be read.
+    pub fn headers(&self) -> Result<Vec<StoredRun>> {
+        let _lock = self.lock(false)?;
+        Ok(self.headers_unlocked()?.0)
+    }
+
+    /// Load all runs from the store without their symbols, along with the column of each
+    /// compacted run in the compacted file, by id. The caller must hold a lock on the store.
+    fn headers_unlocked(&self) -> Result<(Vec<StoredRun>, HashMap<u64, usize>)> {
+        let mut runs = self.load_compacted(&[])?;
+        let columns: HashMap<_, _> = runs
+            .iter()
+            .enumerate()
+            .map(|(column, stored)| (stored.id, column))
+            .collect();
+        for id in self.loose_ids()? {
+            // A run compacted by a process that crashed before removing its file.
+            if columns.contains_key(&id) {
+                continue;
+            }
+            let run = read_file(&self.run_path(id), |reader| {
+                read_header(&mut reader.lines())
+            })?;
+            runs.push(StoredRun { id, run });
+        }
+        Ok((runs, columns))
+    }
+
+    /// Load the symbols of `runs`, as returned by [`Self::headers_unlocked`] along with
+    /// `columns`. The caller must hold a lock on the store.
+    fn load_unlocked(
+        &self,
+        runs: Vec<StoredRun>,
+        columns: &HashMap<u64, usize>,
+    ) -> Result<Vec<StoredRun>> {
+        let wanted = runs
+            .iter()
+            .filter_map(|stored| columns.get(&stored.id).copied())
+            .collect_vec();
+        let mut compacted = if wanted.is_empty() {
+            vec![]
+        } else {
+            self.load_compacted(&wanted)?
+        };
+        runs.into_iter()
+            .map(|stored| {
+                let run = match columns.get(&stored.id) {
+                    Some(&column) => std::mem::take(&mut compacted[column].run),
+                    None => read_file(&self.run_path(stored.id), read_run)?,
+                };
+                Ok(StoredRun { id: stored.id, run })
+            })
+            .collect()
+    }
+
+    /// Load the compacted runs of the store, with the symbols of those at `columns` only.
+    fn load_compacted(&self, columns: &[usize]) -> Result<Vec<StoredRun>> {
+        let path = self.path.join(COMPACTED_FILE_NAME);
+        if !path.exists() {
+            return Ok(vec![]);
+        }
+        read_file(&path, |reader| read_compacted(reader, columns))
+    }
+
+    /// Replace the compacted runs of the store by `runs`. The caller must hold an exclusive lock
+    /// on the store.
+    fn store_compacted(&self, runs: &[StoredRun]) -> Result<()> {
+        let path = self.path.join(COMPACTED_FILE_NAME);
+        if runs.is_empty() {
+            if path.exists() {
+                std::fs::remove_file(&path)
+                    .with_context(|| format!("Failed to remove {}", path.display()))?;
+            }
+            return Ok(());
+        }
+        write_atomically(&path, |writer| write_compacted(runs, writer))
+    }
+
+    /// Load the runs of the given range from the store, from the oldest to the most recent.
+    ///
+    /// An end of the range is the most recent run with that name or, if there is none, the run
+    /// with that id.
+    ///
+    /// # Errors
+    /// Returns an error if the store cannot be locked, a run cannot be read or the range is
+    /// invalid.
+    pub fn runs_in(&self, range: &RunRange) -> Result<Vec<StoredRun>> {
+        let _lock = self.lock(false)?;
+        let (headers, columns) = self.headers_unlocked()?;
+        self.load_unlocked(select(headers, range)?, &columns)
+    }
+
+    /// Append a run to the store and return its id, or `None` if it was skipped as a duplicate.
+    ///
+    /// See [`Self::add_runs`] for how duplicates are handled.
+    ///
+    /// # Errors
+    /// Returns an error if the store cannot be locked, a run cannot be read or written, the run
+    /// is a refused duplicate or its name holds a line break, or the retention cannot be applied.
+    pub fn add_run(&self, run: &Run, on_duplicate: OnDuplicate) -> Result<Option<u64>> {
+        Ok(self.add_runs(std::slice::from_ref(run), on_duplicate)?[0])
+    }
+
+    /// Append runs to the store, in order, and return their ids, or `None` for those that were
+    /// skipped as duplicates.
+    ///
+    /// A run is a duplicate if a run of the store, or an earlier run of `runs`, has the same
+    /// checksum. With [`OnDuplicate::Refuse`], no run is appended if any is a duplicate.
+    ///
+    /// The symbols of the runs are renamed as per the aliases of the store (see [`Self::alias`]).
+    /// The store is locked once, so that the runs have consecutive ids even if other processes
+    /// append runs concurrently. The retention of the store is then applied (see
+    /// [`Self::retention`]).
+    ///
+    /// # Errors
+    /// Returns an error if the store cannot be locked, a run cannot be read or written, a run is
+    /// a refused duplicate or its name holds a line break, or the retention cannot be applied.
+    pub fn add_runs(&self, runs: &[Run], on_duplicate: OnDuplicate) -> Result<Vec<Option<u64>>> {
+        for run in runs {
+            check_name(&run.name)?;
+        }
+        let _lock = self.lock(true)?;
+        let retention = self.retention()?;
+        // The ids of the runs by checksum, those of the store and those of `runs` appended so far.
+        let mut checksums = HashMap::new();
+        if on_duplicate != OnDuplicate::Allow
+            && runs.iter().any(|run| run.metadata.checksum.is_some())
+        {
+            for stored in self.headers_unlocked()?.0 {
+                if let Some(checksum) = stored.run.metadata.checksum {
+                    checksums.insert(checksum, stored.id);
+                }
+            }
+        }
+        let first = self.next_id()?;
+        let mut next = first;
+        // The id of each run, or `None` for skipped duplicates.
+        let mut ids = Vec::with_capacity(runs.len());
+        for run in runs {
+            let checksum = run
+                .metadata
+                .checksum
+                .as_ref()
+                .filter(|_| on_duplicate != OnDuplicate::Allow);
+            match checksum.and_then(|checksum| checksums.get(checksum)) {
+                Some(&id) if on_duplicate == OnDuplicate::Refuse => {
+                    if id >= first {
+                        bail!(
+                            "The runs hold the same run twice, loaded from a file with the same \
+                             SHA-256. Use `--on-duplicate` to skip or allow it"
+                        );
+                    }
+                    bail!(
+                        "The history already holds this run, as run {id}, loaded from a file \
+                         with the same SHA-256. Use `--on-duplicate` to skip or allow it"
+                    );
+                }
+                Some(_) => ids.push(None),
+                None => {
+                    if let Some(checksum) = checksum {
+                        checksums.insert(checksum.clone(), next);
+                    }
+                    ids.push(Some(next));
+                    next += 1;
+                }
+            }
+        }
+        if next != first {
+            write_atomically(&self.path.join(NEXT_ID_FILE_NAME), |writer| {
+                Ok(writeln!(writer, "{next}")?)
+            })?;
+        }
+        let aliases = self.aliases()?;
+        for (run, id) in runs.iter().zip(&ids) {
+            let Some(id) = id else {
+                continue;
+            };
+            let mut renamed;
+            let run = if aliases.is_empty() {
+                run
+            } else {
+                renamed = run.clone();
+                renamed.rename_symbols(|name| alias::rename(&aliases, name));
+                &renamed
+            };
+            write_atomically(&self.run_path(*id), |writer| write_run(run, writer))?;
+        }
+        if retention != Retention::default() {
+            self.retain_unlocked(&retention)?;
+        }
+        Ok(ids)
+    }
+
+    /// Read the retention declared in the configuration file of the store.
+    ///
+    /// # Errors
+    /// Returns an error if the configuration file cannot be read or is not well-formed.
+    pub fn retention(&self) -> Result<Retention> {
+        Retention::from_file(&self.config_path)
+    }
+
+    /// Read the presets declared in the configuration file of the store.
+    ///
+    /// # Errors
+    /// Returns an error if the configuration file cannot be read or a preset is not well-formed.
+    pub fn presets(&self) -> Result<Vec<Preset>> {
+        Preset::from_file(&self.config_path)
+    }
+
+    /// Read the hooks declared in the configuration file of the store.
+    ///
+    /// # Errors
+    /// Returns an error if the configuration file cannot be read.
+    pub fn hooks(&self) -> Result<Hooks> {
+        Hooks::from_file(&self.config_path)
+    }
+
+    /// Read the symbol aliases recorded in the store, in the order in which they were applied.
+    ///
+    /// # Errors
+    /// Returns an error if the file of the aliases cannot be read or is not well-formed.
+    pub fn aliases(&self) -> Result<Vec<Alias>> {
+        let path = self.path.join(ALIASES_FILE_NAME);
+        if !path.exists() {
+            return Ok(vec![]);
+        }
+        Alias::from_file(path)
+    }
+
+    /// Record `aliases` in the store and rename the symbols of its runs accordingly, and return
+    /// the number of aliases recorded and that of runs with renamed symbols.
+    ///
+    /// Aliases which are recorded already are skipped, so that the runs are re-keyed once. A
+    /// symbol renamed to the name of another symbol of a run is merged into it.
+    ///
+    /// # Errors
+    /// Returns an error if the store cannot be locked, a run cannot be read or written, or an
+    /// alias renames a symbol which a recorded alias renames to another name.
+    pub fn alias(&self, aliases: &[Alias]) -> Result<(usize, usize)> {
+        let _lock = self.lock(true)?;
+        let mut recorded = self.aliases()?;
+        let mut added = vec![];
+        for alias in aliases {
+            match recorded.iter().find(|recorded| recorded.from == alias.from) {
+                Some(recorded) if recorded.to == alias.to => {}
+                Some(recorded) => bail!(
+                    "{} is renamed to {} in the history already",
+                    recorded.from,
+                    recorded.to
+                ),
+                None => added.push(alias.clone()),
+            }
+        }
+        if added.is_empty() {
+            return Ok((0, 0));
+        }
+        let (headers, columns) = self.headers_unlocked()?;
+        let mut runs = self.load_unlocked(headers, &columns)?;
+        let mut rekeyed = 0;
+        // Runs are re-keyed before the aliases are recorded, so that a crash in between leaves
+        // aliases to apply again rather than runs which were never re-keyed.
+        let (compacted, loose) = runs.split_at_mut(columns.len());
+        let mut compacted_rekeyed = false;
+        for stored in compacted.iter_mut() {
+            if stored
+                .run
+                .rename_symbols(|name| alias::rename(&added, name))
+            {
+                compacted_rekeyed = true;
+                rekeyed += 1;
+            }
+        }
+        if compacted_rekeyed {
+            self.store_compacted(compacted)?;
+        }
+        for stored in loose {
+            if stored
+                .run
+                .rename_symbols(|name| alias::rename(&added, name))
+            {
+                write_atomically(&self.run_path(stored.id), |writer| {
+                    write_run(&stored.run, writer)
+                })?;
+                rekeyed += 1;
+            }
+        }
+        let n_added = added.len();
+        recorded.extend(added);
+        write_atomically(&self.path.join(ALIASES_FILE_NAME), |writer| {
+            for alias in &recorded {
+                writeln!(writer, "{}{}{}", alias.from, alias::SEPARATOR, alias.to)?;
+            }
+            Ok(())
+        })?;
+        Ok((n_added, rekeyed))
+    }
+
+    /// Remove the runs of the given range from the store and return them.
+    ///
+    /// # Errors
+    /// Returns an error if the store cannot be locked, a run cannot be read or removed, the
+    /// range is invalid or holds a pinned run.
+    pub fn remove(&self, range: &RunRange) -> Result<Vec<StoredRun>> {
+        let _lock = self.lock(true)?;
+        let (headers, columns) = self.headers_unlocked()?;
+        let removed = self.load_unlocked(select(headers, range)?, &columns)?;
+        if let Some(pinned) = removed.iter().find(|stored| stored.run.metadata.pinned) {
+            bail!(
+                "Run {} ({}) is pinned: unpin it before removing it",
+                pinned.id,
+                pinned.run.name
+            );
+        }
+        self.remove_unlocked(&removed, &columns)?;
+        Ok(removed)
+    }
+
+    /// Remove `runs` from the store, given the columns of the compacted runs as returned by
+    /// [`Self::headers_unlocked`]. The caller must hold an exclusive lock on the store.
+    fn remove_unlocked(&self, runs: &[StoredRun], columns: &HashMap<u64, usize>) -> Result<()> {
+        let ids: HashSet<_> = runs.iter().map(|stored| stored.id).collect();
+        if ids.iter().any(|id| columns.contains_key(id)) {
+            let mut compacted = self.load_compacted(&(0..columns.len()).collect_vec())?;
+            compacted.retain(|stored| !ids.contains(&stored.id));
+            self.store_compacted(&compacted)?;
+        }
+        for id in ids.into_iter().filter(|id| !columns.contains_key(id)) {
+            let path = self.run_path(id);
+            std::fs::remove_file(&path)
+                .with_context(|| format!("Failed to remove {}", path.display()))?;
+        }
+        Ok(())
+    }
+
+    /// Rename a run of the store and return its id.
+    ///
+    /// The run is matched by name or id as an end of a [`RunRange`] is.
+    ///
+    /// # Errors
+    /// Returns an error if the store cannot be locked, a run cannot be read or written, no run
+    /// matches, the run is pinned or `name` holds a line break.
+    pub fn rename(&self, run: &str, name: &str) -> Result<u64> {
+        check_name(name)?;
+        self.update(run, |run| {
+            if run.metadata.pinned {
+                bail!("Run {} is pinned: unpin it before renaming it", run.name);
+            }
+            run.name = name.to_string();
+            Ok(())
+        })
+    }
+
+    /// Pin (or unpin) a run of the store as a baseline and return its id.
+    ///
+    /// Pinned runs are never pruned, removed or renamed. The run is matched by name or id as an
+    /// end of a [`RunRange`] is.
+    ///
+    /// # Errors
+    /// Returns an error if the store cannot be locked, a run cannot be read or written, or no
+    /// run matches.
+    pub fn pin(&self, run: &str, pinned: bool) -> Result<u64> {
+        self.update(run, |run| {
+            run.metadata.pinned = pinned;
+            Ok(())
+        })
+    }
+
+    /// Set the note of a run of the store, or remove it if `note` is empty, and return its id.
+    ///
+    /// The run is matched by name or id as an end of a [`RunRange`] is.
+    ///
+    /// # Errors
+    /// Returns an error if the store cannot be locked, a run cannot be read or written, or no
+    /// run matches.
+    pub fn annotate(&self, run: &str, note: &str) -> Result<u64> {
+        self.update(run, |run| {
+            run.metadata.note = (!note.is_empty()).then(|| note.to_string());
+            Ok(())
+        })
+    }
+
+    /// Update a run of the store, matched by name or id, and return its id.
+    ///
+    /// The run is left untouched if `update` fails.
+    fn update<F: FnOnce(&mut Run) -> Result<()>>(&self, run: &str, update: F) -> Result<u64> {
+        let _lock = self.lock(true)?;
+        let range = RunRange {
+            first: Some(run.to_string()),
+            last: Some(run.to_string()),
+        };
+        let (headers, columns) = self.headers_unlocked()?;
+        let id = select(headers, &range)?[0].id;
+        if let Some(&column) = columns.get(&id) {
+            let mut compacted = self.load_compacted(&(0..columns.len()).collect_vec())?;
+            update(&mut compacted[column].run)?;
+            self.store_compacted(&compacted)?;
+        } else {
+            let path = self.run_path(id);
+            let mut run = read_file(&path, read_run)?;
+            update(&mut run)?;
+            write_atomically(&path, |writer| write_run(&run, writer))?;
+        }
+        Ok(id)
+    }
+
+    /// Remove all runs but the `keep_last` most recent ones and return the removed runs, without
+    /// their symbols.
+    ///
+    /// Pinned runs are kept, as are runs with a git tag if `keep_tagged` is set, regardless of
+    /// their age.
+    ///
+    /// # Errors
+    /// Returns an error if the store cannot be locked, or a run cannot be read or removed.
+    pub fn prune(&self, keep_last: usize, keep_tagged: bool) -> Result<Vec<StoredRun>> {
+        let _lock = self.lock(true)?;
+        self.retain_unlocked(&prune_retention(keep_last, keep_tagged))
+    }
+
+    /// Return the runs that [`Self::prune`] would remove, without their symbols.
+    ///
+    /// # Errors
+    /// Returns an error if the store cannot be locked, or a run cannot be read.
+    pub fn prunable(&self, keep_last: usize, keep_tagged: bool) -> Result<Vec<StoredRun>> {
+        let _lock = self.lock(false)?;
+        let (runs, _) = self.headers_unlocked()?;
+        Ok(unretained(runs, &prune_retention(keep_last, keep_tagged)))
+    }
+
+    /// Remove the runs that `retention` does not keep and return them, without their symbols.
+    /// The caller must hold an exclusive lock on the store.
+    fn retain_unlocked(&self, retention: &Retention) -> Result<Vec<StoredRun>> {
+        let (runs, columns) = self.headers_unlocked()?;
+        let removed = unretained(runs, retention);
+        self.remove_unlocked(&removed, &columns)?;
+        Ok(removed)
+    }
+
+    /// Check the store for inconsistencies and return them, repairing those which can be if
+    /// `repair` is set (see [`Inconsistency::is_repairable`]).
+    ///
+    /// Every file of the store must be readable, a symbol must have as many counts as its run
+    /// has events and be listed once per run, the symbols of a run must not account for more IR
+    /// than its total. Runs loaded from the same file are reported as notices (see
+    /// [`Inconsistency::is_notice`]).
+    ///
+    /// # Errors
+    /// Returns an error if the store cannot be locked or read, or an inconsistency cannot be
+    /// repaired.
+    pub fn check(&self, repair: bool) -> Result<Vec<Inconsistency>> {
+        let _lock = self.lock(repair)?;
+        let mut found = vec![];
+        for entry in std::fs::read_dir(&self.path)? {
+            let path = entry?.path();
+            // See `write_atomically`.
+            let is_temporary = path.extension().is_some_and(|ext| ext == "tmp")
+                && path
+                    .file_name()
+                    .is_some_and(|name| name.as_encoded_bytes().starts_with(b"."));
+            if is_temporary {
+                found.push(Inconsistency::StaleTemporaryFile(path));
+            }
+        }
+        let mut runs = vec![];
+        let compacted_path = self.path.join(COMPACTED_FILE_NAME);
+        if compacted_path.exists() {
+            match read_file(&compacted_path, check_compacted) {
+                Ok((compacted, invalid)) => {
+                    runs = compacted;
+                    found.extend(invalid);
+                }
+                Err(error) => found.push(Inconsistency::UnreadableCompacted(format!("{error:#}"))),
+            }
+        }
+        let compacted: HashSet<_> = runs.iter().map(|stored| stored.id).collect();
+        for id in self.loose_ids()? {
+            let path = self.run_path(id);
+            if compacted.contains(&id) {
+                found.push(Inconsistency::CompactedRunFile(path));
+                continue;
+            }
+            match read_file(&path, |reader| check_run(id, reader)) {
+                Ok((stored, invalid)) => {
+                    runs.push(stored);
+                    found.extend(invalid);
+                }
+                Err(error) => found.push(Inconsistency::UnreadableRun {
+                    path,
+                    error: format!("{error:#}"),
+                }),
+            }
+        }
+        let mut checksums = HashMap::new();
+        for stored in &runs {
+            found.extend(
+                run_inconsistency(&stored.run).map(|reason| Inconsistency::InvalidRun {
+                    id: stored.id,
+                    reason,
+                }),
+            );
+            let Some(checksum) = &stored.run.metadata.checksum else {
+                continue;
+            };
+            if let Some(&duplicate_of) = checksums.get(checksum) {
+                found.push(Inconsistency::DuplicateRun {
+                    id: stored.id,
+                    duplicate_of,
+                });
+            } else {
+                checksums.insert(checksum, stored.id);
+            }
+        }
+        if repair {
+            for inconsistency in &found {
+                repair_inconsistency(inconsistency)?;
+            }
+        }
+        Ok(found)
+    }
+
+    /// Move all runs but the `keep_recent` most recent ones into the compacted file of the store
+    /// (see [`History`]) and return the number of runs that were moved.
+    ///
+    /// Runs that were already compacted stay so, and are still listed, renamed, pinned, removed
+    /// and pruned as other runs are.
+    ///
+    /// # Errors
+    /// Returns an error if the store cannot be locked, or a run cannot be read, written or
+    /// removed.
+    pub fn compact(&self, keep_recent: usize) -> Result<usize> {
+        let _lock = self.lock(true)?;
+        let (mut runs, columns) = self.headers_unlocked()?;
+        let n_compacted = runs.len().saturating_sub(keep_recent).max(columns.len());
+        if n_compacted == columns.len() {
+            return Ok(0);
+        }
+        runs.truncate(n_compacted);
+        let runs = self.load_unlocked(runs, &columns)?;
+        self.store_compacted(&runs)?;
+        for stored in &runs[columns.len()..] {
+            let path = self.run_path(stored.id);
+            std::fs::remove_file(&path)
+                .with_context(|| format!("Failed to remove {}", path.display()))?;
+        }
+        Ok(n_compacted - columns.len())
+    }
+
+    /// Load the most recent run of the store, if any.
+    ///
+    /// # Errors
+    /// Returns an error if the store cannot be locked or a run cannot be read.
+    pub fn latest(&self) -> Result<Option<StoredRun>> {
+        Ok(self.latest_runs(1)?.pop())
+    }
+
+    /// Load the `n` most recent runs of the store (or all of them, if there are fewer), from the
+    /// oldest to the most recent.
+    ///
+    /// # Errors
+    /// Returns an error if the store cannot be locked or a run cannot be read.
+    pub fn latest_runs(&self, n: usize) -> Result<Vec<StoredRun>> {
+        let _lock = self.lock(false)?;
+        let (mut headers, columns) = self.headers_unlocked()?;
+        headers.drain(..headers.len().saturating_sub(n));
+        self.load_unlocked(headers, &columns)
+    }
+
+    /// Find the most recent run that matches the given git ref.
+    ///
+    /// See [`RunMetadata::matches_ref`] for how a ref is matched.
+    ///
+    /// # Errors
+    /// Returns an error if the store cannot be locked or a run cannot be read.
+    pub fn find_latest_by_ref(&self, reference: &str) -> Result<Option<StoredRun>> {
+        let _lock = self.lock(false)?;
+        let (headers, columns) = self.headers_unlocked()?;
+        let Some(found) = headers
+            .into_iter()
+            .rev()
+            .find(|stored| stored.run.metadata.matches_ref(reference))
+        else {
+            return Ok(None);
+        };
+        Ok(self.load_unlocked(vec![found], &columns)?.pop())
+    }
+
+    /// Lock the store, blocking until the lock is acquired.
+    ///
+    /// The lock is exclusive if `exclusive` is set and shared otherwise. It is released when the
+    /// returned file is dropped.
+    fn lock(&self, exclusive: bool) -> Result<File> {
+        let path = self.path.join(LOCK_FILE_NAME);
+        let file = File::options()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&path)
+            .with_context(|| format!("Failed to open {}", path.display()))?;
+        if exclusive {
+            file.lock()
+        } else {
+            file.lock_shared()
+        }
+        .with_context(|| format!("Failed to lock {}", path.display()))?;
+        Ok(file)
+    }
+
+    /// Return the id of the next run appended to the store: the one of its `next_id` file, or
+    /// the one after the highest id of its runs if higher (e.g. in stores written before the
+    /// file was). The caller must hold a lock on the store.
+    fn next_id(&self) -> Result<u64> {
+        let path = self.path.join(NEXT_ID_FILE_NAME);
+        let recorded = match std::fs::read_to_string(&path) {
+            Ok(contents) => contents
+                .trim()
+                .parse::<u64>()
+                .with_context(|| format!("Invalid next id in {}", path.display()))?,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => 0,
+            Err(error) => {
+                return Err(error).with_context(|| format!("Failed to read {}", path.display()))
+            }
+        };
+        let after_last = self.run_ids()?.last().map_or(0, |id| id + 1);
+        Ok(recorded.max(after_last))
+    }
+
+    /// Return the ids of the runs in the store, sorted in ascending order.
+    fn run_ids(&self) -> Result<Vec<u64>> {
+        let mut ids = self
+            .load_compacted(&[])?
+            .into_iter()
+            .map(|stored| stored.id)
+            .collect_vec();
+        ids.extend(self.loose_ids()?);
+        ids.sort_unstable();
+        ids.dedup();
+        Ok(ids)
+    }
+
+    /// Return the ids of the runs stored in a file of their own, sorted in ascending order.
+    fn loose_ids(&self) -> Result<Vec<u64>> {
+        let mut ids = vec![];
+        for entry in std::fs::read_dir(&self.path)? {
+            let path = entry?.path();
+            if path
+                .extension()
+                .is_some_and(|ext| ext == RUN_FILE_EXTENSION)
+            {
+                if let Some(id) = path
+                    .file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .and_then(|stem| stem.parse::<u64>().ok())
+                {
+                    ids.push(id);
+                }
+            }
+        }
+        ids.sort_unstable();
+        Ok(ids)
+    }
+
+    /// Return the path of the file in which the run with the given id is stored.
+    fn run_path(&self, id: u64) -> PathBuf {
+        self.path.join(format!("{id:08}.{RUN_FILE_EXTENSION}"))
+    }
+}
+
+/// Run a `history` subcommand on the given history, writing its output into `out`.
+///
+/// `history show`, `history diff` and `history dashboard` are rendered as comparisons instead,
+/// and `history import`, `history export` and `history merge` depend on other arguments: these
+/// are run by [`crate::run`].
+///
+/// # Errors
+/// Returns an error if the subcommand fails, or is one of those run by [`crate::run`].
+pub fn run_command(history: &History, command: &HistoryCommand, out: &mut dyn Write) -> Result<()> {
+    match command {
+        HistoryCommand::List => print_list(&history.headers()?, out)?,
+        HistoryCommand::Show { .. } | HistoryCommand::Diff { .. } => {
+            bail!("`history show`, `diff` and `dashboard` are rendered as comparisons, by `run`")
+        }
+        #[cfg(feature = "dashboard")]
+        HistoryCommand::Dashboard { .. } => {
+            bail!("`history show`, `diff` and `dashboard` are rendered as comparisons, by `run`")
+        }
+        HistoryCommand::Import { .. }
+        | HistoryCommand::Export { .. }
+        | HistoryCommand::Merge { .. } => {
+            bail!("`history import`, `export` and `merge` depend on other arguments, see `run`")
+        }
+        HistoryCommand::Bisect {
+            symbol,
+            threshold,
+            runs,
+        } => print_bisect(&history.runs_in(runs)?, symbol, *threshold, out)?,
+        HistoryCommand::Remove { runs } => {
+            let removed = history.remove(runs)?;
+            writeln!(out, "Removed {} run(s)", removed.len())?;
+        }
+        HistoryCommand::Pin { run } => {
+            let id = history.pin(run, true)?;
+            writeln!(out, "Pinned run {id}")?;
+        }
+        HistoryCommand::Unpin { run } => {
+            let id = history.pin(run, false)?;
+            writeln!(out, "Unpinned run {id}")?;
+        }
+        HistoryCommand::Rename { run, name } => {
+            let id = history.rename(run, name)?;
+            writeln!(out, "Renamed run {id} to {name}")?;
+        }
+        HistoryCommand::Annotate { run, note } => {
+            let id = history.annotate(run, note)?;
+            writeln!(out, "Annotated run {id}")?;
+        }
+        HistoryCommand::Prune {
+            keep_last,
+            keep_tagged,
+            dry_run: false,
+        } => {
+            let removed = history.prune(*keep_last, *keep_tagged)?;
+            writeln!(out, "Removed {} run(s)", removed.len())?;
+        }
+        HistoryCommand::Prune {
+            keep_last,
+            keep_tagged,
+            dry_run: true,
+        } => {
+            let removed = history.prunable(*keep_last, *keep_tagged)?;
+            if !removed.is_empty() {
+                print_list(&removed, out)?;
+            }
+            writeln!(out, "Would remove {} run(s)", removed.len())?;
+        }
+        HistoryCommand::Alias { file: None } => {
+            for alias in history.aliases()? {
+                writeln!(out, "{}{}{}", alias.from, alias::SEPARATOR, alias.to)?;
+            }
+        }
+        HistoryCommand::Alias { file: Some(file) } => {
+            let (recorded, rekeyed) = history.alias(&Alias::from_file(file)?)?;
+            writeln!(
+                out,
+                "Recorded {recorded} alias(es), re-keyed {rekeyed} run(s)"
+            )?;
+        }
+        HistoryCommand::Fsck { repair } => print_check(&history.check(*repair)?, *repair, out)?,
+        HistoryCommand::Compact { keep_recent } => {
+            let compacted = history.compact(*keep_recent)?;
+            writeln!(out, "Compacted {compacted} run(s)")?;
+        }
+    }
+    Ok(())
+}
+
+/// Return the retention of `history prune`.
+fn prune_retention(keep_last: usize, keep_tagged: bool) -> Retention {
+    Retention {
+        keep_last: Some(keep_last),
+        keep_tagged,
+        downsample: None,
+    }
+}
+
+/// Return the runs of `runs` which `retention` does not keep.
+fn unretained(runs: Vec<StoredRun>, retention: &Retention) -> Vec<StoredRun> {
+    let retained = retention.retained(&runs);
+    runs.into_iter()
+        .zip(retained)
+        .filter_map(|(stored, retained)| (!retained).then_some(stored))
+        .collect()
+}
+
+/// Keep the runs of `runs` which are in the given range.
+///
+/// An end of the range is the most recent run with that name or, if there is none, the run with
+/// that id.
+///
+/// # Errors
+/// Returns an error if an end of the range matches no run or the first run of the range is more
+/// recent than the last.
+fn select(mut runs: Vec<StoredRun>, range: &RunRange) -> Result<Vec<StoredRun>> {
+    let position = |end: &str| {
+        runs.iter()
+            .rposition(|stored| stored.run.name == end)
+            .or_else(|| {
+                let id = end.parse::<u64>().ok()?;
+                runs.iter().position(|stored| stored.id == id)
+            })
+            .with_context(|| format!("No run in history is named or has id {end}"))
+    };
+    let first = range.first.as_deref().map(position).transpose()?;
+    let last = range.last.as_deref().map(position).transpose()?;
+    if first.zip(last).is_some_and(|(first, last)| first > last) {
+        bail!("Invalid range {range}: its first run is more recent than its last");
+    }
+    runs.truncate(last.map_or(runs.len(), |last| last + 1));
+    runs.drain(..first.unwrap_or(0));
+    Ok(runs)
+}
+
+/// Find the first run of `runs` whose IR count of `symbol` (or total IR count if `symbol` is
+/// empty) increased beyond `threshold` relative to the first run, and return its position.
+///
+/// Runs in which the symbol does not appear have an IR count of 0 for it.
+///
+/// # Errors
+/// Returns an error if `runs` is empty or the symbol does not appear in the first run.
+pub fn bisect(runs: &[StoredRun], symbol: &str, threshold: Percentage) -> Result<Option<usize>> {
+    let start = start_ir(runs, symbol)?;
+    Ok(runs.iter().position(|stored| {
+        let ir = symbol_ir(&stored.run, symbol).unwrap_or(0);
+        (ir as f64 - start as f64) * 100.0 / start as f64 > threshold.0
+    }))
+}
+
+/// Return the IR count of `symbol` (or the total IR count if `symbol` is empty) in the first run
+/// of `runs`, which [`bisect`] compares the other runs to.
+fn start_ir(runs: &[StoredRun], symbol: &str) -> Result<u64> {
+    let Some(first) = runs.first() else {
+        bail!("No run in history to bisect");
+    };
+    let Some(start) = symbol_ir(&first.run, symbol).filter(|ir| *ir > 0) else {
+        bail!("{symbol} has no IR count in run {}", first.id);
+    };
+    Ok(start)
+}
+
+/// Return the IR count of `symbol` in `run`, or its total IR count if `symbol` is empty.
+fn symbol_ir(run: &Run, symbol: &str) -> Option<u64> {
+    if symbol.is_empty() {
+        Some(run.total_ir)
+    } else {
+        run.symbols
+            .iter()
+            .find(|annotated| &*annotated.name == symbol)
+            .map(|annotated| annotated.ir)
+    }
+}
+
+/// Run `history bisect` on `runs` and write the first regressed run and the run before it into
+/// `out`.
+fn print_bisect(
+    runs: &[StoredRun],
+    symbol: &str,
+    threshold: Percentage,
+    out: &mut dyn Write,
+) -> Result<()> {
+    let what = if symbol.is_empty() {
+        "Total IR"
+    } else {
+        symbol
+    };
+    let Some(position) = bisect(runs, symbol, threshold)? else {
+        writeln!(
+            out,
+            "{what} did not regress beyond {threshold} over {} run(s)",
+            runs.len()
+        )?;
+        return Ok(());
+    };
+    let start = start_ir(runs, symbol)?;
+    writeln!(
+        out,
+        "{what} first regressed beyond {threshold} relative to run {}, in:",
+        runs[0].id
+    )?;
+    print_bisected_run(&runs[position], symbol, start, out)?;
+    writeln!(out, "The run before it was:")?;
+    print_bisected_run(&runs[position - 1], symbol, start, out)?;
+    Ok(())
+}
+
+/// Write the id, name, IR count and git metadata of a run into `out`, for `history bisect`.
+fn print_bisected_run(
+    stored: &StoredRun,
+    symbol: &str,
+    start: u64,
+    out: &mut dyn Write,
+) -> io::Result<()> {
+    let metadata = &stored.run.metadata;
+    let ir = symbol_ir(&stored.run, symbol).unwrap_or(0);
+    let change = (ir as f64 - start as f64) * 100.0 / start as f64;
+    writeln!(
+        out,
+        "  run {} ({}): {ir} IR ({change:+.3}%)",
+        stored.id, stored.run.name
+    )?;
+    let fields = [
+        ("commit", metadata.commit.clone()),
+        ("branch", metadata.branch.clone()),
+        ("tag", metadata.tag.clone()),
+        ("date", metadata.timestamp.map(format_timestamp)),
+    ];
+    for (key, value) in fields {
+        if let Some(value) = value {
+            writeln!(out, "  {key}: {value}")?;
+        }
+    }
+    Ok(())
+}
+
+/// Write the inconsistencies found by [`History::check`] into `out`, one per line, along with
+/// whether they were (or can be) repaired.
+///
+/// # Errors
+/// Returns an error if an inconsistency which is not a notice is left in the store.
+fn print_check(found: &[Inconsistency], repaired: bool, out: &mut dyn Write) -> Result<()> {
+    if found.is_empty() {
+        writeln!(out, "No inconsistency found")?;
+        return Ok(());
+    }
+    for inconsistency in found {
+        match (inconsistency.is_repairable(), repaired) {
+            (true, true) => writeln!(out, "{inconsistency} (repaired)")?,
+            (true, false) => writeln!(out, "{inconsistency} (repairable with `--repair`)")?,
+            (false, _) if inconsistency.is_notice() => writeln!(out, "Note: {inconsistency}")?,
+            (false, _) => writeln!(out, "{inconsistency}")?,
+        }
+    }
+    let left = found
+        .iter()
+        .filter(|inconsistency| {
+            !(inconsistency.is_notice() || repaired && inconsistency.is_repairable())
+        })
+        .count();
+    if left != 0 {
+        bail!("{left} inconsistency(ies) left in the history");
+    }
+    Ok(())
+}
+
+/// Write the id, name, date, commit, total IR and note of each run of `runs` into `out`, one per
+/// line.
+///
+/// Dates are in UTC, and commits are abbreviated. Pinned runs are marked after their name.
+/// Missing values are printed as `-`.
+fn print_list(runs: &[StoredRun], out: &mut dyn Write) -> io::Result<()> {
+    /// The number of characters commit hashes are abbreviated to.
+    const COMMIT_LENGTH: usize = 12;
+
+    let rows = runs
+        .iter()
+        .map(|stored| {
+            let metadata = &stored.run.metadata;
+            [
+                stored.id.to_string(),
+                if metadata.pinned {
+                    format!("{} (pinned)", stored.run.name)
+                } else {
+                    stored.run.name.clone()
+                },
+                metadata.timestamp.map(format_timestamp).unwrap_or_default(),
+                metadata
+                    .commit
+                    .as_deref()
+                    .map(|commit| commit.chars().take(COMMIT_LENGTH).collect())
+                    .unwrap_or_default(),
+                stored.run.total_ir.to_string(),
+                metadata.note.clone().unwrap_or_default(),
+            ]
+            .map(|value| {
+                if value.is_empty() {
+                    "-".to_string()
+                } else {
+                    value
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+    let header = ["id", "name", "date", "commit", "total IR", "note"];
+    let widths: [usize; 6] = std::array::from_fn(|i| {
+        rows.iter()
+            .map(|row| row[i].len())
+            .fold(header[i].len(), usize::max)
+    });
+    for row in std::iter::once(header.map(str::to_string)).chain(rows) {
+        // The note is last, so that it is not padded.
+        writeln!(
+            out,
+            "{:>id$}  {:<name$}  {:<date$}  {:<commit$}  {:>total$}  {}",
+            row[0],
+            row[1],
+            row[2],
+            row[3],
+            row[4],
+            row[5],
+            id = widths[0],
+            name = widths[1],
+            date = widths[2],
+            commit = widths[3],
+            total = widths[4],
+        )?;
+    }
+    Ok(())
+}
+
+/// Format a timestamp, in seconds since the Unix epoch, as a UTC date and time (e.g.
+/// `2024-03-22 09:33`).
+pub fn format_timestamp(timestamp: u64) -> String {
+    let days = timestamp / 86400;
+    let minutes = timestamp % 86400 / 60;
+    // Convert days to a civil date, shifting years to start in March so that leap days are last.
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}",
+        minutes / 60,
+        minutes % 60
+    )
+}
+
+/// Write a file through a temporary file which is renamed once fully written.
+///
+/// The temporary file is in the same directory as `path` (so the rename is atomic) and starts
+/// with a `.`, so that it is never mistaken for a run.
+fn write_atomically<F>(path: &Path, write: F) -> Result<()>
+where
+    F: FnOnce(&mut BufWriter<File>) -> Result<()>,
+{
+    let file_name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .context("Invalid file name")?;
+    let tmp_path = path.with_file_name(format!(".{file_name}.tmp"));
+    let file = File::create(&tmp_path)
+        .with_context(|| format!("Failed to create {}", tmp_path.display()))?;
+    let mut writer = BufWriter::new(file);
+    write(&mut writer)?;
+    writer.flush()?;
+    writer.get_ref().sync_all()?;
+    std::fs::rename(&tmp_path, path).with_context(|| {
+        format!(
+            "Failed to rename {} to {}",
+            tmp_path.display(),
+            path.display()
+        )
+    })?;
+    Ok(())
+}
+
+/// Serialize a run in the format described in [`History`].
+fn write_run<W: Write>(run: &Run, writer: &mut W) -> Result<()> {
+    write_header(run, writer)?;
+    writeln!(writer)?;
+    for symbol in &run.symbols {
+        write!(writer, "{}", symbol.ir)?;
+        for count in &symbol.events {
+            write!(writer, ",{count}")?;
+        }
+        writeln!(writer, " {}", symbol.name)?;
+    }
+    Ok(())
+}
+
+/// Serialize compacted runs in the format described in [`History`].
+fn write_compacted<W: Write>(runs: &[StoredRun], writer: &mut W) -> Result<()> {
+    writeln!(writer, "runs: {}", runs.len())?;
+    writeln!(writer)?;
+    for stored in runs {
+        writeln!(writer, "id: {}", stored.id)?;
+        write_header(&stored.run, writer)?;
+        writeln!(writer)?;
+    }
+    // The symbols of all runs, in the order in which they first appear, with their symbol in
+    // each run.
+    let mut series: Vec<(&str, Vec<_>)> = vec![];
+    let mut rows = HashMap::new();
+    for (column, stored) in runs.iter().enumerate() {
+        for symbol in &stored.run.symbols {
+            let row = *rows.entry(&*symbol.name).or_insert_with(|| {
+                series.push((&symbol.name, vec![None; runs.len()]));
+                series.len() - 1
+            });
+            series[row].1[column] = Some(symbol);
+        }
+    }
+    for (name, symbols) in series {
+        for (column, symbol) in symbols.into_iter().enumerate() {
+            if column > 0 {
+                write!(writer, ";")?;
+            }
+            let Some(symbol) = symbol else {
+                write!(writer, "-")?;
+                continue;
+            };
+            write!(writer, "{}", symbol.ir)?;
+            for count in &symbol.events {
+                write!(writer, ",{count}")?;
+            }
+        }
+        writeln!(writer, " {name}")?;
+    }
+    Ok(())
+}
+
+/// Check that `name` can be the name of a run of the store: as the header of a run is made of
+/// lines, it cannot hold a line break.
+fn check_name(name: &str) -> Result<()> {
+    if name.contains(['\n', '\r']) {
+        bail!("Invalid run name {name:?}: names cannot hold line breaks");
+    }
+    Ok(())
+}
+
+/// Serialize the header of a run in the format described in [`History`], without the empty
+/// line that ends it.
+fn write_header<W: Write>(run: &Run, writer: &mut W) -> Result<()> {
+    writeln!(writer, "name: {}", run.name)?;
+    if let Some(commit) = &run.metadata.commit {
+        writeln!(writer, "commit: {commit}")?;
+    }
+    if let Some(branch) = &run.metadata.branch {
+        writeln!(writer, "branch: {branch}")?;
+    }
+    if let Some(tag) = &run.metadata.tag {
+        writeln!(writer, "tag: {tag}")?;
+    }
+    if let Some(timestamp) = run.metadata.timestamp {
+        writeln!(writer, "timestamp: {timestamp}")?;
+    }
+    if let Some(benchmark) = &run.metadata.benchmark {
+        writeln!(writer, "benchmark: {benchmark}")?;
+    }
+    if let Some(version) = &run.metadata.version {
+        writeln!(writer, "version: {version}")?;
+    }
+    if let Some(iterations) = run.metadata.iterations {
+        writeln!(writer, "iterations: {iterations}")?;
+    }
+    if let Some(command) = &run.metadata.command {
+        writeln!(writer, "command: {command}")?;
+    }
+    if let Some(note) = &run.metadata.note {
+        writeln!(writer, "note: {}", note.replace('\n', " "))?;
+    }
+    if let Some(checksum) = &run.metadata.checksum {
+        writeln!(writer, "checksum: {checksum}")?;
+    }
+    if run.metadata.pinned {
+        writeln!(writer, "pinned: true")?;
+    }
+    let environment = [
+        ("rustc", &run.metadata.rustc),
+        ("target_triple", &run.metadata.target_triple),
+        ("cpu", &run.metadata.cpu),
+        ("valgrind", &run.metadata.valgrind),
+        ("simulation", &run.metadata.simulation),
+        ("unit", &run.metadata.unit),
+        ("origin", &run.metadata.origin),
+    ];
+    for (key, value) in environment {
+        if let Some(value) = value {
+            writeln!(writer, "{key}: {value}")?;
+        }
+    }
+    writeln!(writer, "total_ir: {}", run.total_ir)?;
+    if !run.events.is_empty() {
+        writeln!(writer, "events: {}", run.events.join(" "))?;
+        writeln!(
+            writer,
+            "event_totals: {}",
+            run.event_totals.iter().join(",")
+        )?;
+    }
+    Ok(())
+}
+
+/// Open the file at `path` and deserialize it with `read`.
+fn read_file<T, F>(path: &Path, read: F) -> Result<T>
+where
+    F: FnOnce(BufReader<File>) -> Result<T>,
+{
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    read(BufReader::new(file)).with_context(|| format!("Failed to read {}", path.display()))
+}
+
+/// Deserialize a run from the format described in [`History`].
+fn read_run<R: BufRead>(reader: R) -> Result<Run> {
+    let mut lines = reader.lines();
+    let mut run = read_header(&mut lines)?;
+    for line in lines {
+        let line = line?;
+        let Some((counts, symbol)) = line.split_once(' ') else {
+            bail!("Invalid symbol line: {line}");
+        };
+        add_counts(&mut run, symbol, counts)?;
+    }
+    Ok(run)
+}
+
+/// Deserialize compacted runs from the format described in [`History`], with the symbols of the
+/// runs at `columns` only.
+fn read_compacted<R: BufRead>(reader: R, columns: &[usize]) -> Result<Vec<StoredRun>> {
+    let mut lines = reader.lines();
+    let mut runs = read_compacted_headers(&mut lines)?;
+    if columns.is_empty() {
+        return Ok(runs);
+    }
+    let n_runs = runs.len();
+    for line in lines {
+        let line = line?;
+        let Some((cells, symbol)) = line.split_once(' ') else {
+            bail!("Invalid symbol line: {line}");
+        };
+        let cells = cells.split(';').collect_vec();
+        if cells.len() != n_runs {
+            bail!(
+                "Invalid symbol line: expected {n_runs} counts, got {}: {line}",
+                cells.len()
+            );
+        }
+        for &column in columns {
+            if cells[column] != "-" {
+                add_counts(&mut runs[column].run, symbol, cells[column])?;
+            }
+        }
+    }
+    Ok(runs)
+}
+
+/// Deserialize the headers of the compacted runs of a compacted file, up to its symbols.
+fn read_compacted_headers<I>(lines: &mut I) -> Result<Vec<StoredRun>>
+where
+    I: Iterator<Item = io::Result<String>>,
+{
+    let line = lines.next().transpose()?.unwrap_or_default();
+    let Some(n_runs) = line.strip_prefix("runs: ") else {
+        bail!("Invalid compacted file: expected `runs: <count>`, got {line:?}");
+    };
+    let n_runs = n_runs.parse()?;
+    lines.next().transpose()?;
+    let mut runs = Vec::with_capacity(n_runs);
+    for _ in 0..n_runs {
+        let line = lines.next().transpose()?.unwrap_or_default();
+        let Some(id) = line.strip_prefix("id: ") else {
+            bail!("Invalid compacted run: expected `id: <id>`, got {line:?}");
+        };
+        let id = id.parse()?;
+        runs.push(StoredRun {
+            id,
+            run: read_header(lines)?,
+        });
+    }
+    Ok(runs)
+}
+
+/// Deserialize a run file, and return the run along with the inconsistencies of its symbols
+/// (see [`symbols_inconsistency`]).
+fn check_run<R: BufRead>(id: u64, reader: R) -> Result<(StoredRun, Option<Inconsistency>)> {
+    let contents = reader.lines().collect::<io::Result<Vec<_>>>()?;
+    let mut lines = contents.iter().cloned().map(Ok);
+    let mut run = read_header(&mut lines)?;
+    let invalid = symbols_inconsistency(lines.clone(), &[run.events.len()])?
+        .map(|reason| Inconsistency::InvalidRun { id, reason });
+    for line in lines {
+        let line: String = line?;
+        let Some((counts, symbol)) = line.split_once(' ') else {
+            bail!("Invalid symbol line: {line}");
+        };
+        add_counts(&mut run, symbol, counts)?;
+    }
+    Ok((StoredRun { id, run }, invalid))
+}
+
+/// Deserialize a compacted file, and return its runs along with the inconsistencies of their
+/// symbols (see [`symbols_inconsistency`]).
+fn check_compacted<R: BufRead>(reader: R) -> Result<(Vec<StoredRun>, Option<Inconsistency>)> {
+    let contents = reader.lines().collect::<io::Result<Vec<_>>>()?;
+    let mut lines = contents.iter().cloned().map(Ok);
+    let headers = read_compacted_headers(&mut lines)?;
+    let n_events = headers
+        .iter()
+        .map(|stored| stored.run.events.len())
+        .collect_vec();
+    let invalid = symbols_inconsistency(lines, &n_events)?.map(|reason| {
+        Inconsistency::UnreadableCompacted(format!("inconsistent symbols: {reason}"))
+    });
+    let columns = (0..headers.len()).collect_vec();
+    let runs = read_compacted(contents.join("\n").as_bytes(), &columns)?;
+    Ok((runs, invalid))
+}
+
+/// Return the first inconsistency of the symbol lines of a run or compacted file, after its
+/// headers, if any: a symbol listed twice, or whose counts in a run are not one per event of the
+/// run (and its IR count). `n_events` is the number of events of each run of the file.
+fn symbols_inconsistency<I>(lines: I, n_events: &[usize]) -> Result<Option<String>>
+where
+    I: Iterator<Item = io::Result<String>>,
+{
+    let mut seen = HashSet::new();
+    for line in lines {
+        let line = line?;
+        let Some((cells, symbol)) = line.split_once(' ') else {
+            bail!("Invalid symbol line: {line}");
+        };
+        if !seen.insert(symbol.to_string()) {
+            return Ok(Some(format!("symbol {symbol} is listed twice")));
+        }
+        for (cell, n_events) in cells.split(';').zip(n_events) {
+            let n_counts = cell.split(',').count();
+            if cell != "-" && n_counts != n_events + 1 {
+                return Ok(Some(format!(
+                    "symbol {symbol} has {n_counts} counts instead of {}",
+                    n_events + 1
+                )));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Return what contradicts the header of `run` in its symbols, if anything.
+fn run_inconsistency(run: &Run) -> Option<String> {
+    if run.event_totals.len() != run.events.len() {
+        return Some(format!(
+            "{} event totals for {} events",
+            run.event_totals.len(),
+            run.events.len()
+        ));
+    }
+    let accounted = run
+        .symbols
+        .iter()
+        .map(|symbol| u128::from(symbol.ir))
+        .sum::<u128>();
+    (accounted > u128::from(run.total_ir)).then(|| {
+        format!(
+            "its symbols account for {accounted} IR, more than its total of {}",
+            run.total_ir
+        )
+    })
+}
+
+/// Repair an inconsistency found by [`History::check`], if it can be (see
+/// [`Inconsistency::is_repairable`]).
+fn repair_inconsistency(inconsistency: &Inconsistency) -> Result<()> {
+    match inconsistency {
+        Inconsistency::StaleTemporaryFile(path) | Inconsistency::CompactedRunFile(path) => {
+            std::fs::remove_file(path)
+                .with_context(|| format!("Failed to remove {}", path.display()))
+        }
+        Inconsistency::UnreadableRun { path, .. } => {
+            let mut corrupt = path.clone().into_os_string();
+            corrupt.push(".corrupt");
+            std::fs::rename(path, &corrupt)
+                .with_context(|| format!("Failed to rename {}", path.display()))
+        }
+        Inconsistency::UnreadableCompacted(_)
+        | Inconsistency::InvalidRun { .. }
+        | Inconsistency::DuplicateRun { .. } => Ok(()),
+    }
+}
+
+/// Add the counts of a symbol to `run`: its IR count followed by the counts of the other events
+/// (if any), separated by commas.
+fn add_counts(run: &mut Run, symbol: &str, counts: &str) -> Result<()> {
+    let (ir, counts) = counts.split_once(',').unwrap_or((counts, ""));
+    run.add_counts(symbol, ir.parse()?, &parse_counts(counts)?);
+    Ok(())
+}
+
+/// Deserialize the header of a run from the format described in [`History`], up to and
+/// including the empty line that ends it.
+fn read_header<I: Iterator<Item = io::Result<String>>>(lines: &mut I) -> Result<Run> {
+    let mut run = Run::new();
+    for line in lines {
+        let line = line?;
+        if line.is_empty() {
+            break;
+        }
+        let Some((key, value)) = line.split_once(": ").or_else(|| line.split_once(':')) else {
+            bail!("Invalid header line: {line}");
+        };
+        match key {
+            "name" => run.name = value.to_string(),
+            "commit" => run.metadata.commit = Some(value.to_string()),
+            "branch" => run.metadata.branch = Some(value.to_string()),
+            "tag" => run.metadata.tag = Some(value.to_string()),
+            "timestamp" => run.metadata.timestamp = Some(value.parse()?),
+            "benchmark" => run.metadata.benchmark = Some(value.to_string()),
+            "version" => run.metadata.version = Some(value.to_string()),
+            "iterations" => run.metadata.iterations = Some(value.parse()?),
+            "command" => run.metadata.command = Some(value.to_string()),
+            "note" => run.metadata.note = Some(value.to_string()),
+            "checksum" => run.metadata.checksum = Some(value.to_string()),
+            "pinned" => run.metadata.pinned = value.parse()?,
+            "rustc" => run.metadata.rustc = Some(value.to_string()),
+            "target_triple" => run.metadata.target_triple = Some(value.to_string()),
+            "cpu" => run.metadata.cpu = Some(value.to_string()),
+            "valgrind" => run.metadata.valgrind = Some(value.to_string()),
+            "simulation" => run.metadata.simulation = Some(value.to_string()),
+            "unit" => run.metadata.unit = Some(value.to_string()),
+            "origin" => run.metadata.origin = Some(value.to_string()),
+            "total_ir" => run.total_ir = value.parse()?,
+            "events" => run.events = value.split_whitespace().map(str::to_string).collect(),
+            "event_totals" => run.event_totals = parse_counts(value)?,
+            // Ignore unknown keys so that older versions can read newer stores.
+            _ => {}
+        }
+    }
+    Ok(run)
+}
+
+/// Parse a comma-separated list of counts.
+fn parse_counts(s: &str) -> Result<Vec<u64>> {
+    if s.is_empty() {
+        return Ok(vec![]);
+    }
+    Ok(s.split(',').map(str::parse).collect::<Result<_, _>>()?)
+}
+
+/// Build the metadata of a run from the state of the git repository in the current directory.
+///
+/// The commit hash is that of `HEAD`, the branch is the one currently checked out, if any, and
+/// the tag is the one pointing to `HEAD`, if any.
+///
+/// # Errors
+/// Returns an error if `git` cannot be run or the current directory is not in a repository.
+pub fn metadata_from_git() -> Result<RunMetadata> {
+    let commit = git(&["rev-parse", "HEAD"])?;
+    let branch = git(&["rev-parse", "--abbrev-ref", "HEAD"])?;
+    // This fails if no tag points to `HEAD`.
+    let tag = git(&["describe", "--tags", "--exact-match", "HEAD"]).ok();
+    Ok(RunMetadata {
+        commit: Some(commit),
+        // A detached `HEAD` has no branch.
+        branch: (branch != "HEAD").then_some(branch),
+        tag,
+        timestamp: Some(now()),
+        ..RunMetadata::default()
+    })
+}
+
+/// Fill in the fingerprint of the environment in the metadata of a run appended to the history:
+/// the version and target triple of `rustc` (or of `$RUSTC`), the model of the CPU and the
+/// version of valgrind.
+///
+/// Values which are already set are kept. Those which cannot be found (e.g. valgrind is not
+/// installed) are left unset. The environment is only inspected once per process.
+pub fn stamp_environment(metadata: &mut RunMetadata) {
+    static ENVIRONMENT: OnceLock<RunMetadata> = OnceLock::new();
+    let environment = ENVIRONMENT.get_or_init(|| {
+        let rustc = std::env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+        let rustc_verbose = command_output(&rustc, &["-vV"]);
+        let rustc_field = |key: &str| {
+            rustc_verbose
+                .as_deref()?
+                .lines()
+                .find_map(|line| line.strip_prefix(key))
+                .map(|value| value.trim().to_string())
+        };
+        RunMetadata {
+            rustc: rustc_verbose
+                .as_deref()
+                .and_then(|output| output.lines().next())
+                .map(str::to_string),
+            target_triple: rustc_field("host:"),
+            cpu: std::fs::read_to_string("/proc/cpuinfo")
+                .ok()
+                .and_then(|cpuinfo| {
+                    cpuinfo.lines().find_map(|line| {
+                        let (key, value) = line.split_once(':')?;
+                        (key.trim() == "model name").then(|| value.trim().to_string())
+                    })
+                }),
+            valgrind: command_output(&crate::profile::valgrind(), &["--version"]),
+            ..RunMetadata::default()
+        }
+    });
+    let fields = [
+        (&mut metadata.rustc, &environment.rustc),
+        (&mut metadata.target_triple, &environment.target_triple),
+        (&mut metadata.cpu, &environment.cpu),
+        (&mut metadata.valgrind, &environment.valgrind),
+    ];
+    for (field, value) in fields {
+        if field.is_none() {
+            field.clone_from(value);
+        }
+    }
+}
+
+/// Run `program` with the given arguments and return its trimmed standard output, or `None` if
+/// it cannot be run or fails.
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    let stdout = String::from_utf8(output.stdout).ok()?;
+    output.status.success().then(|| stdout.trim().to_string())
+}
+
+/// Run `git` with the given arguments and return its trimmed standard output.
+fn git(args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .args(args)
+        .output()
+        .context("Failed to run git")?;
+    if !output.status.success() {
+        bail!(
+            "`git {}` failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8(output.stdout)?.trim().to_string())
+}
+
+/// Return the current time, in seconds since the Unix epoch.
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
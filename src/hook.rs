@@ -0,0 +1,85 @@
+use std::{
+    io::Write,
+    path::Path,
+    process::{Command, Stdio},
+};
+
+use anyhow::{bail, Context, Result};
+
+/// The commands a history store runs after some of its operations, as declared in its
+/// configuration file.
+///
+/// Each hook is a `key: command` line of the file, and is run with `sh -c`:
+/// ```no_compile
+/// # Post the summary of each new run to a Slack webhook.
+/// on_add: curl --silent --data-binary @- https://hooks.slack.com/services/T000/B000/XXXX
+/// ```
+/// Other keys are those of the [`crate::retention::Retention`] and [`crate::preset::Preset`]s of
+/// the store.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Hooks {
+    /// The command run after `add` appended a run, with the JSON summary of the comparison of
+    /// that run to the one before it on its standard input (see `pr-compare`).
+    pub on_add: Option<String>,
+}
+
+impl Hooks {
+    /// Read the hooks from a configuration file. A missing file declares none.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be read.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        Ok(Self::parse(&contents))
+    }
+
+    /// Parse the hooks from the contents of a configuration file.
+    pub fn parse(contents: &str) -> Self {
+        let mut hooks = Self::default();
+        for line in contents.lines().map(str::trim) {
+            if line.starts_with('#') {
+                continue;
+            }
+            if let Some(("on_add", command)) = line
+                .split_once(':')
+                .map(|(key, command)| (key.trim(), command.trim()))
+            {
+                hooks.on_add = (!command.is_empty()).then(|| command.to_string());
+            }
+        }
+        hooks
+    }
+}
+
+/// Run `command` with `sh -c`, writing `input` to its standard input.
+///
+/// # Errors
+/// Returns an error if the command cannot be run or exits with a failure.
+pub fn run(command: &str, input: &str) -> Result<()> {
+    let mut child = Command::new("sh")
+        .args(["-c", command])
+        .stdin(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to run {command:?}"))?;
+    let written = child
+        .stdin
+        .take()
+        .context("Failed to open the standard input of the hook")?
+        .write_all(input.as_bytes());
+    let status = child.wait()?;
+    if !status.success() {
+        bail!("{command:?} failed ({status})");
+    }
+    // A hook may not read its input: that it closed it early is not an error of its own.
+    if let Err(error) = written {
+        if error.kind() != std::io::ErrorKind::BrokenPipe {
+            return Err(error).context("Failed to write to the standard input of the hook");
+        }
+    }
+    Ok(())
+}
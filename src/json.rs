@@ -0,0 +1,94 @@
+/// Return the text of a JSON document following the first `key`, from the start of its value.
+///
+/// This is not a JSON parser: a key is the first occurrence of its quoted name, which is all the
+/// files of other tools read by this crate (e.g. Criterion.rs, `cargo bloat`) need. The other
+/// functions of this module look keys up the same way.
+pub fn value<'a>(json: &'a str, key: &str) -> Option<&'a str> {
+    let quoted = format!("\"{key}\"");
+    let after = &json[json.find(&quoted)? + quoted.len()..];
+    Some(after.trim_start().strip_prefix(':')?.trim_start())
+}
+
+/// Return the number `key` of a JSON document.
+///
+/// ```
+/// # use callgrind_differ::json;
+/// let json = r#"{"mean": {"point_estimate": 1.5e3}, "size": 12}"#;
+/// assert_eq!(json::number(json::value(json, "mean").unwrap(), "point_estimate"), Some(1500.0));
+/// assert_eq!(json::number(json, "size"), Some(12.0));
+/// ```
+pub fn number(json: &str, key: &str) -> Option<f64> {
+    let value = value(json, key)?;
+    let end = value
+        .find(|c: char| !(c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E')))
+        .unwrap_or(value.len());
+    value[..end].parse().ok()
+}
+
+/// Return the string `key` of a JSON document, unescaped.
+pub fn string(json: &str, key: &str) -> Option<String> {
+    let mut chars = value(json, key)?.strip_prefix('"')?.chars();
+    let mut string = String::new();
+    loop {
+        match chars.next()? {
+            '"' => return Some(string),
+            '\\' => match chars.next()? {
+                'n' => string.push('\n'),
+                't' => string.push('\t'),
+                'u' => {
+                    let code = chars.by_ref().take(4).collect::<String>();
+                    string.push(char::from_u32(u32::from_str_radix(&code, 16).ok()?)?);
+                }
+                c => string.push(c),
+            },
+            c => string.push(c),
+        }
+    }
+}
+
+/// Return the text of each object of the array `key` of a JSON document, or `None` if the
+/// array is missing or not terminated.
+///
+/// ```
+/// # use callgrind_differ::json;
+/// let json = r#"{"functions": [{"name": "a{b}", "size": 1}, {"name": "c", "size": 2}]}"#;
+/// let objects = json::objects(json, "functions").unwrap();
+/// assert_eq!(objects, [r#"{"name": "a{b}", "size": 1}"#, r#"{"name": "c", "size": 2}"#]);
+/// ```
+pub fn objects<'a>(json: &'a str, key: &str) -> Option<Vec<&'a str>> {
+    let array = value(json, key)?.strip_prefix('[')?;
+    let mut objects = vec![];
+    let mut depth = 0;
+    let mut start = 0;
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, c) in array.char_indices() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '{' | '[' => {
+                if depth == 0 {
+                    start = i;
+                }
+                depth += 1;
+            }
+            ']' if depth == 0 => return Some(objects),
+            '}' | ']' => {
+                depth -= 1;
+                if depth == 0 && c == '}' {
+                    objects.push(&array[start..=i]);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
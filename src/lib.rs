@@ -0,0 +1,1771 @@
+//! Compare the output of multiple `callgrind_annotate` runs.
+//!
+//! The `callgrind_differ` binary is a thin wrapper around [`run`], and benchmark harnesses record
+//! their runs in the history with [`record_run`]. Other tools may use the building blocks
+//! directly: [`runs::Run`] and [`runs::Records`] hold the parsed annotations, [`callgrind`],
+//! [`callgrind_out`], [`csv`] and [`history`] load them (failing with an [`error::Error`]),
+//! [`format`](mod@format) finds the format of each input, [`builder`] builds them from other
+//! sources, [`diff`] computes the differences (and [`metric`] rates derived from the counts of
+//! events), [`regression`] and [`summary`] evaluate them (against the [`noise`] floor, if
+//! calibrated), and [`render`], [`comment`], [`report`] and [`github`] export them, as do `push`,
+//! `gitlab` and `dashboard` with their features.
+//! [`changepoint`] finds when counts jumped across a history of runs, [`contribution`] which
+//! symbols explain the change of the total or moved the most between runs, [`histogram`] how broad
+//! the change is, [`budget`] when the total IR will exceed its budget, [`correlation`] which
+//! symbols move together, [`lifetime`] when symbols appeared and vanished, [`retention`] which runs
+//! a history keeps and [`suite`] summarizes benchmark suites. [`profile`] runs programs under
+//! callgrind, and [`shared`] shares records between threads, so that inputs can be reloaded in the
+//! background.
+//!
+//! With the `serde` feature, the runs, the records and the [`diff::DiffTable`] implement serde's
+//! `Serialize` (and `Deserialize`, except for the table, which borrows the records). The
+//! `dashboard`, `gitlab`, `pprof` and `push` features, disabled by default as well, hold the
+//! `history dashboard` subcommand, `--export-gitlab-codequality`, the `pprof` input format and
+//! `--push-to` and `--push-prometheus`: without them, their code is not built.
+
+#![warn(clippy::pedantic)]
+#![allow(
+    clippy::cast_precision_loss,
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss,
+    clippy::cast_lossless,
+    clippy::must_use_candidate
+)]
+
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+    time::{Instant, UNIX_EPOCH},
+};
+
+use anyhow::{bail, Context, Result};
+use clap::Parser;
+use itertools::Itertools;
+
+use crate::{
+    args::{
+        Args, CallgrindOptions, Command, EventThreshold, ExportFormat, HistoryCommand, IrCount,
+        NameSource, OnDuplicate, Per, Percentage, RelativeTo, RunMeta, RunRange, SortByField,
+    },
+    budget::{BurnDown, Projection},
+    changepoint::find_changepoints,
+    contribution::{top_movers, TotalChange},
+    correlation::correlate,
+    diff::{DiffCell, DiffTable},
+    format::{InputFormat, InputFormats},
+    histogram::{Histogram, BUCKET_LABELS},
+    history::History,
+    lifetime::lifetimes,
+    noise::NoiseFloor,
+    policy::Policy,
+    regression::{find_missing_symbols, find_regressions, Regression, Severity},
+    render::OutputRenderers,
+    runs::{crate_of, Records, Run, RunMetadata, CONTROL_RUN, TREATMENT_RUN},
+    status::{Failure, Status, WithStatus},
+    suite::{Manifest, SuiteSummary},
+    summary::Summary,
+};
+
+pub mod alias;
+pub mod args;
+pub mod budget;
+pub mod builder;
+pub mod callgrind;
+pub mod callgrind_out;
+pub mod cg_diff;
+pub mod changepoint;
+pub mod checksum;
+pub mod code_size;
+pub mod comment;
+pub mod contribution;
+pub mod correlation;
+pub mod criterion;
+pub mod csv;
+#[cfg(feature = "dashboard")]
+pub mod dashboard;
+pub mod diff;
+pub mod diff_profile;
+pub mod display;
+pub mod error;
+pub mod format;
+pub mod github;
+#[cfg(feature = "gitlab")]
+pub mod gitlab;
+pub mod graph;
+#[cfg(feature = "pprof")]
+pub mod gzip;
+pub mod histogram;
+pub mod history;
+pub mod hook;
+pub mod json;
+pub mod lifetime;
+pub mod massif;
+pub mod metric;
+pub mod noise;
+pub mod policy;
+#[cfg(feature = "pprof")]
+pub mod pprof;
+pub mod preset;
+pub mod profile;
+#[cfg(feature = "push")]
+pub mod push;
+pub mod regression;
+pub mod reload;
+pub mod render;
+pub mod report;
+pub mod retention;
+pub mod runs;
+pub mod shared;
+pub mod stats;
+pub mod status;
+pub mod suite;
+pub mod summary;
+
+/// Parse inputs from the configuration into a [`Records`].
+///
+/// The format of each file is found with [`InputFormats`]: CSVs are loaded as multiple runs,
+/// other files as a single `callgrind_annotate` output file. Runs are loaded in order.
+///
+/// If `--compare-ref` is given, the matching run from the history is loaded first. Runs loaded
+/// from `callgrind_annotate` files are merged as per `--merge`, then stamped and appended to the
+/// history, if any and unless `--read-only` is given.
+///
+/// With `--suite`, the inputs of the `suite` manifest are loaded instead, merging the files of
+/// each benchmark and version into a run.
+///
+/// Outliers, duplicates and `--stats` are reported into `err`.
+fn parse_records(config: &Args, suite: Option<&Manifest>, err: &mut dyn Write) -> Result<Records> {
+    let mut records = Records::new();
+    let history = if config.history.is_empty() {
+        None
+    } else {
+        Some(open_history(config)?)
+    };
+    let git_metadata = if config.tag_from_git {
+        Some(history::metadata_from_git()?)
+    } else {
+        None
+    };
+    // The history that loaded runs are appended to.
+    let appended_to = history.as_ref().filter(|_| !config.read_only);
+
+    if let Some(history) = &history {
+        if !config.compare_ref.is_empty() {
+            let Some(stored) = history.find_latest_by_ref(&config.compare_ref)? else {
+                bail!("No run in history matches ref {}", config.compare_ref);
+            };
+            let mut run = stored.run;
+            if run.name.is_empty() {
+                run.name.clone_from(&config.compare_ref);
+            }
+            records.add_run(run);
+        }
+    }
+
+    if let Some(suite) = suite {
+        for (i, run) in load_suite(config, suite, err)?.into_iter().enumerate() {
+            add_run(
+                &mut records,
+                run,
+                config.run_meta.get(i),
+                git_metadata.as_ref(),
+                appended_to,
+                config.on_duplicate,
+                err,
+            )?;
+        }
+        return Ok(records);
+    }
+
+    // Samples waiting to be merged into a single run.
+    let formats = InputFormats::default();
+    let mut samples = vec![];
+    let mut sample_inputs = vec![];
+    // The number of runs loaded from the inputs, which `--run-meta` applies to in order.
+    let mut n_loaded = 0;
+    for input in &config.inputs {
+        let Some(format) = formats.find(Path::new(input)) else {
+            return Err(error::Error::UnknownFormat {
+                path: Some(input.into()),
+            }
+            .into());
+        };
+        let runs = load_input(config, format, input, err)?;
+        if !format.is_sample(config) {
+            for run in runs {
+                records.add_run(run);
+            }
+            continue;
+        }
+        sample_inputs.extend(std::iter::repeat_n(input.as_str(), runs.len()));
+        samples.extend(runs);
+        if samples.len() < config.merge as usize {
+            continue;
+        }
+        let inputs = std::mem::take(&mut sample_inputs);
+        let mut run = merge_samples(config, std::mem::take(&mut samples), &inputs, err)?;
+        // Only runs of a single file can be told apart from a retry of the same job.
+        if let (Some(_), [input]) = (appended_to, &inputs[..]) {
+            run.metadata.checksum = Some(file_checksum(input)?);
+        }
+        add_run(
+            &mut records,
+            run,
+            config.run_meta.get(n_loaded),
+            git_metadata.as_ref(),
+            appended_to,
+            config.on_duplicate,
+            err,
+        )?;
+        n_loaded += 1;
+    }
+    if config.run_meta.len() > n_loaded {
+        bail!(
+            "Got {} --run-meta for {n_loaded} runs",
+            config.run_meta.len()
+        );
+    }
+    Ok(records)
+}
+
+/// Stamp a run loaded from `callgrind_annotate` files with its `--run-meta` and the git metadata,
+/// if any, append it to the history (with the fingerprint of the environment), if any, and add
+/// it to the records.
+///
+/// Runs already in the history are reported into `err`.
+fn add_run(
+    records: &mut Records,
+    mut run: Run,
+    meta: Option<&RunMeta>,
+    git_metadata: Option<&RunMetadata>,
+    history: Option<&History>,
+    on_duplicate: OnDuplicate,
+    err: &mut dyn Write,
+) -> Result<()> {
+    stamp_run(&mut run, meta, git_metadata);
+    if let Some(history) = history {
+        history::stamp_environment(&mut run.metadata);
+        let added = history
+            .add_run(&run, on_duplicate)
+            .with_context(|| format!("Failed to append run {} to the history", records.n_runs()))?;
+        if added.is_none() {
+            writeln!(
+                err,
+                "Run {} is already in the history; not appending it",
+                records.n_runs()
+            )?;
+        }
+    }
+    records.add_run(run);
+    Ok(())
+}
+
+/// Stamp a run with its `--run-meta` and the git metadata, if any.
+///
+/// The benchmark, version, command, checksum, simulation options and unit of the run, if any, are
+/// kept.
+fn stamp_run(run: &mut Run, meta: Option<&RunMeta>, git_metadata: Option<&RunMetadata>) {
+    if let Some(metadata) = git_metadata {
+        run.metadata = RunMetadata {
+            benchmark: run.metadata.benchmark.take(),
+            version: run.metadata.version.take(),
+            command: run.metadata.command.take(),
+            checksum: run.metadata.checksum.take(),
+            simulation: run.metadata.simulation.take(),
+            unit: run.metadata.unit.take(),
+            ..metadata.clone()
+        };
+    }
+    if let Some(iterations) = meta.and_then(|meta| meta.iterations) {
+        run.metadata.iterations = Some(iterations);
+    }
+}
+
+/// Load the runs of a benchmark suite, one per benchmark and version, in order of appearance in
+/// the manifest.
+///
+/// The files of each benchmark and version are merged, and the run is named
+/// `<benchmark>@<version>`.
+fn load_suite(config: &Args, suite: &Manifest, err: &mut dyn Write) -> Result<Vec<Run>> {
+    let formats = InputFormats::default();
+    let mut groups: Vec<(&str, &str, Vec<Run>, Vec<&str>)> = vec![];
+    for entry in &suite.entries {
+        let Some(format) = formats.find(Path::new(&entry.path)) else {
+            return Err(error::Error::UnknownFormat {
+                path: Some(entry.path.clone().into()),
+            }
+            .into());
+        };
+        let runs = load_input(config, format, &entry.path, err)?;
+        if runs.len() != 1 {
+            bail!("{} holds {} runs, expected 1", entry.path, runs.len());
+        }
+        match groups.iter_mut().find(|(benchmark, version, _, _)| {
+            *benchmark == entry.benchmark && *version == entry.version
+        }) {
+            Some((_, _, samples, inputs)) => {
+                samples.extend(runs);
+                inputs.push(&entry.path);
+            }
+            None => groups.push((&entry.benchmark, &entry.version, runs, vec![&entry.path])),
+        }
+    }
+    groups
+        .into_iter()
+        .map(|(benchmark, version, samples, inputs)| {
+            let mut run = merge_samples(config, samples, &inputs, err)?;
+            run.name = format!("{benchmark}@{version}");
+            run.metadata.benchmark = Some(benchmark.to_string());
+            run.metadata.version = Some(version.to_string());
+            Ok(run)
+        })
+        .collect()
+}
+
+/// Merge samples into a single run, reporting the outliers among them (see `--outlier-sigmas`)
+/// into `err` and leaving them out with `--drop-outliers`.
+///
+/// `inputs` are the files the samples were loaded from.
+fn merge_samples(
+    config: &Args,
+    mut samples: Vec<Run>,
+    inputs: &[&str],
+    err: &mut dyn Write,
+) -> io::Result<Run> {
+    /// The maximum number of symbols listed for an outlier sample.
+    const MAX_LISTED: usize = 5;
+
+    let outliers = Run::find_outliers(&samples, config.outlier_sigmas);
+    let outlier_samples = outliers
+        .iter()
+        .map(|outlier| outlier.sample)
+        .dedup()
+        .collect::<Vec<_>>();
+    for sample in &outlier_samples {
+        let symbols = outliers
+            .iter()
+            .filter(|outlier| outlier.sample == *sample)
+            .collect::<Vec<_>>();
+        writeln!(
+            err,
+            "Outlier: {} deviates from the other samples on {} symbol(s)",
+            inputs[*sample],
+            symbols.len()
+        )?;
+        for outlier in symbols.iter().take(MAX_LISTED) {
+            let sigmas = if outlier.sigmas.is_finite() {
+                format!("{:.1} sigmas", outlier.sigmas)
+            } else {
+                "the other samples agree".to_string()
+            };
+            writeln!(
+                err,
+                "  {}: {} IR, against a mean of {:.0} ({sigmas})",
+                outlier.symbol, outlier.ir, outlier.mean
+            )?;
+        }
+        if symbols.len() > MAX_LISTED {
+            writeln!(err, "  and {} more", symbols.len() - MAX_LISTED)?;
+        }
+    }
+    if config.drop_outliers && outlier_samples.len() < samples.len() {
+        for sample in outlier_samples.iter().rev() {
+            writeln!(err, "Dropping {} from the merged column", inputs[*sample])?;
+            samples.remove(*sample);
+        }
+    }
+
+    let n_samples = samples.len();
+    let run = Run::merge(samples);
+    if config.stats && n_samples > 1 {
+        writeln!(
+            err,
+            "stats: merged {n_samples} samples into {} symbols",
+            run.symbols.len()
+        )?;
+    }
+    Ok(run)
+}
+
+/// Load the runs of `input` with `format`, writing how long it took into `err` with `--stats`.
+fn load_input(
+    config: &Args,
+    format: &dyn InputFormat,
+    input: &str,
+    err: &mut dyn Write,
+) -> Result<Vec<Run>> {
+    let start = Instant::now();
+    let runs = format.load(Path::new(input), config)?;
+    if config.stats {
+        let n_symbols = runs.iter().map(|run| run.symbols.len()).sum::<usize>();
+        writeln!(
+            err,
+            "stats: parsed {input} in {:.2?} ({n_symbols} symbols)",
+            start.elapsed()
+        )?;
+    }
+    Ok(runs)
+}
+
+/// Load the base and head runs of `pr-compare` into a [`Records`].
+fn parse_pr_records(config: &Args, base: &str, head: &str, err: &mut dyn Write) -> Result<Records> {
+    let mut records = Records::new();
+    for (i, (name, spec)) in [("base", base), ("head", head)].into_iter().enumerate() {
+        let mut run = load_run(config, spec, err)?;
+        run.name = name.to_string();
+        if let Some(iterations) = config.run_meta.get(i).and_then(|meta| meta.iterations) {
+            run.metadata.iterations = Some(iterations);
+        }
+        records.add_run(run);
+    }
+    Ok(records)
+}
+
+/// Transform the records before diffing: normalize them (see `--per`), aggregate the control and
+/// treatment groups (see `--control`), add the estimated cycles (see `--cycle-model`), group the
+/// symbols by crate (see `--group-summary`) and keep the dominant ones (see `--pareto`).
+///
+/// # Errors
+/// Returns an error if the records cannot be normalized.
+fn transform(config: &Args, records: &mut Records) -> Result<()> {
+    if let Some(per) = config.per {
+        normalize(records, per)?;
+    }
+    if config.compares_groups() {
+        records.aggregate_groups(&[
+            (CONTROL_RUN, &config.control),
+            (TREATMENT_RUN, &config.treatment),
+        ])?;
+    }
+    if let Some(model) = &config.cycle_model {
+        metric::add_estimated_cycles(records, model);
+    }
+    if !config.event.is_empty() {
+        let Some(index) = records.event_index(&config.event) else {
+            bail!("--event={}: no input recorded that event", config.event);
+        };
+        records.swap_event(index);
+    }
+    if config.group_summary {
+        records.group_by(crate_of);
+    }
+    if let Some(Percentage(percent)) = config.pareto {
+        if records.n_runs() != 0 {
+            records.pareto_cutoff(records.n_runs() - 1, percent);
+        }
+    }
+    Ok(())
+}
+
+/// Divide the counts of each run by its metadata of `per` (see `--per`).
+///
+/// # Errors
+/// Returns an error if a run lacks the metadata.
+fn normalize(records: &mut Records, per: Per) -> Result<()> {
+    for column in 0..records.n_runs() {
+        let divisor = match per {
+            Per::Iterations => records.runs_metadata[column].iterations,
+        };
+        let Some(divisor) = divisor.filter(|divisor| *divisor > 0) else {
+            bail!("--per={per}: column {column} has no iteration count (see --run-meta)");
+        };
+        records.divide_run(column, divisor);
+    }
+    Ok(())
+}
+
+/// Load a run from either a file holding a single run or a git ref to look up in the history.
+///
+/// `spec` is interpreted as a file if such a file exists.
+fn load_run(config: &Args, spec: &str, err: &mut dyn Write) -> Result<Run> {
+    if Path::new(spec).is_file() {
+        return load_single_run(config, spec, err);
+    }
+    if config.history.is_empty() {
+        bail!("No such file: {spec} (use `--history` to look it up as a git ref)");
+    }
+    match open_history(config)?.find_latest_by_ref(spec)? {
+        Some(stored) => Ok(stored.run),
+        None => bail!("{spec} is neither a file nor a ref in the history"),
+    }
+}
+
+/// Open the history of `--target` in the store of `--history`.
+fn open_history(config: &Args) -> Result<History> {
+    History::open_target(&config.history, &config.target)
+}
+
+/// Write the targets of the store of `--history` into `out`, with their number of runs, for `show`
+/// without `--target`. Return whether the store has any target.
+fn print_targets(config: &Args, out: &mut dyn Write) -> Result<bool> {
+    let history = History::open(&config.history)?;
+    let targets = history.targets()?;
+    for target in &targets {
+        let n_runs = History::open_target(&config.history, target)?.n_runs()?;
+        writeln!(out, "{target}: {n_runs} run(s)")?;
+    }
+    Ok(!targets.is_empty())
+}
+
+/// Load the run of a file which holds a single run.
+fn load_single_run(config: &Args, path: &str, err: &mut dyn Write) -> Result<Run> {
+    let formats = InputFormats::default();
+    let Some(format) = formats.find(Path::new(path)) else {
+        return Err(error::Error::UnknownFormat {
+            path: Some(path.into()),
+        }
+        .into());
+    };
+    let mut runs = load_input(config, format, path, err)?;
+    if runs.len() != 1 {
+        bail!("{path} holds {} runs, expected 1", runs.len());
+    }
+    Ok(runs.remove(0))
+}
+
+/// Load the records to compare: those of the subcommand, if any, or the inputs.
+///
+/// The symbols renamed in the history of `--history`, if any, are noted with their former names
+/// (see `history alias`).
+fn load_records(config: &Args, suite: Option<&Manifest>, err: &mut dyn Write) -> Result<Records> {
+    let mut records = match &config.command {
+        Some(Command::PrCompare { base, head }) => parse_pr_records(config, base, head, err),
+        Some(Command::Show) => parse_history_records(config, &RunRange::default()),
+        Some(Command::History {
+            command: HistoryCommand::Show { runs },
+        }) => parse_history_records(config, runs),
+        #[cfg(feature = "dashboard")]
+        Some(Command::History {
+            command: HistoryCommand::Dashboard { runs, .. },
+        }) => parse_history_records(config, runs),
+        Some(Command::History {
+            command: HistoryCommand::Diff { store },
+        }) => parse_store_diff_records(config, store),
+        _ => parse_records(config, suite, err),
+    }?;
+    // Opening a store creates it, which only appending runs should do.
+    if !config.history.is_empty() && Path::new(&config.history).is_dir() {
+        let aliases = open_history(config)?.aliases()?;
+        let former_names = alias::former_names(&aliases);
+        for symbol in &mut records.symbols {
+            if let Some(names) = former_names.get(&*symbol.name) {
+                symbol.renamed_from = names.iter().map(ToString::to_string).collect();
+            }
+        }
+    }
+    Ok(records)
+}
+
+/// Load the latest run of the history and that of the same target in `store` into a [`Records`],
+/// for `history diff`.
+///
+/// The runs are named after their store, the history being the reference.
+fn parse_store_diff_records(config: &Args, store: &str) -> Result<Records> {
+    // Opening a store creates it, which would hide a mistyped path.
+    if !Path::new(store).is_dir() {
+        bail!("No history store at {store}");
+    }
+    let mut records = Records::new();
+    for (path, history) in [
+        (config.history.as_str(), open_history(config)?),
+        (store, History::open_target(store, &config.target)?),
+    ] {
+        let Some(stored) = history.latest()? else {
+            bail!("No run in history {path}");
+        };
+        let mut run = stored.run;
+        run.name = path.to_string();
+        records.add_run(run);
+    }
+    Ok(records)
+}
+
+/// Load the runs of the given range of the history into a [`Records`], from the oldest to the
+/// most recent, for `show` and `history show`.
+///
+/// Runs with no name are named after their id in the history (e.g. `#3`).
+fn parse_history_records(config: &Args, range: &RunRange) -> Result<Records> {
+    let mut records = Records::new();
+    for stored in open_history(config)?.runs_in(range)? {
+        let mut run = stored.run;
+        if run.name.is_empty() {
+            run.name = format!("#{}", stored.id);
+        }
+        records.add_run(run);
+    }
+    if records.n_runs() == 0 {
+        bail!("No run in history {}", config.history);
+    }
+    Ok(records)
+}
+
+/// Run `add`: load the run of `file`, name it `name` (or as per `name_from` if `name` is empty),
+/// stamp it and append it to the history. Return its id, or `None` if it was skipped as a
+/// duplicate.
+///
+/// What was done with the run is reported into `err`.
+///
+/// # Errors
+/// Returns a [`Failure`] if the run cannot be loaded or appended to the history.
+fn add(
+    config: &Args,
+    file: &str,
+    name: &str,
+    name_from: NameSource,
+    err: &mut dyn Write,
+) -> Result<Option<u64>, Failure> {
+    let run = load_single_run(config, file, err).with_cause_status()?;
+    add_loaded(config, run, file, name, name_from, err)
+}
+
+/// Run `profile`: run `program` under callgrind with `options`, then append its run to the
+/// history as `add` does, with the simulation options of `options`. The run is named `name`, or
+/// after the command line of the program.
+///
+/// # Errors
+/// Returns a [`Failure`] if the program cannot be profiled, or its run loaded or appended to the
+/// history.
+fn profile(
+    config: &Args,
+    program: &[String],
+    options: &CallgrindOptions,
+    name: &str,
+    out_file: &str,
+    err: &mut dyn Write,
+) -> Result<Option<u64>, Failure> {
+    let file = if out_file.is_empty() {
+        std::env::temp_dir()
+            .join(format!("callgrind_differ.{}.out", std::process::id()))
+            .display()
+            .to_string()
+    } else {
+        out_file.to_string()
+    };
+    let added = profile::run_callgrind(program, options, Path::new(&file))
+        .with_status(Status::UsageError)
+        .and_then(|()| {
+            let mut run = load_single_run(config, &file, err).with_cause_status()?;
+            run.metadata.simulation = Some(profile::simulation_options(options));
+            add_loaded(config, run, &file, name, NameSource::Cmd, err)
+        });
+    if out_file.is_empty() {
+        // The profile may not have been written if valgrind failed.
+        let _ = std::fs::remove_file(&file);
+    }
+    added
+}
+
+/// Name the run loaded from `file` `name` (or as per `name_from` if `name` is empty), stamp it
+/// and append it to the history, as per [`add`].
+///
+/// # Errors
+/// Returns a [`Failure`] if the run cannot be named or appended to the history.
+fn add_loaded(
+    config: &Args,
+    mut run: Run,
+    file: &str,
+    name: &str,
+    name_from: NameSource,
+    err: &mut dyn Write,
+) -> Result<Option<u64>, Failure> {
+    run.name = if name.is_empty() {
+        derive_name(&run, file, name_from).with_status(Status::UsageError)?
+    } else {
+        name.to_string()
+    };
+    let git_metadata = if config.tag_from_git {
+        Some(history::metadata_from_git().with_status(Status::UsageError)?)
+    } else {
+        None
+    };
+    stamp_run(&mut run, config.run_meta.first(), git_metadata.as_ref());
+    run.metadata.timestamp.get_or_insert_with(history::now);
+    history::stamp_environment(&mut run.metadata);
+    run.metadata.checksum = Some(file_checksum(file).with_cause_status()?);
+    let history = open_history(config).with_status(Status::UsageError)?;
+    let id = history
+        .add_run(&run, config.on_duplicate)
+        .with_context(|| format!("Failed to add {file} to the history"))
+        .with_status(Status::UsageError)?;
+    let Some(id) = id else {
+        writeln!(err, "Skipped {file}: it already is in {}", config.history)
+            .with_status(Status::UsageError)?;
+        return Ok(None);
+    };
+    if config.target.is_empty() {
+        writeln!(
+            err,
+            "Added {file} to {} as run {id} ({})",
+            config.history, run.name
+        )
+        .with_status(Status::UsageError)?;
+    } else {
+        writeln!(
+            err,
+            "Added {file} to {} as run {id} ({}) of {}",
+            config.history, run.name, config.target
+        )
+        .with_status(Status::UsageError)?;
+    }
+    // The run is in the history: a failing hook must not suggest adding it again.
+    if let Err(error) = run_add_hook(config, &history) {
+        writeln!(
+            err,
+            "Warning: the `on_add` hook of {} failed: {error:#}",
+            config.history
+        )
+        .with_status(Status::UsageError)?;
+    }
+    Ok(Some(id))
+}
+
+/// Load the policy of `--regression-rules`, if any.
+fn load_policy(config: &Args) -> Result<Policy> {
+    if config.regression_rules.is_empty() {
+        Ok(Policy::default())
+    } else {
+        Policy::from_file(&config.regression_rules)
+    }
+}
+
+/// Run the `on_add` hook of `history`, if any (see [`hook::Hooks`]), with the JSON summary of
+/// the comparison of its latest run to the one before it on its standard input.
+///
+/// # Errors
+/// Returns an error if the runs cannot be loaded or compared, or the hook fails.
+fn run_add_hook(config: &Args, history: &History) -> Result<()> {
+    let Some(command) = history.hooks()?.on_add else {
+        return Ok(());
+    };
+    let mut records = Records::new();
+    for stored in history.latest_runs(2)? {
+        let mut run = stored.run;
+        if run.name.is_empty() {
+            run.name = format!("#{}", stored.id);
+        }
+        records.add_run(run);
+    }
+    check_columns(config, &records)?;
+    let policy = load_policy(config)?;
+    let table = DiffTable::new(config, &records);
+    let regressions = find_regressions(config, &table, &policy);
+    hook::run(&command, &Summary::new(&table, &regressions).to_json())
+}
+
+/// Return the SHA-256 of `file`, to detect it being appended twice to the history.
+fn file_checksum(file: &str) -> Result<String> {
+    checksum::sha256_file(file).map_err(|error| error::Error::from(error).in_file(file).into())
+}
+
+/// Derive the name of the run of `file` from the given source, for `add` without `--name`.
+fn derive_name(run: &Run, file: &str, source: NameSource) -> Result<String> {
+    let file_name = || {
+        Path::new(file).file_name().map_or_else(
+            || file.to_string(),
+            |name| name.to_string_lossy().into_owned(),
+        )
+    };
+    Ok(match source {
+        NameSource::File => file_name(),
+        NameSource::Mtime => {
+            let modified = std::fs::metadata(file)
+                .and_then(|metadata| metadata.modified())
+                .with_context(|| format!("Failed to read the modification time of {file}"))?;
+            let timestamp = modified
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |duration| duration.as_secs());
+            history::format_timestamp(timestamp)
+        }
+        NameSource::Cmd => run.metadata.command.clone().unwrap_or_else(file_name),
+    })
+}
+
+/// Run `history import`: append the runs of `file` to the history, in order, and report how many
+/// were into `err`.
+///
+/// # Errors
+/// Returns a [`Failure`] if the runs cannot be loaded or appended to the history.
+fn import(config: &Args, file: &str, err: &mut dyn Write) -> Result<(), Failure> {
+    let mut runs = InputFormats::default()
+        .load(Path::new(file), config)
+        .with_cause_status()?;
+    let checksum = file_checksum(file).with_cause_status()?;
+    let several = runs.len() > 1;
+    for (i, run) in runs.iter_mut().enumerate() {
+        run.metadata.checksum = Some(if several {
+            format!("{checksum}#{i}")
+        } else {
+            checksum.clone()
+        });
+    }
+    let ids = open_history(config)
+        .and_then(|history| history.add_runs(&runs, config.on_duplicate))
+        .with_status(Status::UsageError)?;
+    writeln!(
+        err,
+        "Imported {} run(s) from {file} into {}",
+        ids.iter().flatten().count(),
+        config.history
+    )
+    .with_status(Status::UsageError)?;
+    Ok(())
+}
+
+/// Run `history merge`: append the runs of `store` (or of its `--target`) to the history, or to
+/// their lane with `lane`, tagged with `origin` (or the name of the directory of `store`), and
+/// report how many were into `err`.
+///
+/// # Errors
+/// Returns an error if `store` is not a store, the origin is invalid or the runs cannot be read
+/// or appended.
+fn merge(config: &Args, store: &str, origin: &str, lane: bool, err: &mut dyn Write) -> Result<()> {
+    // Opening a store creates it, which would hide a mistyped path.
+    if !Path::new(store).is_dir() {
+        bail!("No history store at {store}");
+    }
+    let origin = if origin.is_empty() {
+        Path::new(store)
+            .canonicalize()?
+            .file_name()
+            .with_context(|| format!("Cannot name the origin of {store}: use `--origin`"))?
+            .to_string_lossy()
+            .into_owned()
+    } else {
+        origin.to_string()
+    };
+    if lane && origin.contains(['/', '\\']) {
+        bail!("Invalid origin {origin}: lanes cannot be named after paths");
+    }
+    let runs = History::open_target(store, &config.target)?
+        .runs()?
+        .into_iter()
+        .map(|stored| {
+            let mut run = stored.run;
+            run.metadata.origin.get_or_insert_with(|| origin.clone());
+            run.metadata.pinned = false;
+            run
+        })
+        .collect_vec();
+    let (history, destination) = if lane {
+        let target = if config.target.is_empty() {
+            origin.clone()
+        } else {
+            format!("{}@{origin}", config.target)
+        };
+        let history = History::open_target(&config.history, &target)?;
+        (history, format!("{} (target {target})", config.history))
+    } else {
+        (open_history(config)?, config.history.clone())
+    };
+    let ids = history.add_runs(&runs, config.on_duplicate)?;
+    writeln!(
+        err,
+        "Merged {} run(s) from {store} into {destination}, with origin {origin}",
+        ids.iter().flatten().count()
+    )?;
+    Ok(())
+}
+
+/// Run `history export`: write the runs of the given range of the history to `file`, and report
+/// how many were into `err`.
+///
+/// # Errors
+/// Returns a [`Failure`] if the runs cannot be loaded or `file` cannot be written.
+fn export_history(
+    config: &Args,
+    runs: &RunRange,
+    format: ExportFormat,
+    file: &str,
+    err: &mut dyn Write,
+) -> Result<(), Failure> {
+    let records = parse_history_records(config, runs).with_status(Status::UsageError)?;
+    let write = || -> Result<()> {
+        let mut writer = BufWriter::new(File::create(file)?);
+        match format {
+            ExportFormat::Csv => csv::write(&records, &mut writer)?,
+        }
+        writer.flush()?;
+        Ok(())
+    };
+    write()
+        .with_context(|| format!("Failed to write {file}"))
+        .with_status(Status::UsageError)?;
+    writeln!(
+        err,
+        "Exported {} run(s) from {} to {file}",
+        records.n_runs(),
+        config.history
+    )
+    .with_status(Status::UsageError)?;
+    Ok(())
+}
+
+/// Compare the head of `pr-compare` to its base, with the default CI thresholds if none is given.
+fn apply_pr_compare_defaults(config: &mut Args) {
+    config.relative_to = RelativeTo::First;
+    if config.warn_on_regression.is_none()
+        && config.fail_on_regression.is_empty()
+        && config.regression_rules.is_empty()
+    {
+        config.warn_on_regression = Some(Percentage(1.0));
+        config.fail_on_regression = vec![EventThreshold {
+            event: None,
+            threshold: Percentage(5.0),
+        }];
+    }
+}
+
+/// Run `calibrate`: measure the noise floor of the inputs, write it to `output` and report it into
+/// `err`.
+///
+/// # Errors
+/// Returns a [`Failure`] if the inputs cannot be parsed or the noise floor cannot be written.
+fn calibrate(config: &Args, output: &str, err: &mut dyn Write) -> Result<(), Failure> {
+    let records = parse_records(config, None, err).with_cause_status()?;
+    let floor = NoiseFloor::calibrate(&records);
+    floor
+        .write_to_file(output)
+        .with_status(Status::UsageError)?;
+    writeln!(
+        err,
+        "Calibrated the noise floor of {} symbol(s) over {} runs into {output}",
+        floor.symbols.len(),
+        records.n_runs()
+    )
+    .with_status(Status::UsageError)?;
+    Ok(())
+}
+
+/// Run the subcommands which do not render a table (`history` but `history show`, `diff` and
+/// `dashboard`, `add`, `calibrate`, and `show` without `--target` on a store with targets), and
+/// return whether the command was one of them.
+///
+/// # Errors
+/// Returns a [`Failure`] carrying the status to exit with if the command could not complete.
+fn run_standalone_command(config: &mut Args, output: &mut Output) -> Result<bool, Failure> {
+    match &config.command {
+        Some(Command::Show) if config.target.is_empty() => {
+            return print_targets(config, output.out).with_status(Status::UsageError);
+        }
+        // These render a table.
+        Some(
+            Command::History {
+                command: HistoryCommand::Show { .. } | HistoryCommand::Diff { .. },
+            }
+            | Command::Show
+            | Command::PrCompare { .. },
+        )
+        | None => return Ok(false),
+        #[cfg(feature = "dashboard")]
+        Some(Command::History {
+            command: HistoryCommand::Dashboard { .. },
+        }) => return Ok(false),
+        Some(Command::History {
+            command: HistoryCommand::Import { file },
+        }) => import(config, file, output.err)?,
+        Some(Command::History {
+            command: HistoryCommand::Export { runs, format, file },
+        }) => export_history(config, runs, *format, file, output.err)?,
+        Some(Command::History {
+            command:
+                HistoryCommand::Merge {
+                    store,
+                    origin,
+                    lane,
+                },
+        }) => merge(config, store, origin, *lane, output.err).with_status(Status::UsageError)?,
+        Some(Command::History { command }) => {
+            open_history(config)
+                .and_then(|history| history::run_command(&history, command, output.out))
+                .with_status(Status::UsageError)?;
+        }
+        Some(Command::Add {
+            file,
+            name,
+            name_from,
+        }) => {
+            add(config, file, name, *name_from, output.err)?;
+        }
+        Some(Command::Profile {
+            callgrind,
+            name,
+            out_file,
+            program,
+        }) => {
+            profile(config, program, callgrind, name, out_file, output.err)?;
+        }
+        Some(Command::Calibrate { output: file, runs }) => {
+            let file = file.clone();
+            config.inputs.clone_from(runs);
+            calibrate(config, &file, output.err)?;
+        }
+    }
+    Ok(true)
+}
+
+/// Where [`run`] writes to.
+///
+/// The `callgrind_differ` binary writes to its standard output and error, but other tools may
+/// capture either, or discard them with [`io::sink`].
+pub struct Output<'a> {
+    /// Where the results go: the table, the JSON summary of `pr-compare`, the GitHub annotations
+    /// and the output of `history` subcommands.
+    pub out: &'a mut dyn Write,
+    /// Where the messages go: the warnings, the analyses (e.g. `--explain-total`), `--stats` and
+    /// the reports of the subcommands which modify the history.
+    pub err: &'a mut dyn Write,
+}
+
+/// Run the program with the given configuration, writing into `output`, and return the status to
+/// exit with.
+///
+/// The configuration must have been [validated](Args::validated).
+///
+/// # Errors
+/// Returns a [`Failure`] carrying the status to exit with if the program could not complete.
+pub fn run(mut config: Args, output: &mut Output) -> Result<Status, Failure> {
+    if run_standalone_command(&mut config, output)? {
+        return Ok(Status::Ok);
+    }
+    if !config.compare_ref.is_empty() {
+        // The run from the history is inserted as the first column.
+        config.relative_to = RelativeTo::Column(0);
+    }
+    if config.compares_groups() {
+        // The treatment is compared to the control, which is aggregated into the first column.
+        config.relative_to = RelativeTo::Column(0);
+    }
+    let is_pr_compare = matches!(config.command, Some(Command::PrCompare { .. }));
+    if is_pr_compare {
+        apply_pr_compare_defaults(&mut config);
+    }
+    let suite = if config.suite.is_empty() {
+        None
+    } else {
+        Some(Manifest::from_file(&config.suite).with_status(Status::ParseError)?)
+    };
+    let mut records = load_records(&config, suite.as_ref(), output.err).with_cause_status()?;
+    transform(&config, &mut records).with_status(Status::UsageError)?;
+    let policy = load_policy(&config).with_cause_status()?;
+    if config.stats {
+        writeln!(
+            output.err,
+            "stats: {} runs, {} symbols (~{})",
+            records.n_runs(),
+            records.symbols.len(),
+            stats::mib(stats::estimated_size(&records) as u64)
+        )
+        .with_status(Status::UsageError)?;
+    }
+    check_columns(&config, &records).with_status(Status::UsageError)?;
+    warn_unaccounted_ir(&config, &records, output.err).with_status(Status::UsageError)?;
+    warn_environment_mismatch(&records, output.err).with_status(Status::UsageError)?;
+
+    records
+        .sort(config.sort_by)
+        .with_status(Status::UsageError)?;
+    let mut table = DiffTable::new(&config, &records);
+    if let Some(path) = config.noise_floor_path() {
+        NoiseFloor::from_file(path)
+            .with_cause_status()?
+            .apply(&mut table, config.noise_band);
+    }
+    let regressions = find_regressions(&config, &table, &policy);
+    let start = Instant::now();
+    render(&config, &table, &regressions, output.out)
+        .context("Failed to display the table")
+        .with_status(Status::UsageError)?;
+    if config.stats {
+        writeln!(
+            output.err,
+            "stats: rendered the table in {:.2?}",
+            start.elapsed()
+        )
+        .with_status(Status::UsageError)?;
+    }
+
+    print_analyses(&config, &table, suite.as_ref(), output.err).with_status(Status::UsageError)?;
+    export(&config, &table, &regressions, &policy, output).with_status(Status::UsageError)?;
+    if is_pr_compare {
+        write!(
+            output.out,
+            "{}",
+            Summary::new(&table, &regressions).to_json()
+        )
+        .with_status(Status::UsageError)?;
+    }
+    exit_status(&config, &table, &regressions, output.err).with_status(Status::UsageError)
+}
+
+/// Return the status to exit with after comparing the runs, reporting into `err` the symbols which
+/// regressed beyond their threshold, the patterns of `--fail-on-missing-symbol` which were not
+/// satisfied, the peak memory with `--stats` and the total IR exceeding `--total-budget`.
+fn exit_status(
+    config: &Args,
+    table: &DiffTable,
+    regressions: &[Regression],
+    err: &mut dyn Write,
+) -> io::Result<Status> {
+    let records = table.records;
+    let mut status = Status::Ok;
+    let n_failures = regressions
+        .iter()
+        .filter(|regression| regression.severity == Severity::Error)
+        .count();
+    if n_failures != 0 {
+        writeln!(
+            err,
+            "{n_failures} symbol(s) regressed beyond their allowed threshold"
+        )?;
+        status = Status::RegressionDetected;
+    }
+    for missing in find_missing_symbols(config, table) {
+        if missing.vanished.is_empty() {
+            writeln!(err, "No symbol matching {} was hit", missing.pattern)?;
+        }
+        for symbol in missing.vanished {
+            writeln!(
+                err,
+                "Symbol {symbol} (matching {}) vanished",
+                missing.pattern
+            )?;
+        }
+        status = Status::RegressionDetected;
+    }
+    if config.stats {
+        let peak = stats::peak_memory().map_or_else(|| "unknown".to_string(), stats::mib);
+        writeln!(err, "stats: peak memory {peak}")?;
+    }
+    if let Some(IrCount(budget)) = config.total_budget {
+        let total_ir = records.runs_total_irs[records.n_runs() - 1];
+        if total_ir > budget {
+            writeln!(err, "Total IR {total_ir} exceeds the budget of {budget}")?;
+            status = Status::BudgetViolated;
+        }
+    }
+
+    Ok(status)
+}
+
+/// The environment variable holding the history store [`record_run`] appends runs to. Without
+/// it, no run is recorded.
+pub const HISTORY_ENV_VAR: &str = "CALLGRIND_DIFFER_HISTORY";
+
+/// The environment variable holding the target of the store [`record_run`] appends runs to, if
+/// any (see `--target`).
+pub const TARGET_ENV_VAR: &str = "CALLGRIND_DIFFER_TARGET";
+
+/// The environment variable telling [`record_run`] what to do with runs whose file already was
+/// appended (see `--on-duplicate`). Defaults to `skip`, as benchmarks rerun on unchanged code
+/// give the same outputs.
+pub const ON_DUPLICATE_ENV_VAR: &str = "CALLGRIND_DIFFER_ON_DUPLICATE";
+
+/// The environment variable which, set to `1`, makes [`record_run`] tag runs with the state of
+/// the git repository in the current directory (see `--tag-from-git`).
+pub const TAG_FROM_GIT_ENV_VAR: &str = "CALLGRIND_DIFFER_TAG_FROM_GIT";
+
+/// Append the run of `annotate_path` to the history store named by [`HISTORY_ENV_VAR`], as
+/// `callgrind_differ add` does, and return its id.
+///
+/// This lets custom benchmark harnesses record their runs as they go: after each benchmark, the
+/// harness calls this with the `callgrind_annotate` output (or any input holding a single run)
+/// of the benchmark, and the script running the benchmarks picks the store, e.g.
+/// `CALLGRIND_DIFFER_HISTORY=.bench-history cargo bench`. The store, its target, what to do with
+/// duplicates and whether to tag the run with git are read from [`HISTORY_ENV_VAR`],
+/// [`TARGET_ENV_VAR`], [`ON_DUPLICATE_ENV_VAR`] and [`TAG_FROM_GIT_ENV_VAR`].
+///
+/// The run is named `name`, or after its file if `name` is empty. Its timestamp, checksum and
+/// environment are stamped, and the `on_add` hook of the store is run, as with `add`. What was
+/// done with the run, and whether the hook failed, is reported into `messages` (e.g.
+/// [`io::stderr`], or [`io::sink`] to discard it).
+///
+/// Returns `None` if [`HISTORY_ENV_VAR`] is not set, so that harnesses may call this
+/// unconditionally, or if the run was skipped as a duplicate.
+///
+/// # Errors
+/// Returns an error if a variable is invalid, or the run cannot be loaded or appended to the
+/// store.
+pub fn record_run<P: AsRef<Path>>(
+    name: &str,
+    annotate_path: P,
+    messages: &mut dyn Write,
+) -> Result<Option<u64>> {
+    let Some(history) = std::env::var_os(HISTORY_ENV_VAR).filter(|history| !history.is_empty())
+    else {
+        return Ok(None);
+    };
+    let mut args = vec!["callgrind_differ".into(), "--history".into(), history];
+    if let Some(target) = std::env::var_os(TARGET_ENV_VAR).filter(|target| !target.is_empty()) {
+        args.extend(["--target".into(), target]);
+    }
+    let on_duplicate = std::env::var_os(ON_DUPLICATE_ENV_VAR).unwrap_or_else(|| "skip".into());
+    args.extend(["--on-duplicate".into(), on_duplicate]);
+    if std::env::var_os(TAG_FROM_GIT_ENV_VAR).is_some_and(|tag| tag == "1") {
+        args.push("--tag-from-git".into());
+    }
+    let file = annotate_path.as_ref().to_string_lossy().into_owned();
+    args.extend([
+        "add".into(),
+        file.clone().into(),
+        "--name".into(),
+        name.into(),
+    ]);
+    let config = Args::try_parse_from(args)
+        .context("Invalid callgrind_differ environment variables")?
+        .validated()?;
+    add(&config, &file, name, NameSource::File, messages).map_err(|failure| failure.error)
+}
+
+/// Export the differences and the regressions, as per `--github-annotations`,
+/// `--export-gitlab-codequality`, `--export-pr-comment`, `--export-policy-report`,
+/// `--export-graph`, `--export-callgrind-diff`, `history dashboard`, `--push-to` and
+/// `--push-prometheus`, those of disabled features left out, into `output` or the files and
+/// endpoints they name.
+fn export(
+    config: &Args,
+    table: &DiffTable,
+    regressions: &[Regression],
+    policy: &Policy,
+    output: &mut Output,
+) -> Result<()> {
+    if config.github_annotations {
+        github::print_annotations(regressions, output.out)?;
+    }
+    #[cfg(feature = "gitlab")]
+    if !config.export_gitlab_codequality.is_empty() {
+        gitlab::export_codequality(config, regressions)?;
+    }
+    if !config.export_pr_comment.is_empty() {
+        comment::export_pr_comment(config, table, regressions)?;
+    }
+    if !config.export_policy_report.is_empty() {
+        report::export_policy_report(config, table, policy)?;
+    }
+    if !config.export_graph.is_empty() {
+        graph::export_graph(config, table)?;
+    }
+    if !config.export_callgrind_diff.is_empty() {
+        diff_profile::export_callgrind_diff(config, table)?;
+    }
+    #[cfg(feature = "dashboard")]
+    if let Some(Command::History {
+        command: HistoryCommand::Dashboard { out_dir, .. },
+    }) = &config.command
+    {
+        dashboard::export_dashboard(out_dir, table, regressions)?;
+        writeln!(output.err, "Wrote the dashboard to {out_dir}")?;
+    }
+    #[cfg(feature = "push")]
+    if !config.push_to.is_empty() {
+        let summary = Summary::new(table, regressions);
+        push::push_summary(&config.push_to, &summary)?;
+    }
+    #[cfg(feature = "push")]
+    if !config.push_prometheus.is_empty() {
+        push::push_prometheus(&config.push_prometheus, table)?;
+    }
+    Ok(())
+}
+
+/// Warn into `err` about the runs whose symbols do not account for enough of their total IR.
+///
+/// See `--max-unaccounted`.
+fn warn_unaccounted_ir(config: &Args, records: &Records, err: &mut dyn Write) -> io::Result<()> {
+    for (i, total_ir) in records.runs_total_irs.iter().enumerate() {
+        let unaccounted = records.unaccounted_ir(i);
+        if *total_ir == 0 || unaccounted == 0 {
+            continue;
+        }
+        let percent = unaccounted as f64 * 100.0 / *total_ir as f64;
+        if percent > config.max_unaccounted.0 {
+            let name = &records.run_names[i];
+            let run = if name.is_empty() {
+                format!("run {i}")
+            } else {
+                format!("run {i} ({name})")
+            };
+            writeln!(
+                err,
+                "Warning: {unaccounted} IR ({percent:.3}%) of {run} are not accounted for by its \
+                 symbols; the table is incomplete (see `callgrind_annotate --threshold`)"
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Warn into `err` if the runs were measured with different versions of `rustc`, for different
+/// targets, on different CPU models, with different versions of valgrind or simulation options
+/// of callgrind (e.g. `--cache-sim=yes`) or on different machines (see `history merge`), as their
+/// differences may then come from the environment rather than from the code.
+///
+/// Runs with no fingerprint of their environment (see [`history::stamp_environment`]) are not
+/// compared, but runs with no origin are those measured locally.
+fn warn_environment_mismatch(records: &Records, err: &mut dyn Write) -> io::Result<()> {
+    warn_metadata_mismatch(records, err, "rustc versions", |metadata| {
+        metadata.rustc.as_deref()
+    })?;
+    warn_metadata_mismatch(records, err, "target triples", |metadata| {
+        metadata.target_triple.as_deref()
+    })?;
+    warn_metadata_mismatch(records, err, "CPU models", |metadata| {
+        metadata.cpu.as_deref()
+    })?;
+    warn_metadata_mismatch(records, err, "valgrind versions", |metadata| {
+        metadata.valgrind.as_deref()
+    })?;
+    warn_metadata_mismatch(records, err, "simulation options", |metadata| {
+        metadata.simulation.as_deref()
+    })?;
+    // Runs measured locally have no origin, but mixing them with merged runs is as misleading.
+    warn_metadata_mismatch(records, err, "origins", |metadata| {
+        Some(metadata.origin.as_deref().unwrap_or("local"))
+    })?;
+    Ok(())
+}
+
+/// Warn into `err` if the runs which have a `field` of their metadata do not all have the same
+/// one.
+fn warn_metadata_mismatch(
+    records: &Records,
+    err: &mut dyn Write,
+    what: &str,
+    field: impl Fn(&RunMetadata) -> Option<&str>,
+) -> io::Result<()> {
+    let values = records
+        .runs_metadata
+        .iter()
+        .enumerate()
+        .filter_map(|(i, metadata)| Some((field(metadata)?, i)))
+        .into_group_map();
+    if values.len() < 2 {
+        return Ok(());
+    }
+    let values = values
+        .into_iter()
+        .sorted_by_key(|(_, runs)| runs[0])
+        .map(|(value, runs)| format!("{value} (run(s) {})", runs.iter().join(", ")))
+        .join(", ");
+    writeln!(
+        err,
+        "Warning: the runs were measured with different {what}: {values}; their differences may \
+         not come from the code"
+    )?;
+    Ok(())
+}
+
+/// Print the analyses of `--find-regressions`, `--explain-total`, `--histogram`, `--movers`,
+/// `--correlate` and `--suite` into `err`.
+///
+/// # Errors
+/// Returns an error if the symbol of `--correlate` does not exist, or writing to `err` fails.
+fn print_analyses(
+    config: &Args,
+    table: &DiffTable,
+    suite: Option<&Manifest>,
+    err: &mut dyn Write,
+) -> Result<()> {
+    if let Some(threshold) = config.find_regressions {
+        print_changepoints(table.records, threshold, err)?;
+    }
+    if config.explain_total {
+        print_total_change(table, err)?;
+    }
+    if config.histogram {
+        print_histogram(table, err)?;
+    }
+    if let Some(k) = config.movers {
+        print_movers(table.records, k, err)?;
+    }
+    if config.compares_groups() {
+        print_group_comparison(config, table, err)?;
+    }
+    if let Some(recent) = config.lifetimes {
+        print_lifetimes(table.records, recent, err)?;
+    }
+    if config.burn_down {
+        let Some(IrCount(budget)) = config.total_budget else {
+            bail!("`--burn-down` requires `--total-budget`");
+        };
+        print_burn_down(table.records, budget, err)?;
+    }
+    if !config.correlate.is_empty() {
+        let Some(correlations) = correlate(table.records, &config.correlate) else {
+            bail!("--correlate: no symbol {}", config.correlate);
+        };
+        writeln!(err, "Symbols correlated with {}:", config.correlate)?;
+        for correlation in correlations {
+            writeln!(
+                err,
+                "  {:+.3}  {}",
+                correlation.coefficient, correlation.symbol
+            )?;
+        }
+    }
+    if let Some(summary) = suite.and_then(|suite| SuiteSummary::new(table.records, suite)) {
+        print_suite_summary(&summary, err)?;
+    }
+    Ok(())
+}
+
+/// Print the jumps of `--find-regressions` into `err`.
+fn print_changepoints(
+    records: &Records,
+    threshold: Percentage,
+    err: &mut dyn Write,
+) -> io::Result<()> {
+    for changepoint in find_changepoints(records, threshold) {
+        let series = changepoint.symbol.map_or_else(
+            || "Total IR".to_string(),
+            |symbol| format!("symbol {symbol}"),
+        );
+        writeln!(
+            err,
+            "{series} jumped {:+.1}% ({} to {}) between {} and {}",
+            changepoint.percent(),
+            changepoint.before,
+            changepoint.after,
+            describe_run(records, changepoint.run - 1),
+            describe_run(records, changepoint.run)
+        )?;
+    }
+    Ok(())
+}
+
+/// Print the breakdown of `--explain-total` into `err`.
+fn print_total_change(table: &DiffTable, err: &mut dyn Write) -> io::Result<()> {
+    let column = table.last_column();
+    let change = TotalChange::new(table);
+    writeln!(
+        err,
+        "Total IR of {} changed by {:+} ({:+.3}%)",
+        describe_run(table.records, column),
+        change.diff,
+        table.total[column].percent()
+    )?;
+    let width = change
+        .contributions
+        .iter()
+        .map(|contribution| format!("{:+}", contribution.diff).len())
+        .chain(std::iter::once(format!("{:+}", change.unaccounted).len()))
+        .max()
+        .unwrap_or(0);
+    let mut cumulative_share = 0.0;
+    for contribution in &change.contributions {
+        let status = if contribution.appeared() {
+            " (appeared)"
+        } else if contribution.vanished() {
+            " (vanished)"
+        } else {
+            ""
+        };
+        writeln!(
+            err,
+            "  {:>width$} {:>8.1}% {:>8.1}%  {}{status}",
+            format!("{:+}", contribution.diff),
+            contribution.share,
+            contribution.cumulative_share,
+            contribution.symbol
+        )?;
+        cumulative_share = contribution.cumulative_share;
+    }
+    if change.unaccounted != 0 {
+        writeln!(
+            err,
+            "  {:>width$} {:>8.1}% {:>8.1}%  (not accounted for by the symbols)",
+            format!("{:+}", change.unaccounted),
+            change.unaccounted_share,
+            cumulative_share + change.unaccounted_share
+        )?;
+    }
+    Ok(())
+}
+
+/// The width of the largest bar of `--histogram`, and of the budget of `--burn-down`, in
+/// characters.
+const BAR_WIDTH: usize = 40;
+
+/// Print the histogram of `--histogram` into `err`.
+///
+/// Bars are scaled so that the largest bucket spans [`BAR_WIDTH`] characters.
+fn print_histogram(table: &DiffTable, err: &mut dyn Write) -> io::Result<()> {
+    let histogram = Histogram::new(table);
+    writeln!(
+        err,
+        "Changes of the {} symbol(s) of {}:",
+        histogram.total(),
+        describe_run(table.records, table.last_column())
+    )?;
+    let max = histogram.counts.iter().copied().max().unwrap_or(0).max(1);
+    let count_width = max.to_string().len();
+    for (label, count) in BUCKET_LABELS.iter().zip(histogram.counts) {
+        // Non-empty buckets get at least one character, so that they stand out from empty ones.
+        let bar = "#".repeat((count * BAR_WIDTH).div_ceil(max));
+        let line = format!("  {label:>8} | {count:>count_width$} {bar}");
+        writeln!(err, "{}", line.trim_end())?;
+    }
+    Ok(())
+}
+
+/// Print the chart and the projection of `--burn-down` into `err`.
+///
+/// Each run is a bar of its total IR, scaled so that the budget (`|`) is [`BAR_WIDTH`]
+/// wide. Bars of runs over budget overflow past it.
+fn print_burn_down(records: &Records, budget: u64, err: &mut dyn Write) -> io::Result<()> {
+    let burn_down = BurnDown::new(records, budget);
+    writeln!(
+        err,
+        "Total IR of each run to the budget of {budget} (over budget if positive):"
+    )?;
+    let run_width = records.n_runs().saturating_sub(1).to_string().len();
+    let remaining_width = burn_down
+        .remaining
+        .iter()
+        .map(|remaining| format!("{:+}", -remaining).len())
+        .max()
+        .unwrap_or(0);
+    for (run, (total, remaining)) in records
+        .runs_total_irs
+        .iter()
+        .zip(&burn_down.remaining)
+        .enumerate()
+    {
+        let length = (u128::from(*total) * BAR_WIDTH as u128)
+            .div_ceil(u128::from(budget.max(1)))
+            .min(2 * BAR_WIDTH as u128) as usize;
+        let bar = "#".repeat(length.min(BAR_WIDTH));
+        let overflow = "#".repeat(length.saturating_sub(BAR_WIDTH));
+        let line = format!(
+            "  run {run:>run_width$} {:>remaining_width$} {bar:BAR_WIDTH$}|{overflow}",
+            format!("{:+}", -remaining)
+        );
+        writeln!(err, "{}", line.trim_end())?;
+    }
+    let n_runs = records.n_runs();
+    match burn_down.projection {
+        Projection::ExceededSince(run) => {
+            writeln!(err, "Over budget since {}", describe_run(records, run))
+        }
+        Projection::ExceededAt(run) => writeln!(
+            err,
+            "At the current trend ({:+.1} IR per run), the budget will be exceeded at run {run}, \
+             {} run(s) after the last one",
+            burn_down.slope,
+            run - (n_runs - 1)
+        ),
+        Projection::Never => writeln!(
+            err,
+            "At the current trend ({:+.1} IR per run), the budget will not be exceeded",
+            burn_down.slope
+        ),
+    }
+}
+
+/// Print the summary of the comparison of `--treatment` to `--control` into `err`.
+fn print_group_comparison(config: &Args, table: &DiffTable, err: &mut dyn Write) -> io::Result<()> {
+    let total = &table.total[table.last_column()];
+    let changed = table
+        .rows
+        .iter()
+        .filter(|row| row.cells[1].diff() != 0)
+        .collect::<Vec<_>>();
+    let significant = changed
+        .iter()
+        .filter(|row| row.cells[1].significant)
+        .count();
+    writeln!(
+        err,
+        "Treatment (runs {}) vs control (runs {}): Total IR {:+} ({:+.3}%)",
+        config.treatment.iter().join(", "),
+        config.control.iter().join(", "),
+        total.diff(),
+        total.percent()
+    )?;
+    writeln!(
+        err,
+        "  {} symbol(s) changed, {significant} significantly (by more than {} pooled standard \
+         deviation(s))",
+        changed.len(),
+        config.significance_sigmas
+    )?;
+    Ok(())
+}
+
+/// Print the symbols of `--movers` into `err`.
+fn print_movers(records: &Records, k: usize, err: &mut dyn Write) -> io::Result<()> {
+    for column in 1..records.n_runs() {
+        let total = DiffCell::exact(
+            records.runs_total_irs[column],
+            Some(records.runs_total_irs[column - 1]),
+        );
+        writeln!(
+            err,
+            "{} to {}: Total IR {:+} ({:+.3}%)",
+            describe_run(records, column - 1),
+            describe_run(records, column),
+            total.diff(),
+            total.percent()
+        )?;
+        let movers = top_movers(records, column, k);
+        let width = movers
+            .iter()
+            .map(|mover| format!("{:+}", mover.diff()).len())
+            .max()
+            .unwrap_or(0);
+        for mover in movers {
+            let cell = DiffCell::exact(mover.after, Some(mover.before));
+            writeln!(
+                err,
+                "  {:>width$} {:>9}  {}",
+                format!("{:+}", mover.diff()),
+                format!("{:+.3}%", cell.percent()),
+                mover.symbol
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Print the summary of `--suite` into `err`.
+///
+/// Ratios are those of the total IR of each version to that of the first one.
+fn print_suite_summary(summary: &SuiteSummary, err: &mut dyn Write) -> io::Result<()> {
+    const GEOMEAN: &str = "geomean";
+    const WEIGHTED: &str = "weighted geomean";
+
+    let name_width = summary
+        .benchmarks
+        .iter()
+        .map(|benchmark| benchmark.name.len())
+        .chain([GEOMEAN.len()])
+        .chain(summary.is_weighted().then_some(WEIGHTED.len()))
+        .max()
+        .unwrap_or(0);
+    // Cells are at least as wide as `1234.567x`.
+    let mut print_row = |name: &str, cells: Vec<String>| -> io::Result<()> {
+        write!(err, "{name:name_width$}")?;
+        for (version, cell) in summary.versions.iter().zip(cells) {
+            write!(err, " | {cell:>width$}", width = version.len().max(9))?;
+        }
+        writeln!(err)
+    };
+    let ratios = |ratios: &[Option<f64>]| {
+        ratios
+            .iter()
+            .map(|ratio| ratio.map_or_else(|| "-".to_string(), |ratio| format!("{ratio:.3}x")))
+            .collect()
+    };
+
+    print_row(
+        "Benchmark",
+        summary.versions.iter().map(ToString::to_string).collect(),
+    )?;
+    for benchmark in &summary.benchmarks {
+        print_row(benchmark.name, ratios(&benchmark.ratios))?;
+    }
+    print_row(GEOMEAN, ratios(&summary.geomean))?;
+    if summary.is_weighted() {
+        print_row(WEIGHTED, ratios(&summary.weighted_geomean))?;
+    }
+    Ok(())
+}
+
+/// Print the lifetimes of `--lifetimes` into `err`, marking the symbols which appeared or vanished
+/// within the last `recent` runs.
+fn print_lifetimes(records: &Records, recent: usize, err: &mut dyn Write) -> io::Result<()> {
+    let n_runs = records.n_runs();
+    let lifetimes = lifetimes(records);
+    let width = n_runs.saturating_sub(1).to_string().len();
+    writeln!(
+        err,
+        "Symbol lifetimes over {n_runs} runs (first run..last run):"
+    )?;
+    for lifetime in lifetimes {
+        let mark = match (
+            lifetime.appeared(n_runs, recent),
+            lifetime.vanished(n_runs, recent),
+        ) {
+            (true, true) => "appeared, vanished",
+            (true, false) => "appeared",
+            (false, true) => "vanished",
+            (false, false) => "",
+        };
+        writeln!(
+            err,
+            "  {:>width$}..{:<width$}  {mark:18}  {}",
+            lifetime.first, lifetime.last, lifetime.symbol
+        )?;
+    }
+    Ok(())
+}
+
+/// Describe the `i`-th run of `records` for humans, with its name and commit if known.
+fn describe_run(records: &Records, i: usize) -> String {
+    let name = &records.run_names[i];
+    let commit = records.runs_metadata[i]
+        .commit
+        .as_deref()
+        .map(|commit| format!("commit {}", commit.get(..7).unwrap_or(commit)));
+    let details = [(!name.is_empty()).then(|| name.clone()), commit]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>();
+    if details.is_empty() {
+        format!("run {i}")
+    } else {
+        format!("run {i} ({})", details.join(", "))
+    }
+}
+
+/// Render the differences into `out`, as per `--output-format`.
+///
+/// The output is buffered for the duration of the rendering.
+fn render(
+    config: &Args,
+    table: &DiffTable,
+    regressions: &[Regression],
+    out: &mut dyn Write,
+) -> Result<()> {
+    let renderers = OutputRenderers::default();
+    let Some(renderer) = renderers.find(&config.output_format) else {
+        bail!("Unknown output format {}", config.output_format);
+    };
+    let mut w = BufWriter::new(out);
+    renderer.render(table, regressions, config, &mut w)?;
+    w.flush()?;
+    Ok(())
+}
+
+/// Check that the columns referred to by the configuration exist in the records.
+fn check_columns(config: &Args, records: &Records) -> Result<()> {
+    if records.n_runs() == 0 {
+        bail!("No input run");
+    }
+    if let RelativeTo::Column(x) = &config.relative_to {
+        if (*x as usize) >= records.n_runs() {
+            bail!("--relative-to column index out of range");
+        }
+    }
+    if let RelativeTo::Pinned = &config.relative_to {
+        if !records.runs_metadata.iter().any(|metadata| metadata.pinned) {
+            bail!("--relative-to=pinned: no run is pinned (see `history pin`)");
+        }
+    }
+    if let SortByField::ColumnIR(x) = &config.sort_by.field {
+        if (*x as usize) >= records.n_runs() {
+            bail!("--sort-by column index out of range");
+        }
+    }
+    for (event, _) in config.event_fail_thresholds() {
+        if records.event_index(event).is_none() {
+            bail!("--fail-on-regression: event {event} is not shown in any input");
+        }
+    }
+    for metric in config.metrics() {
+        if let Some(event) = metric.missing_event(records) {
+            bail!("--show={metric}: event {event} is not shown in any input");
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,48 @@
+use crate::runs::Records;
+
+/// The runs in which a symbol had a non-zero IR count (see `--lifetimes`).
+pub struct Lifetime<'a> {
+    /// The name of the symbol.
+    pub symbol: &'a str,
+    /// The index of the first run in which the symbol had a non-zero IR count.
+    pub first: usize,
+    /// The index of the last run in which the symbol had a non-zero IR count.
+    pub last: usize,
+}
+
+impl Lifetime<'_> {
+    /// Return whether the symbol appeared within the last `recent` runs of `n_runs`, i.e. it had
+    /// no IR count in the runs before.
+    pub fn appeared(&self, n_runs: usize, recent: usize) -> bool {
+        self.first != 0 && self.first + recent >= n_runs
+    }
+
+    /// Return whether the symbol vanished within the last `recent` runs of `n_runs`, i.e. it has
+    /// had no IR count since.
+    pub fn vanished(&self, n_runs: usize, recent: usize) -> bool {
+        self.last + 1 != n_runs && self.last + 1 + recent >= n_runs
+    }
+}
+
+/// Return the lifetime of each symbol of `records` across its runs, in order of the first run the
+/// symbol was seen in, then of the last.
+///
+/// Symbols which never had a non-zero IR count are left out. Gaps are not reported: a symbol
+/// which vanished then reappeared lives from its first run to its last.
+pub fn lifetimes(records: &Records) -> Vec<Lifetime<'_>> {
+    let mut lifetimes = records
+        .symbols
+        .iter()
+        .filter_map(|symbol| {
+            Some(Lifetime {
+                symbol: &symbol.name,
+                first: symbol.irs.iter().position(|ir| *ir != 0)?,
+                last: symbol.irs.iter().rposition(|ir| *ir != 0)?,
+            })
+        })
+        .collect::<Vec<_>>();
+    // Names break ties so that the order does not depend on `--sort-by`.
+    lifetimes
+        .sort_unstable_by(|a, b| (a.first, a.last, a.symbol).cmp(&(b.first, b.last, b.symbol)));
+    lifetimes
+}
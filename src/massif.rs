@@ -0,0 +1,259 @@
+use std::{
+    fs::File,
+    io::{BufRead, BufReader},
+    path::Path,
+};
+
+use crate::{
+    args::{Args, MassifSnapshots, StringReplacement},
+    callgrind::{LimitedReader, LineReader},
+    code_size::first_bytes,
+    error::{Error, Result},
+    format::InputFormat,
+    runs::Run,
+};
+
+/// The unit of the counts of massif, the bytes allocated on the heap.
+pub const HEAP_UNIT: &str = "heap bytes";
+
+/// The symbol of the allocations which massif did not attribute to a site, as they were below
+/// its `--threshold`.
+pub const BELOW_THRESHOLD: &str = "(below threshold)";
+
+/// The outputs of `valgrind --tool=massif` (`massif.out.<pid>`), which hold snapshots of the heap
+/// usage of a program.
+///
+/// They are detected by their `time_unit:` header line. With `--massif-snapshots=peak`, each file
+/// holds a single sample; with `--massif-snapshots=all`, each file holds a run per detailed
+/// snapshot.
+pub struct Massif;
+
+impl InputFormat for Massif {
+    fn name(&self) -> &'static str {
+        "massif"
+    }
+
+    fn detect(&self, path: &Path) -> bool {
+        first_bytes(path).is_some_and(|start| {
+            start
+                .lines()
+                .take(3)
+                .any(|line| line.starts_with("time_unit:"))
+        })
+    }
+
+    fn is_sample(&self, config: &Args) -> bool {
+        config.massif_snapshots == MassifSnapshots::Peak
+    }
+
+    fn load(&self, path: &Path, config: &Args) -> anyhow::Result<Vec<Run>> {
+        let limits = config.input_limits();
+        let file = File::open(path).map_err(|error| Error::from(error).in_file(path))?;
+        let mut reader = LimitedReader::new(file, limits.max_size);
+        let runs = parse(
+            BufReader::new(&mut reader),
+            config.massif_snapshots,
+            &config.string_replace,
+            limits.max_symbols,
+        );
+        if reader.exceeded() {
+            return Err(Error::TooLarge {
+                path: Some(path.to_path_buf()),
+                max_size: limits.max_size.unwrap_or_default(),
+            }
+            .into());
+        }
+        let mut runs = runs.map_err(|error| error.in_file(path))?;
+        // Snapshots of several files are told apart by the name of their file.
+        if config.massif_snapshots == MassifSnapshots::All {
+            let file_name = path.file_name().map_or_else(
+                || path.display().to_string(),
+                |name| name.to_string_lossy().into_owned(),
+            );
+            for run in &mut runs {
+                run.name = format!("{file_name} {}", run.name);
+            }
+        }
+        Ok(runs)
+    }
+}
+
+/// Parse the output of massif and return runs from its detailed snapshots, in [`HEAP_UNIT`].
+///
+/// The count of each symbol is the number of bytes allocated by an allocation site, i.e. by the
+/// function which called the allocation function (e.g. `malloc` or `operator new`), from all its
+/// call sites. The file and line of the function are left out, so that sites are tracked across
+/// versions of the program. Allocations below the threshold of massif are counted in
+/// [`BELOW_THRESHOLD`]. The total of a run is the heap usage of the snapshot, extra bytes (of
+/// headers and alignment) and stacks excluded.
+///
+/// With [`MassifSnapshots::Peak`], a single unnamed run is returned, from the peak snapshot (or
+/// the detailed snapshot with the highest heap usage, if massif marked none as the peak). With
+/// [`MassifSnapshots::All`], a run is returned per detailed snapshot, named after its number
+/// (e.g. `snapshot 12`, or `snapshot 40 (peak)`). Snapshots which are not detailed have no
+/// allocation site, and are left out.
+///
+/// Errors have no path; see [`Error::in_file`].
+///
+/// # Errors
+/// Returns an error if the input cannot be read, has no detailed snapshot, a line of a heap tree
+/// is not well-formed or a snapshot has more than `max_symbols` allocation sites.
+pub fn parse<R: BufRead>(
+    input: R,
+    snapshots: MassifSnapshots,
+    replacements: &[StringReplacement],
+    max_symbols: Option<usize>,
+) -> Result<Vec<Run>> {
+    let mut lines = LineReader::new(input);
+    let mut command = None;
+    // The number and the heap usage of the current snapshot.
+    let mut snapshot = (0, 0);
+    // The detailed snapshots, with whether they are the peak.
+    let mut runs: Vec<(Run, bool)> = vec![];
+    // Whether the lines being read are those of the heap tree of the current snapshot.
+    let mut in_tree = false;
+
+    while let Some((number, line)) = lines.next_line()? {
+        let parse_error = |message: String| Error::Parse {
+            path: None,
+            line: number,
+            message,
+        };
+        if in_tree {
+            if let Some(node) = line.strip_prefix(' ') {
+                // Only the children of the root are allocation sites.
+                if node.starts_with(' ') {
+                    continue;
+                }
+                let (bytes, site) = parse_node(node).map_err(parse_error)?;
+                let name = replacements
+                    .iter()
+                    .fold(site.into(), |name, replacement| replacement.perform(name));
+                let Some((run, _)) = runs.last_mut() else {
+                    continue;
+                };
+                run.add_ir(&name, bytes);
+                if let Some(max) = max_symbols {
+                    if run.symbols.len() > max {
+                        return Err(Error::TooManySymbols {
+                            path: None,
+                            max_symbols: max,
+                        });
+                    }
+                }
+                continue;
+            }
+            if line.starts_with('n') {
+                // The root, which holds the heap usage of the snapshot.
+                continue;
+            }
+            in_tree = false;
+        }
+        let Some((key, value)) = line.split_once(['=', ':']) else {
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            return Err(parse_error(format!("Unexpected line {line:?}")));
+        };
+        let value = value.trim();
+        match key {
+            "cmd" => command = Some(value.to_string()),
+            "snapshot" => {
+                let Ok(number) = value.parse::<u64>() else {
+                    return Err(parse_error(format!("Invalid snapshot {value:?}")));
+                };
+                snapshot = (number, 0);
+            }
+            "mem_heap_B" => {
+                let Ok(heap) = value.parse::<u64>() else {
+                    return Err(parse_error(format!("Invalid heap usage {value:?}")));
+                };
+                snapshot.1 = heap;
+            }
+            "heap_tree" if value == "detailed" || value == "peak" => {
+                let (number, heap) = snapshot;
+                let is_peak = value == "peak";
+                let mut run = Run::new_named(if is_peak {
+                    format!("snapshot {number} (peak)")
+                } else {
+                    format!("snapshot {number}")
+                });
+                run.total_ir = heap;
+                runs.push((run, is_peak));
+                in_tree = true;
+            }
+            _ => {}
+        }
+    }
+
+    let mut runs = select(runs, snapshots);
+    if runs.is_empty() {
+        return Err(Error::FormatMismatch {
+            path: None,
+            expected: "a massif output",
+            reason: "No detailed snapshot".to_string(),
+        });
+    }
+    for run in &mut runs {
+        run.metadata.command.clone_from(&command);
+        run.metadata.unit = Some(HEAP_UNIT.to_string());
+    }
+    Ok(runs)
+}
+
+/// Select the runs of `snapshots` among the detailed snapshots `runs`, which tell whether they
+/// are the peak, as per [`parse`].
+fn select(mut runs: Vec<(Run, bool)>, snapshots: MassifSnapshots) -> Vec<Run> {
+    match snapshots {
+        MassifSnapshots::All => runs.into_iter().map(|(run, _)| run).collect(),
+        MassifSnapshots::Peak => {
+            let peak = runs.iter().position(|(_, is_peak)| *is_peak).or_else(|| {
+                // The first of the snapshots with the highest heap usage.
+                runs.iter()
+                    .enumerate()
+                    .rev()
+                    .max_by_key(|(_, (run, _))| run.total_ir)
+                    .map(|(i, _)| i)
+            });
+            peak.map(|i| {
+                let mut run = runs.swap_remove(i).0;
+                run.name.clear();
+                run
+            })
+            .into_iter()
+            .collect()
+        }
+    }
+}
+
+/// Parse a node of a heap tree, without its indentation, into its number of bytes and its
+/// allocation site.
+///
+/// Nodes are e.g. `n2: 600 0x4005A1: parse (parser.c:42)`, whose site is `parse`, or
+/// `n0: 120 in 3 places, all below massif's threshold (1.00%)`, whose site is
+/// [`BELOW_THRESHOLD`].
+fn parse_node(node: &str) -> std::result::Result<(u64, &str), String> {
+    let Some((bytes, description)) = node
+        .strip_prefix('n')
+        .and_then(|node| node.split_once(": "))
+        .and_then(|(_, rest)| rest.split_once(' '))
+    else {
+        return Err(format!("Invalid heap tree node {node:?}"));
+    };
+    let Ok(bytes) = bytes.parse::<u64>() else {
+        return Err(format!("Invalid count {bytes:?}"));
+    };
+    if description.contains("below massif's threshold") {
+        return Ok((bytes, BELOW_THRESHOLD));
+    }
+    let function = description
+        .strip_prefix("0x")
+        .and_then(|description| description.split_once(": "))
+        .map_or(description, |(_, function)| function);
+    // Leave out the location, e.g. `(parser.c:42)` or `(in /usr/lib/libfoo.so)`.
+    let function = function
+        .strip_suffix(')')
+        .and_then(|function| function.rsplit_once(" ("))
+        .map_or(function, |(function, _)| function);
+    Ok((bytes, function))
+}
@@ -0,0 +1,177 @@
+use std::{fmt::Display, ops::Range};
+
+use crate::{
+    args::CycleModel,
+    callgrind::IR_EVENT,
+    runs::{Records, RecordsSymbol},
+};
+
+/// The name of the estimated cycles event (see [`add_estimated_cycles`]).
+pub const CYCLES_EVENT: &str = "CEst";
+
+/// The events whose counts make up the first-level cache misses of [`CycleModel::l1`].
+const L1_MISS_EVENTS: [&str; 3] = ["I1mr", "D1mr", "D1mw"];
+
+/// The events whose counts make up the last-level cache misses of [`CycleModel::ll`].
+const LL_MISS_EVENTS: [&str; 3] = ["ILmr", "DLmr", "DLmw"];
+
+/// The events whose counts make up the branch mispredictions of [`CycleModel::bm`].
+const BRANCH_MISS_EVENTS: [&str; 2] = ["Bcm", "Bim"];
+
+/// A rate computed from the counts of several events (see `--show`).
+///
+/// A rate is the ratio of the sum of the counts of the events of [`Self::numerator`] to that of
+/// the events of [`Self::denominator`], in percent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Metric {
+    /// The rate of data reads and writes which missed the first-level data cache.
+    D1MissRate,
+    /// The rate of instruction fetches, data reads and writes which missed the last-level cache.
+    LLMissRate,
+    /// The rate of conditional and indirect branches which were mispredicted.
+    BranchMissRate,
+}
+
+impl Metric {
+    /// Return the name of the metric, as given to `--show`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::D1MissRate => "d1-miss-rate",
+            Self::LLMissRate => "ll-miss-rate",
+            Self::BranchMissRate => "branch-miss-rate",
+        }
+    }
+
+    /// Return the events whose counts are summed into the numerator of the rate.
+    pub fn numerator(self) -> &'static [&'static str] {
+        match self {
+            Self::D1MissRate => &["D1mr", "D1mw"],
+            Self::LLMissRate => &["ILmr", "DLmr", "DLmw"],
+            Self::BranchMissRate => &["Bcm", "Bim"],
+        }
+    }
+
+    /// Return the events whose counts are summed into the denominator of the rate.
+    pub fn denominator(self) -> &'static [&'static str] {
+        match self {
+            Self::D1MissRate => &["Dr", "Dw"],
+            Self::LLMissRate => &[IR_EVENT, "Dr", "Dw"],
+            Self::BranchMissRate => &["Bc", "Bi"],
+        }
+    }
+
+    /// Return the first event needed for the rate which is not in `records`, if any.
+    pub fn missing_event(self, records: &Records) -> Option<&'static str> {
+        self.numerator()
+            .iter()
+            .chain(self.denominator())
+            .copied()
+            .find(|event| *event != IR_EVENT && records.event_index(event).is_none())
+    }
+
+    /// Return the rate for `symbol` (or for the whole program if `None`) over the runs of
+    /// `columns`, in percent.
+    ///
+    /// Counts of the runs are summed before the rate is computed, so that it can be compared to
+    /// the rate of a reference made of several runs (see `--relative-to=rolling:N`). Returns
+    /// `None` if an event is missing from `records` or if the denominator is 0.
+    pub fn rate(
+        self,
+        records: &Records,
+        symbol: Option<&RecordsSymbol>,
+        columns: Range<usize>,
+    ) -> Option<f64> {
+        let sum = |events: &[&str]| {
+            events
+                .iter()
+                .map(|event| count_of(records, symbol, event, columns.clone()))
+                .sum::<Option<u64>>()
+        };
+        let denominator = sum(self.denominator())?;
+        if denominator == 0 {
+            return None;
+        }
+        Some(sum(self.numerator())? as f64 * 100.0 / denominator as f64)
+    }
+}
+
+impl Display for Metric {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Add the estimated cycles event ([`CYCLES_EVENT`]) to `records`, computed with `model` (see
+/// `--cycle-model`).
+///
+/// The estimate of a symbol (or of the whole program) in a run is the weighted sum of its
+/// instruction count, cache misses and branch mispredictions. Events which are not in `records`
+/// count as 0. If `records` already has the event, its counts are replaced.
+pub fn add_estimated_cycles(records: &mut Records, model: &CycleModel) {
+    let weighted = [
+        (&L1_MISS_EVENTS[..], model.l1),
+        (&LL_MISS_EVENTS[..], model.ll),
+        (&BRANCH_MISS_EVENTS[..], model.bm),
+    ]
+    .into_iter()
+    .flat_map(|(events, weight)| {
+        events
+            .iter()
+            .filter_map(|event| records.event_index(event))
+            .map(move |index| (index, weight))
+    })
+    .collect::<Vec<_>>();
+    let estimate = |irs: &[u64], events: &[Vec<u64>]| {
+        (0..irs.len())
+            .map(|run| {
+                weighted.iter().fold(
+                    irs[run].saturating_mul(model.ir),
+                    |cycles, (index, weight)| {
+                        cycles.saturating_add(events[*index][run].saturating_mul(*weight))
+                    },
+                )
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let totals = estimate(&records.runs_total_irs, &records.runs_event_totals);
+    let counts = records
+        .symbols
+        .iter()
+        .map(|symbol| estimate(&symbol.irs, &symbol.events))
+        .collect::<Vec<_>>();
+    let index = records.event_index(CYCLES_EVENT).unwrap_or_else(|| {
+        records.events.push(CYCLES_EVENT.to_string());
+        records.runs_event_totals.push(vec![]);
+        records.runs_event_recorded.push(vec![]);
+        for symbol in &mut records.symbols {
+            symbol.events.push(vec![]);
+        }
+        records.events.len() - 1
+    });
+    records.runs_event_totals[index] = totals;
+    // Cycles are estimated for every run.
+    records.runs_event_recorded[index] = vec![true; records.run_names.len()];
+    for (symbol, counts) in records.symbols.iter_mut().zip(counts) {
+        symbol.events[index] = counts;
+    }
+}
+
+/// Return the sum of the counts of `event` for `symbol` (or for the whole program if `None`)
+/// over the runs of `columns`, or `None` if the event is not in `records`.
+fn count_of(
+    records: &Records,
+    symbol: Option<&RecordsSymbol>,
+    event: &str,
+    columns: Range<usize>,
+) -> Option<u64> {
+    let counts = if event == IR_EVENT {
+        symbol.map_or(&records.runs_total_irs, |symbol| &symbol.irs)
+    } else {
+        let index = records.event_index(event)?;
+        symbol.map_or(&records.runs_event_totals[index], |symbol| {
+            &symbol.events[index]
+        })
+    };
+    Some(counts[columns].iter().sum())
+}
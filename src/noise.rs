@@ -0,0 +1,141 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::Path,
+};
+
+use anyhow::{bail, Context, Result};
+
+use crate::{diff::DiffTable, runs::Records};
+
+/// The file the noise floor is written to by `calibrate` and read from by `--noise-floor=auto`.
+pub const DEFAULT_NOISE_FLOOR_FILE: &str = "callgrind-noise-floor.txt";
+
+/// The noise of the IR count of each symbol, measured over repeated runs of the same binary (see
+/// `calibrate`).
+///
+/// A noise floor is stored in a file where each line is the standard deviation and the spread of
+/// the IR count of a symbol, followed by its name:
+/// ```no_compile
+/// # Lines starting with a `#` are comments.
+/// 1204.7 3100 yaml_rust2::scanner::Scanner<T>::fetch_more_tokens
+/// 0 0 yaml_rust2::parser::Parser<T>::next_token
+/// ```
+#[derive(Default, Debug)]
+pub struct NoiseFloor {
+    /// The noise of each symbol, by name.
+    pub symbols: HashMap<String, SymbolNoise>,
+}
+
+/// The noise of the IR count of a single symbol of a [`NoiseFloor`].
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
+pub struct SymbolNoise {
+    /// The standard deviation of the IR count across the runs.
+    pub stddev: f64,
+    /// The difference between the highest and the lowest IR count across the runs.
+    pub spread: u64,
+}
+
+impl NoiseFloor {
+    /// Measure the noise of each symbol across the runs of `records`, which are repeated runs of
+    /// the same binary.
+    pub fn calibrate(records: &Records) -> Self {
+        let symbols = records
+            .symbols
+            .iter()
+            .map(|symbol| {
+                let stats = symbol.ir_stats();
+                let noise = SymbolNoise {
+                    stddev: stats.stddev,
+                    spread: stats.max - stats.min,
+                };
+                (symbol.name.to_string(), noise)
+            })
+            .collect();
+        Self { symbols }
+    }
+
+    /// Load a noise floor from a file.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be read or is malformed.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let file =
+            File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+        Self::parse(BufReader::new(file))
+            .with_context(|| format!("Failed to parse noise floor {}", path.display()))
+    }
+
+    /// Parse a noise floor from the format described in [`NoiseFloor`].
+    ///
+    /// # Errors
+    /// Returns an error if the input cannot be read or a line is malformed.
+    pub fn parse<R: BufRead>(input: R) -> Result<Self> {
+        let mut floor = Self::default();
+        for (i, line) in input.lines().enumerate() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut words = line.splitn(3, ' ');
+            let (Some(stddev), Some(spread), Some(symbol)) =
+                (words.next(), words.next(), words.next())
+            else {
+                bail!("Line {}: expected `<stddev> <spread> <symbol>`", i + 1);
+            };
+            let stddev = match stddev.parse::<f64>() {
+                Ok(stddev) if stddev.is_finite() && stddev >= 0.0 => stddev,
+                _ => bail!("Line {}: invalid standard deviation {stddev}", i + 1),
+            };
+            let Ok(spread) = spread.parse::<u64>() else {
+                bail!("Line {}: invalid spread {spread}", i + 1);
+            };
+            floor
+                .symbols
+                .insert(symbol.to_string(), SymbolNoise { stddev, spread });
+        }
+        Ok(floor)
+    }
+
+    /// Write the noise floor to a file, in the format described in [`NoiseFloor`].
+    ///
+    /// Symbols are written in lexicographic order, so that calibrations can be compared.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be written.
+    pub fn write_to_file<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let file =
+            File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+        let mut w = BufWriter::new(file);
+        let mut symbols = self.symbols.iter().collect::<Vec<_>>();
+        symbols.sort_unstable_by(|a, b| a.0.cmp(b.0));
+        writeln!(w, "# <stddev> <spread> <symbol>")?;
+        for (symbol, noise) in symbols {
+            writeln!(w, "{:.1} {} {symbol}", noise.stddev, noise.spread)?;
+        }
+        w.flush()
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Mark the changes of `table` which are within the noise floor as noise, as the noise band
+    /// does (see `--noise-band`).
+    ///
+    /// A change is within the noise floor of its symbol if it is no larger than the spread of the
+    /// symbol, or than `sigmas` times its standard deviation. Symbols which were not calibrated
+    /// keep their noise band.
+    pub fn apply(&self, table: &mut DiffTable, sigmas: f64) {
+        for row in &mut table.rows {
+            let Some(noise) = self.symbols.get(&*row.symbol.name) else {
+                continue;
+            };
+            let floor = (noise.spread as f64).max(sigmas * noise.stddev);
+            for cell in row.cells.iter_mut().filter(|cell| cell.reference.is_some()) {
+                cell.noisy |= cell.abs_diff() as f64 <= floor;
+            }
+        }
+    }
+}
@@ -0,0 +1,114 @@
+use std::{
+    fs::File,
+    io::{BufRead, BufReader},
+    path::Path,
+};
+
+use anyhow::{bail, Context, Result};
+
+use crate::args::Percentage;
+
+/// A set of rules mapping symbol patterns to allowed regressions.
+///
+/// Rules are read from a file where each line is a pattern followed by the maximum regression
+/// (as a percentage) allowed for the symbols it matches:
+/// ```no_compile
+/// # Lines starting with a `#` are comments.
+/// parser::*           1%
+/// <* as core::fmt::Debug>::fmt 10%
+/// *                   5%
+/// ```
+/// In patterns, `*` matches any sequence of characters and `?` matches any single character.
+/// Patterns may contain spaces; the last word of the line is the percentage. Rules are evaluated
+/// in order and the first matching rule applies.
+#[derive(Default, Debug)]
+pub struct Policy {
+    /// The rules, in the order in which they are evaluated.
+    pub rules: Vec<Rule>,
+}
+
+/// A single rule of a [`Policy`].
+#[derive(Debug)]
+pub struct Rule {
+    /// The pattern the symbol names must match.
+    pub pattern: String,
+    /// The maximum regression the matching symbols are allowed.
+    pub max_regression: Percentage,
+}
+
+impl Policy {
+    /// Load a policy from a rules file.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be read or is malformed.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let file =
+            File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+        Self::parse(BufReader::new(file))
+            .with_context(|| format!("Failed to parse rules file {}", path.display()))
+    }
+
+    /// Parse a policy from the format described in [`Policy`].
+    ///
+    /// # Errors
+    /// Returns an error if the input cannot be read or a line is malformed.
+    pub fn parse<R: BufRead>(input: R) -> Result<Self> {
+        let mut rules = vec![];
+        for (i, line) in input.lines().enumerate() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((pattern, max_regression)) = line.rsplit_once(char::is_whitespace) else {
+                bail!("Line {}: expected `<pattern> <percentage>`", i + 1);
+            };
+            rules.push(Rule {
+                pattern: pattern.trim_end().to_string(),
+                max_regression: max_regression
+                    .parse()
+                    .with_context(|| format!("Line {}", i + 1))?,
+            });
+        }
+        Ok(Self { rules })
+    }
+
+    /// Return the first rule that matches the given symbol, if any.
+    pub fn rule_for(&self, symbol: &str) -> Option<&Rule> {
+        self.rules
+            .iter()
+            .find(|rule| glob_match(&rule.pattern, symbol))
+    }
+}
+
+/// Return whether `s` matches the glob `pattern`.
+///
+/// `*` matches any (possibly empty) sequence of characters, `?` matches exactly one character.
+/// Every other character matches itself.
+pub fn glob_match(pattern: &str, s: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let s = s.chars().collect::<Vec<_>>();
+    let (mut p, mut i) = (0, 0);
+    // Position of the last `*` in the pattern and of the character of `s` it was tried against.
+    let mut backtrack = None;
+
+    while i < s.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == s[i]) {
+            p += 1;
+            i += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, i));
+            p += 1;
+        } else if let Some((star_p, star_i)) = backtrack {
+            // Have the last `*` swallow one more character and retry.
+            p = star_p + 1;
+            i = star_i + 1;
+            backtrack = Some((star_p, star_i + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
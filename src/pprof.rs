@@ -0,0 +1,426 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufReader, Read},
+    path::Path,
+};
+
+use crate::{
+    args::{Args, StringReplacement},
+    callgrind::LimitedReader,
+    callgrind_out::sum_self_costs,
+    error::{Error, Result},
+    format::InputFormat,
+    gzip,
+    runs::Run,
+};
+
+/// The key of the `sample_type` field of a profile, its first field: field 1, length-delimited.
+const SAMPLE_TYPE_KEY: u64 = 0x0a;
+
+/// The number of bytes of a file read to detect a profile, compressed or not.
+const DETECTED_PREFIX_SIZE: u64 = 4096;
+
+/// The number of bytes of an uncompressed profile enough to hold its first `sample_type`, of two
+/// integers.
+const SAMPLE_TYPE_PREFIX_SIZE: usize = 32;
+
+/// `pprof` protobuf profiles (`profile.proto`), as written by Go's `runtime/pprof` and
+/// `net/http/pprof`, or by `pprof -proto` from other profiles (e.g. those of gperftools), each of
+/// which holds a single sample.
+///
+/// They are detected by the start of their contents, gzipped or not, which must be the first
+/// `sample_type` of a profile. Only a prefix of the file is read (and decompressed) to detect it.
+pub struct Pprof;
+
+impl InputFormat for Pprof {
+    fn name(&self) -> &'static str {
+        "pprof"
+    }
+
+    fn detect(&self, path: &Path) -> bool {
+        let Ok(file) = File::open(path) else {
+            return false;
+        };
+        let mut start = vec![];
+        // The file may be longer than the prefix: only reading it may fail.
+        if file
+            .take(DETECTED_PREFIX_SIZE)
+            .read_to_end(&mut start)
+            .is_err()
+        {
+            return false;
+        }
+        if start.starts_with(&gzip::MAGIC) {
+            gzip::decompress_prefix(&start, SAMPLE_TYPE_PREFIX_SIZE)
+                .is_ok_and(|start| starts_as_profile(&start))
+        } else {
+            starts_as_profile(&start)
+        }
+    }
+
+    fn is_sample(&self, _config: &Args) -> bool {
+        true
+    }
+
+    fn load(&self, path: &Path, config: &Args) -> anyhow::Result<Vec<Run>> {
+        let limits = config.input_limits();
+        let profile = read(path, limits.max_size)?;
+        let run = parse(
+            &profile,
+            config.pprof_sample_type.as_deref(),
+            &config.string_replace,
+            limits.max_symbols,
+        );
+        Ok(vec![run.map_err(|error| error.in_file(path))?])
+    }
+}
+
+/// Read the profile at `path`, decompressing it if it is gzipped. Both the file and the profile
+/// must be at most `max_size` bytes, if limited.
+///
+/// # Errors
+/// Returns an error if the file cannot be read or decompressed, or is too large.
+fn read(path: &Path, max_size: Option<u64>) -> Result<Vec<u8>> {
+    let file = File::open(path).map_err(|error| Error::from(error).in_file(path))?;
+    let mut reader = LimitedReader::new(BufReader::new(file), max_size);
+    let mut data = vec![];
+    let read = reader.read_to_end(&mut data);
+    if reader.exceeded() {
+        return Err(Error::TooLarge {
+            path: Some(path.to_path_buf()),
+            max_size: max_size.unwrap_or_default(),
+        });
+    }
+    read.map_err(|error| Error::from(error).in_file(path))?;
+    if data.starts_with(&gzip::MAGIC) {
+        data = gzip::decompress(&data, max_size).map_err(|error| error.in_file(path))?;
+    }
+    Ok(data)
+}
+
+/// Parse an uncompressed `pprof` profile and return a `Run` from it, with the flat view of the
+/// profile.
+///
+/// The count of each function is the sum of the values of `sample_type` of the samples whose leaf
+/// is in the function, i.e. its self cost. The other sample types are stored as events in
+/// [`Run::events`], named after their type. Without a `sample_type`, the default sample type of
+/// the profile is used (or the last one, as `pprof` itself does). The unit of the run is that of
+/// the sample type (e.g. `nanoseconds` for `cpu`).
+///
+/// Locations which were not symbolized are named after their address (e.g. `0x4a3f20`), and
+/// negative values (of profiles which were subtracted from others) count as 0.
+///
+/// Errors have no path; see [`Error::in_file`].
+///
+/// # Errors
+/// Returns an error if the input is not a profile, has no `sample_type` samples or has more than
+/// `max_symbols` functions.
+pub fn parse(
+    input: &[u8],
+    sample_type: Option<&str>,
+    replacements: &[StringReplacement],
+    max_symbols: Option<usize>,
+) -> Result<Run> {
+    let profile = Profile::decode(input)?;
+    let types = profile
+        .sample_types
+        .iter()
+        .map(|(kind, _)| profile.string(*kind))
+        .collect::<Vec<_>>();
+    let index = match sample_type {
+        Some(sample_type) => types.iter().position(|kind| *kind == sample_type),
+        None => (profile.default_sample_type != 0)
+            .then(|| profile.string(profile.default_sample_type))
+            .and_then(|default| types.iter().position(|kind| *kind == default))
+            .or(types.len().checked_sub(1)),
+    };
+    let Some(index) = index else {
+        return Err(mismatch(match sample_type {
+            Some(sample_type) => {
+                format!("No sample type {sample_type:?} (got {types:?}, see `--pprof-sample-type`)")
+            }
+            None => "No sample type".to_string(),
+        }));
+    };
+
+    let mut run = Run::new();
+    run.metadata.unit = Some(profile.string(profile.sample_types[index].1).to_string());
+    run.metadata.timestamp = (profile.time_nanos > 0).then_some(profile.time_nanos / 1_000_000_000);
+    run.events = types
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| *i != index)
+        .map(|(_, kind)| (*kind).to_string())
+        .collect();
+    let mut names = HashMap::new();
+    let mut counts = vec![];
+    for (locations, values) in &profile.samples {
+        let Some(leaf) = locations.first() else {
+            continue;
+        };
+        let name = names
+            .entry(*leaf)
+            .or_insert_with(|| {
+                let name = profile.location_name(*leaf);
+                replacements
+                    .iter()
+                    .fold(name.into(), |name, replacement| replacement.perform(name))
+                    .into_owned()
+            })
+            .as_str();
+        // Values are `int64`s.
+        let value = |i: usize| {
+            values
+                .get(i)
+                .map_or(0, |value| value.cast_signed().max(0).cast_unsigned())
+        };
+        counts.clear();
+        counts.extend((0..types.len()).filter(|i| *i != index).map(value));
+        run.add_counts(name, value(index), &counts);
+        if let Some(max) = max_symbols {
+            if run.symbols.len() > max {
+                return Err(Error::TooManySymbols {
+                    path: None,
+                    max_symbols: max,
+                });
+            }
+        }
+    }
+    sum_self_costs(&mut run);
+    Ok(run)
+}
+
+/// The parts of a decoded profile which make up its flat view.
+#[derive(Default)]
+struct Profile {
+    /// The type and the unit of each value of the samples, as indices in `strings`.
+    sample_types: Vec<(u64, u64)>,
+    /// The locations of each sample, starting from the leaf, and its values.
+    samples: Vec<(Vec<u64>, Vec<u64>)>,
+    /// The address of each location and the id of the function of its innermost line, if it
+    /// was symbolized, by id.
+    locations: HashMap<u64, (u64, Option<u64>)>,
+    /// The name of each function, as an index in `strings`, by id.
+    functions: HashMap<u64, u64>,
+    /// The strings the other fields refer to.
+    strings: Vec<String>,
+    /// When the profile was recorded, in nanoseconds since the Unix epoch, or 0.
+    time_nanos: u64,
+    /// The type of the sample type shown by default, as an index in `strings`, or 0.
+    default_sample_type: u64,
+}
+
+impl Profile {
+    /// Decode a `Profile` message.
+    ///
+    /// # Errors
+    /// Returns an error if the input is not a `Profile` message.
+    fn decode(input: &[u8]) -> Result<Self> {
+        let mut profile = Self::default();
+        for field in Fields(input) {
+            match field? {
+                (1, Value::Bytes(message)) => {
+                    let (mut kind, mut unit) = (0, 0);
+                    for field in Fields(message) {
+                        match field? {
+                            (1, Value::Varint(value)) => kind = value,
+                            (2, Value::Varint(value)) => unit = value,
+                            _ => {}
+                        }
+                    }
+                    profile.sample_types.push((kind, unit));
+                }
+                (2, Value::Bytes(message)) => {
+                    let (mut locations, mut values) = (vec![], vec![]);
+                    for field in Fields(message) {
+                        match field? {
+                            (1, value) => value.push_varints(&mut locations)?,
+                            (2, value) => value.push_varints(&mut values)?,
+                            _ => {}
+                        }
+                    }
+                    profile.samples.push((locations, values));
+                }
+                (4, Value::Bytes(message)) => {
+                    let (mut id, mut address, mut function) = (0, 0, None);
+                    for field in Fields(message) {
+                        match field? {
+                            (1, Value::Varint(value)) => id = value,
+                            (3, Value::Varint(value)) => address = value,
+                            // Inlined functions come first: the innermost is the leaf.
+                            (4, Value::Bytes(line)) if function.is_none() => {
+                                for field in Fields(line) {
+                                    if let (1, Value::Varint(value)) = field? {
+                                        function = Some(value);
+                                    }
+                                }
+                            }
+                            _ => {}
+                        }
+                    }
+                    profile.locations.insert(id, (address, function));
+                }
+                (5, Value::Bytes(message)) => {
+                    let (mut id, mut name) = (0, 0);
+                    for field in Fields(message) {
+                        match field? {
+                            (1, Value::Varint(value)) => id = value,
+                            (2, Value::Varint(value)) => name = value,
+                            _ => {}
+                        }
+                    }
+                    profile.functions.insert(id, name);
+                }
+                (6, Value::Bytes(string)) => profile
+                    .strings
+                    .push(String::from_utf8_lossy(string).into_owned()),
+                (9, Value::Varint(value)) => profile.time_nanos = value,
+                (14, Value::Varint(value)) => profile.default_sample_type = value,
+                _ => {}
+            }
+        }
+        if profile
+            .strings
+            .first()
+            .is_some_and(|first| !first.is_empty())
+        {
+            return Err(mismatch("The first string is not empty".to_string()));
+        }
+        let n_strings = profile.strings.len() as u64;
+        if profile
+            .sample_types
+            .iter()
+            .any(|(kind, unit)| *kind >= n_strings || *unit >= n_strings)
+        {
+            return Err(mismatch("Invalid sample type".to_string()));
+        }
+        Ok(profile)
+    }
+
+    /// Return the string at `index`, or an empty string if there is none.
+    fn string(&self, index: u64) -> &str {
+        usize::try_from(index)
+            .ok()
+            .and_then(|index| self.strings.get(index))
+            .map_or("", String::as_str)
+    }
+
+    /// Return the name of the function of the location with the given id, or its address if it
+    /// was not symbolized.
+    fn location_name(&self, id: u64) -> String {
+        let (address, function) = self.locations.get(&id).copied().unwrap_or_default();
+        function
+            .and_then(|function| self.functions.get(&function))
+            .map(|name| self.string(*name))
+            .filter(|name| !name.is_empty())
+            .map_or_else(|| format!("{address:#x}"), ToString::to_string)
+    }
+}
+
+/// The value of a field of a protobuf message.
+enum Value<'a> {
+    /// An integer.
+    Varint(u64),
+    /// A length-delimited value: a string, a message or packed integers.
+    Bytes(&'a [u8]),
+    /// A fixed-size value, which no field of the flat view has.
+    Fixed,
+}
+
+impl Value<'_> {
+    /// Push the integers of a repeated integer field, packed or not, into `values`.
+    fn push_varints(self, values: &mut Vec<u64>) -> Result<()> {
+        match self {
+            Self::Varint(value) => values.push(value),
+            Self::Bytes(mut packed) => {
+                while !packed.is_empty() {
+                    values.push(varint(&mut packed)?);
+                }
+            }
+            Self::Fixed => return Err(mismatch("Invalid integer field".to_string())),
+        }
+        Ok(())
+    }
+}
+
+/// The fields of a protobuf message, with their numbers.
+struct Fields<'a>(&'a [u8]);
+
+impl<'a> Iterator for Fields<'a> {
+    type Item = Result<(u64, Value<'a>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.0.is_empty() {
+            return None;
+        }
+        let field = (|| {
+            let key = varint(&mut self.0)?;
+            let value = match key & 7 {
+                0 => Value::Varint(varint(&mut self.0)?),
+                1 | 5 => {
+                    let size = if key & 7 == 1 { 8 } else { 4 };
+                    self.0 = self.0.get(size..).ok_or_else(truncated)?;
+                    Value::Fixed
+                }
+                2 => {
+                    let length = usize::try_from(varint(&mut self.0)?).map_err(|_| truncated())?;
+                    let bytes = self.0.get(..length).ok_or_else(truncated)?;
+                    self.0 = &self.0[length..];
+                    Value::Bytes(bytes)
+                }
+                _ => return Err(mismatch(format!("Invalid wire type in key {key}"))),
+            };
+            Ok((key >> 3, value))
+        })();
+        if field.is_err() {
+            // Stop at the first error.
+            self.0 = &[];
+        }
+        Some(field)
+    }
+}
+
+/// Return whether `start`, the start of an uncompressed input, is that of a profile: the first
+/// `sample_type` field, whose `ValueType` message holds integers only.
+fn starts_as_profile(mut start: &[u8]) -> bool {
+    if varint(&mut start).ok() != Some(SAMPLE_TYPE_KEY) {
+        return false;
+    }
+    let Some(message) = varint(&mut start)
+        .ok()
+        .and_then(|length| usize::try_from(length).ok())
+        .and_then(|length| start.get(..length))
+    else {
+        return false;
+    };
+    Fields(message).all(|field| matches!(field, Ok((1 | 2, Value::Varint(_)))))
+}
+
+/// Read a variable-length integer from the start of `input`.
+fn varint(input: &mut &[u8]) -> Result<u64> {
+    let mut value = 0;
+    for shift in (0..64).step_by(7) {
+        let (byte, rest) = input.split_first().ok_or_else(truncated)?;
+        *input = rest;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(mismatch("Invalid integer".to_string()))
+}
+
+/// Return the error of a message which ends in the middle of a field.
+fn truncated() -> Error {
+    mismatch("Truncated message".to_string())
+}
+
+/// Return the error of an input which is not a profile.
+fn mismatch(reason: String) -> Error {
+    Error::FormatMismatch {
+        path: None,
+        expected: "a `pprof` profile",
+        reason,
+    }
+}
@@ -0,0 +1,78 @@
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+
+/// The prefix of the keys of the configuration file of a store which declare presets.
+const PRESET_KEY_PREFIX: &str = "preset.";
+
+/// A named set of arguments, declared in the configuration file of a history store so that a
+/// whole team runs identical comparisons (see `--preset`).
+///
+/// Each preset is a `preset.<name>: <arguments>` line of the file, whose arguments are separated
+/// by whitespace:
+/// ```no_compile
+/// preset.release-check: --relative-to=pinned --show=percentagediff --fail-on-regression=2%
+/// ```
+/// Other keys are those of the [`crate::retention::Retention`] and [`crate::hook::Hooks`] of the
+/// store.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Preset {
+    /// The name of the preset.
+    pub name: String,
+    /// The arguments the preset stands for.
+    pub args: Vec<String>,
+}
+
+impl Preset {
+    /// Read the presets from a configuration file. A missing file declares none.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be read or a preset is not well-formed.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Vec<Self>> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(vec![]);
+        }
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        Self::parse(&contents).with_context(|| format!("Invalid preset in {}", path.display()))
+    }
+
+    /// Parse the presets from the contents of a configuration file.
+    ///
+    /// # Errors
+    /// Returns an error if a preset has no name or no arguments, or is declared twice.
+    pub fn parse(contents: &str) -> Result<Vec<Self>> {
+        let mut presets: Vec<Self> = vec![];
+        for (i, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.starts_with('#') {
+                continue;
+            }
+            let Some((name, args)) = line
+                .split_once(':')
+                .and_then(|(key, args)| Some((key.trim().strip_prefix(PRESET_KEY_PREFIX)?, args)))
+            else {
+                continue;
+            };
+            if name.is_empty() {
+                bail!("Line {}: expected `preset.<name>: <arguments>`", i + 1);
+            }
+            if presets.iter().any(|preset| preset.name == name) {
+                bail!("Line {}: preset {name} is declared twice", i + 1);
+            }
+            let args = args
+                .split_whitespace()
+                .map(str::to_string)
+                .collect::<Vec<_>>();
+            if args.is_empty() {
+                bail!("Line {}: preset {name} has no arguments", i + 1);
+            }
+            presets.push(Self {
+                name: name.to_string(),
+                args,
+            });
+        }
+        Ok(presets)
+    }
+}
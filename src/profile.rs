@@ -0,0 +1,111 @@
+use std::{path::Path, process::Command};
+
+use anyhow::{bail, Context, Result};
+
+use crate::args::CallgrindOptions;
+
+/// The environment variable naming the valgrind program to run, `valgrind` by default.
+pub const VALGRIND_ENV_VAR: &str = "VALGRIND";
+
+/// Return the valgrind program to run, as per [`VALGRIND_ENV_VAR`].
+pub fn valgrind() -> String {
+    std::env::var(VALGRIND_ENV_VAR).unwrap_or_else(|_| "valgrind".to_string())
+}
+
+/// Return the arguments of callgrind for `options`, those left to their default excluded.
+///
+/// ```
+/// # use callgrind_differ::{args::CallgrindOptions, profile::callgrind_args};
+/// let options = CallgrindOptions {
+///     cache_sim: true,
+///     toggle_collect: vec!["bench::*".to_string()],
+///     collect_atstart: false,
+///     ..CallgrindOptions::default()
+/// };
+/// assert_eq!(
+///     callgrind_args(&options),
+///     ["--cache-sim=yes", "--toggle-collect=bench::*", "--collect-atstart=no"]
+/// );
+/// ```
+pub fn callgrind_args(options: &CallgrindOptions) -> Vec<String> {
+    let mut args = vec![];
+    if options.cache_sim {
+        args.push("--cache-sim=yes".to_string());
+    }
+    if options.branch_sim {
+        args.push("--branch-sim=yes".to_string());
+    }
+    for pattern in &options.toggle_collect {
+        args.push(format!("--toggle-collect={pattern}"));
+    }
+    if !options.collect_atstart {
+        args.push("--collect-atstart=no".to_string());
+    }
+    args
+}
+
+/// Return the simulation options recorded in the metadata of a run collected with `options`.
+///
+/// The cache and branch simulations are written as they are told from the events of profiles
+/// (see [`crate::callgrind::simulation_options`]), so that runs collected by `profile` compare
+/// equal to those loaded from files collected with the same options. `--toggle-collect` and
+/// `--collect-atstart`, which leave no trace in profiles, follow if they are given.
+///
+/// ```
+/// # use callgrind_differ::{args::CallgrindOptions, profile::simulation_options};
+/// let options = CallgrindOptions {
+///     branch_sim: true,
+///     ..CallgrindOptions::default()
+/// };
+/// assert_eq!(simulation_options(&options), "--cache-sim=no --branch-sim=yes");
+/// ```
+pub fn simulation_options(options: &CallgrindOptions) -> String {
+    let yes_no = |simulated| if simulated { "yes" } else { "no" };
+    let mut simulation = format!(
+        "--cache-sim={} --branch-sim={}",
+        yes_no(options.cache_sim),
+        yes_no(options.branch_sim)
+    );
+    for pattern in &options.toggle_collect {
+        simulation.push_str(" --toggle-collect=");
+        simulation.push_str(pattern);
+    }
+    if !options.collect_atstart {
+        simulation.push_str(" --collect-atstart=no");
+    }
+    simulation
+}
+
+/// Run `program` (the program and its arguments) under callgrind with `options`, writing its
+/// profile to `out_file`.
+///
+/// The output of valgrind and of the program go to the standard output and error.
+///
+/// # Errors
+/// Returns an error if `program` is empty, valgrind cannot be run or it fails (e.g. because the
+/// program failed).
+pub fn run_callgrind(
+    program: &[String],
+    options: &CallgrindOptions,
+    out_file: &Path,
+) -> Result<()> {
+    if program.is_empty() {
+        bail!("No program to profile");
+    }
+    let valgrind = valgrind();
+    let status = Command::new(&valgrind)
+        .arg("--tool=callgrind")
+        .arg(format!("--callgrind-out-file={}", out_file.display()))
+        .args(callgrind_args(options))
+        .arg("--")
+        .args(program)
+        .status()
+        .with_context(|| format!("Failed to run {valgrind}"))?;
+    if !status.success() {
+        bail!(
+            "{valgrind} failed to profile {} ({status})",
+            program.join(" ")
+        );
+    }
+    Ok(())
+}
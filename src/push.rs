@@ -0,0 +1,162 @@
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+};
+
+use anyhow::{bail, Context, Result};
+
+use itertools::Itertools;
+
+use crate::{diff::DiffTable, summary::Summary};
+
+/// The environment variable from which the token for `--push-to` is read.
+pub const TOKEN_ENV_VAR: &str = "CALLGRIND_DIFFER_TOKEN";
+
+/// The job the metrics of `--push-prometheus` are grouped under in the pushgateway.
+pub const PROMETHEUS_JOB: &str = "callgrind_differ";
+
+/// The number of symbols, with the highest IR, whose IR is pushed by `--push-prometheus`.
+const PROMETHEUS_SYMBOLS: usize = 10;
+
+/// Post the JSON summary to the given URL.
+///
+/// The request is performed with `curl`. If [`TOKEN_ENV_VAR`] is set, its value is sent as a
+/// bearer token in the `Authorization` header. Both the token and the body are given to `curl`
+/// through its standard input so that they don't show up in the process list.
+///
+/// # Errors
+/// Returns an error if `curl` cannot be run or the request fails.
+pub fn push_summary(url: &str, summary: &Summary) -> Result<()> {
+    curl("POST", "application/json", &summary.to_json(), url)
+        .with_context(|| format!("Failed to push the summary to {url}"))
+}
+
+/// Push the total IR and the IR of the symbols of the last column of `table` as gauges to the
+/// Prometheus pushgateway at `url` (see [`prometheus_metrics`]).
+///
+/// The metrics replace those of the [`PROMETHEUS_JOB`] job, so that the gauges always hold the
+/// latest run. As with [`push_summary`], the request is performed with `curl`, with the token of
+/// [`TOKEN_ENV_VAR`], if any.
+///
+/// # Errors
+/// Returns an error if `curl` cannot be run or the request fails.
+pub fn push_prometheus(url: &str, table: &DiffTable) -> Result<()> {
+    let url = format!("{}/metrics/job/{PROMETHEUS_JOB}", url.trim_end_matches('/'));
+    curl(
+        "PUT",
+        "text/plain; version=0.0.4",
+        &prometheus_metrics(table),
+        &url,
+    )
+    .with_context(|| format!("Failed to push the metrics to {url}"))
+}
+
+/// Return the total IR and the IR of the 10 symbols with the highest IR in the last column of
+/// `table`, in the text format of Prometheus.
+///
+/// Gauges are labeled with the name of the run and, if known, its commit, e.g.:
+/// ```no_compile
+/// # TYPE callgrind_total_ir gauge
+/// callgrind_total_ir{run="v1.4",commit="0123456789abcdef"} 14418621168
+/// # TYPE callgrind_symbol_ir gauge
+/// callgrind_symbol_ir{run="v1.4",commit="0123456789abcdef",symbol="parser::parse"} 1516457
+/// ```
+pub fn prometheus_metrics(table: &DiffTable) -> String {
+    let column = table.last_column();
+    let metadata = &table.records.runs_metadata[column];
+    let mut labels = format!(
+        "run=\"{}\"",
+        prometheus_label(&table.records.run_names[column])
+    );
+    if let Some(commit) = &metadata.commit {
+        labels = format!("{labels},commit=\"{}\"", prometheus_label(commit));
+    }
+    let mut metrics = format!(
+        "# TYPE callgrind_total_ir gauge\ncallgrind_total_ir{{{labels}}} {}\n",
+        table.total[column].ir
+    );
+    metrics += "# TYPE callgrind_symbol_ir gauge\n";
+    let rows = table
+        .rows
+        .iter()
+        .sorted_by_key(|row| std::cmp::Reverse(row.cells[column].ir))
+        .take(PROMETHEUS_SYMBOLS);
+    for row in rows {
+        metrics = format!(
+            "{metrics}callgrind_symbol_ir{{{labels},symbol=\"{}\"}} {}\n",
+            prometheus_label(row.name()),
+            row.cells[column].ir
+        );
+    }
+    metrics
+}
+
+/// Escape `s` so that it can be used as the value of a label in the text format of Prometheus.
+fn prometheus_label(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Send `body` to `url` with `curl`, as a `method` request of the given content type, along with
+/// the token of [`TOKEN_ENV_VAR`], if any (see [`push_summary`]).
+///
+/// # Errors
+/// Returns an error if `curl` cannot be run or the request fails.
+fn curl(method: &str, content_type: &str, body: &str, url: &str) -> Result<()> {
+    let mut config = String::new();
+    if let Ok(token) = std::env::var(TOKEN_ENV_VAR) {
+        config += "header = ";
+        config += &curl_config_string(&format!("Authorization: Bearer {token}"));
+        config.push('\n');
+    }
+    config += "data-binary = ";
+    config += &curl_config_string(body);
+    config.push('\n');
+
+    let mut child = Command::new("curl")
+        .args([
+            "--silent",
+            "--show-error",
+            "--fail",
+            "--request",
+            method,
+            "--header",
+            &format!("Content-Type: {content_type}"),
+            "--config",
+            "-",
+            url,
+        ])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()
+        .context("Failed to run curl")?;
+    child
+        .stdin
+        .take()
+        .context("Failed to open curl's standard input")?
+        .write_all(config.as_bytes())?;
+    let status = child.wait()?;
+    if !status.success() {
+        bail!("curl failed ({status})");
+    }
+    Ok(())
+}
+
+/// Quote and escape `s` so that it can be used as a value in a `curl` config file.
+fn curl_config_string(s: &str) -> String {
+    let mut ret = String::with_capacity(s.len() + 2);
+    ret.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => ret.push_str("\\\""),
+            '\\' => ret.push_str("\\\\"),
+            '\n' => ret.push_str("\\n"),
+            '\r' => ret.push_str("\\r"),
+            '\t' => ret.push_str("\\t"),
+            c => ret.push(c),
+        }
+    }
+    ret.push('"');
+    ret
+}
@@ -0,0 +1,192 @@
+use crate::{
+    args::{Args, Percentage},
+    callgrind::IR_EVENT,
+    diff::DiffTable,
+    policy::{glob_match, Policy},
+};
+
+/// How bad a regression is, depending on which threshold it crossed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// The regression crossed `--warn-on-regression`.
+    Warning,
+    /// The regression crossed `--fail-on-regression`, or the threshold of the rule matching the
+    /// symbol.
+    Error,
+}
+
+/// A symbol whose IR (or count of another event) increased beyond a threshold.
+pub struct Regression<'a> {
+    /// The name of the symbol.
+    pub symbol: &'a str,
+    /// The event which regressed ([`IR_EVENT`] for the instruction count).
+    pub event: &'a str,
+    /// The count of the event for the symbol in the checked column.
+    pub ir: u64,
+    /// The count of the event for the symbol in the reference column.
+    pub reference_ir: u64,
+    /// The increase, in percent of `reference_ir`.
+    pub percent: f64,
+    /// Which threshold was crossed.
+    pub severity: Severity,
+}
+
+impl Regression<'_> {
+    /// Return the increase in count, or 0 if the count did not increase.
+    pub fn diff(&self) -> u64 {
+        self.ir.saturating_sub(self.reference_ir)
+    }
+
+    /// Return whether the regression is about the instruction count.
+    pub fn is_ir(&self) -> bool {
+        self.event == IR_EVENT
+    }
+
+    /// Return the name of the event which regressed, as shown in reports (`IR` for the
+    /// instruction count).
+    pub fn event_name(&self) -> &str {
+        if self.is_ir() {
+            "IR"
+        } else {
+            self.event
+        }
+    }
+
+    /// Describe the regression on a single line, e.g.
+    /// `parser::parse: +50000 IR (+10.000%, 500000 -> 550000)`.
+    pub fn describe(&self) -> String {
+        format!(
+            "{}: +{} {} (+{:.3}%, {} -> {})",
+            self.symbol,
+            self.diff(),
+            self.event_name(),
+            self.percent,
+            self.reference_ir,
+            self.ir
+        )
+    }
+}
+
+/// Find the symbols which regressed beyond `--warn-on-regression` or `--fail-on-regression`.
+///
+/// If a rule of the `policy` matches a symbol, its threshold is used instead of
+/// `--fail-on-regression` for that symbol.
+///
+/// Only the last column (i.e. the most recent run) is checked against its reference, as per
+/// `--relative-to`. Changes within the noise band (see `--noise-band`) are ignored. Regressions
+/// of the IR are returned first, in the order of the symbols in `records`, followed by those of
+/// the events qualified in `--fail-on-regression`, event by event.
+pub fn find_regressions<'a>(
+    config: &Args,
+    table: &DiffTable<'a>,
+    policy: &Policy,
+) -> Vec<Regression<'a>> {
+    let records = table.records;
+    let n_runs = table.n_runs();
+    if n_runs < 2
+        || (config.warn_on_regression.is_none()
+            && config.fail_on_regression.is_empty()
+            && policy.rules.is_empty())
+    {
+        return vec![];
+    }
+    let column = table.last_column();
+    if table.is_reference(column) {
+        return vec![];
+    }
+
+    let ir_fail_threshold = config.ir_fail_threshold();
+    let mut regressions = table
+        .rows
+        .iter()
+        .filter_map(|row| {
+            let cell = &row.cells[column];
+            if !cell.is_increase() || cell.noisy {
+                return None;
+            }
+            let percent = cell.percent();
+            let fail_threshold = policy
+                .rule_for(&row.symbol.name)
+                .map(|rule| rule.max_regression)
+                .or(ir_fail_threshold);
+            let severity = if exceeds(percent, fail_threshold) {
+                Severity::Error
+            } else if exceeds(percent, config.warn_on_regression) {
+                Severity::Warning
+            } else {
+                return None;
+            };
+            Some(Regression {
+                symbol: &row.symbol.name,
+                event: IR_EVENT,
+                ir: cell.ir,
+                reference_ir: cell.reference_ir(),
+                percent,
+                severity,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    // Other events. These are not merged from multiple samples, hence have no noise band.
+    for (event, threshold) in config.event_fail_thresholds() {
+        let Some(index) = records.event_index(event) else {
+            continue;
+        };
+        let event = records.events[index].as_str();
+        regressions.extend(
+            table
+                .event_cells(index, column)
+                .filter(|(_, cell)| cell.is_increase() && exceeds(cell.percent(), Some(threshold)))
+                .map(|(symbol, cell)| Regression {
+                    symbol: &symbol.name,
+                    event,
+                    ir: cell.ir,
+                    reference_ir: cell.reference_ir(),
+                    percent: cell.percent(),
+                    severity: Severity::Error,
+                }),
+        );
+    }
+    regressions
+}
+
+/// Return whether `percent` is strictly beyond `threshold`, if any.
+pub fn exceeds(percent: f64, threshold: Option<Percentage>) -> bool {
+    threshold.is_some_and(|Percentage(threshold)| percent > threshold)
+}
+
+/// A pattern given to `--fail-on-missing-symbol` which is not satisfied by the last column.
+pub struct MissingSymbol<'a> {
+    /// The pattern.
+    pub pattern: &'a str,
+    /// The symbols matching the pattern which were hit in the reference but not in the last
+    /// column. If empty, no symbol matching the pattern was hit in either.
+    pub vanished: Vec<&'a str>,
+}
+
+/// Find the patterns of `--fail-on-missing-symbol` which are not satisfied by the last column.
+///
+/// A pattern is not satisfied if a matching symbol that was hit in the reference was not hit in
+/// the last column, or if no matching symbol was hit in the last column at all.
+pub fn find_missing_symbols<'a>(config: &'a Args, table: &DiffTable<'a>) -> Vec<MissingSymbol<'a>> {
+    let column = table.last_column();
+
+    config
+        .fail_on_missing_symbol
+        .iter()
+        .filter_map(|pattern| {
+            let matching = table
+                .rows
+                .iter()
+                .filter(|row| glob_match(pattern, row.name()))
+                .collect::<Vec<_>>();
+            let vanished = matching
+                .iter()
+                .filter(|row| row.cells[column].ir == 0 && row.cells[column].reference_ir() != 0)
+                .map(|row| &*row.symbol.name)
+                .collect::<Vec<_>>();
+            let present = matching.iter().any(|row| row.cells[column].ir != 0);
+            (!present || !vanished.is_empty()).then_some(MissingSymbol { pattern, vanished })
+        })
+        .collect()
+}
@@ -0,0 +1,167 @@
+use std::{
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use anyhow::{Context, Result};
+
+use crate::{args::Args, checksum, format::InputFormats, runs::Records};
+
+/// The input files of a comparison and the [`Records`] loaded from them, reloaded file by file.
+///
+/// Reloading (e.g. in a watch mode, along with [`crate::shared::SharedRecords`]) re-parses only
+/// the files which changed since they were last loaded, and replaces their columns in the records
+/// in place, so that many large inputs do not have to be parsed again whenever one of them
+/// changes. A file changed if its modification time or size did, and its SHA-256 as well, so
+/// that touching a file does not reload it.
+///
+/// Each file is loaded with its format, as the inputs of a comparison are, but samples are not
+/// merged (see `--merge`) and runs are not appended to the history.
+///
+/// ```
+/// # use callgrind_differ::{args::Args, reload::Inputs};
+/// # use clap::Parser;
+/// # fn main() -> anyhow::Result<()> {
+/// let config = Args::parse_from(["callgrind_differ"]);
+/// let dir = std::env::temp_dir().join(format!("reload-doctest-{}", std::process::id()));
+/// std::fs::create_dir_all(&dir)?;
+/// let (head, base) = (dir.join("head.cg"), dir.join("base.cg"));
+/// std::fs::copy("tests/data/run1.cg", &base)?;
+/// std::fs::copy("tests/data/run1.cg", &head)?;
+/// let mut inputs = Inputs::load(&[&base, &head], &config)?;
+/// assert!(inputs.reload(&config)?.is_empty());
+/// std::fs::copy("tests/data/run2.cg", &head)?;
+/// assert_eq!(inputs.reload(&config)?, [head.as_path()]);
+/// assert_eq!(inputs.records().runs_total_irs, [1_121_000, 1_143_000]);
+/// # std::fs::remove_dir_all(&dir)?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct Inputs {
+    /// The input files, in the order of their columns.
+    files: Vec<InputFile>,
+    /// The runs of all files.
+    records: Records,
+}
+
+/// An input file of [`Inputs`].
+struct InputFile {
+    /// The path to the file.
+    path: PathBuf,
+    /// The version of the file that was last loaded.
+    fingerprint: Fingerprint,
+    /// The number of runs loaded from the file.
+    n_runs: usize,
+}
+
+/// What tells a version of a file from another.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Fingerprint {
+    /// The modification time of the file, if the platform records it.
+    modified: Option<SystemTime>,
+    /// The size of the file, in bytes.
+    len: u64,
+    /// The SHA-256 of the file.
+    checksum: String,
+}
+
+impl Fingerprint {
+    /// Return the fingerprint of the file at `path`.
+    ///
+    /// The SHA-256 is that of `previous` if the modification time and size match its own, so
+    /// that unchanged files are not read.
+    fn of(path: &Path, previous: Option<&Self>) -> Result<Self> {
+        let metadata = std::fs::metadata(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let modified = metadata.modified().ok();
+        let len = metadata.len();
+        let checksum = match previous {
+            Some(previous) if previous.modified == modified && previous.len == len => {
+                previous.checksum.clone()
+            }
+            _ => checksum::sha256_file(path)
+                .with_context(|| format!("Failed to read {}", path.display()))?,
+        };
+        Ok(Self {
+            modified,
+            len,
+            checksum,
+        })
+    }
+}
+
+impl Inputs {
+    /// Load the runs of the given files, in order.
+    ///
+    /// # Errors
+    /// Returns an error if a file cannot be read or loaded with its format.
+    pub fn load<P: AsRef<Path>>(paths: &[P], config: &Args) -> Result<Self> {
+        let formats = InputFormats::default();
+        let mut inputs = Self {
+            files: vec![],
+            records: Records::new(),
+        };
+        for path in paths {
+            let path = path.as_ref();
+            let fingerprint = Fingerprint::of(path, None)?;
+            let runs = formats.load(path, config)?;
+            inputs.files.push(InputFile {
+                path: path.to_path_buf(),
+                fingerprint,
+                n_runs: runs.len(),
+            });
+            for run in runs {
+                inputs.records.try_add_run(run)?;
+            }
+        }
+        Ok(inputs)
+    }
+
+    /// Return the records loaded from the files.
+    pub fn records(&self) -> &Records {
+        &self.records
+    }
+
+    /// Return the records loaded from the files, consuming the inputs.
+    pub fn into_records(self) -> Records {
+        self.records
+    }
+
+    /// Re-parse the files which changed since they were last loaded, replace their runs in the
+    /// records and return the paths of those files.
+    ///
+    /// # Errors
+    /// Returns an error if a file cannot be read, loaded with its format or added to the records
+    /// (e.g. it has a run named after one of another file). The files before it are reloaded,
+    /// and it is reloaded again on the next call.
+    pub fn reload(&mut self, config: &Args) -> Result<Vec<&Path>> {
+        let formats = InputFormats::default();
+        let mut reloaded = vec![];
+        // The column of the next run of the files.
+        let mut column = 0;
+        for (i, file) in self.files.iter_mut().enumerate() {
+            let fingerprint = Fingerprint::of(&file.path, Some(&file.fingerprint))?;
+            if fingerprint.checksum == file.fingerprint.checksum {
+                // The file was touched, but not changed.
+                file.fingerprint = fingerprint;
+                column += file.n_runs;
+                continue;
+            }
+            let runs = formats.load(&file.path, config)?;
+            self.records.remove_runs(column..column + file.n_runs)?;
+            // Until all its runs are in the records, the file is still to be reloaded.
+            file.n_runs = 0;
+            for run in runs {
+                self.records.append_run_at(column, run)?;
+                column += 1;
+                file.n_runs += 1;
+            }
+            file.fingerprint = fingerprint;
+            reloaded.push(i);
+        }
+        Ok(reloaded
+            .into_iter()
+            .map(|i| self.files[i].path.as_path())
+            .collect())
+    }
+}
@@ -0,0 +1,126 @@
+use std::io::Write;
+
+use anyhow::Result;
+
+use crate::{
+    args::Args, comment::write_pr_comment, diff::DiffTable, display::display_to,
+    regression::Regression, summary::Summary,
+};
+
+/// A way of rendering the differences, selected with `--output-format`.
+///
+/// Renderers are gathered in [`OutputRenderers`], which finds them by name.
+pub trait OutputRenderer: Send + Sync {
+    /// The name of the renderer, as given to `--output-format`.
+    fn name(&self) -> &'static str;
+
+    /// Render the differences, along with the regressions that were found, into `w`.
+    ///
+    /// # Errors
+    /// Returns an error if writing to `w` fails.
+    fn render(
+        &self,
+        table: &DiffTable,
+        regressions: &[Regression],
+        config: &Args,
+        w: &mut dyn Write,
+    ) -> Result<()>;
+}
+
+/// The table shown in a terminal (see [`display_to`]).
+pub struct Table;
+
+impl OutputRenderer for Table {
+    fn name(&self) -> &'static str {
+        "table"
+    }
+
+    fn render(
+        &self,
+        table: &DiffTable,
+        _: &[Regression],
+        config: &Args,
+        mut w: &mut dyn Write,
+    ) -> Result<()> {
+        display_to(config, table, &mut w)?;
+        Ok(())
+    }
+}
+
+/// The Markdown report of `--export-pr-comment` (see [`write_pr_comment`]).
+pub struct Markdown;
+
+impl OutputRenderer for Markdown {
+    fn name(&self) -> &'static str {
+        "markdown"
+    }
+
+    fn render(
+        &self,
+        table: &DiffTable,
+        regressions: &[Regression],
+        config: &Args,
+        mut w: &mut dyn Write,
+    ) -> Result<()> {
+        write_pr_comment(config, table, regressions, &mut w)
+    }
+}
+
+/// The JSON summary of the last column (see [`Summary::to_json`]).
+pub struct Json;
+
+impl OutputRenderer for Json {
+    fn name(&self) -> &'static str {
+        "json"
+    }
+
+    fn render(
+        &self,
+        table: &DiffTable,
+        regressions: &[Regression],
+        _: &Args,
+        w: &mut dyn Write,
+    ) -> Result<()> {
+        w.write_all(Summary::new(table, regressions).to_json().as_bytes())?;
+        Ok(())
+    }
+}
+
+/// The output renderers.
+///
+/// By default, these are [`Table`], [`Markdown`] and [`Json`].
+pub struct OutputRenderers {
+    /// The renderers. Names are unique.
+    renderers: Vec<Box<dyn OutputRenderer>>,
+}
+
+impl Default for OutputRenderers {
+    fn default() -> Self {
+        Self {
+            renderers: vec![Box::new(Table), Box::new(Markdown), Box::new(Json)],
+        }
+    }
+}
+
+impl OutputRenderers {
+    /// Add a renderer, replacing the one with the same name, if any.
+    pub fn register<R: OutputRenderer + 'static>(&mut self, renderer: R) -> &mut Self {
+        self.renderers
+            .retain(|registered| registered.name() != renderer.name());
+        self.renderers.push(Box::new(renderer));
+        self
+    }
+
+    /// Return the renderer with the given name, if any.
+    pub fn find(&self, name: &str) -> Option<&dyn OutputRenderer> {
+        self.renderers
+            .iter()
+            .find(|renderer| renderer.name() == name)
+            .map(AsRef::as_ref)
+    }
+
+    /// Return the names of the renderers.
+    pub fn names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.renderers.iter().map(|renderer| renderer.name())
+    }
+}
@@ -0,0 +1,248 @@
+use std::{
+    fmt::Write as _,
+    fs::File,
+    io::{BufWriter, Write},
+};
+
+use anyhow::{Context, Result};
+
+use crate::{
+    args::{Args, IrCount, Percentage},
+    callgrind::IR_EVENT,
+    diff::{DiffCell, DiffTable},
+    policy::{glob_match, Policy},
+    regression::exceeds,
+    summary::json_string,
+};
+
+/// Write a JSON report of the evaluation of the policy to `--export-policy-report`.
+///
+/// The report lists every check that was configured, along with the symbols it applied to, their
+/// measured and allowed values, and whether the check passed:
+///   * One check per rule of `--regression-rules`. A symbol is listed under the first rule that
+///     matches it, as this is the rule that applies to it.
+///   * One check for the IR threshold of `--fail-on-regression`, which applies to the symbols no
+///     rule matched.
+///   * One check per event qualified in `--fail-on-regression`, which applies to all symbols.
+///   * One check per pattern of `--fail-on-missing-symbol`.
+///   * One check for `--total-budget`.
+///
+/// Percentages are the signed change of the last column with respect to its reference. A
+/// symbol whose change is within the noise band (see `--noise-band`) always passes.
+///
+/// # Errors
+/// Returns an error if the report cannot be written.
+pub fn export_policy_report(config: &Args, table: &DiffTable, policy: &Policy) -> Result<()> {
+    let path = &config.export_policy_report;
+    let mut writer =
+        BufWriter::new(File::create(path).with_context(|| format!("Failed to create {path}"))?);
+    write!(writer, "{}", policy_report(config, table, policy))?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Build the report described in [`export_policy_report`].
+///
+/// ```no_compile
+/// {
+///   "name": "v1.3",
+///   "passed": false,
+///   "checks": [
+///     {"kind": "rule", "pattern": "parser::*", "event": "Ir", "allowed_percent": 1.0, "passed": false, "symbols": [
+///       {"symbol": "parser::parse", "reference": 500000, "measured": 550000, "percent": 10.0, "within_noise": false, "passed": false}
+///     ]},
+///     {"kind": "total_budget", "allowed": 1100000, "measured": 1050000, "passed": true}
+///   ]
+/// }
+/// ```
+fn policy_report(config: &Args, table: &DiffTable, policy: &Policy) -> String {
+    let records = table.records;
+    let column = table.last_column();
+    let mut checks = vec![];
+
+    // Regression rules, then the IR threshold for symbols that no rule matched.
+    for (i, rule) in policy.rules.iter().enumerate() {
+        let symbols = ir_checks(table, rule.max_regression, |symbol| {
+            policy
+                .rules
+                .iter()
+                .position(|rule| glob_match(&rule.pattern, symbol))
+                == Some(i)
+        });
+        checks.push(symbols_check(
+            &format!(
+                "\"kind\": \"rule\", \"pattern\": {}",
+                json_string(&rule.pattern)
+            ),
+            IR_EVENT,
+            rule.max_regression,
+            &symbols,
+        ));
+    }
+    if let Some(threshold) = config.ir_fail_threshold() {
+        let symbols = ir_checks(table, threshold, |symbol| policy.rule_for(symbol).is_none());
+        checks.push(symbols_check(
+            "\"kind\": \"fail_on_regression\"",
+            IR_EVENT,
+            threshold,
+            &symbols,
+        ));
+    }
+
+    // Other events.
+    for (event, threshold) in config.event_fail_thresholds() {
+        let Some(index) = records.event_index(event) else {
+            continue;
+        };
+        let symbols = table
+            .event_cells(index, column)
+            .map(|(symbol, cell)| SymbolCheck::new(&symbol.name, cell, threshold))
+            .collect::<Vec<_>>();
+        checks.push(symbols_check(
+            "\"kind\": \"fail_on_regression\"",
+            event,
+            threshold,
+            &symbols,
+        ));
+    }
+
+    // Required symbols.
+    for pattern in &config.fail_on_missing_symbol {
+        checks.push(missing_symbol_check(table, pattern));
+    }
+
+    // Total budget.
+    if let Some(IrCount(budget)) = config.total_budget {
+        let total_ir = records.runs_total_irs[column];
+        let passed = total_ir <= budget;
+        checks.push((passed, format!(
+            "{{\"kind\": \"total_budget\", \"allowed\": {budget}, \"measured\": {total_ir}, \"passed\": {passed}}}"
+        )));
+    }
+
+    let passed = checks.iter().all(|(passed, _)| *passed);
+    let mut s = String::new();
+    s.push_str("{\n");
+    let _ = writeln!(
+        s,
+        "  \"name\": {},",
+        json_string(&records.run_names[column])
+    );
+    let _ = writeln!(s, "  \"passed\": {passed},");
+    s.push_str("  \"checks\": [");
+    for (i, (_, check)) in checks.iter().enumerate() {
+        if i != 0 {
+            s.push(',');
+        }
+        s += "\n    ";
+        s += check;
+    }
+    if !checks.is_empty() {
+        s.push_str("\n  ");
+    }
+    s.push_str("]\n}\n");
+    s
+}
+
+/// Evaluate a pattern of `--fail-on-missing-symbol`, serialize the check and return whether it
+/// passed along with it.
+fn missing_symbol_check(table: &DiffTable, pattern: &str) -> (bool, String) {
+    let column = table.last_column();
+    let matching = table
+        .rows
+        .iter()
+        .filter(|row| glob_match(pattern, row.name()));
+    let (mut present, mut vanished) = (vec![], vec![]);
+    for row in matching {
+        let cell = &row.cells[column];
+        if cell.ir != 0 {
+            present.push(json_string(row.name()));
+        } else if cell.reference_ir() != 0 {
+            vanished.push(json_string(row.name()));
+        }
+    }
+    let passed = !present.is_empty() && vanished.is_empty();
+    (
+        passed,
+        format!(
+            "{{\"kind\": \"fail_on_missing_symbol\", \"pattern\": {}, \"passed\": {passed}, \"present\": [{}], \"vanished\": [{}]}}",
+            json_string(pattern),
+            present.join(", "),
+            vanished.join(", ")
+        ),
+    )
+}
+
+/// The evaluation of a threshold for a single symbol.
+struct SymbolCheck<'a> {
+    /// The name of the symbol.
+    symbol: &'a str,
+    /// The count in the last column, compared to the reference.
+    cell: DiffCell,
+    /// Whether the symbol is within its threshold.
+    passed: bool,
+}
+
+impl<'a> SymbolCheck<'a> {
+    /// Evaluate `threshold` for a symbol.
+    fn new(symbol: &'a str, cell: DiffCell, threshold: Percentage) -> Self {
+        let passed = !cell.is_increase() || cell.noisy || !exceeds(cell.percent(), Some(threshold));
+        Self {
+            symbol,
+            cell,
+            passed,
+        }
+    }
+}
+
+/// Evaluate `threshold` on the IR of the symbols for which `applies` returns `true`.
+fn ir_checks<'a, F: Fn(&str) -> bool>(
+    table: &DiffTable<'a>,
+    threshold: Percentage,
+    applies: F,
+) -> Vec<SymbolCheck<'a>> {
+    let column = table.last_column();
+    table
+        .rows
+        .iter()
+        .filter(|row| applies(row.name()))
+        .map(|row| SymbolCheck::new(&row.symbol.name, row.cells[column], threshold))
+        .collect()
+}
+
+/// Serialize a check made of per-symbol checks and return whether it passed along with it.
+///
+/// `kind` is the beginning of the JSON object, identifying the check.
+fn symbols_check(
+    kind: &str,
+    event: &str,
+    allowed: Percentage,
+    symbols: &[SymbolCheck],
+) -> (bool, String) {
+    let passed = symbols.iter().all(|symbol| symbol.passed);
+    let mut s = format!(
+        "{{{kind}, \"event\": {}, \"allowed_percent\": {}, \"passed\": {passed}, \"symbols\": [",
+        json_string(event),
+        allowed.0
+    );
+    for (i, symbol) in symbols.iter().enumerate() {
+        if i != 0 {
+            s.push(',');
+        }
+        let _ = write!(
+            s,
+            "\n      {{\"symbol\": {}, \"reference\": {}, \"measured\": {}, \"percent\": {}, \"within_noise\": {}, \"passed\": {}}}",
+            json_string(symbol.symbol),
+            symbol.cell.reference_ir(),
+            symbol.cell.ir,
+            symbol.cell.percent(),
+            symbol.cell.noisy,
+            symbol.passed
+        );
+    }
+    if !symbols.is_empty() {
+        s.push_str("\n    ");
+    }
+    s.push_str("]}");
+    (passed, s)
+}
@@ -0,0 +1,142 @@
+use std::{fmt::Display, path::Path, str::FromStr};
+
+use anyhow::{bail, Context, Result};
+
+use crate::history::StoredRun;
+
+/// How often runs older than `keep_last` are kept when downsampling (see [`Retention`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Downsample {
+    /// Keep one run per day.
+    Daily,
+    /// Keep one run per week.
+    Weekly,
+}
+
+impl Downsample {
+    /// Return the length of a period, in seconds.
+    pub fn period(self) -> u64 {
+        match self {
+            Self::Daily => 86400,
+            Self::Weekly => 7 * 86400,
+        }
+    }
+}
+
+impl FromStr for Downsample {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "daily" => Ok(Self::Daily),
+            "weekly" => Ok(Self::Weekly),
+            _ => bail!("Invalid downsample. Accepted values are: daily, weekly"),
+        }
+    }
+}
+
+impl Display for Downsample {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Daily => write!(f, "daily"),
+            Self::Weekly => write!(f, "weekly"),
+        }
+    }
+}
+
+/// Which runs of a history store are kept, as declared in the configuration file of the store.
+///
+/// The file holds one `key: value` per line, and lines starting with `#` are comments:
+/// ```no_compile
+/// # Keep the 30 most recent runs, the tagged ones and one run per week of the others.
+/// keep_last: 30
+/// keep_tagged: true
+/// downsample: weekly
+/// ```
+/// Without `keep_last`, all runs are kept. Without `downsample`, the runs which are neither among
+/// the `keep_last` most recent ones nor tagged (with `keep_tagged`) are removed. With
+/// `downsample`, the most recent of them in each day or week is kept as well; runs with no
+/// timestamp then are kept, as their age is unknown. Pinned runs (see `history pin`) are always
+/// kept.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Retention {
+    /// The number of most recent runs which are kept, or `None` to keep all runs.
+    pub keep_last: Option<usize>,
+    /// Whether runs measured on a git tag are kept, regardless of their age.
+    pub keep_tagged: bool,
+    /// How often older runs are kept, if any.
+    pub downsample: Option<Downsample>,
+}
+
+impl Retention {
+    /// Read the retention from a configuration file. A missing file keeps all runs.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be read or a line is not well-formed.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        Self::parse(&contents).with_context(|| format!("Invalid retention in {}", path.display()))
+    }
+
+    /// Parse the retention from the contents of a configuration file.
+    ///
+    /// # Errors
+    /// Returns an error if a line is not well-formed.
+    pub fn parse(contents: &str) -> Result<Self> {
+        let mut retention = Self::default();
+        for (i, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((key, value)) = line.split_once(':') else {
+                bail!("Line {}: expected `key: value`, got {line:?}", i + 1);
+            };
+            let value = value.trim();
+            match key.trim() {
+                "keep_last" => match value.parse()? {
+                    0 => bail!("Line {}: `keep_last` must be at least 1", i + 1),
+                    n => retention.keep_last = Some(n),
+                },
+                "keep_tagged" => retention.keep_tagged = value.parse()?,
+                "downsample" => retention.downsample = Some(value.parse()?),
+                // Ignore unknown keys so that older versions can read newer stores.
+                _ => {}
+            }
+        }
+        Ok(retention)
+    }
+
+    /// Return whether each run of `runs`, from the oldest to the most recent, is kept.
+    pub fn retained(&self, runs: &[StoredRun]) -> Vec<bool> {
+        let Some(keep_last) = self.keep_last else {
+            return vec![true; runs.len()];
+        };
+        let n_old = runs.len().saturating_sub(keep_last);
+        // The period of the last old run that was kept, when downsampling.
+        let mut kept_period = None;
+        let mut retained = vec![true; runs.len()];
+        // Old runs are walked from the most recent, so that the most recent of each period is
+        // kept.
+        for (stored, retained) in runs[..n_old].iter().zip(&mut retained[..n_old]).rev() {
+            let metadata = &stored.run.metadata;
+            if metadata.pinned || (self.keep_tagged && metadata.tag.is_some()) {
+                continue;
+            }
+            *retained = match (self.downsample, metadata.timestamp) {
+                (None, _) => false,
+                (Some(_), None) => true,
+                (Some(downsample), Some(timestamp)) => {
+                    let period = timestamp / downsample.period();
+                    kept_period.replace(period) != Some(period)
+                }
+            };
+        }
+        retained
+    }
+}
@@ -1,11 +1,24 @@
-use std::{fs::File, io::BufReader, path::Path};
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    fs::File,
+    io::BufReader,
+    ops::Range,
+    path::Path,
+    sync::Arc,
+};
 
-use anyhow::{bail, Result};
+use itertools::Itertools;
 
-use crate::args::{SortBy, SortByField, SortByOrder, StringReplacement};
+use crate::{
+    args::{SortBy, SortByField, SortByOrder, StringReplacement},
+    callgrind::{InputLimits, LimitedReader, IR_EVENT},
+    error::{Error, Result},
+};
 
 /// Annotations of a run of a binary.
-#[derive(Default)]
+#[derive(Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Run {
     // The name of the run, if any. This is purely for human readability purposes.
     pub name: String,
@@ -13,6 +26,103 @@ pub struct Run {
     pub symbols: Vec<AnnotatedSymbol>,
     /// The total number of IR for this run.
     pub total_ir: u64,
+    /// Where and when the run was measured, if known.
+    pub metadata: RunMetadata,
+    /// The names of the events other than `Ir` that were recorded (e.g. `Dr`, `D1mr`, `Bcm`).
+    pub events: Vec<String>,
+    /// The total count of each event of [`Self::events`].
+    pub event_totals: Vec<u64>,
+    /// The position of each symbol in [`Self::symbols`], by name.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    index: SymbolIndex,
+}
+
+/// A symbol of a sample whose IR count deviates from its IR count in the other samples of the same
+/// run (see [`Run::find_outliers`]).
+#[derive(Debug, Clone)]
+pub struct Outlier {
+    /// The index of the sample.
+    pub sample: usize,
+    /// The name of the symbol.
+    pub symbol: Arc<str>,
+    /// The IR count of the symbol in the sample.
+    pub ir: u64,
+    /// The mean IR count of the symbol in the other samples.
+    pub mean: f64,
+    /// How far `ir` is from `mean`, in standard deviations of the other samples. This is infinite
+    /// if the other samples all agree.
+    pub sigmas: f64,
+}
+
+/// Information about the context in which a run was measured.
+///
+/// All fields are optional. They are filled in when runs are tagged (e.g. with `--tag-from-git`)
+/// and persisted along with the run in the history store.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RunMetadata {
+    /// The hash of the commit the binary was built from.
+    pub commit: Option<String>,
+    /// The branch the binary was built from.
+    pub branch: Option<String>,
+    /// The git tag of the commit the binary was built from.
+    pub tag: Option<String>,
+    /// When the run was recorded, in seconds since the Unix epoch.
+    pub timestamp: Option<u64>,
+    /// The benchmark of a suite the run measured (see `--suite`).
+    pub benchmark: Option<String>,
+    /// The version of the program the run measured, within a suite (see `--suite`).
+    pub version: Option<String>,
+    /// The number of iterations of the workload the run measured (see `--run-meta`).
+    pub iterations: Option<u64>,
+    /// The command line of the profiled program, as recorded by callgrind.
+    pub command: Option<String>,
+    /// A free-text note about the run (e.g. what changed), set with `history annotate`.
+    pub note: Option<String>,
+    /// The SHA-256 of the file the run was loaded from, used to detect runs appended twice to the
+    /// history (see `--on-duplicate`). Runs of files which hold several (e.g. the columns of a
+    /// CSV) are told apart by their index in the file, after a `#`.
+    pub checksum: Option<String>,
+    /// Whether the run is pinned as a baseline of the history, set with `history pin`.
+    pub pinned: bool,
+    /// The version of `rustc` the run was measured with (e.g.
+    /// `rustc 1.77.0 (aedd173a2 2024-03-17)`).
+    pub rustc: Option<String>,
+    /// The target triple of `rustc` (e.g. `x86_64-unknown-linux-gnu`).
+    pub target_triple: Option<String>,
+    /// The model of the CPU of the machine the run was measured on.
+    pub cpu: Option<String>,
+    /// The version of valgrind the run was measured with (e.g. `valgrind-3.22.0`).
+    pub valgrind: Option<String>,
+    /// The simulation options callgrind was run with (e.g. `--cache-sim=yes --branch-sim=no`),
+    /// as told by the events the profile recorded.
+    pub simulation: Option<String>,
+    /// The unit of the counts of the run, if they are not instruction counts (e.g. `ns` for the
+    /// wall times of Criterion.rs benchmarks).
+    pub unit: Option<String>,
+    /// The machine or store the run was merged from with `history merge`, if it was not measured
+    /// locally.
+    pub origin: Option<String>,
+}
+
+impl RunMetadata {
+    /// Return whether the run matches the given git ref.
+    ///
+    /// A ref matches if it is the name of the branch or tag, or a prefix of the commit hash.
+    pub fn matches_ref(&self, reference: &str) -> bool {
+        self.branch.as_deref() == Some(reference)
+            || self.tag.as_deref() == Some(reference)
+            || (!reference.is_empty()
+                && self
+                    .commit
+                    .as_deref()
+                    .is_some_and(|commit| commit.starts_with(reference)))
+    }
+
+    /// Return the benchmark and the version the run measured, if it belongs to a suite.
+    pub fn group(&self) -> Option<(&str, &str)> {
+        Some((self.benchmark.as_deref()?, self.version.as_deref()?))
+    }
 }
 
 impl Run {
@@ -22,7 +132,6 @@ impl Run {
     }
 
     /// Create a new run with a name.
-    #[allow(unused)]
     pub fn new_named(name: String) -> Self {
         Self {
             name,
@@ -40,28 +149,292 @@ impl Run {
     /// let mut run = Run::new();
     /// run.add_ir("foo", 12);
     /// run.add_ir("foo", 24);
-    /// assert_eq!(run.symbols.iter().find(|sym| sym.name == "foo").unwrap().ir, 36);
+    /// assert_eq!(run.symbols.iter().find(|sym| &*sym.name == "foo").unwrap().ir, 36);
     /// ```
     pub fn add_ir(&mut self, symbol: &str, ir: u64) {
-        if let Some(ref mut symbol) = self.symbols.iter_mut().find(|sym| sym.name == symbol) {
-            symbol.ir += ir;
-        } else {
-            self.symbols.push(AnnotatedSymbol {
-                name: symbol.to_string(),
-                ir,
+        self.add_counts(symbol, ir, &[]);
+    }
+
+    /// Add an IR count and counts for other events for the given symbol in the run.
+    ///
+    /// `counts` are the counts of the events of [`Self::events`], in the same order. Missing
+    /// counts are considered to be 0. As with [`Self::add_ir`], counts are _added_ each time, and
+    /// saturate at `u64::MAX`.
+    pub fn add_counts(&mut self, symbol: &str, ir: u64, counts: &[u64]) {
+        let n_events = self.events.len();
+        let position = self
+            .index
+            .position(&self.symbols, |sym| &sym.name, symbol)
+            .unwrap_or_else(|| {
+                let name = Arc::<str>::from(symbol);
+                self.index.insert(&name, self.symbols.len());
+                self.symbols.push(AnnotatedSymbol {
+                    name,
+                    ..Default::default()
+                });
+                self.symbols.len() - 1
             });
+        // Counts come from untrusted inputs: saturate rather than overflow.
+        let symbol = &mut self.symbols[position];
+        symbol.ir = symbol.ir.saturating_add(ir);
+        symbol.events.resize(n_events, 0);
+        for (total, count) in symbol.events.iter_mut().zip(counts) {
+            *total = total.saturating_add(*count);
+        }
+    }
+
+    /// Return the index of the given event in [`Self::events`].
+    pub fn event_index(&self, event: &str) -> Option<usize> {
+        self.events.iter().position(|name| name == event)
+    }
+
+    /// Check that the run can be added to [`Records`].
+    ///
+    /// Runs built with [`Self::add_counts`] always can. Runs whose public fields were modified
+    /// may not.
+    ///
+    /// # Errors
+    /// Returns an error if the run has the same symbol or event twice.
+    pub fn validate(&self) -> Result<()> {
+        let invalid = |reason| {
+            Err(Error::InvalidRun {
+                run: self.name.clone(),
+                reason,
+            })
+        };
+        if let Some(event) = self.events.iter().duplicates().next() {
+            return invalid(format!("Duplicate event {event}"));
+        }
+        if let Some(name) = self
+            .symbols
+            .iter()
+            .map(|symbol| &symbol.name)
+            .duplicates()
+            .next()
+        {
+            return invalid(format!("Duplicate symbol {name}"));
+        }
+        Ok(())
+    }
+
+    /// Rename the symbols of the run for which `rename` returns a new name, and return whether
+    /// any was.
+    ///
+    /// Symbols renamed to the name of another are merged into it: their counts are summed, as
+    /// are the variances of their counts. Symbols keep their order, merged ones that of the first
+    /// of them.
+    ///
+    /// ```
+    /// # use callgrind_differ::runs::Run;
+    /// let mut run = Run::new();
+    /// run.add_ir("old", 12);
+    /// run.add_ir("new", 24);
+    /// assert!(run.rename_symbols(|name| (name == "old").then(|| "new".to_string())));
+    /// assert_eq!(run.symbols.len(), 1);
+    /// assert_eq!(run.symbols[0].ir, 36);
+    /// ```
+    pub fn rename_symbols<F: Fn(&str) -> Option<String>>(&mut self, rename: F) -> bool {
+        let names = self
+            .symbols
+            .iter()
+            .map(|symbol| rename(&symbol.name))
+            .collect::<Vec<_>>();
+        if names.iter().all(Option::is_none) {
+            return false;
+        }
+        let symbols = std::mem::take(&mut self.symbols);
+        self.index = SymbolIndex::default();
+        for (symbol, name) in symbols.into_iter().zip(names) {
+            let name = name.map_or(symbol.name, Arc::from);
+            if let Some(position) = self.index.position(&self.symbols, |sym| &sym.name, &name) {
+                let merged = &mut self.symbols[position];
+                merged.ir = merged.ir.saturating_add(symbol.ir);
+                merged.stddev = merged.stddev.hypot(symbol.stddev);
+                merged
+                    .events
+                    .resize(merged.events.len().max(symbol.events.len()), 0);
+                for (total, count) in merged.events.iter_mut().zip(symbol.events) {
+                    *total = total.saturating_add(count);
+                }
+            } else {
+                self.index.insert(&name, self.symbols.len());
+                self.symbols.push(AnnotatedSymbol { name, ..symbol });
+            }
         }
+        true
+    }
+
+    /// Merge multiple samples of the same run into a single run.
+    ///
+    /// The IR count of each symbol is the mean of its IR count across samples (a sample in which
+    /// the symbol does not appear counts as 0) and its standard deviation is kept in
+    /// [`AnnotatedSymbol::stddev`]. Counts of other events are averaged as well. The name,
+    /// metadata and events of the first sample are kept.
+    ///
+    /// # Panics
+    /// This function panics if `samples` is empty.
+    pub fn merge(samples: Vec<Run>) -> Run {
+        assert!(!samples.is_empty(), "Cannot merge an empty set of samples");
+        let n = samples.len();
+        let events = samples[0].events.clone();
+        let n_events = events.len();
+
+        // Gather the IR and event counts of each symbol, in order of first appearance. Event
+        // counts are indexed by event, then sample.
+        let mut counts = vec![];
+        let mut positions: HashMap<Arc<str>, usize> = HashMap::new();
+        let mut event_totals = vec![vec![0; n]; n_events];
+        for (i, sample) in samples.iter().enumerate() {
+            // Where each event of the merged run is in the sample.
+            let indices = events
+                .iter()
+                .map(|event| sample.event_index(event))
+                .collect::<Vec<_>>();
+            for (j, index) in indices.iter().enumerate() {
+                if let Some(index) = index {
+                    event_totals[j][i] = sample.event_totals.get(*index).copied().unwrap_or(0);
+                }
+            }
+            for symbol in &sample.symbols {
+                let position = *positions.entry(symbol.name.clone()).or_insert_with(|| {
+                    counts.push((
+                        symbol.name.clone(),
+                        vec![0u64; n],
+                        vec![vec![0u64; n]; n_events],
+                    ));
+                    counts.len() - 1
+                });
+                let (_, irs, symbol_events) = &mut counts[position];
+                irs[i] = irs[i].saturating_add(symbol.ir);
+                for (j, index) in indices.iter().enumerate() {
+                    if let Some(index) = index {
+                        let count = symbol.events.get(*index).copied().unwrap_or(0);
+                        symbol_events[j][i] = symbol_events[j][i].saturating_add(count);
+                    }
+                }
+            }
+        }
+
+        let mut samples = samples.into_iter();
+        let first = samples.next().unwrap();
+        let total_irs = std::iter::once(first.total_ir)
+            .chain(samples.map(|sample| sample.total_ir))
+            .collect::<Vec<_>>();
+        Run {
+            name: first.name,
+            symbols: counts
+                .into_iter()
+                .map(|(name, irs, events)| {
+                    // Means are computed exactly, as counts may be too large for an `f64`.
+                    AnnotatedSymbol {
+                        name,
+                        ir: mean_of(&irs),
+                        stddev: mean_and_stddev(&irs).1,
+                        events: events.iter().map(|counts| mean_of(counts)).collect(),
+                    }
+                })
+                .collect(),
+            total_ir: mean_of(&total_irs),
+            metadata: first.metadata,
+            events,
+            event_totals: event_totals.iter().map(|totals| mean_of(totals)).collect(),
+            index: SymbolIndex::default(),
+        }
+    }
+
+    /// Find the symbols of samples whose IR count deviates by more than `sigmas` standard
+    /// deviations from their IR count in the other samples.
+    ///
+    /// Each sample is compared to the mean and standard deviation of the others, so that an
+    /// outlier does not widen the band it is checked against. If the others all agree, any
+    /// deviation is an outlier. A sample in which a symbol does not appear counts as 0. At least 3
+    /// samples are needed to tell which one deviates, so none is found in fewer.
+    ///
+    /// Outliers are returned sample by sample, in the order in which symbols first appear.
+    pub fn find_outliers(samples: &[Run], sigmas: f64) -> Vec<Outlier> {
+        let n = samples.len();
+        if n < 3 {
+            return vec![];
+        }
+        let mut irs: Vec<(Arc<str>, Vec<u64>)> = vec![];
+        let mut positions: HashMap<Arc<str>, usize> = HashMap::new();
+        for (i, sample) in samples.iter().enumerate() {
+            for symbol in &sample.symbols {
+                let position = *positions.entry(symbol.name.clone()).or_insert_with(|| {
+                    irs.push((symbol.name.clone(), vec![0; n]));
+                    irs.len() - 1
+                });
+                irs[position].1[i] = irs[position].1[i].saturating_add(symbol.ir);
+            }
+        }
+
+        let mut outliers = vec![];
+        for sample in 0..n {
+            for (symbol, irs) in &irs {
+                let ir = irs[sample];
+                let others = irs
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, _)| *i != sample)
+                    .map(|(_, ir)| *ir)
+                    .collect::<Vec<_>>();
+                let (mean, stddev) = mean_and_stddev(&others);
+                let deviation = (ir as f64 - mean).abs();
+                let deviation = if stddev > 0.0 {
+                    deviation / stddev
+                } else if deviation > 0.0 {
+                    f64::INFINITY
+                } else {
+                    0.0
+                };
+                if deviation > sigmas {
+                    outliers.push(Outlier {
+                        sample,
+                        symbol: Arc::clone(symbol),
+                        ir,
+                        mean,
+                        sigmas: deviation,
+                    });
+                }
+            }
+        }
+        outliers
     }
 
     /// Load a run from a `callgrind_annotate` output file.
+    ///
+    /// Loading fails if the file exceeds any of the given `limits`.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be read, cannot be parsed or exceeds the limits.
     pub fn from_callgrind_annotate_file<P: AsRef<Path>>(
         path: P,
         replacements: &[StringReplacement],
+        limits: InputLimits,
     ) -> Result<Self> {
-        Ok(crate::callgrind::parse(
-            BufReader::new(File::open(path)?),
+        let path = path.as_ref();
+        let file = File::open(path).map_err(|error| Error::from(error).in_file(path))?;
+        let too_large = |max_size| Error::TooLarge {
+            path: Some(path.to_path_buf()),
+            max_size,
+        };
+        if let Some(max) = limits.max_size {
+            // Fail early on regular files. Other files (e.g. pipes) are checked while reading.
+            if file.metadata().is_ok_and(|metadata| metadata.len() > max) {
+                return Err(too_large(max));
+            }
+        }
+        let mut reader = LimitedReader::new(file, limits.max_size);
+        let run = crate::callgrind::parse(
+            BufReader::new(&mut reader),
             replacements,
-        ))
+            limits.max_symbols,
+        );
+        // A truncated input may fail to parse; report the actual cause.
+        if reader.exceeded() {
+            return Err(too_large(limits.max_size.unwrap_or_default()));
+        }
+        run.map_err(|error| error.in_file(path))
     }
 }
 
@@ -70,6 +443,7 @@ impl Run {
 /// The annotations do make sense only if they all refer to the same binary (though it may be at
 /// different stages of development).
 #[derive(Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Records {
     /// The names of the runs, if any. This is purely for human readability purposes.
     ///
@@ -78,8 +452,25 @@ pub struct Records {
     pub run_names: Vec<String>,
     /// The total IR of each run.
     pub runs_total_irs: Vec<u64>,
+    /// The metadata of each run.
+    pub runs_metadata: Vec<RunMetadata>,
+    /// The names of the events other than `Ir` that were recorded in any of the runs.
+    pub events: Vec<String>,
+    /// The total count of each event of [`Self::events`], for each run.
+    ///
+    /// This is indexed by event, then by run. Runs which did not record an event have a count of
+    /// 0 for it.
+    pub runs_event_totals: Vec<Vec<u64>>,
+    /// Whether each run recorded each event of [`Self::events`].
+    ///
+    /// This is indexed as [`Self::runs_event_totals`] is, so that the counts of 0 of the runs
+    /// which did not record an event are told from measured ones.
+    pub runs_event_recorded: Vec<Vec<bool>>,
     /// The symbols and their IR count for each run.
     pub symbols: Vec<RecordsSymbol>,
+    /// The position of each symbol in [`Self::symbols`], by name.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    index: SymbolIndex,
 }
 
 impl Records {
@@ -89,38 +480,107 @@ impl Records {
     }
 
     /// Add annotations about a run to the records.
+    ///
+    /// Events are matched by name. Events that are new to the records are given a count of 0 for
+    /// previous runs.
+    ///
+    /// The run must be well-formed, as runs built with [`Run::add_counts`] are. Runs and records
+    /// whose public fields were modified should be added with [`Self::try_add_run`] instead; in
+    /// debug builds, this panics if an invariant is broken (see [`Self::assert_invariants`]).
     pub fn add_run(&mut self, run: Run) {
         self.assert_invariants();
 
+        // Register new events and find where each event of the run goes.
+        let n_symbol_runs = self.n_runs();
+        let event_indices = run
+            .events
+            .iter()
+            .map(|event| {
+                self.event_index(event).unwrap_or_else(|| {
+                    self.events.push(event.clone());
+                    self.runs_event_totals.push(vec![0; n_symbol_runs]);
+                    self.runs_event_recorded.push(vec![false; n_symbol_runs]);
+                    for symbol in &mut self.symbols {
+                        symbol.events.push(vec![0; n_symbol_runs]);
+                    }
+                    self.events.len() - 1
+                })
+            })
+            .collect::<Vec<_>>();
+        let n_events = self.events.len();
+        // Symbols which the run does not list are measured as 0 only if the run is complete.
+        let complete = run
+            .symbols
+            .iter()
+            .map(|symbol| u128::from(symbol.ir))
+            .sum::<u128>()
+            >= u128::from(run.total_ir);
+        // Whether the previous runs are complete, computed once a symbol is new to the records.
+        let mut previous_complete = None;
+
         for run_symbol in run.symbols {
             // Add an `irs` entry for each symbol.
-            if let Some(ref mut symbol) = self
-                .symbols
-                .iter_mut()
-                .find(|symbol| symbol.name == run_symbol.name)
-            {
-                symbol.irs.push(run_symbol.ir);
+            let position =
+                self.index
+                    .position(&self.symbols, |symbol| &symbol.name, &run_symbol.name);
+            let symbol = if let Some(position) = position {
+                &mut self.symbols[position]
             } else {
                 // If we can't find the symbol, we have to create it. However, we must already push
                 // `self.n_runs()` zeroes into it to account for previous runs.
-                let mut new_symbol = RecordsSymbol {
+                let measured = previous_complete
+                    .get_or_insert_with(|| {
+                        (0..n_symbol_runs)
+                            .map(|column| self.unaccounted_ir(column) == 0)
+                            .collect::<Vec<_>>()
+                    })
+                    .clone();
+                let position = self.symbols.len();
+                self.index.insert(&run_symbol.name, position);
+                self.symbols.push(RecordsSymbol {
                     name: run_symbol.name,
-                    irs: vec![0; self.n_runs()],
-                };
-                new_symbol.irs.push(run_symbol.ir);
-                self.symbols.push(new_symbol);
+                    irs: vec![0; n_symbol_runs],
+                    stddevs: vec![0.0; n_symbol_runs],
+                    measured,
+                    events: vec![vec![0; n_symbol_runs]; n_events],
+                    renamed_from: vec![],
+                });
+                &mut self.symbols[position]
+            };
+            symbol.irs.push(run_symbol.ir);
+            symbol.stddevs.push(run_symbol.stddev);
+            symbol.measured.push(true);
+            for (index, count) in event_indices.iter().zip(run_symbol.events) {
+                symbol.events[*index].push(count);
             }
         }
 
         // Push the name of the run, this will update [`Self::n_runs`].
         self.run_names.push(run.name);
         self.runs_total_irs.push(run.total_ir);
+        self.runs_metadata.push(run.metadata);
+        for (index, total) in event_indices.iter().zip(run.event_totals) {
+            self.runs_event_totals[*index].push(total);
+            self.runs_event_recorded[*index].push(true);
+        }
 
         let n_runs = self.n_runs();
-        // Add a 0 to each symbol that was not hit by the run.
+        // Add a 0 to each symbol that was not hit by the run (measured only if the run is
+        // complete), and to each event the run did not record.
+        for totals in &mut self.runs_event_totals {
+            totals.resize(n_runs, 0);
+        }
+        for recorded in &mut self.runs_event_recorded {
+            recorded.resize(n_runs, false);
+        }
         for ref mut symbol in &mut self.symbols {
             if symbol.irs.len() != n_runs {
                 symbol.irs.push(0);
+                symbol.stddevs.push(0.0);
+                symbol.measured.push(complete);
+            }
+            for counts in &mut symbol.events {
+                counts.resize(n_runs, 0);
             }
         }
 
@@ -128,82 +588,987 @@ impl Records {
         self.assert_invariants();
     }
 
+    /// Add annotations about a run to the records, checking both beforehand.
+    ///
+    /// Unlike [`Self::add_run`], this never panics. The records are left untouched if an error is
+    /// returned.
+    ///
+    /// ```
+    /// # use callgrind_differ::runs::{AnnotatedSymbol, Records, Run};
+    /// let mut run = Run::new();
+    /// run.add_ir("foo", 12);
+    /// run.symbols.push(AnnotatedSymbol { name: "foo".into(), ..Default::default() });
+    /// let mut records = Records::new();
+    /// assert!(records.try_add_run(run).is_err());
+    /// assert_eq!(records.n_runs(), 0);
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error if an invariant of the records is broken (see
+    /// [`Self::check_invariants`]), or if the run is invalid (see [`Run::validate`]).
+    pub fn try_add_run(&mut self, run: Run) -> Result<()> {
+        self.check_invariants()?;
+        run.validate()?;
+        self.add_run(run);
+        Ok(())
+    }
+
+    /// Add annotations about a run to the records, as the column at `index`.
+    ///
+    /// Runs at `index` and after are shifted to the right. Symbols are aligned as with
+    /// [`Self::try_add_run`]. The records are left untouched if an error is returned.
+    ///
+    /// ```
+    /// # use callgrind_differ::runs::{Records, Run};
+    /// let mut records = Records::new();
+    /// records.add_run(Run::new_named("a".to_string()));
+    /// records.add_run(Run::new_named("c".to_string()));
+    /// records.append_run_at(1, Run::new_named("b".to_string())).unwrap();
+    /// assert_eq!(records.run_names, ["a", "b", "c"]);
+    /// assert!(records.append_run_at(0, Run::new_named("a".to_string())).is_err());
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error if `index` is greater than the number of runs, if the records already
+    /// have a run with the same (non-blank) name, or if the run cannot be added (see
+    /// [`Self::try_add_run`]).
+    pub fn append_run_at(&mut self, index: usize, run: Run) -> Result<()> {
+        let n_runs = self.n_runs();
+        if index > n_runs {
+            return Err(Error::ColumnOutOfRange {
+                column: index,
+                n_columns: n_runs,
+            });
+        }
+        self.check_run_names(std::slice::from_ref(&run.name))?;
+        self.try_add_run(run)?;
+
+        // The run was added as the last column: move it to `index`.
+        self.run_names[index..].rotate_right(1);
+        self.runs_total_irs[index..].rotate_right(1);
+        self.runs_metadata[index..].rotate_right(1);
+        for totals in &mut self.runs_event_totals {
+            totals[index..].rotate_right(1);
+        }
+        for recorded in &mut self.runs_event_recorded {
+            recorded[index..].rotate_right(1);
+        }
+        for symbol in &mut self.symbols {
+            symbol.irs[index..].rotate_right(1);
+            symbol.stddevs[index..].rotate_right(1);
+            symbol.measured[index..].rotate_right(1);
+            for counts in &mut symbol.events {
+                counts[index..].rotate_right(1);
+            }
+        }
+        self.assert_invariants();
+        Ok(())
+    }
+
+    /// Remove the runs of the columns in `columns`.
+    ///
+    /// Symbols left with no count in any of the remaining runs are removed as well, as they would
+    /// be missing from records built from the remaining runs only. Events are kept.
+    ///
+    /// ```
+    /// # use callgrind_differ::builder::RecordsBuilder;
+    /// # fn main() -> callgrind_differ::error::Result<()> {
+    /// let mut records = RecordsBuilder::new();
+    /// records.add_named_run("a", [("parse", 500)]);
+    /// records.add_named_run("b", [("parse", 450), ("init", 10)]);
+    /// records.add_named_run("c", [("parse", 400)]);
+    /// let mut records = records.finish()?;
+    /// records.remove_runs(1..2)?;
+    /// assert_eq!(records.run_names, ["a", "c"]);
+    /// assert_eq!(records.symbols.len(), 1);
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error if `columns` ends after the last run. The records are then left
+    /// untouched.
+    pub fn remove_runs(&mut self, columns: Range<usize>) -> Result<()> {
+        let n_runs = self.n_runs();
+        if columns.end > n_runs {
+            return Err(Error::ColumnOutOfRange {
+                column: columns.end - 1,
+                n_columns: n_runs,
+            });
+        }
+        self.run_names.drain(columns.clone());
+        self.runs_total_irs.drain(columns.clone());
+        self.runs_metadata.drain(columns.clone());
+        for totals in &mut self.runs_event_totals {
+            totals.drain(columns.clone());
+        }
+        for recorded in &mut self.runs_event_recorded {
+            recorded.drain(columns.clone());
+        }
+        for symbol in &mut self.symbols {
+            symbol.irs.drain(columns.clone());
+            symbol.stddevs.drain(columns.clone());
+            symbol.measured.drain(columns.clone());
+            for counts in &mut symbol.events {
+                counts.drain(columns.clone());
+            }
+        }
+        self.symbols.retain(|symbol| {
+            symbol.irs.iter().any(|&ir| ir != 0)
+                || symbol.events.iter().flatten().any(|&count| count != 0)
+        });
+        self.index.rebuild(&self.symbols, |symbol| &symbol.name);
+        self.assert_invariants();
+        Ok(())
+    }
+
+    /// Append the runs of `other` after those of `self`.
+    ///
+    /// This combines records loaded from different sources (e.g. a history per machine). Symbols
+    /// and events are matched by name. Those which are missing from either records are given a
+    /// count of 0 for the runs of these records, measured as per [`RecordsSymbol::measured`].
+    ///
+    /// ```
+    /// # use callgrind_differ::builder::RecordsBuilder;
+    /// # fn main() -> callgrind_differ::error::Result<()> {
+    /// let mut records = RecordsBuilder::new();
+    /// records.add_named_run("x86", [("parse", 500), ("main", 50)]);
+    /// let mut records = records.finish()?;
+    /// let mut other = RecordsBuilder::new();
+    /// other.add_named_run("arm", [("parse", 450), ("init", 10)]);
+    /// records.merge(other.finish()?)?;
+    /// assert_eq!(records.run_names, ["x86", "arm"]);
+    /// assert_eq!(records.symbols.len(), 3);
+    /// assert_eq!(records.symbols[2].irs, [0, 10]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error if runs of both records have the same (non-blank) name, or if an
+    /// invariant of either records is broken (see [`Self::check_invariants`]). `self` is left
+    /// untouched if an error is returned.
+    pub fn merge(&mut self, other: Records) -> Result<()> {
+        self.check_invariants()?;
+        other.check_invariants()?;
+        self.check_run_names(&other.run_names)?;
+        for run in other.into_runs() {
+            self.add_run(run);
+        }
+        Ok(())
+    }
+
+    /// Return an error listing the non-blank names of `names` which are already those of runs of
+    /// `self`.
+    fn check_run_names(&self, names: &[String]) -> Result<()> {
+        let names = names
+            .iter()
+            .filter(|name| !name.is_empty() && self.run_names.contains(name))
+            .cloned()
+            .collect::<Vec<_>>();
+        if names.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::DuplicateRunNames { names })
+        }
+    }
+
+    /// Split the records back into their runs.
+    ///
+    /// Every run has the events it recorded, and every symbol of the records but those it did not
+    /// measure.
+    fn into_runs(self) -> Vec<Run> {
+        // The events recorded by the run at `column`, by index.
+        let recorded = &self.runs_event_recorded;
+        let recorded_events =
+            |column: usize| (0..recorded.len()).filter(move |event| recorded[*event][column]);
+        let mut runs = self
+            .run_names
+            .into_iter()
+            .zip(self.runs_total_irs)
+            .zip(self.runs_metadata)
+            .enumerate()
+            .map(|(column, ((name, total_ir), metadata))| Run {
+                name,
+                total_ir,
+                metadata,
+                events: recorded_events(column)
+                    .map(|event| self.events[event].clone())
+                    .collect(),
+                event_totals: recorded_events(column)
+                    .map(|event| self.runs_event_totals[event][column])
+                    .collect(),
+                ..Run::default()
+            })
+            .collect::<Vec<_>>();
+        for symbol in self.symbols {
+            for (column, run) in runs.iter_mut().enumerate() {
+                if !symbol.measured[column] {
+                    continue;
+                }
+                run.index.insert(&symbol.name, run.symbols.len());
+                run.symbols.push(AnnotatedSymbol {
+                    name: Arc::clone(&symbol.name),
+                    ir: symbol.irs[column],
+                    stddev: symbol.stddevs[column],
+                    events: recorded_events(column)
+                        .map(|event| symbol.events[event][column])
+                        .collect(),
+                });
+            }
+        }
+        runs
+    }
+
+    /// Return the index of the given event in [`Self::events`].
+    pub fn event_index(&self, event: &str) -> Option<usize> {
+        self.events.iter().position(|name| name == event)
+    }
+
     /// Sort the symbols according to the given order.
     ///
-    /// See [`SortBy`] for more details.
+    /// See [`SortBy`] for more details. Symbols with the same IR are sorted by ascending name,
+    /// whichever the order, so that the result does not depend on the order the symbols were
+    /// added in.
+    ///
+    /// # Errors
+    /// Returns an error if the column to sort by does not exist.
     pub fn sort(&mut self, by: SortBy) -> Result<()> {
         let n = self.n_runs();
-        match by.field {
-            SortByField::Symbol => self.symbols.sort_by(|a, b| a.name.cmp(&b.name)),
-            SortByField::FirstIR => self.symbols.sort_by(|a, b| a.irs[0].cmp(&b.irs[0])),
-            SortByField::LastIR => self.symbols.sort_by(|a, b| a.irs[n - 1].cmp(&b.irs[n - 1])),
-            SortByField::ColumnIR(x) if (x as usize) < n => self
-                .symbols
-                .sort_by(|a, b| a.irs[x as usize].cmp(&b.irs[x as usize])),
-            SortByField::ColumnIR(x) => bail!("Invalid column {x} (got {n} columns)"),
+        let column = match by.field {
+            // Impact depends on the references, and is sorted by the `DiffTable`.
+            SortByField::Symbol | SortByField::Trend | SortByField::Impact => None,
+            SortByField::FirstIR => Some(0),
+            // Without runs, there are no symbols to sort.
+            SortByField::LastIR => Some(n.saturating_sub(1)),
+            SortByField::ColumnIR(x) if (x as usize) < n => Some(x as usize),
+            SortByField::ColumnIR(x) => {
+                return Err(Error::ColumnOutOfRange {
+                    column: x as usize,
+                    n_columns: n,
+                })
+            }
+        };
+        let directed = |ordering: Ordering| match by.order {
+            SortByOrder::Ascending => ordering,
+            SortByOrder::Descending => ordering.reverse(),
+        };
+
+        // Names are unique, so that this is a total order and an unstable sort is deterministic.
+        if matches!(by.field, SortByField::Trend) {
+            // Slopes are computed once per symbol rather than once per comparison.
+            let mut symbols = std::mem::take(&mut self.symbols)
+                .into_iter()
+                .map(|symbol| (symbol.ir_slope(), symbol))
+                .collect::<Vec<_>>();
+            symbols.sort_unstable_by(|(a_slope, a), (b_slope, b)| {
+                directed(a_slope.total_cmp(b_slope)).then_with(|| a.name.cmp(&b.name))
+            });
+            self.symbols = symbols.into_iter().map(|(_, symbol)| symbol).collect();
+        } else {
+            self.symbols.sort_unstable_by(|a, b| {
+                let ordering = match column {
+                    Some(column) => a.irs[column].cmp(&b.irs[column]),
+                    None => a.name.cmp(&b.name),
+                };
+                directed(ordering).then_with(|| a.name.cmp(&b.name))
+            });
         }
+        self.index.rebuild(&self.symbols, |symbol| &symbol.name);
 
-        if matches!(by.order, SortByOrder::Descending) {
-            self.symbols.reverse();
+        Ok(())
+    }
+
+    /// Merge the symbols whose names map to the same key into a single symbol, named after the
+    /// key (see `--group-summary`).
+    ///
+    /// The counts of the merged symbols are summed run by run, as are the variances of their
+    /// counts. A group is measured in the runs in which any of its symbols is. Groups are in the
+    /// order of the first of their symbols.
+    pub fn group_by<F: Fn(&str) -> &str>(&mut self, key: F) {
+        let mut positions = HashMap::<&str, usize>::new();
+        let mut groups = Vec::<RecordsSymbol>::new();
+        for symbol in &self.symbols {
+            let position = *positions
+                .entry(key(&symbol.name))
+                .or_insert_with_key(|name| {
+                    groups.push(RecordsSymbol {
+                        name: Arc::from(*name),
+                        irs: vec![0; symbol.irs.len()],
+                        stddevs: vec![0.0; symbol.stddevs.len()],
+                        measured: vec![false; symbol.measured.len()],
+                        events: vec![vec![0; symbol.irs.len()]; symbol.events.len()],
+                        renamed_from: vec![],
+                    });
+                    groups.len() - 1
+                });
+            let group = &mut groups[position];
+            for (total, ir) in group.irs.iter_mut().zip(&symbol.irs) {
+                *total = total.saturating_add(*ir);
+            }
+            for (total, stddev) in group.stddevs.iter_mut().zip(&symbol.stddevs) {
+                // Kept as variances until all symbols are summed.
+                *total += stddev * stddev;
+            }
+            for (group, measured) in group.measured.iter_mut().zip(&symbol.measured) {
+                *group |= measured;
+            }
+            for (totals, counts) in group.events.iter_mut().zip(&symbol.events) {
+                for (total, count) in totals.iter_mut().zip(counts) {
+                    *total = total.saturating_add(*count);
+                }
+            }
+        }
+        for group in &mut groups {
+            for stddev in &mut group.stddevs {
+                *stddev = stddev.sqrt();
+            }
+        }
+        self.symbols = groups;
+        self.index.rebuild(&self.symbols, |symbol| &symbol.name);
+    }
+
+    /// Keep the smallest set of symbols which account for `percent` of the total IR of the run at
+    /// index `column`, and merge the others into a single [`PARETO_REMAINDER`] symbol (see
+    /// `--pareto`).
+    ///
+    /// Symbols are taken from the hottest in that run. If the symbols do not account for enough
+    /// of the total IR (e.g. `callgrind_annotate` omitted those below its threshold), all of them
+    /// are kept.
+    ///
+    /// # Panics
+    /// Panics if `column` is out of range.
+    pub fn pareto_cutoff(&mut self, column: usize, percent: f64) {
+        let target = self.runs_total_irs[column] as f64 * percent / 100.0;
+        let mut hottest = self.symbols.iter().collect::<Vec<_>>();
+        hottest.sort_unstable_by(|a, b| {
+            b.irs[column]
+                .cmp(&a.irs[column])
+                .then_with(|| a.name.cmp(&b.name))
+        });
+        let mut sum = 0u128;
+        let dominant = hottest
+            .into_iter()
+            .take_while(|symbol| {
+                let reached = sum as f64 >= target;
+                sum += u128::from(symbol.irs[column]);
+                !reached
+            })
+            .map(|symbol| Arc::clone(&symbol.name))
+            .collect::<HashSet<_>>();
+        if dominant.len() < self.symbols.len() {
+            self.group_by(|name| {
+                if dominant.contains(name) {
+                    name
+                } else {
+                    PARETO_REMAINDER
+                }
+            });
         }
+    }
 
+    /// Replace the runs by one run per group of runs, each of which is the given name and the
+    /// indices of its runs (see `--control` and `--treatment`).
+    ///
+    /// The counts of each symbol in a group run are the mean of its counts in the runs of the
+    /// group, and its standard deviation is that of its IR counts across them, as if they were
+    /// samples merged with `--merge`. A symbol is measured in a group run if it is in all of its
+    /// runs, and an event recorded if all of its runs recorded it. The metadata of a group run is
+    /// that of its first run.
+    ///
+    /// # Errors
+    /// Returns an error if a group is empty or refers to a run which does not exist.
+    pub fn aggregate_groups(&mut self, groups: &[(&str, &[usize])]) -> Result<()> {
+        let n_columns = self.n_runs();
+        for (name, columns) in groups {
+            if columns.is_empty() {
+                return Err(Error::InvalidRun {
+                    run: (*name).to_string(),
+                    reason: "The group has no run".to_string(),
+                });
+            }
+            if let Some(column) = columns.iter().find(|column| **column >= n_columns) {
+                return Err(Error::ColumnOutOfRange {
+                    column: *column,
+                    n_columns,
+                });
+            }
+        }
+        let aggregate = |counts: &[u64]| {
+            groups
+                .iter()
+                .map(|(_, columns)| mean_of(&columns.iter().map(|i| counts[*i]).collect_vec()))
+                .collect_vec()
+        };
+        self.run_names = groups.iter().map(|(name, _)| (*name).to_string()).collect();
+        self.runs_total_irs = aggregate(&self.runs_total_irs);
+        self.runs_metadata = groups
+            .iter()
+            .map(|(_, columns)| self.runs_metadata[columns[0]].clone())
+            .collect();
+        for totals in &mut self.runs_event_totals {
+            *totals = aggregate(totals);
+        }
+        for recorded in &mut self.runs_event_recorded {
+            *recorded = groups
+                .iter()
+                .map(|(_, columns)| columns.iter().all(|i| recorded[*i]))
+                .collect();
+        }
+        for symbol in &mut self.symbols {
+            symbol.stddevs = groups
+                .iter()
+                .map(|(_, columns)| {
+                    mean_and_stddev(&columns.iter().map(|i| symbol.irs[*i]).collect_vec()).1
+                })
+                .collect();
+            symbol.irs = aggregate(&symbol.irs);
+            symbol.measured = groups
+                .iter()
+                .map(|(_, columns)| columns.iter().all(|i| symbol.measured[*i]))
+                .collect();
+            for counts in &mut symbol.events {
+                *counts = aggregate(counts);
+            }
+        }
         Ok(())
     }
 
+    /// Swap the instruction counts with the counts of the event at `index` in [`Self::events`],
+    /// so that the event is diffed instead (see `--event`).
+    ///
+    /// The instruction counts become the counts of an [`IR_EVENT`] event, in place of the
+    /// event. Standard deviations are reset to 0, as other events are not merged from multiple
+    /// samples. Symbols are not measured in the runs which did not record the event, rather than
+    /// measured as 0.
+    ///
+    /// # Panics
+    /// Panics if `index` is out of range.
+    pub fn swap_event(&mut self, index: usize) {
+        self.events[index] = IR_EVENT.to_string();
+        std::mem::swap(&mut self.runs_total_irs, &mut self.runs_event_totals[index]);
+        // Every run recorded instructions.
+        let recorded = std::mem::replace(
+            &mut self.runs_event_recorded[index],
+            vec![true; self.run_names.len()],
+        );
+        for symbol in &mut self.symbols {
+            std::mem::swap(&mut symbol.irs, &mut symbol.events[index]);
+            symbol.stddevs.fill(0.0);
+            for (measured, recorded) in symbol.measured.iter_mut().zip(&recorded) {
+                *measured &= recorded;
+            }
+        }
+    }
+
+    /// Divide all the counts of the run at index `column` by `divisor`, rounding to the nearest
+    /// integer (see `--per`).
+    ///
+    /// # Panics
+    /// Panics if `column` is out of range or if `divisor` is 0.
+    pub fn divide_run(&mut self, column: usize, divisor: u64) {
+        assert!(divisor > 0, "Cannot divide a run by 0");
+        let divide = |count: &mut u64| {
+            let remainder = *count % divisor;
+            *count = *count / divisor + u64::from(remainder >= divisor.div_ceil(2));
+        };
+        divide(&mut self.runs_total_irs[column]);
+        for totals in &mut self.runs_event_totals {
+            divide(&mut totals[column]);
+        }
+        for symbol in &mut self.symbols {
+            divide(&mut symbol.irs[column]);
+            symbol.stddevs[column] /= divisor as f64;
+            for counts in &mut symbol.events {
+                divide(&mut counts[column]);
+            }
+        }
+    }
+
+    /// Return the reference total IR made out of the given columns, i.e. their mean.
+    ///
+    /// See [`RelativeTo::reference_columns`](crate::args::RelativeTo::reference_columns).
+    pub fn reference_total_ir(&self, columns: Range<usize>) -> u64 {
+        mean_of(&self.runs_total_irs[columns])
+    }
+
+    /// Return the IR of the run at index `column` which is not accounted for by its symbols.
+    ///
+    /// `callgrind_annotate` omits the symbols below its `--threshold`, in which case the sum of
+    /// the IR of the symbols is lower than the total IR.
+    pub fn unaccounted_ir(&self, column: usize) -> u64 {
+        let accounted = self
+            .symbols
+            .iter()
+            .map(|symbol| u128::from(symbol.irs[column]))
+            .sum::<u128>();
+        u128::from(self.runs_total_irs[column]).saturating_sub(accounted) as u64
+    }
+
     /// Return the number of runs that have been stored in `Self`.
     pub fn n_runs(&self) -> usize {
         self.run_names.len()
     }
 
-    /// Make sure that the invariants of the structure are held.
+    /// Make sure that the invariants of the structure are held, in debug builds only.
     ///
     /// This function functionally does nothing, but checking integrity is cheap and may save time
-    /// in debugging.
+    /// in debugging. Use [`Self::check_invariants`] to check them in release builds as well.
     ///
     /// # Panics
-    /// This function panics if an invariant is broken.
+    /// In debug builds, this function panics if an invariant is broken.
     pub fn assert_invariants(&self) {
+        if cfg!(debug_assertions) {
+            if let Err(error) = self.check_invariants() {
+                panic!("{error}");
+            }
+        }
+    }
+
+    /// Check that the invariants of the structure are held.
+    ///
+    /// Every run must have a name, a total IR and metadata, every event a total and whether it was
+    /// recorded for each run, and every symbol an IR count, a standard deviation, whether it was
+    /// measured and a count of each event for each run.
+    ///
+    /// # Errors
+    /// Returns an error describing the first invariant that is broken, if any.
+    pub fn check_invariants(&self) -> Result<()> {
         let n_runs = self.n_runs();
+        let broken = |reason| Err(Error::InvalidRecords { reason });
 
         // The number of runs contained in `self.run_names` must match that of
         // `self.runs_total_irs`.
-        assert!(
-            n_runs == self.runs_total_irs.len(),
-            "Invalid # of total irs (got {}, expected{n_runs})",
-            self.runs_total_irs.len()
-        );
+        if self.runs_total_irs.len() != n_runs {
+            return broken(format!(
+                "Invalid # of total irs (got {}, expected {n_runs})",
+                self.runs_total_irs.len()
+            ));
+        }
+
+        // The number of runs contained in `self.run_names` must match that of
+        // `self.runs_metadata`.
+        if self.runs_metadata.len() != n_runs {
+            return broken(format!(
+                "Invalid # of run metadata (got {}, expected {n_runs})",
+                self.runs_metadata.len()
+            ));
+        }
+
+        // Each event must have a total for each run.
+        if self.runs_event_totals.len() != self.events.len() {
+            return broken(format!(
+                "Invalid # of event totals (got {}, expected {})",
+                self.runs_event_totals.len(),
+                self.events.len()
+            ));
+        }
+        for (event, totals) in self.events.iter().zip(&self.runs_event_totals) {
+            if totals.len() != n_runs {
+                return broken(format!(
+                    "Invalid # of runs for event {event} (got {}, expected {n_runs})",
+                    totals.len()
+                ));
+            }
+        }
+        if self.runs_event_recorded.len() != self.events.len() {
+            return broken(format!(
+                "Invalid # of recorded event flags (got {}, expected {})",
+                self.runs_event_recorded.len(),
+                self.events.len()
+            ));
+        }
+        for (event, recorded) in self.events.iter().zip(&self.runs_event_recorded) {
+            if recorded.len() != n_runs {
+                return broken(format!(
+                    "Invalid # of recorded flags for event {event} (got {}, expected {n_runs})",
+                    recorded.len()
+                ));
+            }
+        }
 
         // The number of runs contained in `self.run_names` must match that of each symbol in
         // `self.symbols`.
         for symbol in &self.symbols {
-            assert!(
-                symbol.irs.len() == n_runs,
-                "Invalid # of runs for symbol {} (got {}, expected {n_runs})",
-                symbol.name,
-                symbol.irs.len()
-            );
+            if symbol.irs.len() != n_runs {
+                return broken(format!(
+                    "Invalid # of runs for symbol {} (got {}, expected {n_runs})",
+                    symbol.name,
+                    symbol.irs.len()
+                ));
+            }
+            if symbol.stddevs.len() != n_runs {
+                return broken(format!(
+                    "Invalid # of standard deviations for symbol {} (got {}, expected {n_runs})",
+                    symbol.name,
+                    symbol.stddevs.len()
+                ));
+            }
+            if symbol.measured.len() != n_runs {
+                return broken(format!(
+                    "Invalid # of measured flags for symbol {} (got {}, expected {n_runs})",
+                    symbol.name,
+                    symbol.measured.len()
+                ));
+            }
+            if symbol.events.len() != self.events.len() {
+                return broken(format!(
+                    "Invalid # of events for symbol {} (got {}, expected {})",
+                    symbol.name,
+                    symbol.events.len(),
+                    self.events.len()
+                ));
+            }
+            for (event, counts) in self.events.iter().zip(&symbol.events) {
+                if counts.len() != n_runs {
+                    return broken(format!(
+                        "Invalid # of runs for event {event} of symbol {} (got {}, expected {n_runs})",
+                        symbol.name,
+                        counts.len()
+                    ));
+                }
+            }
         }
+        Ok(())
+    }
+}
+
+/// An index of symbols by name, so that looking a symbol up doesn't require a linear search.
+///
+/// The symbols themselves are kept in a `Vec`, in insertion order. The index maps their names to
+/// their position in it. Since the symbols are public, they may be modified without the index
+/// being updated; the index is then rebuilt when it is found to be out of sync.
+///
+/// The keys share their allocation with the names of the symbols.
+#[derive(Default, Debug, Clone)]
+struct SymbolIndex(HashMap<Arc<str>, usize>);
+
+impl SymbolIndex {
+    /// Return the position of the symbol named `name` in `symbols`, if any.
+    fn position<T>(
+        &mut self,
+        symbols: &[T],
+        name_of: fn(&T) -> &Arc<str>,
+        name: &str,
+    ) -> Option<usize> {
+        if self.0.len() != symbols.len() {
+            self.rebuild(symbols, name_of);
+        }
+        match self.0.get(name) {
+            Some(&i)
+                if symbols
+                    .get(i)
+                    .is_some_and(|symbol| &**name_of(symbol) == name) =>
+            {
+                Some(i)
+            }
+            Some(_) => {
+                self.rebuild(symbols, name_of);
+                self.0.get(name).copied()
+            }
+            None => None,
+        }
+    }
+
+    /// Record that the symbol named `name` is at position `i`.
+    fn insert(&mut self, name: &Arc<str>, i: usize) {
+        self.0.insert(Arc::clone(name), i);
+    }
+
+    /// Rebuild the index from scratch.
+    fn rebuild<T>(&mut self, symbols: &[T], name_of: fn(&T) -> &Arc<str>) {
+        self.0.clear();
+        self.0.extend(
+            symbols
+                .iter()
+                .enumerate()
+                .map(|(i, symbol)| (Arc::clone(name_of(symbol)), i)),
+        );
     }
 }
 
 /// A symbol in the file and its IR count for a single run.
-#[derive(Default)]
+///
+/// Names are shared: the name of a symbol is allocated once per run, and moved rather than copied
+/// when the run is added to [`Records`] (see [`Records::add_run`]).
+#[derive(Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct AnnotatedSymbol {
     /// The name of the symbol.
-    pub name: String,
+    pub name: Arc<str>,
     /// The instruction count for that run.
     pub ir: u64,
+    /// The standard deviation of the instruction count, if the run was merged from multiple
+    /// samples (see [`Run::merge`]) or estimated by Criterion.rs. 0 otherwise.
+    pub stddev: f64,
+    /// The counts of the other events of the run (see [`Run::events`]), in the same order.
+    pub events: Vec<u64>,
 }
 
 /// A symbol in the file and its IR counts for multiple runs.
 #[derive(Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct RecordsSymbol {
     /// The name of the symbol.
-    pub name: String,
+    pub name: Arc<str>,
     /// The instruction counts for different runs.
     ///
     /// When storing a collection of [`RecordsSymbol`]s, care must be taken in order to not assign
     /// an IR count of one run to another (i.e. before inserting, the length of `irs` for each
     /// [`RecordsSymbol`] in the collection must be the same).
     pub irs: Vec<u64>,
+    /// The standard deviations of the instruction counts for different runs.
+    ///
+    /// This has the same length as [`Self::irs`]. Runs that were not merged from multiple samples
+    /// have a standard deviation of 0.
+    pub stddevs: Vec<f64>,
+    /// Whether the symbol was measured in each run.
+    ///
+    /// This has the same length as [`Self::irs`]. A symbol is not measured in a run which does
+    /// not list it while its symbols do not account for its total IR (e.g. a `callgrind_annotate`
+    /// file with a threshold): its counts there are 0, but unknown.
+    pub measured: Vec<bool>,
+    /// The counts of the other events (see [`Records::events`]) for different runs.
+    ///
+    /// This is indexed by event, then by run.
+    pub events: Vec<Vec<u64>>,
+    /// The former names of the symbol, whose counts the history reports under its current name
+    /// (see `history alias`).
+    pub renamed_from: Vec<String>,
+}
+
+/// Statistics of IR counts across runs.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IrStats {
+    /// The lowest IR count.
+    pub min: u64,
+    /// The highest IR count.
+    pub max: u64,
+    /// The mean IR count, rounded to the nearest integer.
+    pub mean: u64,
+    /// The (sample) standard deviation of the IR counts, 0 for a single run.
+    pub stddev: f64,
+}
+
+impl IrStats {
+    /// Compute the statistics of the given IR counts.
+    ///
+    /// # Panics
+    /// This function panics if `irs` is empty.
+    pub fn of(irs: &[u64]) -> Self {
+        Self {
+            min: *irs
+                .iter()
+                .min()
+                .expect("Cannot compute the statistics of no value"),
+            max: *irs
+                .iter()
+                .max()
+                .expect("Cannot compute the statistics of no value"),
+            mean: mean_of(irs),
+            stddev: mean_and_stddev(irs).1,
+        }
+    }
+
+    /// Return the coefficient of variation of the IR counts, i.e. their standard deviation
+    /// relative to their mean, in percent. It is 0 if the mean is 0.
+    pub fn coefficient_of_variation(&self) -> f64 {
+        if self.mean == 0 {
+            0.0
+        } else {
+            self.stddev * 100.0 / self.mean as f64
+        }
+    }
+
+    /// Return how stable the IR counts are across runs (see `--show=stability`).
+    pub fn stability(&self) -> Stability {
+        let cv = self.coefficient_of_variation();
+        if cv < Stability::STABLE_BELOW {
+            Stability::Stable
+        } else if cv < Stability::UNSTABLE_FROM {
+            Stability::Moderate
+        } else {
+            Stability::Unstable
+        }
+    }
+}
+
+/// How much the IR counts of a symbol vary across runs, by their coefficient of variation (see
+/// [`IrStats::stability`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stability {
+    /// The coefficient of variation is below [`Self::STABLE_BELOW`].
+    Stable,
+    /// The coefficient of variation is between [`Self::STABLE_BELOW`] and
+    /// [`Self::UNSTABLE_FROM`].
+    Moderate,
+    /// The coefficient of variation is [`Self::UNSTABLE_FROM`] or more.
+    Unstable,
+}
+
+impl Stability {
+    /// The coefficient of variation (in percent) under which IR counts are stable.
+    pub const STABLE_BELOW: f64 = 1.0;
+    /// The coefficient of variation (in percent) from which IR counts are unstable.
+    pub const UNSTABLE_FROM: f64 = 10.0;
+
+    /// Return the mark of the stability: `●` when stable, `◐` when moderate and `○` when unstable.
+    pub fn mark(self) -> char {
+        match self {
+            Self::Stable => '●',
+            Self::Moderate => '◐',
+            Self::Unstable => '○',
+        }
+    }
+}
+
+impl RecordsSymbol {
+    /// Return the reference IR count made out of the given columns, i.e. their mean.
+    ///
+    /// See [`RelativeTo::reference_columns`](crate::args::RelativeTo::reference_columns).
+    pub fn reference_ir(&self, columns: Range<usize>) -> u64 {
+        mean_of(&self.irs[columns])
+    }
+
+    /// Return the statistics of the IR counts of the symbol across all runs.
+    ///
+    /// # Panics
+    /// This function panics if there is no run.
+    pub fn ir_stats(&self) -> IrStats {
+        IrStats::of(&self.irs)
+    }
+
+    /// Return whether the symbol was measured in the run at index `column` and in all the
+    /// `reference` columns (see [`Self::measured`]).
+    pub fn is_measured(&self, column: usize, reference: Range<usize>) -> bool {
+        self.measured[column] && self.measured[reference].iter().all(|measured| *measured)
+    }
+
+    /// Return the trend of the IR counts of the symbol across all runs (see [`slope_of`]).
+    pub fn ir_slope(&self) -> f64 {
+        slope_of(&self.irs)
+    }
+
+    /// Return whether the change between the run at index `column` and the reference made out
+    /// of the `reference` columns is within the noise band.
+    ///
+    /// The noise band is `sigmas` times the combined standard deviation of the run and the
+    /// reference. If no run was merged from multiple samples, only an absence of change is within
+    /// the band.
+    pub fn is_within_noise(&self, column: usize, reference: Range<usize>, sigmas: f64) -> bool {
+        let diff = self.irs[column].abs_diff(self.reference_ir(reference.clone())) as f64;
+        // The standard deviation of the mean of independent values.
+        let reference_stddev = self.stddevs[reference.clone()]
+            .iter()
+            .map(|x| x * x)
+            .sum::<f64>()
+            .sqrt()
+            / reference.len() as f64;
+        let band = sigmas * self.stddevs[column].hypot(reference_stddev);
+        diff <= band
+    }
+
+    /// Return whether the difference between the IR count of `column` and the mean of the
+    /// `reference` columns is larger than `sigmas` times their pooled standard deviation.
+    ///
+    /// The pooled standard deviation is the root mean square of the standard deviations of the
+    /// columns. If no column was merged from multiple samples, any change is significant.
+    pub fn is_significant(&self, column: usize, reference: Range<usize>, sigmas: f64) -> bool {
+        let diff = self.irs[column].abs_diff(self.reference_ir(reference.clone())) as f64;
+        let n = reference.len() + 1;
+        let pooled = (self.stddevs[reference]
+            .iter()
+            .chain(std::iter::once(&self.stddevs[column]))
+            .map(|x| x * x)
+            .sum::<f64>()
+            / n as f64)
+            .sqrt();
+        diff > sigmas * pooled
+    }
+}
+
+/// The name of the run of the control group of `--control`.
+pub const CONTROL_RUN: &str = "control";
+
+/// The name of the run of the treatment group of `--treatment`.
+pub const TREATMENT_RUN: &str = "treatment";
+
+/// The symbol into which `--pareto` merges the symbols outside of the dominant set.
+pub const PARETO_REMAINDER: &str = "(remainder)";
+
+/// The group of `--group-summary` of the symbols which are not in a crate (e.g. C functions).
+pub const NO_CRATE_GROUP: &str = "(no crate)";
+
+/// Return the crate of a Rust symbol, i.e. the first segment of its path, or [`NO_CRATE_GROUP`]
+/// if it has none.
+///
+/// The path of a trait implementation is that of its type.
+///
+/// ```
+/// # use callgrind_differ::runs::{crate_of, NO_CRATE_GROUP};
+/// assert_eq!(crate_of("yaml_rust2::parser::Parser<T>::next_token"), "yaml_rust2");
+/// assert_eq!(crate_of("<a::B as core::cmp::PartialEq>::eq"), "a");
+/// assert_eq!(crate_of("memcpy"), NO_CRATE_GROUP);
+/// ```
+pub fn crate_of(symbol: &str) -> &str {
+    let path = symbol.trim_start_matches(['<', '&', '*']);
+    let path = path.strip_prefix("mut ").unwrap_or(path);
+    let end = path
+        .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .unwrap_or(path.len());
+    if end != 0 && path[end..].starts_with("::") {
+        &path[..end]
+    } else {
+        NO_CRATE_GROUP
+    }
+}
+
+/// Return the mean of the given values, rounded to the nearest integer.
+///
+/// # Panics
+/// This function panics if `values` is empty.
+pub fn mean_of(values: &[u64]) -> u64 {
+    assert!(!values.is_empty(), "Cannot compute the mean of no value");
+    let n = values.len() as u128;
+    let sum = values.iter().map(|x| u128::from(*x)).sum::<u128>();
+    ((sum + n / 2) / n) as u64
+}
+
+/// Return the slope of the linear regression of the given values over their indices.
+///
+/// This is the change per index which best fits the values, in the least squares sense. The
+/// slope of fewer than 2 values is 0.
+pub fn slope_of(values: &[u64]) -> f64 {
+    if values.len() < 2 {
+        return 0.0;
+    }
+    let n = values.len() as f64;
+    let mean_x = (n - 1.0) / 2.0;
+    let mean_y = values.iter().map(|y| *y as f64).sum::<f64>() / n;
+    let (covariance, variance) = values
+        .iter()
+        .enumerate()
+        .map(|(x, y)| (x as f64 - mean_x, *y as f64 - mean_y))
+        .fold((0.0, 0.0), |(covariance, variance), (dx, dy)| {
+            (covariance + dx * dy, variance + dx * dx)
+        });
+    covariance / variance
+}
+
+/// Return the mean and the (sample) standard deviation of the given values.
+///
+/// The standard deviation of a single value is 0.
+fn mean_and_stddev(values: &[u64]) -> (f64, f64) {
+    let n = values.len() as f64;
+    let mean = values.iter().map(|x| *x as f64).sum::<f64>() / n;
+    if values.len() < 2 {
+        return (mean, 0.0);
+    }
+    let variance = values
+        .iter()
+        .map(|x| (*x as f64 - mean).powi(2))
+        .sum::<f64>()
+        / (n - 1.0);
+    (mean, variance.sqrt())
 }
@@ -0,0 +1,96 @@
+use std::{
+    sync::{Arc, PoisonError, RwLock, RwLockReadGuard},
+    thread::{self, JoinHandle},
+};
+
+use crate::{error::Result, runs::Records};
+
+/// [`Records`] shared between threads, e.g. one rendering them and one reloading the inputs when
+/// they change.
+///
+/// Records are replaced as a whole, and each replacement bumps a generation number, so that
+/// readers can tell whether they have to render again. Clones refer to the same records.
+///
+/// ```
+/// # use callgrind_differ::{builder::RecordsBuilder, shared::SharedRecords, runs::Records};
+/// let shared = SharedRecords::new(Records::new());
+/// let reload = shared.spawn_reload(|| {
+///     let mut builder = RecordsBuilder::new();
+///     builder.add_named_run("head", [("main", 100)]);
+///     builder.finish()
+/// });
+/// reload.join().unwrap().unwrap();
+/// assert_eq!(shared.generation(), 1);
+/// assert_eq!(shared.read().n_runs(), 1);
+/// ```
+#[derive(Clone, Default)]
+pub struct SharedRecords {
+    /// The records and their generation.
+    inner: Arc<RwLock<Versioned>>,
+}
+
+/// Records along with the number of times they were replaced.
+#[derive(Default)]
+struct Versioned {
+    /// The records.
+    records: Records,
+    /// The number of times [`Self::records`] were replaced.
+    generation: u64,
+}
+
+impl SharedRecords {
+    /// Share the given records. Their generation is 0.
+    pub fn new(records: Records) -> Self {
+        Self {
+            inner: Arc::new(RwLock::new(Versioned {
+                records,
+                generation: 0,
+            })),
+        }
+    }
+
+    /// Lock the records for reading and return them.
+    ///
+    /// Writers wait until the guard is dropped, so it should not be held for long.
+    pub fn read(&self) -> SharedRecordsGuard<'_> {
+        SharedRecordsGuard(self.inner.read().unwrap_or_else(PoisonError::into_inner))
+    }
+
+    /// Return the number of times the records were replaced.
+    pub fn generation(&self) -> u64 {
+        self.read().0.generation
+    }
+
+    /// Replace the records and return the new generation.
+    pub fn replace(&self, records: Records) -> u64 {
+        // Records are only ever replaced as a whole, so a panic of another writer cannot have
+        // left them half-updated.
+        let mut inner = self.inner.write().unwrap_or_else(PoisonError::into_inner);
+        inner.records = records;
+        inner.generation += 1;
+        inner.generation
+    }
+
+    /// Load records in a background thread, then replace the shared records with them.
+    ///
+    /// The records are not locked while `load` runs. If it fails, the shared records are left
+    /// untouched and the error is returned when joining the thread.
+    pub fn spawn_reload<F>(&self, load: F) -> JoinHandle<Result<u64>>
+    where
+        F: FnOnce() -> Result<Records> + Send + 'static,
+    {
+        let shared = self.clone();
+        thread::spawn(move || Ok(shared.replace(load()?)))
+    }
+}
+
+/// The records of [`SharedRecords`], locked for reading.
+pub struct SharedRecordsGuard<'a>(RwLockReadGuard<'a, Versioned>);
+
+impl std::ops::Deref for SharedRecordsGuard<'_> {
+    type Target = Records;
+
+    fn deref(&self) -> &Records {
+        &self.0.records
+    }
+}
@@ -0,0 +1,42 @@
+use std::{fs, mem::size_of};
+
+use crate::runs::{Records, RecordsSymbol};
+
+/// The number of bytes in a mebibyte.
+const MIB: f64 = (1 << 20) as f64;
+
+/// Return the peak memory used by the process so far, in bytes, if the platform tells.
+///
+/// This is the peak resident set size (`VmHWM` in `/proc/self/status`), available on Linux only.
+pub fn peak_memory() -> Option<u64> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmHWM:"))?;
+    let kib = line["VmHWM:".len()..]
+        .trim()
+        .strip_suffix("kB")?
+        .trim()
+        .parse::<u64>()
+        .ok()?;
+    Some(kib * 1024)
+}
+
+/// Return an estimate of the memory used by `records`, in bytes.
+///
+/// This accounts for the symbols, their names and their counts, which make up most of it, but not
+/// for the index of the symbols nor for allocator overhead.
+pub fn estimated_size(records: &Records) -> usize {
+    let n_runs = records.n_runs();
+    let per_symbol = size_of::<RecordsSymbol>()
+        + n_runs * (size_of::<u64>() + size_of::<f64>())
+        + records.events.len() * (size_of::<Vec<u64>>() + n_runs * size_of::<u64>());
+    records
+        .symbols
+        .iter()
+        .map(|symbol| per_symbol + symbol.name.len())
+        .sum()
+}
+
+/// Format a number of bytes in mebibytes.
+pub fn mib(bytes: u64) -> String {
+    format!("{:.1} MiB", bytes as f64 / MIB)
+}
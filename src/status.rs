@@ -0,0 +1,138 @@
+use std::process::ExitCode;
+
+/// Why the program exits. Each status has a distinct exit code so that pipelines can branch on
+/// the cause of a failure.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Status {
+    /// Everything went fine (exit code 0).
+    Ok,
+    /// The command line was invalid, or any error that is not covered by another status
+    /// occurred (exit code 1).
+    UsageError,
+    /// An input could not be read or parsed (exit code 2).
+    ParseError,
+    /// A symbol regressed beyond its allowed threshold, or a symbol required by
+    /// `--fail-on-missing-symbol` is missing (exit code 3).
+    RegressionDetected,
+    /// The total IR exceeded `--total-budget` (exit code 4).
+    BudgetViolated,
+}
+
+impl Status {
+    /// Return the exit code associated with the status.
+    pub fn code(self) -> u8 {
+        match self {
+            Self::Ok => 0,
+            Self::UsageError => 1,
+            Self::ParseError => 2,
+            Self::RegressionDetected => 3,
+            Self::BudgetViolated => 4,
+        }
+    }
+
+    /// Return the machine-readable name of the status.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Ok => "ok",
+            Self::UsageError => "usage-error",
+            Self::ParseError => "parse-error",
+            Self::RegressionDetected => "regression-detected",
+            Self::BudgetViolated => "budget-violated",
+        }
+    }
+
+    /// Return the status of a failure because of `error`: [`Self::ParseError`] if an input could
+    /// not be read or parsed (see [`crate::error::Error::is_input`]), [`Self::UsageError`]
+    /// otherwise (e.g. the history could not be locked or appended to).
+    pub fn of(error: &anyhow::Error) -> Self {
+        let is_input = error.chain().any(|cause| {
+            cause
+                .downcast_ref::<crate::error::Error>()
+                .is_some_and(crate::error::Error::is_input)
+        });
+        if is_input {
+            Self::ParseError
+        } else {
+            Self::UsageError
+        }
+    }
+
+    /// Return the line printed by `--report-status`.
+    ///
+    /// ```no_compile
+    /// callgrind_differ: status=regression-detected code=3
+    /// ```
+    pub fn report_line(self) -> String {
+        format!(
+            "callgrind_differ: status={} code={}",
+            self.name(),
+            self.code()
+        )
+    }
+}
+
+impl From<Status> for ExitCode {
+    fn from(status: Status) -> Self {
+        ExitCode::from(status.code())
+    }
+}
+
+/// An error, along with the status the program should exit with.
+pub struct Failure {
+    /// The status to exit with.
+    pub status: Status,
+    /// The error that caused the failure.
+    pub error: anyhow::Error,
+}
+
+/// Attach a [`Status`] to the error of a result.
+pub trait WithStatus<T> {
+    /// Turn the error, if any, into a [`Failure`] with the given status.
+    ///
+    /// # Errors
+    /// Returns the error of `self`, if any, as a [`Failure`].
+    fn with_status(self, status: Status) -> Result<T, Failure>;
+
+    /// Turn the error, if any, into a [`Failure`] with the status of its cause (see
+    /// [`Status::of`]), for steps which both load inputs and handle them.
+    ///
+    /// # Errors
+    /// Returns the error of `self`, if any, as a [`Failure`].
+    fn with_cause_status(self) -> Result<T, Failure>;
+}
+
+impl<T> WithStatus<T> for anyhow::Result<T> {
+    fn with_status(self, status: Status) -> Result<T, Failure> {
+        self.map_err(|error| Failure { status, error })
+    }
+
+    fn with_cause_status(self) -> Result<T, Failure> {
+        self.map_err(|error| Failure {
+            status: Status::of(&error),
+            error,
+        })
+    }
+}
+
+impl<T> WithStatus<T> for crate::error::Result<T> {
+    fn with_status(self, status: Status) -> Result<T, Failure> {
+        self.map_err(|error| Failure {
+            status,
+            error: error.into(),
+        })
+    }
+
+    fn with_cause_status(self) -> Result<T, Failure> {
+        self.map_err(anyhow::Error::from).with_cause_status()
+    }
+}
+
+impl<T> WithStatus<T> for std::io::Result<T> {
+    fn with_status(self, status: Status) -> Result<T, Failure> {
+        self.map_err(anyhow::Error::from).with_status(status)
+    }
+
+    fn with_cause_status(self) -> Result<T, Failure> {
+        self.map_err(anyhow::Error::from).with_cause_status()
+    }
+}
@@ -0,0 +1,220 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufRead, BufReader},
+    path::Path,
+};
+
+use anyhow::{bail, Context, Result};
+
+use crate::runs::Records;
+
+/// The inputs of a benchmark suite, each measuring a benchmark at a version of the program (see
+/// `--suite`).
+///
+/// A manifest is a file where each line is a benchmark, a version and the path of a
+/// `callgrind_annotate` file, optionally followed by the weight of the benchmark in the summary:
+/// ```no_compile
+/// # Lines starting with a `#` are comments.
+/// parse   v1.2  bench/parse-v1.2.cg   2
+/// parse   v1.3  bench/parse-v1.3.cg
+/// render  v1.2  bench/render-v1.2.cg
+/// render  v1.3  bench/render-v1.3.cg
+/// ```
+/// Benchmarks weigh 1 unless given otherwise, on any of their lines. Files of the same benchmark
+/// and version are samples of the same run, which are merged.
+#[derive(Default, Debug)]
+pub struct Manifest {
+    /// The inputs, in order.
+    pub entries: Vec<ManifestEntry>,
+    /// The weights of the benchmarks that were given one.
+    pub weights: HashMap<String, f64>,
+}
+
+/// A single input of a [`Manifest`].
+#[derive(Debug)]
+pub struct ManifestEntry {
+    /// The name of the benchmark.
+    pub benchmark: String,
+    /// The version of the program.
+    pub version: String,
+    /// The path of the `callgrind_annotate` file.
+    pub path: String,
+}
+
+impl Manifest {
+    /// Load a manifest from a file.
+    ///
+    /// Relative paths of inputs are relative to the directory of the manifest.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be read or is malformed.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let file =
+            File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+        let mut manifest = Self::parse(BufReader::new(file))
+            .with_context(|| format!("Failed to parse suite manifest {}", path.display()))?;
+        if let Some(dir) = path.parent() {
+            for entry in &mut manifest.entries {
+                entry.path = dir.join(&entry.path).to_string_lossy().into_owned();
+            }
+        }
+        Ok(manifest)
+    }
+
+    /// Parse a manifest from the format described in [`Manifest`].
+    ///
+    /// # Errors
+    /// Returns an error if the input cannot be read, a line is malformed or a benchmark is given
+    /// different weights.
+    pub fn parse<R: BufRead>(input: R) -> Result<Self> {
+        let mut manifest = Self::default();
+        for (i, line) in input.lines().enumerate() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let words = line.split_whitespace().collect::<Vec<_>>();
+            let (benchmark, version, path, weight) = match words[..] {
+                [benchmark, version, path] => (benchmark, version, path, None),
+                [benchmark, version, path, weight] => (benchmark, version, path, Some(weight)),
+                _ => bail!(
+                    "Line {}: expected `<benchmark> <version> <path> [weight]`",
+                    i + 1
+                ),
+            };
+            if let Some(weight) = weight {
+                let weight = match weight.parse::<f64>() {
+                    Ok(weight) if weight.is_finite() && weight > 0.0 => weight,
+                    _ => bail!("Line {}: invalid weight {weight}", i + 1),
+                };
+                let previous = manifest.weights.insert(benchmark.to_string(), weight);
+                if previous.is_some_and(|previous| previous.total_cmp(&weight).is_ne()) {
+                    bail!(
+                        "Line {}: benchmark {benchmark} is given different weights",
+                        i + 1
+                    );
+                }
+            }
+            manifest.entries.push(ManifestEntry {
+                benchmark: benchmark.to_string(),
+                version: version.to_string(),
+                path: path.to_string(),
+            });
+        }
+        if manifest.entries.is_empty() {
+            bail!("The manifest has no input");
+        }
+        Ok(manifest)
+    }
+
+    /// Return the weight of the given benchmark.
+    pub fn weight(&self, benchmark: &str) -> f64 {
+        self.weights.get(benchmark).copied().unwrap_or(1.0)
+    }
+}
+
+/// The ratios of the total IR of each version to that of the first one, benchmark by benchmark,
+/// and their geometric means over the suite.
+pub struct SuiteSummary<'a> {
+    /// The versions, in order of appearance. The first one is the baseline.
+    pub versions: Vec<&'a str>,
+    /// The benchmarks, in order of appearance.
+    pub benchmarks: Vec<BenchmarkRatios<'a>>,
+    /// The geometric mean of the ratios of each version, or `None` if no benchmark has a ratio
+    /// for it.
+    pub geomean: Vec<Option<f64>>,
+    /// The geometric mean of the ratios of each version, weighted by the weights of the
+    /// benchmarks.
+    pub weighted_geomean: Vec<Option<f64>>,
+}
+
+/// The ratios of a single benchmark of a [`SuiteSummary`].
+pub struct BenchmarkRatios<'a> {
+    /// The name of the benchmark.
+    pub name: &'a str,
+    /// The weight of the benchmark.
+    pub weight: f64,
+    /// The ratio of the total IR of each version to that of the baseline, or `None` if either
+    /// was not measured (or the baseline is 0).
+    pub ratios: Vec<Option<f64>>,
+}
+
+impl<'a> SuiteSummary<'a> {
+    /// Summarize the runs of `records` that belong to a benchmark, weighting benchmarks as per
+    /// `manifest`.
+    ///
+    /// Returns `None` if no run belongs to a benchmark.
+    pub fn new(records: &'a Records, manifest: &Manifest) -> Option<Self> {
+        let mut versions: Vec<&str> = vec![];
+        // The total IR of each benchmark, by version.
+        let mut totals: Vec<(&str, HashMap<&str, u64>)> = vec![];
+        for (metadata, total_ir) in records.runs_metadata.iter().zip(&records.runs_total_irs) {
+            let Some((benchmark, version)) = metadata.group() else {
+                continue;
+            };
+            if !versions.contains(&version) {
+                versions.push(version);
+            }
+            let position = totals
+                .iter()
+                .position(|(name, _)| *name == benchmark)
+                .unwrap_or_else(|| {
+                    totals.push((benchmark, HashMap::new()));
+                    totals.len() - 1
+                });
+            totals[position].1.insert(version, *total_ir);
+        }
+        if versions.is_empty() {
+            return None;
+        }
+
+        let benchmarks = totals
+            .into_iter()
+            .map(|(name, totals)| {
+                let baseline = totals.get(versions[0]).copied().filter(|total| *total != 0);
+                BenchmarkRatios {
+                    name,
+                    weight: manifest.weight(name),
+                    ratios: versions
+                        .iter()
+                        .map(|version| {
+                            let total = totals.get(version)?;
+                            Some(*total as f64 / baseline? as f64)
+                        })
+                        .collect(),
+                }
+            })
+            .collect::<Vec<_>>();
+        let geomean_of = |column: usize, weighted: bool| {
+            let (sum, weights) = benchmarks
+                .iter()
+                .filter_map(|benchmark| {
+                    let weight = if weighted { benchmark.weight } else { 1.0 };
+                    benchmark.ratios[column]
+                        .filter(|ratio| *ratio > 0.0)
+                        .map(|ratio| (ratio.ln() * weight, weight))
+                })
+                .fold((0.0, 0.0), |(sum, weights), (x, weight)| {
+                    (sum + x, weights + weight)
+                });
+            (weights > 0.0).then(|| (sum / weights).exp())
+        };
+        Some(Self {
+            geomean: (0..versions.len()).map(|i| geomean_of(i, false)).collect(),
+            weighted_geomean: (0..versions.len()).map(|i| geomean_of(i, true)).collect(),
+            versions,
+            benchmarks,
+        })
+    }
+
+    /// Return whether benchmarks have different weights, in which case the weighted geometric
+    /// mean differs from the plain one.
+    pub fn is_weighted(&self) -> bool {
+        self.benchmarks
+            .windows(2)
+            .any(|pair| pair[0].weight.total_cmp(&pair[1].weight).is_ne())
+    }
+}
@@ -0,0 +1,147 @@
+use std::fmt::Write;
+
+use crate::{
+    diff::{DiffCell, DiffTable},
+    regression::{Regression, Severity},
+};
+
+/// The headline numbers of the comparison of the last column to its reference.
+pub struct Summary<'a> {
+    /// The name of the last column.
+    pub name: &'a str,
+    /// The note of the run of the last column, if any (see `history annotate`).
+    pub note: Option<&'a str>,
+    /// The total IR of the reference.
+    pub reference_total_ir: u64,
+    /// The total IR of the last column.
+    pub total_ir: u64,
+    /// The number of symbols whose IR increased.
+    pub symbols_increased: usize,
+    /// The number of symbols whose IR decreased.
+    pub symbols_decreased: usize,
+    /// The symbols that regressed beyond a threshold.
+    pub regressions: &'a [Regression<'a>],
+}
+
+impl<'a> Summary<'a> {
+    /// Summarize the comparison of the last column of `table` to its reference.
+    pub fn new(table: &DiffTable<'a>, regressions: &'a [Regression<'a>]) -> Self {
+        let column = table.last_column();
+        let cells = table.rows.iter().map(|row| row.cells[column]);
+        let symbols_increased = cells.clone().filter(DiffCell::is_increase).count();
+        let symbols_decreased = cells.filter(DiffCell::is_decrease).count();
+        let total = table.total[column];
+        Self {
+            name: &table.records.run_names[column],
+            note: table.records.runs_metadata[column].note.as_deref(),
+            reference_total_ir: total.reference_ir(),
+            total_ir: total.ir,
+            symbols_increased,
+            symbols_decreased,
+            regressions,
+        }
+    }
+
+    /// Return the signed difference of total IR.
+    pub fn total_ir_diff(&self) -> i128 {
+        self.total_cell().diff()
+    }
+
+    /// Return the signed difference of total IR, in percent of the reference.
+    ///
+    /// See [`DiffCell::percent`].
+    pub fn total_ir_percent(&self) -> f64 {
+        self.total_cell().percent()
+    }
+
+    /// Return the total IR of the last column, compared to its reference.
+    fn total_cell(&self) -> DiffCell {
+        DiffCell::exact(self.total_ir, Some(self.reference_total_ir))
+    }
+
+    /// Return the number of regressions which crossed the failure threshold.
+    pub fn n_failures(&self) -> usize {
+        self.regressions
+            .iter()
+            .filter(|regression| regression.severity == Severity::Error)
+            .count()
+    }
+
+    /// Serialize the summary as a JSON object.
+    ///
+    /// ```no_compile
+    /// {
+    ///   "name": "v1.3",
+    ///   "note": "Switched to hashbrown",
+    ///   "reference_total_ir": 1000000,
+    ///   "total_ir": 1050000,
+    ///   "total_ir_diff": 50000,
+    ///   "total_ir_percent": 5.0,
+    ///   "symbols_increased": 2,
+    ///   "symbols_decreased": 1,
+    ///   "failures": 1,
+    ///   "regressions": [
+    ///     {"symbol": "parser::parse", "event": "Ir", "reference_ir": 500000, "ir": 550000, "percent": 10.0, "severity": "error"}
+    ///   ]
+    /// }
+    /// ```
+    pub fn to_json(&self) -> String {
+        let mut s = String::new();
+        s.push_str("{\n");
+        let _ = writeln!(s, "  \"name\": {},", json_string(self.name));
+        let note = self.note.map_or_else(|| "null".to_string(), json_string);
+        let _ = writeln!(s, "  \"note\": {note},");
+        let _ = writeln!(s, "  \"reference_total_ir\": {},", self.reference_total_ir);
+        let _ = writeln!(s, "  \"total_ir\": {},", self.total_ir);
+        let _ = writeln!(s, "  \"total_ir_diff\": {},", self.total_ir_diff());
+        let _ = writeln!(s, "  \"total_ir_percent\": {},", self.total_ir_percent());
+        let _ = writeln!(s, "  \"symbols_increased\": {},", self.symbols_increased);
+        let _ = writeln!(s, "  \"symbols_decreased\": {},", self.symbols_decreased);
+        let _ = writeln!(s, "  \"failures\": {},", self.n_failures());
+        s.push_str("  \"regressions\": [");
+        for (i, regression) in self.regressions.iter().enumerate() {
+            if i != 0 {
+                s.push(',');
+            }
+            let severity = match regression.severity {
+                Severity::Warning => "warning",
+                Severity::Error => "error",
+            };
+            let _ = write!(
+                s,
+                "\n    {{\"symbol\": {}, \"event\": {}, \"reference_ir\": {}, \"ir\": {}, \"percent\": {}, \"severity\": \"{severity}\"}}",
+                json_string(regression.symbol),
+                json_string(regression.event),
+                regression.reference_ir,
+                regression.ir,
+                regression.percent
+            );
+        }
+        if !self.regressions.is_empty() {
+            s.push_str("\n  ");
+        }
+        s.push_str("]\n}\n");
+        s
+    }
+}
+
+/// Return `s` as a quoted and escaped JSON string.
+pub fn json_string(s: &str) -> String {
+    let mut ret = String::with_capacity(s.len() + 2);
+    ret.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => ret.push_str("\\\""),
+            '\\' => ret.push_str("\\\\"),
+            '\n' => ret.push_str("\\n"),
+            '\r' => ret.push_str("\\r"),
+            '\t' => ret.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(ret, "\\u{:04x}", c as u32);
+            }
+            c => ret.push(c),
+        }
+    }
+    ret.push('"');
+    ret
+}
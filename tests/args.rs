@@ -0,0 +1,92 @@
+//! Tests of the validation of the command line, with and without a subcommand.
+
+use callgrind_differ::args::Args;
+use clap::Parser;
+
+/// Parse and validate the command line made of `args`, and return the error, if any.
+fn check(args: &[&str]) -> Result<(), String> {
+    let args =
+        Args::try_parse_from(std::iter::once("callgrind_differ").chain(args.iter().copied()))
+            .map_err(|error| error.to_string())?;
+    args.validated()
+        .map(|_| ())
+        .map_err(|error| error.to_string())
+}
+
+/// The subcommands which render comparisons, with the arguments they require.
+const COMPARISONS: [&[&str]; 4] = [
+    &["pr-compare", "--base=a.cg", "--head=b.cg"],
+    &["--history=h", "show"],
+    &["--history=h", "history", "show"],
+    &["--history=h", "history", "diff", "other"],
+];
+
+#[test]
+fn comparison_subcommands_are_checked() {
+    for command in COMPARISONS {
+        assert_eq!(check(command), Ok(()), "{command:?}");
+        for (options, error) in [
+            (
+                &["--burn-down"][..],
+                "`--burn-down` requires `--total-budget`",
+            ),
+            (
+                &["--show=acceleration", "--relative-to=first"],
+                "`--show=acceleration` requires `--relative-to=previous`",
+            ),
+            (
+                &["--control=0"],
+                "`--control` and `--treatment` must be given together",
+            ),
+            (
+                &["--control=0", "--treatment=0"],
+                "Run 0 cannot be in both `--control` and `--treatment`",
+            ),
+            (&["--merge=0"], "`--merge` must be at least 1"),
+            (&["--noise-band=-1"], "Invalid noise band: -1"),
+        ] {
+            let args = [options, command].concat();
+            assert_eq!(check(&args), Err(error.to_string()), "{args:?}");
+        }
+    }
+}
+
+#[test]
+fn history_options_are_checked() {
+    assert_eq!(
+        check(&[
+            "--compare-ref=main",
+            "pr-compare",
+            "--base=a.cg",
+            "--head=b.cg"
+        ]),
+        Err("`--compare-ref` requires `--history`".to_string())
+    );
+    assert_eq!(
+        check(&["--target=.hidden", "--history=h", "show"]),
+        Err(
+            "Invalid target \".hidden\": targets are made of letters, digits, `_`, `-` and `.`, \
+             and do not start with `.`"
+                .to_string()
+        )
+    );
+    assert_eq!(
+        check(&["show"]),
+        Err("`show` requires `--history`".to_string())
+    );
+    assert_eq!(
+        check(&["--read-only", "a.cg", "b.cg"]),
+        Err("`--read-only` requires `--history`".to_string())
+    );
+}
+
+#[test]
+fn inputs_are_checked_without_subcommand() {
+    assert_eq!(check(&[]), Err("No input file".to_string()));
+    assert_eq!(
+        check(&["--csv-names", "a", "b", "--", "tests/data/run1.cg"]),
+        Err("Mismatch between `csv-names` count 2 and number of callgrind files 1".to_string())
+    );
+    // Subcommands load their runs from elsewhere.
+    assert_eq!(check(&["--csv-names", "a", "--history=h", "show"]), Ok(()));
+}
@@ -0,0 +1,67 @@
+//! Tests of the outputs of valgrind's `cg_diff`, whose counts are signed changes.
+//!
+//! `tests/data/cg_diff.out` has an `Ir` event which is not the first, functions whose counts
+//! decreased, increased or both, and a function whose costs are split across two blocks.
+
+use std::{fs::File, io::BufReader, path::Path};
+
+use callgrind_differ::{cg_diff::parse, format::InputFormats, runs::Run};
+
+/// Parse `tests/data/cg_diff.out`.
+fn load() -> [Run; 2] {
+    let file = File::open("tests/data/cg_diff.out").unwrap();
+    let runs = parse(BufReader::new(file), &[], None).unwrap();
+    runs.try_into().unwrap_or_else(|_| panic!("Not two runs"))
+}
+
+/// Return the counts (`Ir` first) of `symbol` in `run`, or `None` if it is not in `run`.
+fn counts(run: &Run, symbol: &str) -> Option<Vec<u64>> {
+    let sym = run.symbols.iter().find(|sym| &*sym.name == symbol)?;
+    Some(
+        std::iter::once(sym.ir)
+            .chain(sym.events.iter().copied())
+            .collect(),
+    )
+}
+
+#[test]
+fn cg_diff_outputs_are_detected() {
+    let formats = InputFormats::default();
+    let format = formats.find(Path::new("tests/data/cg_diff.out")).unwrap();
+    assert_eq!(format.name(), "cg_diff");
+}
+
+#[test]
+fn changes_are_split_between_runs() {
+    let [old, new] = load();
+    assert_eq!(old.name, "cachegrind.out.old");
+    assert_eq!(new.name, "cachegrind.out.new");
+    assert_eq!(old.events, ["Dr", "D1mr"]);
+    assert_eq!(new.events, ["Dr", "D1mr"]);
+
+    // `parser::parse` changed by -4000 Ir, -100 Dr and -2 D1mr over its three lines.
+    assert_eq!(counts(&old, "parser::parse").unwrap(), [4000, 100, 2]);
+    assert_eq!(counts(&new, "parser::parse").unwrap(), [0, 0, 0]);
+    // Each count of `parser::tokenize` goes to the run it is the highest in.
+    assert_eq!(counts(&old, "parser::tokenize").unwrap(), [0, 0, 2]);
+    assert_eq!(counts(&new, "parser::tokenize").unwrap(), [1500, 50, 0]);
+    assert_eq!(counts(&old, "alloc::grow").unwrap(), [0, 0, 0]);
+    assert_eq!(counts(&new, "alloc::grow").unwrap(), [4000, 0, 0]);
+    assert_eq!(counts(&old, "old::helper").unwrap(), [700, 10, 0]);
+
+    // The totals are the sums of the counts, so that their difference is that of the summary.
+    assert_eq!(old.total_ir, 4700);
+    assert_eq!(new.total_ir, 5500);
+    assert_eq!(old.event_totals, [110, 4]);
+    assert_eq!(new.event_totals, [50, 0]);
+}
+
+#[test]
+fn invalid_outputs() {
+    let input = std::fs::read_to_string("tests/data/cg_diff.out").unwrap();
+    let no_events = input.replace("events: Dr Ir D1mr\n", "");
+    assert!(parse(no_events.as_bytes(), &[], None).is_err());
+    let invalid = input.replace("-5000", "-5k");
+    assert!(parse(invalid.as_bytes(), &[], None).is_err());
+    assert!(parse(input.as_bytes(), &[], Some(3)).is_err());
+}
@@ -0,0 +1,111 @@
+//! Tests of the SHA-256 of `checksum`, against the examples of FIPS 180-4 and padding edge cases.
+
+use std::io::{self, Read};
+
+use callgrind_differ::checksum::{sha256, sha256_file};
+
+/// Return the SHA-256 of `input` as lowercase hexadecimal.
+fn hex_sha256<R: Read>(input: R) -> String {
+    sha256(input)
+        .unwrap()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// A reader which returns at most one byte per read, to check that blocks are filled across
+/// short reads.
+struct OneByteReader<'a>(&'a [u8]);
+
+impl Read for OneByteReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let Some((first, rest)) = self.0.split_first() else {
+            return Ok(0);
+        };
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        buf[0] = *first;
+        self.0 = rest;
+        Ok(1)
+    }
+}
+
+#[test]
+fn fips_180_4_examples() {
+    for (input, digest) in [
+        (
+            &b""[..],
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+        ),
+        (
+            b"abc",
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+        ),
+        // 448 bits: the padding takes a block of its own.
+        (
+            b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq",
+            "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1",
+        ),
+    ] {
+        assert_eq!(hex_sha256(input), digest);
+        assert_eq!(hex_sha256(OneByteReader(input)), digest);
+    }
+    // A million `a`s, over many blocks.
+    assert_eq!(
+        hex_sha256(io::repeat(b'a').take(1_000_000)),
+        "cdc76e5c9914fb9281a1c7e284d73e67f1809a48a497200e046d39ccc7112cd0"
+    );
+}
+
+#[test]
+fn padding_boundaries() {
+    // Inputs of `a`s around the lengths at which the padding spills into another block.
+    for (length, digest) in [
+        (
+            55,
+            "9f4390f8d30c2dd92ec9f095b65e2b9ae9b0a925a5258e241c9f1e910f734318",
+        ),
+        (
+            56,
+            "b35439a4ac6f0948b6d6f9e3c6af0f5f590ce20f1bde7090ef7970686ec6738a",
+        ),
+        (
+            63,
+            "7d3e74a05d7db15bce4ad9ec0658ea98e3f06eeecf16b4c6fff2da457ddc2f34",
+        ),
+        (
+            64,
+            "ffe054fe7ae0cb6dc65c3af9b61d5209f439851db43d0ba5997337df154668eb",
+        ),
+        (
+            65,
+            "635361c48bb9eab14198e76ea8ab7f1a41685d6ad62aa9146d301d4f17eb0ae0",
+        ),
+        (
+            119,
+            "31eba51c313a5c08226adf18d4a359cfdfd8d2e816b13f4af952f7ea6584dcfb",
+        ),
+        (
+            120,
+            "2f3d335432c70b580af0e8e1b3674a7c020d683aa5f73aaaedfdc55af904c21c",
+        ),
+    ] {
+        let input = vec![b'a'; length];
+        assert_eq!(hex_sha256(&input[..]), digest, "{length} bytes");
+        assert_eq!(hex_sha256(OneByteReader(&input)), digest, "{length} bytes");
+    }
+}
+
+#[test]
+fn files() {
+    let path =
+        std::env::temp_dir().join(format!("callgrind_differ-checksum-{}", std::process::id()));
+    std::fs::write(&path, b"abc").unwrap();
+    assert_eq!(
+        sha256_file(&path).unwrap(),
+        "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+    );
+    std::fs::remove_file(&path).unwrap();
+    assert!(sha256_file(&path).is_err());
+}
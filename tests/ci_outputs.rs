@@ -0,0 +1,159 @@
+//! Tests of the outputs of `callgrind_differ` meant for CI, on `tests/data/run1.cg` (the
+//! reference), `run2.cg` (`parser::parse` +10%, `main` -20%) and `run3.cg`.
+
+use std::{path::PathBuf, process::Command};
+
+/// Run `callgrind_differ` on the given inputs (in `tests/data`) with the given options, and
+/// return its exit code and standard output.
+fn compare(inputs: &[&str], options: &[&str]) -> (Option<i32>, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_callgrind_differ"))
+        .args(inputs.iter().map(|input| format!("tests/data/{input}")))
+        .args(options)
+        .output()
+        .unwrap();
+    (
+        output.status.code(),
+        String::from_utf8(output.stdout).unwrap(),
+    )
+}
+
+/// Return the path to the file `name` of the test `test`, removing any previous one.
+fn output_file(test: &str, name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!(
+        "callgrind_differ-{test}-{}-{name}",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+/// Return the lines of `output` which are GitHub Actions workflow commands.
+fn workflow_commands(output: &str) -> Vec<&str> {
+    output
+        .lines()
+        .filter(|line| line.starts_with("::"))
+        .collect()
+}
+
+#[test]
+fn github_annotations() {
+    let inputs = ["run1.cg", "run2.cg"];
+    let (code, output) = compare(
+        &inputs,
+        &[
+            "--github-annotations",
+            "--warn-on-regression=5%",
+            "--fail-on-regression=50%",
+        ],
+    );
+    assert_eq!(code, Some(0));
+    assert_eq!(
+        workflow_commands(&output),
+        ["::warning title=IR regression::parser::parse: +50000 IR (+10.000%25, 500000 -> 550000)"]
+    );
+
+    let (code, output) = compare(
+        &inputs,
+        &[
+            "--github-annotations",
+            "--warn-on-regression=1%",
+            "--fail-on-regression=5%",
+        ],
+    );
+    assert_eq!(code, Some(3));
+    assert_eq!(
+        workflow_commands(&output),
+        ["::error title=IR regression::parser::parse: +50000 IR (+10.000%25, 500000 -> 550000)"]
+    );
+
+    // Decreases and regressions within the thresholds are not annotated.
+    let (code, output) = compare(
+        &inputs,
+        &[
+            "--github-annotations",
+            "--warn-on-regression=15%",
+            "--fail-on-regression=50%",
+        ],
+    );
+    assert_eq!(code, Some(0));
+    assert!(workflow_commands(&output).is_empty());
+}
+
+#[test]
+fn pr_comment_marker() {
+    let path = output_file("pr_comment_marker", "comment.md");
+    let (code, _) = compare(
+        &["run1.cg", "run2.cg"],
+        &[
+            "--export-pr-comment",
+            path.to_str().unwrap(),
+            "--comment-marker=bench-bot",
+        ],
+    );
+    assert_eq!(code, Some(0));
+    let comment = std::fs::read_to_string(&path).unwrap();
+    assert!(comment.starts_with(
+        "<!-- callgrind_differ:bench-bot -->\n\
+         <!--\n\
+         reference_total_ir: 1121000\n\
+         total_ir: 1143000\n\
+         total_ir_diff: +22000\n\
+         total_ir_percent: +1.963\n\
+         symbols_increased: 1\n\
+         symbols_decreased: 1\n\
+         regressions: 0\n\
+         failures: 0\n\
+         -->\n"
+    ));
+    assert!(comment.contains("| `parser::parse` | 500000 | 550000 | +50000 | +10.000% |\n"));
+
+    // Without a marker, the comment starts with its front matter.
+    let (code, _) = compare(
+        &["run1.cg", "run2.cg"],
+        &["--export-pr-comment", path.to_str().unwrap()],
+    );
+    assert_eq!(code, Some(0));
+    let comment = std::fs::read_to_string(&path).unwrap();
+    assert!(comment.starts_with("<!--\nreference_total_ir: 1121000\n"));
+    assert!(!comment.contains("callgrind_differ:"));
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn rolling_reference() {
+    let path = output_file("rolling_reference", "comment.md");
+    let inputs = ["run1.cg", "run2.cg", "run3.cg"];
+    // The last run is compared to the mean of the two runs before it, even if fewer runs than
+    // the window precede it.
+    for window in ["2", "5"] {
+        let (code, _) = compare(
+            &inputs,
+            &[
+                &format!("--relative-to=rolling:{window}"),
+                "--export-pr-comment",
+                path.to_str().unwrap(),
+            ],
+        );
+        assert_eq!(code, Some(0));
+        let comment = std::fs::read_to_string(&path).unwrap();
+        assert!(comment.contains("**Total IR:** 1132000 → 1125000 (-7000, -0.618%)\n"));
+        assert!(comment.contains("| `parser::parse` | 525000 | 450000 | -75000 | -14.286% |\n"));
+        assert!(comment.contains("| `main` | 45000 | 50000 | +5000 | +11.111% |\n"));
+    }
+
+    // With a window of 1, the reference is the previous run.
+    let (code, _) = compare(
+        &inputs,
+        &[
+            "--relative-to=rolling:1",
+            "--export-pr-comment",
+            path.to_str().unwrap(),
+        ],
+    );
+    assert_eq!(code, Some(0));
+    let comment = std::fs::read_to_string(&path).unwrap();
+    assert!(comment.contains("**Total IR:** 1143000 → 1125000 (-18000, -1.575%)\n"));
+
+    assert_eq!(compare(&inputs, &["--relative-to=rolling:0"]).0, Some(1));
+    std::fs::remove_file(&path).unwrap();
+}
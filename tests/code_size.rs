@@ -0,0 +1,96 @@
+//! Tests of the outputs of `cargo llvm-lines` and `cargo bloat --message-format json`.
+//!
+//! `tests/data/llvm-lines.txt` only lists some of the functions counted in its `(TOTAL)` row, as
+//! with `cargo llvm-lines --filter`.
+
+use std::{fs::File, io::BufReader, path::Path};
+
+use callgrind_differ::{
+    code_size::{parse_cargo_bloat, parse_llvm_lines, BYTES_UNIT, LINES_UNIT},
+    format::InputFormats,
+    runs::Run,
+};
+use itertools::Itertools;
+
+/// Return the count of `symbol` in `run`.
+fn count(run: &Run, symbol: &str) -> u64 {
+    run.symbols
+        .iter()
+        .find(|sym| &*sym.name == symbol)
+        .unwrap_or_else(|| panic!("No symbol {symbol:?}"))
+        .ir
+}
+
+#[test]
+fn code_size_outputs_are_detected() {
+    let formats = InputFormats::default();
+    for (name, format) in [
+        ("llvm-lines.txt", "cargo llvm-lines"),
+        ("cargo-bloat.json", "cargo bloat"),
+    ] {
+        let detected = formats.find(&Path::new("tests/data").join(name)).unwrap();
+        assert_eq!(detected.name(), format, "{name}");
+    }
+}
+
+#[test]
+fn llvm_lines() {
+    let file = File::open("tests/data/llvm-lines.txt").unwrap();
+    let run = parse_llvm_lines(BufReader::new(file), &[], None).unwrap();
+    assert_eq!(run.metadata.unit.as_deref(), Some(LINES_UNIT));
+    // The total is that of the `(TOTAL)` row, not the sum of the listed functions.
+    assert_eq!(run.total_ir, 30737);
+    assert_eq!(run.symbols.len(), 4);
+    assert_eq!(
+        count(
+            &run,
+            "<callgrind_differ::args::Args as clap::FromArgMatches>::from_arg_matches_mut"
+        ),
+        1395
+    );
+    assert_eq!(count(&run, "core::result::Result<T,E>::map_err"), 760);
+    assert_eq!(count(&run, "alloc::vec::Vec<T,A>::push"), 512);
+    assert_eq!(count(&run, "callgrind_differ::main"), 45);
+
+    // Without a `(TOTAL)` row, the total is the sum of the functions.
+    let input = std::fs::read_to_string("tests/data/llvm-lines.txt").unwrap();
+    let input = input
+        .lines()
+        .filter(|line| !line.ends_with("(TOTAL)"))
+        .join("\n");
+    let run = parse_llvm_lines(input.as_bytes(), &[], None).unwrap();
+    assert_eq!(run.total_ir, 2712);
+
+    assert!(parse_llvm_lines(&b"   12 (1.0%,  1.0%)\n"[..], &[], None).is_err());
+    assert!(parse_llvm_lines(&b"many lines\n"[..], &[], None).is_err());
+}
+
+#[test]
+fn cargo_bloat() {
+    let input = std::fs::read_to_string("tests/data/cargo-bloat.json").unwrap();
+    let run = parse_cargo_bloat(&input, &[], None).unwrap();
+    assert_eq!(run.metadata.unit.as_deref(), Some(BYTES_UNIT));
+    // The total is the sum of the listed functions, not the size of the `.text` section.
+    assert_eq!(run.total_ir, 17400);
+    assert_eq!(run.symbols.len(), 4);
+    assert_eq!(count(&run, "std::rt::lang_start_internal"), 1830);
+    assert_eq!(count(&run, "callgrind_differ::display::display_to"), 12400);
+    assert_eq!(
+        count(
+            &run,
+            "<clap_builder::builder::command::Command as core::fmt::Debug>::fmt"
+        ),
+        3072
+    );
+    assert_eq!(count(&run, "[Unknown]"), 98);
+    assert!(parse_cargo_bloat(&input, &[], Some(3)).is_err());
+
+    // With `--crates`.
+    let crates = r#"{"file-size":10,"text-section-size":8,"crates":[{"name":"std","size":5},{"name":"clap","size":3}]}"#;
+    let run = parse_cargo_bloat(crates, &[], None).unwrap();
+    assert_eq!(run.total_ir, 8);
+    assert_eq!(count(&run, "clap"), 3);
+
+    assert!(parse_cargo_bloat(r#"{"text-section-size":8}"#, &[], None).is_err());
+    assert!(parse_cargo_bloat(r#"{"functions":[{"name":"f"}]}"#, &[], None).is_err());
+}
@@ -0,0 +1,199 @@
+//! Tests of the compaction of history stores, which moves old runs into a single file.
+
+use std::path::{Path, PathBuf};
+
+use callgrind_differ::{
+    args::{OnDuplicate, RunRange},
+    history::{History, StoredRun},
+    runs::Run,
+};
+
+/// Return an empty directory for the store of the test `name`.
+fn store(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("callgrind_differ-{name}-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&path);
+    path
+}
+
+/// Return the `i`-th run of a store: `main` has `100 * i` IR, and odd runs have an `odd` symbol.
+fn run(i: u64) -> Run {
+    let mut run = Run::new_named(format!("r{i}"));
+    run.add_ir("main", 100 * i);
+    if i % 2 == 1 {
+        run.add_ir("odd", i);
+    }
+    run.total_ir = 100 * i + i % 2 * i;
+    run.metadata.checksum = Some(i.to_string());
+    run
+}
+
+/// A stored run as its id, name, total IR and symbols, to be compared.
+type Summary = (u64, String, u64, Vec<(String, u64)>);
+
+/// Summarize `runs`.
+fn summarize(runs: &[StoredRun]) -> Vec<Summary> {
+    runs.iter()
+        .map(|stored| {
+            let symbols = stored
+                .run
+                .symbols
+                .iter()
+                .map(|symbol| (symbol.name.to_string(), symbol.ir))
+                .collect();
+            (
+                stored.id,
+                stored.run.name.clone(),
+                stored.run.total_ir,
+                symbols,
+            )
+        })
+        .collect()
+}
+
+/// Return the number of runs of the store at `path` which are in files of their own.
+fn n_loose(path: &Path) -> usize {
+    std::fs::read_dir(path)
+        .unwrap()
+        .filter(|entry| {
+            entry
+                .as_ref()
+                .unwrap()
+                .path()
+                .extension()
+                .is_some_and(|extension| extension == "run")
+        })
+        .count()
+}
+
+#[test]
+fn compacted_runs_are_read_back() {
+    let path = store("compacted_runs_are_read_back");
+    let history = History::open(&path).unwrap();
+    let runs = (0..6).map(run).collect::<Vec<_>>();
+    history.add_runs(&runs, OnDuplicate::Refuse).unwrap();
+    let before = summarize(&history.runs().unwrap());
+
+    assert_eq!(history.compact(2).unwrap(), 4);
+    assert!(path.join("compacted").is_file());
+    assert_eq!(n_loose(&path), 2);
+    // Compacted and loose runs are read together, in order.
+    assert_eq!(summarize(&history.runs().unwrap()), before);
+    assert_eq!(summarize(&history.latest_runs(3).unwrap()), before[3..]);
+    let range = RunRange {
+        first: Some("r2".to_string()),
+        last: Some("4".to_string()),
+    };
+    assert_eq!(summarize(&history.runs_in(&range).unwrap()), before[2..5]);
+    assert!(history.check(false).unwrap().is_empty());
+
+    // Compacting again only moves the runs added since.
+    assert_eq!(history.compact(2).unwrap(), 0);
+    history.add_run(&run(6), OnDuplicate::Refuse).unwrap();
+    assert_eq!(history.compact(2).unwrap(), 1);
+    assert_eq!(n_loose(&path), 2);
+    // Already compacted runs stay so.
+    assert_eq!(history.compact(10).unwrap(), 0);
+    assert_eq!(summarize(&history.runs().unwrap())[..6], before);
+    std::fs::remove_dir_all(&path).unwrap();
+}
+
+#[test]
+fn compacted_runs_are_updated_in_place() {
+    let path = store("compacted_runs_are_updated_in_place");
+    let history = History::open(&path).unwrap();
+    let runs = (0..4).map(run).collect::<Vec<_>>();
+    history.add_runs(&runs, OnDuplicate::Refuse).unwrap();
+    assert_eq!(history.compact(1).unwrap(), 3);
+
+    assert_eq!(history.rename("r1", "v1.0").unwrap(), 1);
+    assert_eq!(history.pin("v1.0", true).unwrap(), 1);
+    let range = RunRange {
+        first: Some("0".to_string()),
+        last: Some("0".to_string()),
+    };
+    assert_eq!(history.remove(&range).unwrap().len(), 1);
+    // Duplicates are found among compacted runs as well.
+    assert!(history.add_run(&run(2), OnDuplicate::Refuse).is_err());
+
+    let runs = history.runs().unwrap();
+    assert_eq!(
+        summarize(&runs),
+        [
+            (
+                1,
+                "v1.0".to_string(),
+                101,
+                vec![("main".to_string(), 100), ("odd".to_string(), 1)]
+            ),
+            (2, "r2".to_string(), 200, vec![("main".to_string(), 200)]),
+            (
+                3,
+                "r3".to_string(),
+                303,
+                vec![("main".to_string(), 300), ("odd".to_string(), 3)]
+            ),
+        ]
+    );
+    assert!(runs[0].run.metadata.pinned);
+    assert_eq!(n_loose(&path), 1);
+    assert!(history.check(false).unwrap().is_empty());
+
+    // Removing every compacted run removes the compacted file.
+    history.pin("v1.0", false).unwrap();
+    let range = RunRange {
+        first: Some("1".to_string()),
+        last: Some("2".to_string()),
+    };
+    assert_eq!(history.remove(&range).unwrap().len(), 2);
+    assert!(!path.join("compacted").exists());
+    assert_eq!(summarize(&history.runs().unwrap())[0].0, 3);
+    std::fs::remove_dir_all(&path).unwrap();
+}
+
+#[test]
+fn compaction_under_a_concurrent_writer() {
+    /// The number of runs appended by the writer, one at a time.
+    const RUNS: u64 = 30;
+
+    let path = store("compaction_under_a_concurrent_writer");
+    History::open(&path).unwrap();
+    std::thread::scope(|scope| {
+        let writer = scope.spawn(|| {
+            let history = History::open(&path).unwrap();
+            (0..RUNS)
+                .map(|i| {
+                    history
+                        .add_run(&run(i), OnDuplicate::Refuse)
+                        .unwrap()
+                        .unwrap()
+                })
+                .collect::<Vec<_>>()
+        });
+        let history = History::open(&path).unwrap();
+        while !writer.is_finished() {
+            history.compact(3).unwrap();
+            // Readers never see a run both compacted and loose, nor a run missing.
+            let ids = history
+                .headers()
+                .unwrap()
+                .iter()
+                .map(|stored| stored.id)
+                .collect::<Vec<_>>();
+            assert_eq!(ids, (0..ids.len() as u64).collect::<Vec<_>>());
+        }
+        assert_eq!(writer.join().unwrap(), (0..RUNS).collect::<Vec<_>>());
+    });
+    let history = History::open(&path).unwrap();
+    history.compact(3).unwrap();
+    assert_eq!(n_loose(&path), 3);
+    assert_eq!(
+        summarize(&history.runs().unwrap()),
+        summarize(
+            &(0..RUNS)
+                .map(|id| StoredRun { id, run: run(id) })
+                .collect::<Vec<_>>()
+        )
+    );
+    assert!(history.check(false).unwrap().is_empty());
+    std::fs::remove_dir_all(&path).unwrap();
+}
@@ -0,0 +1,78 @@
+//! Tests of the breakdown of `--explain-total`, on the outputs of `callgrind_annotate` in
+//! `tests/data` (whose symbols do not account for their totals, as of a threshold).
+
+use callgrind_differ::{
+    args::Args, callgrind::InputLimits, contribution::TotalChange, diff::DiffTable, runs::Records,
+    runs::Run,
+};
+use clap::Parser;
+
+/// Return the configuration and the records comparing `tests/data/<input>` to
+/// `tests/data/run1.cg`.
+fn load(input: &str) -> (Args, Records) {
+    let inputs = ["run1.cg", input].map(|input| format!("tests/data/{input}"));
+    let config = Args::try_parse_from(
+        std::iter::once("callgrind_differ").chain(inputs.iter().map(String::as_str)),
+    )
+    .unwrap()
+    .validated()
+    .unwrap();
+    let mut records = Records::new();
+    for input in inputs {
+        records.add_run(
+            Run::from_callgrind_annotate_file(input, &[], InputLimits::default()).unwrap(),
+        );
+    }
+    (config, records)
+}
+
+#[test]
+fn shares_sum_to_the_total() {
+    for input in ["run2.cg", "run3.cg"] {
+        let (config, records) = load(input);
+        let table = DiffTable::new(&config, &records);
+        let change = TotalChange::new(&table);
+        let accounted = change
+            .contributions
+            .iter()
+            .map(|contribution| contribution.diff)
+            .sum::<i128>();
+        assert_eq!(accounted + change.unaccounted, change.diff);
+        let cumulative = change
+            .contributions
+            .last()
+            .map_or(0.0, |contribution| contribution.cumulative_share);
+        assert!((cumulative + change.unaccounted_share - 100.0).abs() < 1e-9);
+    }
+}
+
+#[test]
+fn appeared_and_vanished_symbols() {
+    let (config, records) = load("run3.cg");
+    let table = DiffTable::new(&config, &records);
+    let change = TotalChange::new(&table);
+    assert_eq!(change.diff, 4000);
+    let contributions = change
+        .contributions
+        .iter()
+        .map(|contribution| {
+            (
+                contribution.symbol,
+                contribution.diff,
+                contribution.appeared(),
+                contribution.vanished(),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        contributions,
+        [
+            ("parser::parse", -50000, false, false),
+            ("alloc::grow", 30000, true, false),
+            ("small::fmt", 24000, false, false),
+            ("memcpy", 20000, false, false),
+            ("old::helper", -20000, false, true),
+        ]
+    );
+    assert_eq!(change.unaccounted, 0);
+}
@@ -0,0 +1,108 @@
+//! Tests of profiles converted to the callgrind format from other languages (e.g. by
+//! `pyprof2calltree` or `pprof -callgrind`).
+//!
+//! Their headers differ from those of callgrind, and their symbols hold spaces, parentheses,
+//! braces, module paths and bytes which are not UTF-8.
+
+use std::{borrow::Cow, fs::File, io::BufReader, path::Path};
+
+use callgrind_differ::{
+    args::StringReplacement,
+    callgrind_out::parse,
+    format::InputFormats,
+    runs::{crate_of, Run, NO_CRATE_GROUP},
+};
+
+/// Parse the `callgrind.out` file of `tests/data` with the given name.
+fn load(name: &str, replacements: &[StringReplacement]) -> Run {
+    let file = File::open(Path::new("tests/data").join(name)).unwrap();
+    parse(BufReader::new(file), replacements, None, false).unwrap()
+}
+
+/// Return the IR count of `symbol` in `run`.
+fn ir(run: &Run, symbol: &str) -> u64 {
+    run.symbols
+        .iter()
+        .find(|sym| &*sym.name == symbol)
+        .unwrap_or_else(|| panic!("No symbol {symbol:?}"))
+        .ir
+}
+
+#[test]
+fn converted_profiles_are_detected() {
+    let formats = InputFormats::default();
+    for name in [
+        "pyprof2calltree.out",
+        "pprof.callgrind",
+        "unusual_names.callgrind",
+    ] {
+        let format = formats.find(&Path::new("tests/data").join(name)).unwrap();
+        assert_eq!(format.name(), "callgrind.out", "{name}");
+    }
+    // `callgrind_annotate` outputs are not mistaken for them.
+    let format = formats.find(Path::new("tests/data/run1.cg")).unwrap();
+    assert_eq!(format.name(), "callgrind_annotate");
+}
+
+#[test]
+fn pyprof2calltree_symbols() {
+    let run = load("pyprof2calltree.out", &[]);
+    assert_eq!(run.total_ir, 9100);
+    assert!(run.events.is_empty());
+    assert_eq!(run.symbols.len(), 6);
+    // Costs of calls are left out of the cost of the caller.
+    assert_eq!(ir(&run, "<built-in method builtins.exec>"), 100);
+    assert_eq!(ir(&run, "<module> /srv/app/main.py:1"), 200);
+    assert_eq!(ir(&run, "<lambda> /srv/app/main.py:7"), 1200);
+    assert_eq!(ir(&run, "<listcomp> /srv/app/main.py:9"), 2600);
+    assert_eq!(ir(&run, "<method 'append' of 'list' objects>"), 1000);
+    assert_eq!(ir(&run, "{built-in method builtins.len}"), 4000);
+    assert_eq!(crate_of("<lambda> /srv/app/main.py:7"), NO_CRATE_GROUP);
+}
+
+#[test]
+fn module_paths_can_be_replaced() {
+    let replacements = [
+        r" \/srv\/app\//@".parse::<StringReplacement>().unwrap(),
+        "<lambda>/lambda".parse().unwrap(),
+    ];
+    let run = load("pyprof2calltree.out", &replacements);
+    assert_eq!(ir(&run, "<module>@main.py:1"), 200);
+    assert_eq!(ir(&run, "lambda@main.py:7"), 1200);
+    assert_eq!(
+        replacements[0].perform(Cow::Borrowed("a /srv/app/b")),
+        "a@b"
+    );
+}
+
+#[test]
+fn pprof_symbols() {
+    let run = load("pprof.callgrind", &[]);
+    // Without `Ir`, the first event is the count of the run.
+    assert_eq!(run.events, ["cpu(nanoseconds)"]);
+    assert_eq!(run.total_ir, 12);
+    assert_eq!(run.event_totals, [120_000_000]);
+    assert_eq!(ir(&run, "runtime.main"), 1);
+    // Compressed names are resolved, and relative positions do not count as costs.
+    assert_eq!(ir(&run, "main.(*Server).ServeHTTP"), 4);
+    assert_eq!(
+        ir(
+            &run,
+            "java.util.HashMap.get(Ljava/lang/Object;)Ljava/lang/Object;"
+        ),
+        2
+    );
+    assert_eq!(ir(&run, "System.Collections.Generic.List`1<T>.Add(T)"), 5);
+    assert_eq!(crate_of("main.(*Server).ServeHTTP"), NO_CRATE_GROUP);
+}
+
+#[test]
+fn unusual_names() {
+    let run = load("unusual_names.callgrind", &[]);
+    // Parentheses which do not hold a number are part of the name, not a compressed id.
+    assert_eq!(ir(&run, "(anonymous namespace)::parse"), 300);
+    // Bytes which are not UTF-8 are replaced, and the compressed name still refers to the
+    // symbol.
+    assert_eq!(ir(&run, "caf\u{fffd}::serve"), 300);
+    assert_eq!(run.symbols.len(), 2);
+}
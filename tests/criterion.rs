@@ -0,0 +1,73 @@
+//! Tests of the output directories of Criterion.rs (`target/criterion`).
+//!
+//! `tests/data/criterion` has a benchmark whose ID was sanitized into its directory name, a
+//! benchmark with the estimates of a previous run, one without a `benchmark.json` file (as
+//! written before Criterion.rs 0.3) and a `report` directory.
+
+use std::{fs, path::Path};
+
+use callgrind_differ::{
+    callgrind::InputLimits, criterion::load, error::Error, format::InputFormats, runs::Run,
+};
+
+/// Return the mean and the standard deviation of `benchmark` in `run`.
+fn estimate(run: &Run, benchmark: &str) -> (u64, f64) {
+    let symbol = run
+        .symbols
+        .iter()
+        .find(|symbol| &*symbol.name == benchmark)
+        .unwrap_or_else(|| panic!("No benchmark {benchmark:?}"));
+    (symbol.ir, symbol.stddev)
+}
+
+#[test]
+fn criterion_directories_are_detected() {
+    let formats = InputFormats::default();
+    let format = formats.find(Path::new("tests/data/criterion")).unwrap();
+    assert_eq!(format.name(), "Criterion.rs");
+    // A single benchmark is a run of its own.
+    let format = formats
+        .find(Path::new("tests/data/criterion/parse/large"))
+        .unwrap();
+    assert_eq!(format.name(), "Criterion.rs");
+}
+
+#[test]
+fn latest_estimates() {
+    let run = load(Path::new("tests/data/criterion"), InputLimits::default()).unwrap();
+    assert_eq!(run.name, "criterion");
+    assert_eq!(run.metadata.unit.as_deref(), Some("ns"));
+    let names = run
+        .symbols
+        .iter()
+        .map(|symbol| &*symbol.name)
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["fmt/u64:max", "parse/large", "parse/small", "sum"]);
+    // Means are rounded to the nanosecond, and previous runs (`base`) are left out.
+    assert_eq!(estimate(&run, "parse/large"), (11_000, 4.5));
+    assert_eq!(estimate(&run, "parse/small"), (251, 2.25));
+    assert_eq!(estimate(&run, "fmt/u64:max"), (31, 0.5));
+    assert_eq!(estimate(&run, "sum"), (100, 0.25));
+    assert_eq!(run.total_ir, 11_382);
+}
+
+#[test]
+fn limits() {
+    let dir = Path::new("tests/data/criterion");
+    let max_symbols = InputLimits {
+        max_symbols: Some(3),
+        ..InputLimits::default()
+    };
+    assert!(matches!(
+        load(dir, max_symbols),
+        Err(Error::TooManySymbols { .. })
+    ));
+    let max_size = fs::metadata(dir.join("parse/large/new/estimates.json"))
+        .unwrap()
+        .len();
+    let max_size = InputLimits {
+        max_size: Some(max_size - 1),
+        ..InputLimits::default()
+    };
+    assert!(matches!(load(dir, max_size), Err(Error::TooLarge { .. })));
+}
@@ -0,0 +1,87 @@
+//! Tests of the callgrind profiles written by `--export-callgrind-diff`.
+
+use std::path::Path;
+
+use callgrind_differ::{
+    args::Args, callgrind::InputLimits, callgrind_out, diff::DiffTable,
+    diff_profile::write_callgrind_diff, runs::Records, runs::Run,
+};
+use clap::Parser;
+
+/// Return the profile of the changes of the last of `inputs` (in `tests/data`).
+fn export(inputs: &[&str]) -> String {
+    let data = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data");
+    let inputs = inputs
+        .iter()
+        .map(|input| data.join(input).display().to_string())
+        .collect::<Vec<_>>();
+    let config = Args::try_parse_from(
+        std::iter::once("callgrind_differ").chain(inputs.iter().map(String::as_str)),
+    )
+    .unwrap()
+    .validated()
+    .unwrap();
+    let mut records = Records::new();
+    for input in inputs {
+        records.add_run(
+            Run::from_callgrind_annotate_file(input, &[], InputLimits::default()).unwrap(),
+        );
+    }
+    let table = DiffTable::new(&config, &records);
+    let mut output = vec![];
+    write_callgrind_diff(&table, &mut output).unwrap();
+    String::from_utf8(output).unwrap()
+}
+
+/// Return the increase and the decrease of `symbol` in `run`.
+fn change(run: &Run, symbol: &str) -> (u64, u64) {
+    let symbol = run
+        .symbols
+        .iter()
+        .find(|sym| &*sym.name == symbol)
+        .unwrap_or_else(|| panic!("No symbol {symbol:?}"));
+    (symbol.ir, symbol.events[0])
+}
+
+#[test]
+fn changes_are_split_into_increases_and_decreases() {
+    let profile = export(&["run1.cg", "run2.cg"]);
+    let run = callgrind_out::parse(profile.as_bytes(), &[], None, false).unwrap();
+    assert_eq!(run.events, ["IrDec"]);
+    // Only the symbols whose counts changed are written, one of their changes being 0.
+    let names = run
+        .symbols
+        .iter()
+        .map(|symbol| &*symbol.name)
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["parser::parse", "main"]);
+    assert_eq!(change(&run, "parser::parse"), (50_000, 0));
+    assert_eq!(change(&run, "main"), (0, 10_000));
+    // The summary is the sum of the changes.
+    let increases = run.symbols.iter().map(|symbol| symbol.ir).sum::<u64>();
+    let decreases = run
+        .symbols
+        .iter()
+        .map(|symbol| symbol.events[0])
+        .sum::<u64>();
+    assert_eq!((run.total_ir, run.event_totals[0]), (increases, decreases));
+}
+
+#[test]
+fn profiles_are_flat() {
+    let profile = export(&["run1.cg", "run3.cg"]);
+    let run = callgrind_out::parse(profile.as_bytes(), &[], None, false).unwrap();
+    assert_eq!(change(&run, "parser::parse"), (0, 50_000));
+    assert_eq!(change(&run, "small::fmt"), (24_000, 0));
+    assert_eq!(change(&run, "memcpy"), (20_000, 0));
+    assert_eq!(run.symbols.len(), 3);
+    // Every function is in an unknown file, and there is no call.
+    let files = profile
+        .lines()
+        .filter(|line| line.starts_with("fl="))
+        .collect::<Vec<_>>();
+    assert_eq!(files, ["fl=???"]);
+    assert!(!profile
+        .lines()
+        .any(|line| line.starts_with("cfn=") || line.starts_with("calls=")));
+}
@@ -0,0 +1,205 @@
+//! Golden-file tests for the table.
+//!
+//! The runs in `tests/data` are rendered for each combination of `--show`, `--relative-to` and
+//! `--sort-by`, and compared to the files in `tests/golden/display`. Run with `UPDATE_GOLDEN=1`
+//! to write the golden files instead (and review the diff).
+
+use std::{fs, path::Path};
+
+use callgrind_differ::{
+    args::Args, callgrind::InputLimits, diff::DiffTable, display::display_to, runs::Records,
+    runs::Run,
+};
+use clap::Parser;
+
+/// The runs to compare, in order.
+const INPUTS: [&str; 3] = ["run1.cg", "run2.cg", "run3.cg"];
+
+/// The values of `--show` to test.
+const SHOWS: [&str; 4] = ["ircount", "percentagediff", "ircountdiff", "all"];
+
+/// The values of `--relative-to` to test.
+const RELATIVE_TOS: [&str; 5] = ["first", "last", "previous", "column1", "rolling:2"];
+
+/// The values of `--sort-by` to test.
+const SORT_BYS: [&str; 5] = ["symbol", "-symbol", "first-ir", "-last-ir", "column1"];
+
+/// Render the table for `inputs` (in `tests/data`) with the given extra arguments.
+fn render(inputs: &[&str], args: &[String]) -> String {
+    let data = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data");
+    let inputs = inputs
+        .iter()
+        .map(|input| data.join(input).display().to_string());
+    let config = Args::try_parse_from(
+        std::iter::once("callgrind_differ".to_string())
+            .chain(inputs.clone())
+            .chain(args.iter().cloned()),
+    )
+    .unwrap()
+    .validated()
+    .unwrap();
+
+    let mut records = Records::new();
+    for input in inputs {
+        records.add_run(
+            Run::from_callgrind_annotate_file(input, &[], InputLimits::default()).unwrap(),
+        );
+    }
+    records.sort(config.sort_by).unwrap();
+    let table = DiffTable::new(&config, &records);
+    let mut output = vec![];
+    display_to(&config, &table, &mut output).unwrap();
+    String::from_utf8(output).unwrap()
+}
+
+/// Compare `actual` to the golden file `name`, or write it if `UPDATE_GOLDEN` is set.
+///
+/// Return a description of the mismatch, if any.
+fn check(name: &str, actual: &str) -> Option<String> {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden/display")
+        .join(format!("{name}.txt"));
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::write(&path, actual).unwrap();
+        return None;
+    }
+    let expected = fs::read_to_string(&path)
+        .unwrap_or_else(|_| panic!("Missing {} (run with UPDATE_GOLDEN=1)", path.display()));
+    (expected != actual).then(|| format!("{name}:\n--- expected\n{expected}--- actual\n{actual}"))
+}
+
+/// Return a file name for the given argument value.
+fn slug(value: &str) -> String {
+    match value.strip_prefix('-') {
+        Some(value) => format!("desc-{value}"),
+        None => value.to_string(),
+    }
+    .replace(':', "-")
+}
+
+#[test]
+fn display_combinations() {
+    let mut mismatches = vec![];
+    for show in SHOWS {
+        for relative_to in RELATIVE_TOS {
+            for sort_by in SORT_BYS {
+                let args = [
+                    format!("--show={show}"),
+                    format!("--relative-to={relative_to}"),
+                    format!("--sort-by={sort_by}"),
+                ];
+                let name = format!(
+                    "show-{show}_relative-{}_sort-{}",
+                    slug(relative_to),
+                    slug(sort_by)
+                );
+                mismatches.extend(check(&name, &render(&INPUTS, &args)));
+            }
+        }
+    }
+    assert!(mismatches.is_empty(), "{}", mismatches.join("\n"));
+}
+
+#[test]
+fn display_all_symbols() {
+    let args = ["--all".to_string(), "--show=all".to_string()];
+    if let Some(mismatch) = check("all-symbols", &render(&INPUTS, &args)) {
+        panic!("{mismatch}");
+    }
+}
+
+#[test]
+fn display_stats() {
+    let args = ["--all".to_string(), "--show=all,stats".to_string()];
+    if let Some(mismatch) = check("stats", &render(&INPUTS, &args)) {
+        panic!("{mismatch}");
+    }
+}
+
+#[test]
+fn display_trend() {
+    let args = [
+        "--all".to_string(),
+        "--show=ircount,trend".to_string(),
+        "--sort-by=-trend".to_string(),
+    ];
+    if let Some(mismatch) = check("trend", &render(&INPUTS, &args)) {
+        panic!("{mismatch}");
+    }
+}
+
+#[test]
+fn display_impact() {
+    let args = ["--all".to_string(), "--sort-by=-impact".to_string()];
+    if let Some(mismatch) = check("impact", &render(&INPUTS, &args)) {
+        panic!("{mismatch}");
+    }
+}
+
+#[test]
+fn display_cumulative() {
+    let args = [
+        "--show=ircount,cumulative".to_string(),
+        "--relative-to=previous".to_string(),
+    ];
+    if let Some(mismatch) = check("cumulative", &render(&INPUTS, &args)) {
+        panic!("{mismatch}");
+    }
+}
+
+#[test]
+fn display_rank() {
+    let args = [
+        "--show=ircount,percentagediff,rank".to_string(),
+        "--relative-to=previous".to_string(),
+    ];
+    if let Some(mismatch) = check("rank", &render(&INPUTS, &args)) {
+        panic!("{mismatch}");
+    }
+}
+
+#[test]
+fn display_stability() {
+    let args = ["--all".to_string(), "--show=ircount,stability".to_string()];
+    if let Some(mismatch) = check("stability", &render(&INPUTS, &args)) {
+        panic!("{mismatch}");
+    }
+}
+
+#[test]
+fn display_acceleration() {
+    let args = [
+        "--show=ircountdiff,acceleration,ircount".to_string(),
+        "--relative-to=previous".to_string(),
+    ];
+    if let Some(mismatch) = check("acceleration", &render(&INPUTS, &args)) {
+        panic!("{mismatch}");
+    }
+}
+
+#[test]
+fn display_metrics() {
+    let args = [
+        "--all".to_string(),
+        "--show=ircount,percentagediff,d1-miss-rate,branch-miss-rate".to_string(),
+    ];
+    if let Some(mismatch) = check("metrics", &render(&["cache1.cg", "cache2.cg"], &args)) {
+        panic!("{mismatch}");
+    }
+}
+
+#[test]
+fn display_single_run() {
+    let args = ["--show=all".to_string()];
+    if let Some(mismatch) = check("single-run", &render(&INPUTS[..1], &args)) {
+        panic!("{mismatch}");
+    }
+}
+
+#[test]
+fn display_empty_run() {
+    let args = ["--show=all".to_string()];
+    if let Some(mismatch) = check("empty-run", &render(&["empty.cg", "empty.cg"], &args)) {
+        panic!("{mismatch}");
+    }
+}
@@ -0,0 +1,56 @@
+//! Tests of `--event`, which compares the counts of an event other than instructions, on runs of
+//! which some did not record that event.
+
+use std::process::Command;
+
+use callgrind_differ::{builder::RecordsBuilder, callgrind::IR_EVENT};
+
+#[test]
+fn runs_without_the_event_are_not_measured() {
+    let mut builder = RecordsBuilder::new();
+    builder
+        .add_named_run("a", [("main", 100), ("init", 50)])
+        .add_event_counters("Dr", [("main", 10), ("init", 5)])
+        .unwrap()
+        .add_named_run("b", [("main", 120), ("init", 40)])
+        .add_named_run("c", [("main", 110), ("init", 60)])
+        .add_event_counters("Dr", [("main", 0), ("init", 7)])
+        .unwrap();
+    let mut records = builder.finish().unwrap();
+    assert_eq!(records.runs_event_recorded, [[true, false, true]]);
+    records.swap_event(records.event_index("Dr").unwrap());
+    records.check_invariants().unwrap();
+    assert_eq!(records.events, [IR_EVENT]);
+    assert_eq!(records.runs_event_recorded, [[true, true, true]]);
+    for symbol in &records.symbols {
+        // A count of 0 recorded by a run is still measured.
+        assert_eq!(symbol.measured, [true, false, true], "{}", symbol.name);
+    }
+    let main = records.symbols.iter().find(|s| &*s.name == "main").unwrap();
+    assert_eq!(main.irs, [10, 0, 0]);
+    assert_eq!(main.events, [[100, 120, 110]]);
+}
+
+#[test]
+fn cells_of_runs_without_the_event_are_unmeasured() {
+    let output = Command::new(env!("CARGO_BIN_EXE_callgrind_differ"))
+        .args([
+            "tests/data/cache1.cg",
+            "tests/data/run1.cg",
+            "tests/data/cache2.cg",
+            "--event=Dr",
+        ])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let row = stdout
+        .lines()
+        .find(|line| line.starts_with("parser::parse"))
+        .unwrap();
+    let cells = row.split('|').collect::<Vec<_>>();
+    assert_eq!(cells[1].trim(), "150000");
+    // `run1.cg` only recorded instructions.
+    assert_eq!(cells[2].split_whitespace().collect::<Vec<_>>(), ["·"; 3]);
+    assert!(cells[3].ends_with(" 160000"));
+}
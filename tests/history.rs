@@ -0,0 +1,705 @@
+//! Tests of the history store, through its API and through `callgrind_differ history`.
+
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use callgrind_differ::{
+    alias::{self, Alias},
+    args::{HistoryCommand, OnDuplicate, Percentage, RunRange},
+    history::{bisect, run_command, History, StoredRun},
+    runs::Run,
+};
+
+/// Return an empty directory for the store of the test `name`.
+fn store(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("callgrind_differ-{name}-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&path);
+    path
+}
+
+/// Return a run named `name`, loaded from a file with the given checksum.
+fn run(name: &str, checksum: &str) -> Run {
+    let mut run = Run::new_named(name.to_string());
+    run.add_ir("main", 100);
+    run.total_ir = 100;
+    run.metadata.checksum = Some(checksum.to_string());
+    run
+}
+
+#[test]
+fn duplicates_within_a_batch() {
+    let path = store("duplicates_within_a_batch");
+    let history = History::open(&path).unwrap();
+    let runs = [run("a", "1"), run("b", "2"), run("c", "1")];
+    assert!(history.add_runs(&runs, OnDuplicate::Refuse).is_err());
+    assert!(history.runs().unwrap().is_empty());
+    assert_eq!(
+        history.add_runs(&runs, OnDuplicate::Skip).unwrap(),
+        [Some(0), Some(1), None]
+    );
+    // Runs of the batch are compared to those of the store as well.
+    assert_eq!(
+        history
+            .add_runs(&[run("d", "2"), run("e", "3")], OnDuplicate::Skip)
+            .unwrap(),
+        [None, Some(2)]
+    );
+    assert_eq!(
+        history.add_runs(&runs, OnDuplicate::Allow).unwrap(),
+        [Some(3), Some(4), Some(5)]
+    );
+    std::fs::remove_dir_all(&path).unwrap();
+}
+
+/// Run `callgrind_differ` with the store at `path` and the given arguments, and return whether
+/// it succeeded and its standard output.
+fn history_command(path: &Path, args: &[&str]) -> (bool, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_callgrind_differ"))
+        .arg("--history")
+        .arg(path)
+        .args(args)
+        .output()
+        .unwrap();
+    (
+        output.status.success(),
+        String::from_utf8(output.stdout).unwrap(),
+    )
+}
+
+#[test]
+fn imported_runs_are_consistent() {
+    let source = store("imported_runs_are_consistent-source");
+    let path = store("imported_runs_are_consistent");
+    for input in ["run1.cg", "run2.cg", "run3.cg"] {
+        let input = format!("tests/data/{input}");
+        assert!(history_command(&source, &["add", &input]).0);
+    }
+    let csv = source.join("export.csv");
+    let csv = csv.to_str().unwrap();
+    assert!(history_command(&source, &["history", "export", csv]).0);
+    assert!(history_command(&path, &["history", "import", csv]).0);
+    assert_eq!(History::open(&path).unwrap().runs().unwrap().len(), 3);
+    assert_eq!(
+        history_command(&path, &["history", "fsck"]),
+        (true, "No inconsistency found\n".to_string())
+    );
+
+    // Runs appended again on purpose are noted, without failing.
+    assert!(history_command(&path, &["--on-duplicate=allow", "history", "import", csv]).0);
+    let (success, output) = history_command(&path, &["history", "fsck"]);
+    assert!(success);
+    assert_eq!(output.lines().count(), 3);
+    assert!(output.starts_with("Note: Run 3: loaded from the same file as run 0\n"));
+    std::fs::remove_dir_all(&source).unwrap();
+    std::fs::remove_dir_all(&path).unwrap();
+}
+
+#[test]
+fn concurrent_writers() {
+    /// The number of threads appending runs at once, each with its own handle on the store.
+    const WRITERS: usize = 12;
+
+    let path = store("concurrent_writers");
+    std::thread::scope(|scope| {
+        for i in 0..WRITERS {
+            let path = &path;
+            scope.spawn(move || {
+                let checksum = i.to_string();
+                let runs = [run("a", &checksum), run("b", &format!("{checksum}#1"))];
+                History::open(path)
+                    .unwrap()
+                    .add_runs(&runs, OnDuplicate::Refuse)
+                    .unwrap()
+            });
+        }
+    });
+    let runs = History::open(&path).unwrap().runs().unwrap();
+    let ids = runs.iter().map(|stored| stored.id).collect::<Vec<_>>();
+    assert_eq!(ids, (0..2 * WRITERS as u64).collect::<Vec<_>>());
+    // The runs of a writer have consecutive ids.
+    for pair in runs.chunks(2) {
+        assert_eq!(pair[0].run.name, "a");
+        assert_eq!(pair[1].run.name, "b");
+        assert_eq!(
+            pair[1].run.metadata.checksum.as_deref(),
+            Some(format!("{}#1", pair[0].run.metadata.checksum.as_deref().unwrap()).as_str())
+        );
+    }
+    std::fs::remove_dir_all(&path).unwrap();
+}
+
+#[test]
+fn comparisons_are_not_history_commands() {
+    let path = store("comparisons_are_not_history_commands");
+    let history = History::open(&path).unwrap();
+    for command in [
+        HistoryCommand::Show {
+            runs: RunRange::default(),
+        },
+        HistoryCommand::Import {
+            file: "runs.csv".to_string(),
+        },
+    ] {
+        assert!(run_command(&history, &command, &mut std::io::sink()).is_err());
+    }
+    let mut out = vec![];
+    assert!(run_command(&history, &HistoryCommand::List, &mut out).is_ok());
+    assert!(String::from_utf8(out).unwrap().starts_with("id  name"));
+    std::fs::remove_dir_all(&path).unwrap();
+}
+
+#[test]
+fn environment_mismatches_are_warned() {
+    let path = store("environment_mismatches_are_warned");
+    let mut runs = [run("a", "1"), run("b", "2"), run("c", "3")];
+    for (run, (cpu, valgrind)) in runs.iter_mut().zip([
+        ("Intel Xeon", "valgrind-3.22.0"),
+        ("Intel Xeon", "valgrind-3.23.0"),
+        ("AMD EPYC", "valgrind-3.22.0"),
+    ]) {
+        run.metadata.cpu = Some(cpu.to_string());
+        run.metadata.valgrind = Some(valgrind.to_string());
+    }
+    History::open(&path)
+        .unwrap()
+        .add_runs(&runs, OnDuplicate::Refuse)
+        .unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_callgrind_differ"))
+        .arg("--history")
+        .arg(&path)
+        .args(["history", "show"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let warnings = String::from_utf8(output.stderr).unwrap();
+    assert!(warnings.contains(
+        "Warning: the runs were measured with different CPU models: Intel Xeon (run(s) 0, 1), \
+         AMD EPYC (run(s) 2);"
+    ));
+    assert!(warnings.contains(
+        "Warning: the runs were measured with different valgrind versions: valgrind-3.22.0 \
+         (run(s) 0, 2), valgrind-3.23.0 (run(s) 1);"
+    ));
+    assert!(!warnings.contains("different rustc versions"));
+    std::fs::remove_dir_all(&path).unwrap();
+}
+
+/// Return the ids of the runs of the store at `path`.
+fn ids(path: &Path) -> Vec<u64> {
+    let runs = History::open(path).unwrap().runs().unwrap();
+    runs.iter().map(|stored| stored.id).collect()
+}
+
+#[test]
+fn prune_keeps_recent_and_tagged_runs() {
+    let path = store("prune_keeps_recent_and_tagged_runs");
+    let history = History::open(&path).unwrap();
+    let mut runs = (0..6)
+        .map(|i| run(&format!("r{i}"), &i.to_string()))
+        .collect::<Vec<_>>();
+    runs[1].metadata.tag = Some("v1.0".to_string());
+    history.add_runs(&runs, OnDuplicate::Refuse).unwrap();
+
+    // A dry run lists the runs it would remove, and removes none.
+    let (success, output) = history_command(
+        &path,
+        &[
+            "history",
+            "prune",
+            "--keep-last=2",
+            "--keep-tagged",
+            "--dry-run",
+        ],
+    );
+    assert!(success);
+    let lines = output.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 5, "{output}");
+    assert!(lines[0].starts_with("id  name"));
+    let listed = lines[1..lines.len() - 1]
+        .iter()
+        .map(|line| line.split_whitespace().take(2).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        listed,
+        [["0", "r0"], ["2", "r2"], ["3", "r3"]].map(|row| row.to_vec())
+    );
+    assert_eq!(lines[lines.len() - 1], "Would remove 3 run(s)");
+    assert_eq!(ids(&path), [0, 1, 2, 3, 4, 5]);
+
+    assert_eq!(
+        history_command(
+            &path,
+            &["history", "prune", "--keep-last=2", "--keep-tagged"]
+        ),
+        (true, "Removed 3 run(s)\n".to_string())
+    );
+    assert_eq!(ids(&path), [1, 4, 5]);
+    // Nothing is left to prune.
+    assert_eq!(
+        history_command(
+            &path,
+            &[
+                "history",
+                "prune",
+                "--keep-last=2",
+                "--keep-tagged",
+                "--dry-run"
+            ]
+        ),
+        (true, "Would remove 0 run(s)\n".to_string())
+    );
+    // Without `--keep-tagged`, tagged runs are pruned as well.
+    assert_eq!(
+        history_command(&path, &["history", "prune", "--keep-last=2"]),
+        (true, "Removed 1 run(s)\n".to_string())
+    );
+    assert_eq!(ids(&path), [4, 5]);
+    std::fs::remove_dir_all(&path).unwrap();
+}
+
+#[test]
+fn prune_under_a_concurrent_writer() {
+    /// The number of runs appended by the writer, one at a time.
+    const RUNS: u64 = 30;
+    /// The number of runs kept by each prune.
+    const KEEP_LAST: usize = 3;
+
+    let path = store("prune_under_a_concurrent_writer");
+    History::open(&path).unwrap();
+    std::thread::scope(|scope| {
+        let writer = scope.spawn(|| {
+            let history = History::open(&path).unwrap();
+            (0..RUNS)
+                .map(|i| {
+                    let run = run(&format!("r{i}"), &i.to_string());
+                    history.add_run(&run, OnDuplicate::Refuse).unwrap().unwrap()
+                })
+                .collect::<Vec<_>>()
+        });
+        let history = History::open(&path).unwrap();
+        while !writer.is_finished() {
+            let removed = history.prune(KEEP_LAST, false).unwrap();
+            assert!(removed.iter().all(|stored| stored.id < RUNS));
+        }
+        // Every run was appended with its own id, even as older runs were removed.
+        assert_eq!(writer.join().unwrap(), (0..RUNS).collect::<Vec<_>>());
+    });
+    History::open(&path)
+        .unwrap()
+        .prune(KEEP_LAST, false)
+        .unwrap();
+    let runs = History::open(&path).unwrap().runs().unwrap();
+    let names = runs
+        .iter()
+        .map(|stored| (stored.id, stored.run.name.as_str()))
+        .collect::<Vec<_>>();
+    assert_eq!(names, [(27, "r27"), (28, "r28"), (29, "r29")]);
+    assert_eq!(
+        history_command(&path, &["history", "fsck"]),
+        (true, "No inconsistency found\n".to_string())
+    );
+    std::fs::remove_dir_all(&path).unwrap();
+}
+
+#[test]
+fn names_with_line_breaks_are_refused() {
+    let path = store("names_with_line_breaks_are_refused");
+    let history = History::open(&path).unwrap();
+    history
+        .add_runs(&[run("a", "1"), run("b", "2")], OnDuplicate::Refuse)
+        .unwrap();
+    for name in ["v1\ntotal_ir: 0", "v1\r", "\n"] {
+        assert!(history.rename("a", name).is_err());
+        assert!(history
+            .add_run(&run(name, "3"), OnDuplicate::Refuse)
+            .is_err());
+    }
+    let (renamed, _) = history_command(&path, &["history", "rename", "b", "v1\nv2"]);
+    assert!(!renamed);
+    // The store is still readable, and its runs untouched.
+    let runs = history.runs().unwrap();
+    assert_eq!(
+        runs.iter()
+            .map(|stored| &*stored.run.name)
+            .collect::<Vec<_>>(),
+        ["a", "b"]
+    );
+    assert!(runs.iter().all(|stored| stored.run.total_ir == 100));
+    assert!(history.check(false).unwrap().is_empty());
+    std::fs::remove_dir_all(&path).unwrap();
+}
+
+#[test]
+fn ids_of_removed_runs_are_not_reused() {
+    let path = store("ids_of_removed_runs_are_not_reused");
+    let history = History::open(&path).unwrap();
+    history
+        .add_runs(
+            &[run("a", "1"), run("b", "2"), run("c", "3")],
+            OnDuplicate::Refuse,
+        )
+        .unwrap();
+    // The newest run.
+    assert!(history_command(&path, &["history", "remove", "c"]).0);
+    assert_eq!(
+        history
+            .add_run(&run("d", "4"), OnDuplicate::Refuse)
+            .unwrap(),
+        Some(3)
+    );
+    // All runs.
+    assert!(history_command(&path, &["history", "remove", "a..d"]).0);
+    assert!(ids(&path).is_empty());
+    assert_eq!(
+        history
+            .add_run(&run("e", "5"), OnDuplicate::Refuse)
+            .unwrap(),
+        Some(4)
+    );
+    // Skipped duplicates take no id.
+    assert_eq!(
+        history
+            .add_runs(&[run("f", "5"), run("g", "6")], OnDuplicate::Skip)
+            .unwrap(),
+        [None, Some(5)]
+    );
+    std::fs::remove_dir_all(&path).unwrap();
+}
+
+#[test]
+fn pinned_runs() {
+    let path = store("pinned_runs");
+    for input in ["run1.cg", "run2.cg", "run3.cg"] {
+        assert!(history_command(&path, &["add", &format!("tests/data/{input}")]).0);
+    }
+    assert_eq!(
+        history_command(&path, &["history", "pin", "run2.cg"]),
+        (true, "Pinned run 1\n".to_string())
+    );
+    let (_, list) = history_command(&path, &["history", "list"]);
+    assert!(list.contains(" 1  run2.cg (pinned) "), "{list}");
+
+    // Other runs are compared to the pinned one.
+    let (success, table) = history_command(&path, &["--relative-to=pinned", "history", "show"]);
+    assert!(success);
+    let parse = table
+        .lines()
+        .find(|line| line.starts_with("parser::parse"))
+        .unwrap();
+    let cells = parse.split('|').collect::<Vec<_>>();
+    assert_eq!(cells[2].trim(), "550000");
+    assert!(cells[3].contains("18.182%"), "{parse}");
+    assert!(cells[3].ends_with(" 450000"), "{parse}");
+
+    // Pinned runs can be neither removed nor renamed.
+    assert!(!history_command(&path, &["history", "remove", "0..1"]).0);
+    assert!(!history_command(&path, &["history", "rename", "run2.cg", "v1.0"]).0);
+    assert_eq!(ids(&path), [0, 1, 2]);
+
+    // Nor pruned, by `history prune` or by the retention of the store.
+    assert_eq!(
+        history_command(&path, &["history", "prune", "--keep-last=1"]),
+        (true, "Removed 1 run(s)\n".to_string())
+    );
+    assert_eq!(ids(&path), [1, 2]);
+    std::fs::write(path.join("config"), "keep_last: 1\n").unwrap();
+    let history = History::open(&path).unwrap();
+    history
+        .add_run(&run("r3", "3"), OnDuplicate::Refuse)
+        .unwrap();
+    assert_eq!(ids(&path), [1, 3]);
+
+    // Once unpinned, they are pruned as other runs are.
+    assert_eq!(
+        history_command(&path, &["history", "unpin", "1"]),
+        (true, "Unpinned run 1\n".to_string())
+    );
+    history
+        .add_run(&run("r4", "4"), OnDuplicate::Refuse)
+        .unwrap();
+    assert_eq!(ids(&path), [4]);
+    std::fs::remove_dir_all(&path).unwrap();
+}
+
+#[test]
+fn presets_do_not_append_their_inputs() {
+    let path = store("presets_do_not_append_their_inputs");
+    assert!(history_command(&path, &["add", "tests/data/run1.cg"]).0);
+    std::fs::write(
+        path.join("config"),
+        "preset.check: --relative-to=first --fail-on-regression=50%\n",
+    )
+    .unwrap();
+    // The same comparison can be run again.
+    for _ in 0..2 {
+        let (success, output) = history_command(
+            &path,
+            &[
+                "--preset",
+                "check",
+                "tests/data/run1.cg",
+                "tests/data/run2.cg",
+            ],
+        );
+        assert!(success);
+        assert!(output.contains("parser::parse"));
+    }
+    assert_eq!(ids(&path), [0]);
+    let inputs = ["tests/data/run2.cg", "tests/data/run3.cg"];
+    assert!(history_command(&path, &["--read-only", inputs[0], inputs[1]]).0);
+    assert_eq!(ids(&path), [0]);
+    // Without `--read-only`, the inputs are appended once.
+    assert!(history_command(&path, &inputs).0);
+    assert_eq!(ids(&path), [0, 1, 2]);
+    assert!(!history_command(&path, &inputs).0);
+    std::fs::remove_dir_all(&path).unwrap();
+}
+
+#[test]
+fn merge_stores() {
+    let path = store("merge_stores");
+    let remote = store("merge_stores-remote");
+    let history = History::open(&path).unwrap();
+    history
+        .add_runs(&[run("a", "1"), run("b", "2")], OnDuplicate::Refuse)
+        .unwrap();
+    let mut runs = [run("c", "3"), run("d", "4"), run("e", "2")];
+    runs[0].metadata.pinned = true;
+    runs[1].metadata.origin = Some("ci-runner-1".to_string());
+    History::open(&remote)
+        .unwrap()
+        .add_runs(&runs, OnDuplicate::Refuse)
+        .unwrap();
+    let remote = remote.to_str().unwrap();
+    let merge = |args: &[&str]| {
+        let merge = ["history", "merge", remote, "--origin=ci-runner-2"];
+        history_command(&path, &[&merge[..], args].concat()).0
+    };
+    let origins = |history: &History| {
+        let runs = history.runs().unwrap();
+        runs.iter()
+            .map(|stored| {
+                let origin = stored.run.metadata.origin.clone();
+                (stored.id, stored.run.name.clone(), origin)
+            })
+            .collect::<Vec<_>>()
+    };
+
+    // `e` has the checksum of `b`: nothing is merged.
+    assert!(!merge(&[]));
+    assert_eq!(ids(&path), [0, 1]);
+    // The ids of the other store are not kept, as they collide with those of the history.
+    assert!(merge(&["--on-duplicate=skip"]));
+    let merged = |id| Some(format!("ci-runner-{id}"));
+    assert_eq!(
+        origins(&history),
+        [
+            (0, "a".to_string(), None),
+            (1, "b".to_string(), None),
+            (2, "c".to_string(), merged(2)),
+            (3, "d".to_string(), merged(1)),
+        ]
+    );
+    assert!(history
+        .runs()
+        .unwrap()
+        .iter()
+        .all(|stored| !stored.run.metadata.pinned));
+    // Merged runs are found by their checksum as well.
+    assert!(merge(&["--on-duplicate=skip"]));
+    assert_eq!(ids(&path), [0, 1, 2, 3]);
+    assert!(merge(&["--on-duplicate=allow"]));
+    assert_eq!(ids(&path), [0, 1, 2, 3, 4, 5, 6]);
+
+    // A lane is the history of a target of its own.
+    assert!(merge(&["--lane", "--on-duplicate=skip"]));
+    assert_eq!(ids(&path), [0, 1, 2, 3, 4, 5, 6]);
+    let lane = History::open_target(&path, "ci-runner-2").unwrap();
+    assert_eq!(
+        origins(&lane),
+        [
+            (0, "c".to_string(), merged(2)),
+            (1, "d".to_string(), merged(1)),
+            (2, "e".to_string(), merged(2)),
+        ]
+    );
+    assert!(!history_command(&path, &["history", "merge", "missing"]).0);
+    std::fs::remove_dir_all(&path).unwrap();
+    std::fs::remove_dir_all(remote).unwrap();
+}
+
+#[test]
+fn aliased_runs_are_renamed_and_removed() {
+    let path = store("aliased_runs_are_renamed_and_removed");
+    let history = History::open(&path).unwrap();
+    let aliased = |name: &str, ir| {
+        let mut run = run(name, name);
+        run.add_ir("parser::scan", ir);
+        run
+    };
+    history
+        .add_runs(&[aliased("v1", 10), aliased("v2", 20)], OnDuplicate::Refuse)
+        .unwrap();
+    let aliases = Alias::parse("parser::scan => scanner::scan\n").unwrap();
+    assert_eq!(history.alias(&aliases).unwrap(), (1, 2));
+    // The symbols of each run, by run name.
+    let symbols = |history: &History| {
+        let runs = history.runs().unwrap();
+        runs.iter()
+            .map(|stored| {
+                let run = &stored.run;
+                let names = run.symbols.iter().map(|symbol| symbol.name.to_string());
+                (run.name.clone(), names.collect::<Vec<_>>())
+            })
+            .collect::<Vec<_>>()
+    };
+
+    // Renaming and removing runs leaves the aliases recorded and the runs re-keyed.
+    history.rename("v1", "v1.0").unwrap();
+    let range = RunRange {
+        first: Some("v2".to_string()),
+        last: Some("v2".to_string()),
+    };
+    history.remove(&range).unwrap();
+    assert_eq!(history.aliases().unwrap(), aliases);
+    assert_eq!(
+        alias::rename(&history.aliases().unwrap(), "parser::scan").as_deref(),
+        Some("scanner::scan")
+    );
+    // Runs appended since are re-keyed as well, and those of the store only once.
+    history
+        .add_run(&aliased("v3", 30), OnDuplicate::Refuse)
+        .unwrap();
+    assert_eq!(history.alias(&aliases).unwrap(), (0, 0));
+    let rekeyed = |name: &str| {
+        let names = ["main", "scanner::scan"].map(String::from).to_vec();
+        (name.to_string(), names)
+    };
+    assert_eq!(symbols(&history), [rekeyed("v1.0"), rekeyed("v3")]);
+
+    // A symbol renamed again resolves to its latest name, with all its former ones.
+    let renamed = Alias::parse("scanner::scan => lexer::scan\n").unwrap();
+    assert_eq!(history.alias(&renamed).unwrap(), (1, 2));
+    let aliases = history.aliases().unwrap();
+    assert_eq!(
+        alias::rename(&aliases, "parser::scan").as_deref(),
+        Some("lexer::scan")
+    );
+    assert_eq!(
+        alias::former_names(&aliases)["lexer::scan"],
+        ["parser::scan", "scanner::scan"]
+    );
+    let (_, listed) = history_command(&path, &["history", "alias"]);
+    assert_eq!(
+        listed,
+        "parser::scan => scanner::scan\nscanner::scan => lexer::scan\n"
+    );
+    std::fs::remove_dir_all(&path).unwrap();
+}
+
+/// Return stored runs with the given IR counts of `parse`, and total IR counts of twice those.
+fn parse_runs(irs: &[u64]) -> Vec<StoredRun> {
+    (0..)
+        .zip(irs)
+        .map(|(id, &ir)| {
+            let mut run = Run::new_named(format!("r{id}"));
+            if ir != 0 {
+                run.add_ir("parse", ir);
+            }
+            run.total_ir = 2 * ir;
+            StoredRun { id, run }
+        })
+        .collect()
+}
+
+#[test]
+fn bisect_runs() {
+    let threshold = Percentage(5.0);
+    // A known regression point: the first run beyond the threshold relative to the first run,
+    // even though each run is within it relative to the one before it.
+    let runs = parse_runs(&[100, 102, 104, 106, 108]);
+    assert_eq!(bisect(&runs, "parse", threshold).unwrap(), Some(3));
+    assert_eq!(bisect(&runs, "", threshold).unwrap(), Some(3));
+    // A regression which was undone is still found.
+    let runs = parse_runs(&[100, 99, 120, 100]);
+    assert_eq!(bisect(&runs, "parse", threshold).unwrap(), Some(2));
+    // No regression, including runs in which the symbol vanished.
+    let runs = parse_runs(&[100, 105, 90, 0]);
+    assert_eq!(bisect(&runs, "parse", threshold).unwrap(), None);
+    // All runs regressed.
+    let runs = parse_runs(&[100, 200, 300]);
+    assert_eq!(bisect(&runs, "parse", threshold).unwrap(), Some(1));
+    // The first run is the reference: it must have an IR count.
+    assert!(bisect(&[], "parse", threshold).is_err());
+    assert!(bisect(&parse_runs(&[0, 100]), "parse", threshold).is_err());
+    assert!(bisect(&parse_runs(&[100, 200]), "main", threshold).is_err());
+}
+
+#[test]
+fn bisect_command() {
+    let path = store("bisect_command");
+    for input in ["run1.cg", "run2.cg", "run3.cg"] {
+        let input = format!("tests/data/{input}");
+        assert!(history_command(&path, &["add", &input]).0);
+    }
+    // `parser::parse` is 500000, 550000 then 450000 IR.
+    let (succeeded, output) = history_command(
+        &path,
+        &[
+            "history",
+            "bisect",
+            "--symbol=parser::parse",
+            "--threshold=5%",
+        ],
+    );
+    assert!(succeeded);
+    let lines = output.lines().collect::<Vec<_>>();
+    assert_eq!(
+        lines[0],
+        "parser::parse first regressed beyond 5% relative to run 0, in:"
+    );
+    assert_eq!(lines[1], "  run 1 (run2.cg): 550000 IR (+10.000%)");
+    assert!(lines.contains(&"The run before it was:"));
+    assert!(lines.contains(&"  run 0 (run1.cg): 500000 IR (+0.000%)"));
+
+    let (succeeded, output) = history_command(
+        &path,
+        &[
+            "history",
+            "bisect",
+            "--symbol=parser::parse",
+            "--threshold=15%",
+        ],
+    );
+    assert!(succeeded);
+    assert_eq!(
+        output,
+        "parser::parse did not regress beyond 15% over 3 run(s)\n"
+    );
+    // From run 1 on, the symbol only decreased.
+    let (succeeded, output) = history_command(
+        &path,
+        &[
+            "history",
+            "bisect",
+            "--symbol=parser::parse",
+            "--threshold=5%",
+            "--runs=1..",
+        ],
+    );
+    assert!(succeeded);
+    assert_eq!(
+        output,
+        "parser::parse did not regress beyond 5% over 2 run(s)\n"
+    );
+    assert!(
+        !history_command(
+            &path,
+            &["history", "bisect", "--symbol=none", "--threshold=5%"]
+        )
+        .0
+    );
+    std::fs::remove_dir_all(&path).unwrap();
+}
@@ -0,0 +1,110 @@
+//! Tests of the arithmetic on counts near `u64::MAX`, as long-running simulations produce.
+//!
+//! Sums saturate, means are exact and differences are computed without overflowing.
+
+use callgrind_differ::{
+    args::Args,
+    builder::RecordsBuilder,
+    diff::{DiffCell, DiffTable, Relative},
+    display::display_to,
+    runs::{mean_of, Records, Run},
+};
+use clap::Parser;
+
+const MAX: u64 = u64::MAX;
+
+/// Return a cell of `ir` compared to `reference`.
+fn cell(ir: u64, reference: u64) -> DiffCell {
+    DiffCell::exact(ir, Some(reference))
+}
+
+/// Return a run named `name` with the given IR counts, its total being their (saturated) sum.
+fn run(name: &str, irs: &[(&str, u64)]) -> Run {
+    let mut run = Run::new_named(name.to_string());
+    for (symbol, ir) in irs {
+        run.add_ir(symbol, *ir);
+        run.total_ir = run.total_ir.saturating_add(*ir);
+    }
+    run
+}
+
+/// Parse the arguments, as given on the command line.
+fn config(args: &[&str]) -> Args {
+    Args::try_parse_from(["callgrind_differ", "a.cg", "b.cg"].iter().chain(args))
+        .unwrap()
+        .validated()
+        .unwrap()
+}
+
+#[test]
+fn cell_differences_do_not_overflow() {
+    let up = cell(MAX, 0);
+    assert_eq!(up.diff(), i128::from(MAX));
+    assert_eq!(up.abs_diff(), MAX);
+    assert!(up.is_increase());
+    assert_eq!(up.percent(), 100.0);
+
+    let down = cell(0, MAX);
+    assert_eq!(down.diff(), -i128::from(MAX));
+    assert_eq!(down.abs_diff(), MAX);
+    assert_eq!(down.percent(), -100.0);
+    assert_eq!(down.relative(), Relative::Percent(-100.0));
+
+    assert!(matches!(cell(MAX, 1).relative(), Relative::Ratio(ratio) if ratio > 1e18));
+    assert_eq!(cell(MAX, MAX).relative(), Relative::Unchanged);
+    assert_eq!(cell(MAX, MAX - 1).diff(), 1);
+}
+
+#[test]
+fn means_are_exact() {
+    assert_eq!(mean_of(&[MAX, MAX]), MAX);
+    assert_eq!(mean_of(&[MAX, MAX - 2]), MAX - 1);
+    assert_eq!(mean_of(&[MAX, MAX, MAX - 1]), MAX);
+
+    // An `f64` cannot tell these counts apart.
+    let merged = Run::merge(vec![
+        run("a", &[("main", MAX - 1)]),
+        run("a", &[("main", MAX - 3)]),
+    ]);
+    assert_eq!(merged.symbols[0].ir, MAX - 2);
+    assert_eq!(merged.total_ir, MAX - 2);
+}
+
+#[test]
+fn sums_saturate() {
+    let mut run = Run::new();
+    run.add_ir("main", MAX);
+    run.add_ir("main", MAX);
+    assert_eq!(run.symbols[0].ir, MAX);
+
+    let merged = Run::merge(vec![run, Run::new()]);
+    assert_eq!(merged.symbols[0].ir, MAX / 2 + 1);
+
+    let mut builder = RecordsBuilder::new();
+    builder
+        .add_named_run("a", [("main", MAX), ("init", MAX)])
+        .add_event_counters("Dr", [("main", MAX), ("init", MAX)])
+        .unwrap();
+    let records = builder.finish().unwrap();
+    assert_eq!(records.runs_total_irs, [MAX]);
+    assert_eq!(records.runs_event_totals, [[MAX]]);
+    // The symbols account for more than the total.
+    assert_eq!(records.unaccounted_ir(0), 0);
+}
+
+#[test]
+fn table_of_huge_counts() {
+    let mut records = Records::new();
+    records.add_run(run("a", &[("main", MAX), ("init", 0), ("exit", 1)]));
+    records.add_run(run("b", &[("main", 0), ("init", MAX), ("exit", MAX)]));
+    let config = config(&["--show=all", "--all"]);
+    let table = DiffTable::new(&config, &records);
+    assert_eq!(table.total[1].diff(), 0);
+    assert_eq!(table.rows[0].cells[1].diff(), -i128::from(MAX));
+    assert_eq!(table.rows[2].cells[1].diff(), i128::from(MAX - 1));
+
+    let mut output = vec![];
+    display_to(&config, &table, &mut output).unwrap();
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.contains(&MAX.to_string()));
+}
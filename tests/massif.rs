@@ -0,0 +1,98 @@
+//! Tests of the outputs of `valgrind --tool=massif`.
+//!
+//! `tests/data/massif.out` has two empty snapshots, a detailed one and the peak, where the
+//! allocations of `parse` come from two lines.
+
+use std::{fs::File, io::BufReader, path::Path};
+
+use callgrind_differ::{
+    args::{Args, MassifSnapshots},
+    format::InputFormats,
+    massif::{parse, BELOW_THRESHOLD, HEAP_UNIT},
+    runs::Run,
+};
+use clap::Parser;
+
+/// Parse `tests/data/massif.out`.
+fn load(snapshots: MassifSnapshots) -> Vec<Run> {
+    let file = File::open("tests/data/massif.out").unwrap();
+    parse(BufReader::new(file), snapshots, &[], None).unwrap()
+}
+
+/// Return the heap bytes of `symbol` in `run`.
+fn bytes(run: &Run, symbol: &str) -> u64 {
+    run.symbols
+        .iter()
+        .find(|sym| &*sym.name == symbol)
+        .unwrap_or_else(|| panic!("No symbol {symbol:?}"))
+        .ir
+}
+
+#[test]
+fn peak_snapshot() {
+    let runs = load(MassifSnapshots::Peak);
+    let [run] = &runs[..] else {
+        panic!("{} runs", runs.len());
+    };
+    assert_eq!(run.name, "");
+    assert_eq!(run.total_ir, 3000);
+    assert_eq!(run.metadata.unit.as_deref(), Some(HEAP_UNIT));
+    assert_eq!(
+        run.metadata.command.as_deref(),
+        Some("./server --port 8080")
+    );
+    assert_eq!(run.symbols.len(), 2);
+    assert_eq!(bytes(run, "Buffer::grow(unsigned long)"), 2000);
+    // Sites are told apart by their function only, not by their line.
+    assert_eq!(bytes(run, "parse(char const*)"), 1000);
+}
+
+#[test]
+fn detailed_snapshots() {
+    // Empty snapshots have no allocation site, and are left out.
+    let runs = load(MassifSnapshots::All);
+    let names = runs.iter().map(|run| run.name.as_str()).collect::<Vec<_>>();
+    assert_eq!(names, ["snapshot 1", "snapshot 2 (peak)"]);
+    assert_eq!(runs[0].total_ir, 1500);
+    assert_eq!(bytes(&runs[0], "parse(char const*)"), 1000);
+    assert_eq!(bytes(&runs[0], "Buffer::grow(unsigned long)"), 400);
+    assert_eq!(bytes(&runs[0], BELOW_THRESHOLD), 100);
+    assert_eq!(runs[1].total_ir, 3000);
+
+    // Snapshots are named after their file when loaded from it.
+    let config = Args::try_parse_from([
+        "callgrind_differ",
+        "--massif-snapshots=all",
+        "tests/data/massif.out",
+    ])
+    .unwrap()
+    .validated()
+    .unwrap();
+    let runs = InputFormats::default()
+        .load(Path::new("tests/data/massif.out"), &config)
+        .unwrap();
+    assert_eq!(runs[0].name, "massif.out snapshot 1");
+}
+
+#[test]
+fn peak_without_a_marked_peak() {
+    let input = std::fs::read_to_string("tests/data/massif.out")
+        .unwrap()
+        .replace("heap_tree=peak", "heap_tree=detailed");
+    let runs = parse(input.as_bytes(), MassifSnapshots::Peak, &[], None).unwrap();
+    assert_eq!(runs.len(), 1);
+    // The detailed snapshot with the highest heap usage.
+    assert_eq!(runs[0].total_ir, 3000);
+}
+
+#[test]
+fn invalid_outputs() {
+    let input = std::fs::read_to_string("tests/data/massif.out").unwrap();
+    let empty = input
+        .replace("heap_tree=peak", "heap_tree=empty")
+        .replace("heap_tree=detailed", "heap_tree=empty");
+    assert!(parse(empty.as_bytes(), MassifSnapshots::All, &[], None).is_err());
+    let malformed = input.replace(" n1: 400 0x4005C3", " n1: many 0x4005C3");
+    assert!(parse(malformed.as_bytes(), MassifSnapshots::All, &[], None).is_err());
+    assert!(parse(input.as_bytes(), MassifSnapshots::All, &[], Some(2)).is_err());
+}
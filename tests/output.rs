@@ -0,0 +1,65 @@
+//! Tests of where [`callgrind_differ::run`] writes its results and messages.
+
+use callgrind_differ::{args::Args, run, status::Status, Output};
+use clap::Parser;
+
+/// Run `callgrind_differ` with the given arguments, and return its status and what it wrote into
+/// its output and messages.
+fn run_captured(args: &[&str]) -> (Status, String, String) {
+    let config = Args::parse_from(std::iter::once("callgrind_differ").chain(args.iter().copied()))
+        .validated()
+        .unwrap();
+    let (mut out, mut err) = (vec![], vec![]);
+    let Ok(status) = run(
+        config,
+        &mut Output {
+            out: &mut out,
+            err: &mut err,
+        },
+    ) else {
+        panic!("Failed to run with {args:?}");
+    };
+    (
+        status,
+        String::from_utf8(out).unwrap(),
+        String::from_utf8(err).unwrap(),
+    )
+}
+
+#[test]
+fn results_and_messages_are_written_into_the_output() {
+    let (status, out, err) = run_captured(&[
+        "tests/data/run1.cg",
+        "tests/data/run2.cg",
+        "--explain-total",
+        "--github-annotations",
+        "--fail-on-regression=5%",
+    ]);
+    assert_eq!(status, Status::RegressionDetected);
+    assert!(out.contains("parser::parse"));
+    assert!(out.contains("::error title=IR regression::parser::parse: +50000 IR"));
+    assert!(err.starts_with("Warning: 100000 IR (8.921%) of run 0 are not accounted for"));
+    assert!(err.contains("\nTotal IR of run 1 changed by +22000 (+1.963%)\n"));
+    assert!(err.ends_with("1 symbol(s) regressed beyond their allowed threshold\n"));
+    assert!(!err.contains("::error"));
+}
+
+#[test]
+fn history_commands_are_written_into_the_output() {
+    let path = std::env::temp_dir().join(format!(
+        "callgrind_differ-history_commands_are_written_into_the_output-{}",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_dir_all(&path);
+    let history = path.to_str().unwrap();
+    let (status, out, err) = run_captured(&["--history", history, "add", "tests/data/run1.cg"]);
+    assert_eq!(status, Status::Ok);
+    assert!(out.is_empty());
+    assert!(err.starts_with("Added tests/data/run1.cg to "));
+    let (status, out, err) = run_captured(&["--history", history, "history", "list"]);
+    assert_eq!(status, Status::Ok);
+    assert!(out.starts_with("id"));
+    assert!(out.contains("run1.cg"));
+    assert!(err.is_empty());
+    std::fs::remove_dir_all(&path).unwrap();
+}
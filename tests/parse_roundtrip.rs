@@ -0,0 +1,193 @@
+//! Property tests for the `callgrind_annotate` parser.
+//!
+//! Runs are generated, rendered as `callgrind_annotate` would, parsed back and compared to the
+//! original. Arbitrary and mangled inputs must be rejected or accepted, but never panic.
+
+use std::fmt::Write;
+
+use callgrind_differ::callgrind::parse;
+use proptest::prelude::*;
+
+/// A symbol of a generated run.
+#[derive(Debug, Clone)]
+struct Symbol {
+    /// The name of the symbol.
+    name: String,
+    /// The IR count.
+    ir: u64,
+    /// The counts of the other events of the run.
+    events: Vec<u64>,
+}
+
+/// A generated run.
+#[derive(Debug, Clone)]
+struct GeneratedRun {
+    /// The events other than `Ir`.
+    events: Vec<String>,
+    /// The symbols, with unique names.
+    symbols: Vec<Symbol>,
+}
+
+impl GeneratedRun {
+    /// Return the total count of the event at `i` (0 being `Ir`).
+    fn total(&self, i: usize) -> u64 {
+        self.symbols
+            .iter()
+            .map(|symbol| {
+                if i == 0 {
+                    symbol.ir
+                } else {
+                    symbol.events[i - 1]
+                }
+            })
+            .sum()
+    }
+
+    /// Render the run as `callgrind_annotate` would. Counts are comma-delimited and followed by a
+    /// percentage; null counts are shown as `.`.
+    fn render(&self) -> String {
+        let events = std::iter::once("Ir")
+            .chain(self.events.iter().map(String::as_str))
+            .collect::<Vec<_>>();
+        let dashes = "-".repeat(80);
+        let mut s = String::new();
+        let _ = writeln!(s, "{dashes}");
+        let _ = writeln!(
+            s,
+            "Profile data file 'callgrind.out.1' (creator: callgrind-3.22.0)"
+        );
+        let _ = writeln!(s, "{dashes}");
+        let _ = writeln!(s, "Events recorded: {}", events.join(" "));
+        let _ = writeln!(s, "Events shown:    {}", events.join(" "));
+        let _ = writeln!(s, "Event sort order: Ir");
+        let _ = writeln!(s, "Thresholds:      99");
+        let _ = writeln!(s);
+        let _ = writeln!(s, "{dashes}");
+        let _ = writeln!(s, "{}", events.join("                 "));
+        let _ = writeln!(s, "{dashes}");
+        for i in 0..events.len() {
+            let _ = write!(s, "{} (100.0%) ", delimited(self.total(i)));
+        }
+        let _ = writeln!(s, " PROGRAM TOTALS");
+        let _ = writeln!(s);
+        let _ = writeln!(s, "{dashes}");
+        let _ = writeln!(s, "{}  file:function", events.join("                 "));
+        let _ = writeln!(s, "{dashes}");
+        for symbol in &self.symbols {
+            for count in std::iter::once(&symbol.ir).chain(&symbol.events) {
+                if *count == 0 {
+                    let _ = write!(s, "{:>12}          ", '.');
+                } else {
+                    let _ = write!(s, "{:>12} ( 1.23%) ", delimited(*count));
+                }
+            }
+            let _ = writeln!(s, " src/lib.rs:{} [/bin/x]", symbol.name);
+        }
+        let _ = writeln!(s);
+        let _ = writeln!(s, "{dashes}");
+        let _ = writeln!(s, "-- Auto-annotated source: src/lib.rs");
+        s
+    }
+}
+
+/// Format a count with commas between groups of 3 digits, as `callgrind_annotate` does.
+fn delimited(count: u64) -> String {
+    let digits = count.to_string();
+    let mut s = String::new();
+    for (i, c) in digits.chars().enumerate() {
+        if i != 0 && (digits.len() - i).is_multiple_of(3) {
+            s.push(',');
+        }
+        s.push(c);
+    }
+    s
+}
+
+/// Generate a symbol name, possibly with spaces and colons, as demangled Rust symbols have.
+fn symbol_name() -> impl Strategy<Value = String> {
+    "(<[a-z_]{1,8} as [a-z_]{1,8}>::)?[a-z_][a-z0-9_]{0,10}(::[a-z_][a-z0-9_]{0,10}){0,3}"
+}
+
+/// Generate a run with between 0 and 3 events other than `Ir`.
+fn generated_run() -> impl Strategy<Value = GeneratedRun> {
+    proptest::sample::subsequence(vec!["Dr", "Dw", "LLd"], 0..=3).prop_flat_map(|events| {
+        let n_events = events.len();
+        let symbol = (
+            symbol_name(),
+            0..1_000_000_000_000u64,
+            proptest::collection::vec(0..1_000_000u64, n_events),
+        )
+            .prop_map(|(name, ir, events)| Symbol { name, ir, events });
+        proptest::collection::vec(symbol, 0..50).prop_map(move |symbols| {
+            // Keep the first occurrence of each name.
+            let mut seen = std::collections::HashSet::new();
+            GeneratedRun {
+                events: events.iter().map(ToString::to_string).collect(),
+                symbols: symbols
+                    .into_iter()
+                    .filter(|symbol| seen.insert(symbol.name.clone()))
+                    .collect(),
+            }
+        })
+    })
+}
+
+proptest! {
+    #[test]
+    fn annotate_roundtrip(generated in generated_run()) {
+        let run = parse(generated.render().as_bytes(), &[], None).unwrap();
+
+        prop_assert_eq!(&run.events, &generated.events);
+        prop_assert_eq!(run.total_ir, generated.total(0));
+        let event_totals = (1..=generated.events.len())
+            .map(|i| generated.total(i))
+            .collect::<Vec<_>>();
+        prop_assert_eq!(&run.event_totals, &event_totals);
+        prop_assert_eq!(run.symbols.len(), generated.symbols.len());
+        for (parsed, symbol) in run.symbols.iter().zip(&generated.symbols) {
+            prop_assert_eq!(&*parsed.name, symbol.name.as_str());
+            prop_assert_eq!(parsed.ir, symbol.ir);
+            prop_assert_eq!(&parsed.events, &symbol.events);
+        }
+    }
+
+    #[test]
+    fn annotate_max_symbols(generated in generated_run(), max in 0usize..60) {
+        let result = parse(generated.render().as_bytes(), &[], Some(max));
+        prop_assert_eq!(result.is_ok(), generated.symbols.len() <= max);
+    }
+
+    #[test]
+    fn arbitrary_input_does_not_panic(input in proptest::collection::vec(any::<u8>(), 0..2000)) {
+        let _ = parse(input.as_slice(), &[], None);
+    }
+
+    #[test]
+    fn mangled_input_does_not_panic(
+        generated in generated_run(),
+        cuts in proptest::collection::vec((any::<prop::sample::Index>(), "[ 0-9,.:()%\\[\\]\n-]{0,20}"), 1..5),
+    ) {
+        // Replace random ranges of a valid input with plausible garbage.
+        let mut input = generated.render();
+        for (index, garbage) in cuts {
+            let mut at = index.index(input.len() + 1);
+            while !input.is_char_boundary(at) {
+                at -= 1;
+            }
+            let end = (at + garbage.len()).min(input.len());
+            let end = (end..=input.len()).find(|i| input.is_char_boundary(*i)).unwrap();
+            input.replace_range(at..end, &garbage);
+        }
+        let _ = parse(input.as_bytes(), &[], None);
+    }
+
+    #[test]
+    fn huge_counts_do_not_panic(digits in "[1-9][0-9]{19,40}", repeat in 1usize..4) {
+        // Counts beyond `u64::MAX`, possibly repeated for the same symbol.
+        let mut input = format!("Ir\n---\n{digits} (100.0%)  PROGRAM TOTALS\n\nIr\n---\n");
+        for _ in 0..repeat {
+            let _ = writeln!(input, "{digits} (100.0%)  src/lib.rs:main [/bin/x]");
+        }
+        let _ = parse(input.as_bytes(), &[], None);
+    }
+}
@@ -0,0 +1,103 @@
+//! Tests of the rules files of `--policy`, and of the globs of their patterns.
+
+use callgrind_differ::{
+    args::Percentage,
+    policy::{glob_match, Policy},
+};
+
+#[test]
+fn stars_match_any_sequence() {
+    assert!(glob_match("parser::*", "parser::parse"));
+    assert!(glob_match("parser::*", "parser::"));
+    assert!(!glob_match("parser::*", "lexer::parser::parse"));
+    assert!(glob_match("*::fmt", "<a::B as core::fmt::Debug>::fmt"));
+    assert!(glob_match(
+        "<* as *>::fmt",
+        "<a::B as core::fmt::Debug>::fmt"
+    ));
+    assert!(!glob_match(
+        "<* as *>::fmt",
+        "<a::B as core::fmt::Debug>::fmt2"
+    ));
+    // A `*` which first matches too little backtracks to match more.
+    assert!(glob_match("*a*b", "xaxbxab"));
+    assert!(!glob_match("*a*b", "xaxbxa"));
+    assert!(glob_match("a**b", "ab"));
+}
+
+#[test]
+fn trailing_stars_match_the_rest() {
+    assert!(glob_match("*", ""));
+    assert!(glob_match("*", "any symbol"));
+    assert!(glob_match("main*", "main"));
+    assert!(glob_match("main**", "main::inner"));
+    assert!(!glob_match("main*", "mai"));
+}
+
+#[test]
+fn question_marks_match_a_character() {
+    assert!(glob_match("f?o", "foo"));
+    assert!(glob_match("f?o", "féo"));
+    assert!(!glob_match("f?o", "fo"));
+    assert!(!glob_match("f?o", "fooo"));
+    assert!(glob_match("??*", "ab"));
+    assert!(!glob_match("??*", "a"));
+}
+
+#[test]
+fn empty_patterns_match_empty_symbols() {
+    assert!(glob_match("", ""));
+    assert!(!glob_match("", "main"));
+    assert!(!glob_match("main", ""));
+}
+
+#[test]
+fn first_matching_rule_applies() {
+    let policy = Policy::parse(
+        "# Lines starting with a `#` are comments.\n\
+         parser::*           1%\n\
+         \n\
+         <* as core::fmt::Debug>::fmt 10\n\
+         parser::parse       50%\n\
+         *                   5%\n"
+            .as_bytes(),
+    )
+    .unwrap();
+    let patterns = policy
+        .rules
+        .iter()
+        .map(|rule| rule.pattern.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        patterns,
+        [
+            "parser::*",
+            "<* as core::fmt::Debug>::fmt",
+            "parser::parse",
+            "*"
+        ]
+    );
+    let max_regression = |symbol: &str| policy.rule_for(symbol).map(|rule| rule.max_regression);
+    // `parser::parse` also matches the later rules.
+    assert_eq!(max_regression("parser::parse"), Some(Percentage(1.0)));
+    assert_eq!(
+        max_regression("<a::B as core::fmt::Debug>::fmt"),
+        Some(Percentage(10.0))
+    );
+    assert_eq!(max_regression("main"), Some(Percentage(5.0)));
+    assert_eq!(
+        Policy::parse("parser::* 1%\n".as_bytes())
+            .unwrap()
+            .rule_for("main")
+            .map(|rule| rule.pattern.as_str()),
+        None
+    );
+}
+
+#[test]
+fn malformed_rules() {
+    for input in ["parser::*\n", "5%\n", "parser::* -1%\n", "parser::* many\n"] {
+        assert!(Policy::parse(input.as_bytes()).is_err(), "{input:?}");
+    }
+    assert!(Policy::parse("".as_bytes()).unwrap().rules.is_empty());
+}
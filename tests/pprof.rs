@@ -0,0 +1,165 @@
+//! Tests of `pprof` protobuf profiles, gzipped as Go's `runtime/pprof` writes them.
+//!
+//! `tests/data/cpu.pb.gz` has the `samples` (`count`) and `cpu` (`nanoseconds`) sample types,
+//! `cpu` being the default. Its first location has an inlined function, and its last one was not
+//! symbolized.
+
+#![cfg(feature = "pprof")]
+
+use std::path::{Path, PathBuf};
+
+use callgrind_differ::{args::Args, format::InputFormats, gzip, pprof::parse, runs::Run};
+use clap::Parser;
+
+/// Return the decompressed profile of `tests/data/cpu.pb.gz`.
+fn profile() -> Vec<u8> {
+    gzip::decompress(&std::fs::read("tests/data/cpu.pb.gz").unwrap(), None).unwrap()
+}
+
+/// Write `contents` into the file `name` of the test `test`, and return its path.
+fn input_file(test: &str, name: &str, contents: &[u8]) -> PathBuf {
+    let path = std::env::temp_dir().join(format!(
+        "callgrind_differ-{test}-{}-{name}",
+        std::process::id()
+    ));
+    std::fs::write(&path, contents).unwrap();
+    path
+}
+
+/// Return the count of `symbol` in `run`.
+fn ir(run: &Run, symbol: &str) -> u64 {
+    run.symbols
+        .iter()
+        .find(|sym| &*sym.name == symbol)
+        .unwrap_or_else(|| panic!("No symbol {symbol:?}"))
+        .ir
+}
+
+#[test]
+fn gzipped_profiles_are_detected() {
+    let formats = InputFormats::default();
+    let format = formats.find(Path::new("tests/data/cpu.pb.gz")).unwrap();
+    assert_eq!(format.name(), "pprof");
+    let format = formats.find(Path::new("tests/data/run1.cg")).unwrap();
+    assert_eq!(format.name(), "callgrind_annotate");
+}
+
+#[test]
+fn detection_reads_the_start_of_inputs() {
+    let test = "detection_reads_the_start_of_inputs";
+    let formats = InputFormats::default();
+    let format = |path: &Path| formats.find(path).unwrap().name();
+    let compressed = std::fs::read("tests/data/cpu.pb.gz").unwrap();
+    let uncompressed = input_file(test, "cpu.pb", &profile());
+    // Truncated profiles are detected, and fail to load.
+    let truncated = input_file(test, "truncated.pb.gz", &compressed[..100]);
+    // Text starting with the key of the first `sample_type` of a profile.
+    let mut text = b"\n".to_vec();
+    text.extend(std::fs::read("tests/data/run1.cg").unwrap());
+    let text = input_file(test, "run1.cg", &text);
+    // `echo -n hello | gzip`
+    let gzipped_text = input_file(
+        test,
+        "hello.gz",
+        &[
+            0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x03, 0xcb, 0x48, 0xcd, 0xc9,
+            0xc9, 0x07, 0x00, 0x86, 0xa6, 0x10, 0x36, 0x05, 0x00, 0x00, 0x00,
+        ],
+    );
+    assert_eq!(format(&uncompressed), "pprof");
+    assert_eq!(format(&truncated), "pprof");
+    assert_eq!(format(&text), "callgrind_annotate");
+    assert_eq!(format(&gzipped_text), "callgrind_annotate");
+    let config = Args::parse_from(["callgrind_differ", "a.cg"]);
+    assert!(formats.load(&uncompressed, &config).is_ok());
+    assert!(formats.load(&truncated, &config).is_err());
+    for path in [uncompressed, truncated, text, gzipped_text] {
+        std::fs::remove_file(path).unwrap();
+    }
+}
+
+#[test]
+fn limits_apply_to_decompressed_profiles() {
+    // The file is 253 bytes long, and its profile 267 bytes long.
+    let path = Path::new("tests/data/cpu.pb.gz");
+    let formats = InputFormats::default();
+    let load = |max_size| {
+        let config = Args::parse_from(["callgrind_differ", "--max-input-size", max_size, "a.cg"]);
+        formats.load(path, &config)
+    };
+    assert!(load("267").is_ok());
+    let Err(error) = load("260") else {
+        panic!("The profile is larger than the limit");
+    };
+    assert!(format!("{error:#}").contains("260"), "{error:#}");
+}
+
+#[test]
+fn default_sample_type() {
+    let run = parse(&profile(), None, &[], None).unwrap();
+    assert_eq!(run.metadata.unit.as_deref(), Some("nanoseconds"));
+    assert_eq!(run.metadata.timestamp, Some(1_760_000_000));
+    assert_eq!(run.total_ir, 110_000_000);
+    assert_eq!(run.events, ["samples"]);
+    assert_eq!(run.event_totals, [11]);
+    assert_eq!(run.symbols.len(), 3);
+    // Samples are attributed to the innermost function of their leaf.
+    assert_eq!(ir(&run, "inlined.helper"), 40_000_000);
+    assert_eq!(ir(&run, "main.(*Server).ServeHTTP"), 50_000_000);
+    assert_eq!(ir(&run, "0x4a3f20"), 20_000_000);
+}
+
+#[test]
+fn sample_type_selection() {
+    let run = parse(&profile(), Some("samples"), &[], None).unwrap();
+    assert_eq!(run.metadata.unit.as_deref(), Some("count"));
+    assert_eq!(run.total_ir, 11);
+    assert_eq!(run.events, ["cpu"]);
+    assert_eq!(run.event_totals, [110_000_000]);
+    assert_eq!(ir(&run, "inlined.helper"), 4);
+    assert_eq!(ir(&run, "main.(*Server).ServeHTTP"), 5);
+    assert_eq!(ir(&run, "0x4a3f20"), 2);
+
+    let Err(error) = parse(&profile(), Some("alloc_space"), &[], None) else {
+        panic!("alloc_space is not a sample type of the profile");
+    };
+    assert!(
+        error
+            .to_string()
+            .contains(r#"No sample type "alloc_space""#),
+        "{error}"
+    );
+    assert!(parse(&profile(), None, &[], Some(2)).is_err());
+}
+
+#[test]
+fn truncated_or_garbage_inputs() {
+    let compressed = std::fs::read("tests/data/cpu.pb.gz").unwrap();
+    assert!(gzip::decompress(&compressed[..compressed.len() / 2], None).is_err());
+    assert!(gzip::decompress(&compressed, Some(100)).is_err());
+    assert!(gzip::decompress(b"not gzipped at all", None).is_err());
+    // The trailer is checked: its CRC-32, then its size.
+    let n = compressed.len();
+    assert!(gzip::decompress(&compressed[..n - 4], None).is_err());
+    for (i, reason) in [(n - 8, "CRC"), (n - 1, "size"), (n / 2, "")] {
+        let mut corrupted = compressed.clone();
+        corrupted[i] ^= 0x40;
+        let error = gzip::decompress(&corrupted, None).unwrap_err();
+        assert!(error.to_string().contains(reason), "{error}");
+    }
+    // Prefixes of the data, from prefixes of the file.
+    assert_eq!(
+        gzip::decompress_prefix(&compressed[..100], 16).unwrap(),
+        profile()[..16]
+    );
+    assert_eq!(
+        gzip::decompress_prefix(&compressed, 1000).unwrap(),
+        profile()
+    );
+    assert!(gzip::decompress_prefix(&compressed[..20], 16).is_err());
+
+    let profile = profile();
+    assert!(parse(&profile[..profile.len() - 3], None, &[], None).is_err());
+    assert!(parse(b"\x0a\xff\xff\xff", None, &[], None).is_err());
+    assert!(parse(b"", None, &[], None).is_err());
+}
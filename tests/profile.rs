@@ -0,0 +1,169 @@
+//! Tests of `callgrind_differ profile`, with a fake valgrind (see `VALGRIND`) which records its
+//! arguments and writes a canned profile.
+
+#![cfg(unix)]
+
+use std::{
+    fs,
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use callgrind_differ::{history::History, profile::VALGRIND_ENV_VAR};
+
+/// The profile written by the fake valgrind.
+const PROFILE: &str = "\
+# callgrind format
+version: 1
+cmd: ./bench --quick
+events: Ir Bc Bcm
+fl=src/main.rs
+fn=main
+1 500 10 1
+fn=bench::work
+2 1500 40 6
+";
+
+/// Return an empty directory for the test `name`, holding a fake valgrind which exits with
+/// `status` after writing [`PROFILE`].
+fn setup(name: &str, status: u8) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "callgrind_differ-profile-{name}-{}",
+        std::process::id()
+    ));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("profile"), PROFILE).unwrap();
+    let valgrind = dir.join("valgrind");
+    fs::write(
+        &valgrind,
+        format!(
+            r##"#!/bin/sh
+if [ "$1" = --version ]; then echo valgrind-3.22.0; exit 0; fi
+dir=$(dirname "$0")
+printf '%s\n' "$@" > "$dir/args"
+for arg; do
+    case "$arg" in
+        --callgrind-out-file=*) out=${{arg#--callgrind-out-file=}}
+            cp "$dir/profile" "$out"
+            # Profiles of different runs differ.
+            echo "# pid $$" >> "$out";;
+    esac
+done
+exit {status}
+"##
+        ),
+    )
+    .unwrap();
+    fs::set_permissions(&valgrind, fs::Permissions::from_mode(0o755)).unwrap();
+    dir
+}
+
+/// Run `callgrind_differ` with the store in `dir`, the fake valgrind and the given arguments,
+/// and return whether it succeeded.
+fn run(dir: &Path, args: &[&str]) -> bool {
+    Command::new(env!("CARGO_BIN_EXE_callgrind_differ"))
+        .arg("--history")
+        .arg(dir.join("store"))
+        .args(args)
+        .env(VALGRIND_ENV_VAR, dir.join("valgrind"))
+        .status()
+        .unwrap()
+        .success()
+}
+
+/// Return the arguments the fake valgrind was last run with, without the output file.
+fn valgrind_args(dir: &Path) -> Vec<String> {
+    fs::read_to_string(dir.join("args"))
+        .unwrap()
+        .lines()
+        .filter(|arg| !arg.starts_with("--callgrind-out-file="))
+        .map(str::to_string)
+        .collect()
+}
+
+#[test]
+fn options_are_passed_through_and_recorded() {
+    let dir = setup("options", 0);
+    assert!(run(&dir, &["profile", "--", "./bench", "--quick"]));
+    assert_eq!(
+        valgrind_args(&dir),
+        ["--tool=callgrind", "--", "./bench", "--quick"]
+    );
+    assert!(run(
+        &dir,
+        &[
+            "profile",
+            "--cache-sim=yes",
+            "--branch-sim",
+            "--toggle-collect=bench::*",
+            "--collect-atstart=no",
+            "--name",
+            "toggled",
+            "--",
+            "./bench",
+        ]
+    ));
+    assert_eq!(
+        valgrind_args(&dir),
+        [
+            "--tool=callgrind",
+            "--cache-sim=yes",
+            "--branch-sim=yes",
+            "--toggle-collect=bench::*",
+            "--collect-atstart=no",
+            "--",
+            "./bench",
+        ]
+    );
+
+    let runs = History::open(dir.join("store")).unwrap().runs().unwrap();
+    assert_eq!(runs.len(), 2);
+    let (first, second) = (&runs[0].run, &runs[1].run);
+    // Runs are named after the command line recorded by callgrind, unless named.
+    assert_eq!(first.name, "./bench --quick");
+    assert_eq!(first.total_ir, 2000);
+    assert_eq!(first.metadata.valgrind.as_deref(), Some("valgrind-3.22.0"));
+    assert_eq!(
+        first.metadata.simulation.as_deref(),
+        Some("--cache-sim=no --branch-sim=no")
+    );
+    assert_eq!(second.name, "toggled");
+    assert_eq!(
+        second.metadata.simulation.as_deref(),
+        Some("--cache-sim=yes --branch-sim=yes --toggle-collect=bench::* --collect-atstart=no")
+    );
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn failures_append_nothing() {
+    let dir = setup("failures", 1);
+    let out_file = dir.join("callgrind.out");
+    assert!(!run(
+        &dir,
+        &[
+            "profile",
+            "--out-file",
+            out_file.to_str().unwrap(),
+            "--",
+            "./bench"
+        ]
+    ));
+    assert!(History::open(dir.join("store"))
+        .unwrap()
+        .runs()
+        .unwrap()
+        .is_empty());
+    // The profile is kept where it was asked to be, even if valgrind failed.
+    assert!(out_file.exists());
+    // `profile` needs a program and a store.
+    assert!(!run(&dir, &["profile"]));
+    let status = Command::new(env!("CARGO_BIN_EXE_callgrind_differ"))
+        .args(["profile", "--", "./bench"])
+        .status()
+        .unwrap();
+    assert!(!status.success());
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,82 @@
+//! Tests of [`Inputs`], reloading only the input files which changed.
+
+use std::{
+    fs::{self, File},
+    path::PathBuf,
+    time::{Duration, SystemTime},
+};
+
+use callgrind_differ::{args::Args, reload::Inputs};
+use clap::Parser;
+
+/// The total IR of `tests/data/run1.cg`, `run2.cg` and `run3.cg`.
+const TOTALS: [u64; 3] = [1_121_000, 1_143_000, 1_125_000];
+
+/// Return an empty directory for the test `name`, holding copies of `tests/data/run1.cg` named
+/// `a.cg`, `b.cg` and `c.cg`.
+fn inputs(name: &str) -> (PathBuf, [PathBuf; 3]) {
+    let dir = std::env::temp_dir().join(format!("callgrind_differ-{name}-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let files = ["a.cg", "b.cg", "c.cg"].map(|file| dir.join(file));
+    for file in &files {
+        fs::copy("tests/data/run1.cg", file).unwrap();
+    }
+    (dir, files)
+}
+
+#[test]
+fn only_changed_inputs_are_reloaded() {
+    let config = Args::parse_from(["callgrind_differ"]);
+    let (dir, files) = inputs("only_changed_inputs_are_reloaded");
+    let mut inputs = Inputs::load(&files, &config).unwrap();
+    assert_eq!(inputs.records().runs_total_irs, [TOTALS[0]; 3]);
+    assert!(inputs.reload(&config).unwrap().is_empty());
+
+    fs::copy("tests/data/run2.cg", &files[1]).unwrap();
+    assert_eq!(inputs.reload(&config).unwrap(), [files[1].as_path()]);
+    assert_eq!(
+        inputs.records().runs_total_irs,
+        [TOTALS[0], TOTALS[1], TOTALS[0]]
+    );
+    assert!(inputs.reload(&config).unwrap().is_empty());
+
+    // Touching a file does not reload it, as its contents did not change.
+    let later = SystemTime::now() + Duration::from_secs(60);
+    File::options()
+        .write(true)
+        .open(&files[2])
+        .unwrap()
+        .set_modified(later)
+        .unwrap();
+    assert!(inputs.reload(&config).unwrap().is_empty());
+
+    fs::copy("tests/data/run3.cg", &files[0]).unwrap();
+    fs::copy("tests/data/run3.cg", &files[2]).unwrap();
+    assert_eq!(
+        inputs.reload(&config).unwrap(),
+        [files[0].as_path(), files[2].as_path()]
+    );
+    assert_eq!(
+        inputs.records().runs_total_irs,
+        [TOTALS[2], TOTALS[1], TOTALS[2]]
+    );
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn failed_reloads_are_retried() {
+    let config = Args::parse_from(["callgrind_differ"]);
+    let (dir, files) = inputs("failed_reloads_are_retried");
+    let mut inputs = Inputs::load(&files, &config).unwrap();
+    fs::write(&files[1], "Not an output of callgrind_annotate\n").unwrap();
+    assert!(inputs.reload(&config).is_err());
+    assert!(inputs.reload(&config).is_err());
+    fs::copy("tests/data/run2.cg", &files[1]).unwrap();
+    assert_eq!(inputs.reload(&config).unwrap(), [files[1].as_path()]);
+    assert_eq!(
+        inputs.into_records().runs_total_irs,
+        [TOTALS[0], TOTALS[1], TOTALS[0]]
+    );
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,156 @@
+//! Tests of the retention of history stores, declared in their `config` file.
+
+use callgrind_differ::{
+    args::OnDuplicate,
+    history::{History, StoredRun},
+    retention::{Downsample, Retention},
+    runs::Run,
+};
+
+/// The number of seconds in an hour.
+const HOUR: u64 = 3600;
+
+/// The number of seconds in a day.
+const DAY: u64 = 24 * HOUR;
+
+/// Return a stored run with the given id and timestamp, if any.
+fn stored(id: u64, timestamp: Option<u64>) -> StoredRun {
+    let mut run = Run::new_named(format!("r{id}"));
+    run.metadata.timestamp = timestamp;
+    StoredRun { id, run }
+}
+
+/// Return which of `runs` are kept by `retention`, by id.
+fn kept(retention: &Retention, runs: &[StoredRun]) -> Vec<u64> {
+    runs.iter()
+        .zip(retention.retained(runs))
+        .filter_map(|(stored, retained)| retained.then_some(stored.id))
+        .collect()
+}
+
+#[test]
+fn parse() {
+    let retention = Retention::parse(
+        "# Keep the 30 most recent runs.\n\
+         keep_last: 30\n\
+         \n\
+         keep_tagged: true\n\
+         downsample: weekly\n\
+         compress: zstd\n",
+    )
+    .unwrap();
+    assert_eq!(
+        retention,
+        Retention {
+            keep_last: Some(30),
+            keep_tagged: true,
+            downsample: Some(Downsample::Weekly),
+        }
+    );
+    assert_eq!(Retention::parse("").unwrap(), Retention::default());
+    for invalid in [
+        "keep_last: 0",
+        "keep_last: -1",
+        "keep_last 30",
+        "keep_tagged: yes",
+        "downsample: monthly",
+    ] {
+        assert!(Retention::parse(invalid).is_err(), "{invalid}");
+    }
+}
+
+#[test]
+fn keep_last() {
+    let runs = (0..5).map(|id| stored(id, None)).collect::<Vec<_>>();
+    assert_eq!(kept(&Retention::default(), &runs), [0, 1, 2, 3, 4]);
+    let keep_last = |n| Retention {
+        keep_last: Some(n),
+        ..Retention::default()
+    };
+    assert_eq!(kept(&keep_last(2), &runs), [3, 4]);
+    assert_eq!(kept(&keep_last(5), &runs), [0, 1, 2, 3, 4]);
+    assert_eq!(kept(&keep_last(10), &runs), [0, 1, 2, 3, 4]);
+}
+
+#[test]
+fn downsample() {
+    let runs = [
+        stored(0, Some(DAY + 9 * HOUR)),
+        stored(1, Some(DAY + 18 * HOUR)),
+        stored(2, Some(2 * DAY + 9 * HOUR)),
+        // Runs with no timestamp are kept, as their age is unknown.
+        stored(3, None),
+        stored(4, Some(2 * DAY + 10 * HOUR)),
+        stored(5, Some(9 * DAY)),
+        stored(6, Some(9 * DAY + HOUR)),
+    ];
+    let downsample = |downsample| Retention {
+        keep_last: Some(1),
+        keep_tagged: false,
+        downsample: Some(downsample),
+    };
+    // The most recent old run of each day, then the most recent run.
+    assert_eq!(kept(&downsample(Downsample::Daily), &runs), [1, 3, 4, 5, 6]);
+    // Days 1 and 2 are in the first week since the epoch, day 9 in the second one.
+    assert_eq!(kept(&downsample(Downsample::Weekly), &runs), [3, 4, 5, 6]);
+}
+
+#[test]
+fn pinned_and_tagged_runs() {
+    let mut runs = (0..5)
+        .map(|id| stored(id, Some(id * HOUR)))
+        .collect::<Vec<_>>();
+    runs[0].run.metadata.pinned = true;
+    runs[1].run.metadata.tag = Some("v1.0".to_string());
+    let mut retention = Retention {
+        keep_last: Some(1),
+        ..Retention::default()
+    };
+    assert_eq!(kept(&retention, &runs), [0, 4]);
+    retention.keep_tagged = true;
+    assert_eq!(kept(&retention, &runs), [0, 1, 4]);
+    // Pinned and tagged runs do not count as the run kept for their period.
+    retention.downsample = Some(Downsample::Daily);
+    assert_eq!(kept(&retention, &runs), [0, 1, 3, 4]);
+}
+
+#[test]
+fn retention_is_applied_on_add() {
+    let path = std::env::temp_dir().join(format!(
+        "callgrind_differ-retention_is_applied_on_add-{}",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_dir_all(&path);
+    let history = History::open(&path).unwrap();
+    std::fs::write(path.join("config"), "keep_last: 2\n").unwrap();
+    let ids = |history: &History| {
+        history
+            .runs()
+            .unwrap()
+            .iter()
+            .map(|stored| stored.id)
+            .collect::<Vec<_>>()
+    };
+    let run = |name: &str| {
+        let mut run = Run::new_named(name.to_string());
+        run.add_ir("main", 100);
+        run.total_ir = 100;
+        run
+    };
+    for name in ["a", "b", "c"] {
+        history.add_run(&run(name), OnDuplicate::Allow).unwrap();
+    }
+    assert_eq!(ids(&history), [1, 2]);
+    history.pin("b", true).unwrap();
+    history
+        .add_runs(&[run("d"), run("e")], OnDuplicate::Allow)
+        .unwrap();
+    assert_eq!(ids(&history), [1, 3, 4]);
+    // Targets without a config of their own use that of the store.
+    let target = History::open_target(&path, "bench").unwrap();
+    for name in ["a", "b", "c"] {
+        target.add_run(&run(name), OnDuplicate::Allow).unwrap();
+    }
+    assert_eq!(ids(&target), [1, 2]);
+    std::fs::remove_dir_all(&path).unwrap();
+}
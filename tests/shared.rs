@@ -0,0 +1,79 @@
+//! Tests of [`SharedRecords`] read by several threads while another replaces them.
+
+use std::{
+    io,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use callgrind_differ::{builder::RecordsBuilder, runs::Records, shared::SharedRecords};
+
+/// The number of times the writer replaces the records.
+const GENERATIONS: u64 = 200;
+
+/// Return the records of generation `generation`: `generation % 5 + 1` runs, each with a total IR
+/// of `generation`.
+fn records(generation: u64) -> Records {
+    let mut builder = RecordsBuilder::new();
+    for run in 0..=generation % 5 {
+        builder.add_named_run(format!("run {run}"), [("main", generation)]);
+    }
+    builder.finish().unwrap()
+}
+
+#[test]
+fn readers_see_whole_replacements() {
+    let shared = SharedRecords::new(records(0));
+    let done = AtomicBool::new(false);
+    std::thread::scope(|scope| {
+        let readers = (0..4)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut last = 0;
+                    let mut reads = 0;
+                    while !done.load(Ordering::Acquire) || reads == 0 {
+                        let records = shared.read();
+                        let total = records.runs_total_irs[0];
+                        // Records are never seen half-replaced.
+                        assert_eq!(records.n_runs() as u64, total % 5 + 1);
+                        assert!(records.runs_total_irs.iter().all(|ir| *ir == total));
+                        // Nor older than those seen before.
+                        assert!(total >= last, "{total} < {last}");
+                        last = total;
+                        reads += 1;
+                    }
+                    last
+                })
+            })
+            .collect::<Vec<_>>();
+        for generation in 1..=GENERATIONS {
+            assert_eq!(shared.replace(records(generation)), generation);
+        }
+        done.store(true, Ordering::Release);
+        for reader in readers {
+            assert!(reader.join().unwrap() <= GENERATIONS);
+        }
+    });
+    assert_eq!(shared.generation(), GENERATIONS);
+    assert_eq!(shared.read().runs_total_irs[0], GENERATIONS);
+}
+
+#[test]
+fn failed_reloads_leave_records_untouched() {
+    let shared = SharedRecords::new(records(3));
+    let reload = shared.spawn_reload(|| Err(io::Error::other("unreadable input").into()));
+    assert!(reload.join().unwrap().is_err());
+    assert_eq!(shared.generation(), 0);
+    assert_eq!(shared.read().runs_total_irs, [3; 4]);
+
+    // Clones share the records.
+    let clone = shared.clone();
+    assert_eq!(
+        clone
+            .spawn_reload(|| Ok(records(7)))
+            .join()
+            .unwrap()
+            .unwrap(),
+        1
+    );
+    assert_eq!(shared.read().runs_total_irs, [7; 3]);
+}
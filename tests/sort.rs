@@ -0,0 +1,98 @@
+//! Tests for the order of symbols after [`Records::sort`].
+//!
+//! Symbols with the same IR must be sorted by ascending name, whichever the field and the order,
+//! and regardless of the order in which they were added.
+
+use callgrind_differ::{args::SortBy, builder::RecordsBuilder, runs::Records};
+
+/// The IR of each symbol in each of the 2 runs. `a`, `b` and `c` tie in both runs, `d` and `e`
+/// tie in the first one only.
+const SYMBOLS: [(&str, [u64; 2]); 5] = [
+    ("c", [10, 30]),
+    ("e", [20, 40]),
+    ("a", [10, 30]),
+    ("d", [20, 10]),
+    ("b", [10, 30]),
+];
+
+/// Build records from [`SYMBOLS`], adding the symbols in the given order.
+fn records(order: &[usize]) -> Records {
+    let mut builder = RecordsBuilder::new();
+    for run in 0..2 {
+        builder.add_named_run(
+            format!("run{run}"),
+            order.iter().map(|i| (SYMBOLS[*i].0, SYMBOLS[*i].1[run])),
+        );
+    }
+    builder.finish().unwrap()
+}
+
+/// Sort records built from [`SYMBOLS`] in the given order and return the names of the symbols.
+fn sorted(order: &[usize], by: &str) -> Vec<String> {
+    let mut records = records(order);
+    records.sort(by.parse::<SortBy>().unwrap()).unwrap();
+    records
+        .symbols
+        .iter()
+        .map(|symbol| symbol.name.to_string())
+        .collect()
+}
+
+#[test]
+fn ties_are_sorted_by_ascending_name() {
+    let order = [0, 1, 2, 3, 4];
+    assert_eq!(sorted(&order, "symbol"), ["a", "b", "c", "d", "e"]);
+    assert_eq!(sorted(&order, "-symbol"), ["e", "d", "c", "b", "a"]);
+    assert_eq!(sorted(&order, "first-ir"), ["a", "b", "c", "d", "e"]);
+    assert_eq!(sorted(&order, "-first-ir"), ["d", "e", "a", "b", "c"]);
+    assert_eq!(sorted(&order, "last-ir"), ["d", "a", "b", "c", "e"]);
+    assert_eq!(sorted(&order, "-last-ir"), ["e", "a", "b", "c", "d"]);
+    assert_eq!(sorted(&order, "column0"), sorted(&order, "first-ir"));
+    assert_eq!(sorted(&order, "-column1"), sorted(&order, "-last-ir"));
+}
+
+#[test]
+fn order_does_not_depend_on_insertion_order() {
+    let orders = [
+        [0, 1, 2, 3, 4],
+        [4, 3, 2, 1, 0],
+        [2, 0, 4, 1, 3],
+        [3, 1, 4, 2, 0],
+    ];
+    for by in [
+        "symbol",
+        "-symbol",
+        "first-ir",
+        "-first-ir",
+        "last-ir",
+        "-last-ir",
+        "column1",
+    ] {
+        let expected = sorted(&orders[0], by);
+        for order in &orders[1..] {
+            assert_eq!(
+                sorted(order, by),
+                expected,
+                "--sort-by={by}, order {order:?}"
+            );
+        }
+    }
+}
+
+#[test]
+fn sorting_twice_is_idempotent() {
+    let mut records = records(&[2, 0, 4, 1, 3]);
+    let by = "-last-ir".parse::<SortBy>().unwrap();
+    let names = |records: &Records| {
+        records
+            .symbols
+            .iter()
+            .map(|symbol| symbol.name.to_string())
+            .collect::<Vec<_>>()
+    };
+    records.sort(by).unwrap();
+    let once = names(&records);
+    records.sort(by).unwrap();
+    assert_eq!(names(&records), once);
+    records.check_invariants().unwrap();
+}
@@ -0,0 +1,140 @@
+//! Tests of the exit codes of `callgrind_differ`, by cause of failure.
+
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use anyhow::Context;
+use callgrind_differ::{error::Error, status::Status};
+
+/// Return an empty directory for the test `name`.
+fn temp_dir(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("callgrind_differ-{name}-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&path);
+    std::fs::create_dir_all(&path).unwrap();
+    path
+}
+
+/// Run `callgrind_differ --report-status` with the given arguments, and return its exit code
+/// and its status line.
+fn status(args: &[&str]) -> (Option<i32>, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_callgrind_differ"))
+        .arg("--report-status")
+        .args(args)
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    (
+        output.status.code(),
+        stdout.lines().last().unwrap_or_default().to_string(),
+    )
+}
+
+/// Return the path of `file` in `dir`, as a string.
+fn path_in(dir: &Path, file: &str) -> String {
+    dir.join(file).display().to_string()
+}
+
+#[test]
+fn inputs_which_cannot_be_parsed() {
+    let dir = temp_dir("inputs_which_cannot_be_parsed");
+    let garbage = path_in(&dir, "garbage.cg");
+    std::fs::write(&garbage, "Not an output of callgrind_annotate\n").unwrap();
+    let missing = path_in(&dir, "missing.cg");
+    let parse_error = (
+        Some(2),
+        "callgrind_differ: status=parse-error code=2".to_string(),
+    );
+    assert_eq!(status(&["tests/data/run1.cg", &garbage]), parse_error);
+    assert_eq!(status(&["tests/data/run1.cg", &missing]), parse_error);
+    // Inputs appended to the history as well.
+    let history = path_in(&dir, "history");
+    assert_eq!(
+        status(&["--history", &history, "add", &garbage]),
+        parse_error
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn history_errors_are_usage_errors() {
+    let dir = temp_dir("history_errors_are_usage_errors");
+    let history = path_in(&dir, "history");
+    let usage_error = (
+        Some(1),
+        "callgrind_differ: status=usage-error code=1".to_string(),
+    );
+    assert_eq!(
+        status(&["--history", &history, "add", "tests/data/run1.cg"]).0,
+        Some(0)
+    );
+    // The same file twice.
+    assert_eq!(
+        status(&["--history", &history, "add", "tests/data/run1.cg"]),
+        usage_error
+    );
+    assert_eq!(
+        status(&["--history", &history, "tests/data/run1.cg"]),
+        usage_error
+    );
+    assert_eq!(
+        status(&[
+            "--history",
+            &history,
+            "--compare-ref=unknown",
+            "tests/data/run2.cg"
+        ]),
+        usage_error
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn configuration_errors_are_usage_errors() {
+    let dir = temp_dir("configuration_errors_are_usage_errors");
+    let rules = path_in(&dir, "rules");
+    std::fs::write(&rules, "parser::*\n").unwrap();
+    let usage_error = (
+        Some(1),
+        "callgrind_differ: status=usage-error code=1".to_string(),
+    );
+    let inputs = ["tests/data/run1.cg", "tests/data/run2.cg"];
+    assert_eq!(
+        status(&[inputs[0], inputs[1], "--regression-rules", &rules]),
+        usage_error
+    );
+    assert_eq!(
+        status(&[
+            inputs[0],
+            inputs[1],
+            "--regression-rules",
+            &path_in(&dir, "missing")
+        ]),
+        usage_error
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn status_of_errors() {
+    let parse = || Error::Parse {
+        path: None,
+        line: 3,
+        message: "Invalid count".to_string(),
+    };
+    assert_eq!(Status::of(&parse().into()), Status::ParseError);
+    assert_eq!(
+        Status::of(&Err::<(), _>(parse()).context("Failed to load").unwrap_err()),
+        Status::ParseError
+    );
+    let invalid_run = Error::InvalidRun {
+        run: "a".to_string(),
+        reason: "Its name is already taken".to_string(),
+    };
+    assert_eq!(Status::of(&invalid_run.into()), Status::UsageError);
+    assert_eq!(
+        Status::of(&anyhow::anyhow!("The history is locked")),
+        Status::UsageError
+    );
+}